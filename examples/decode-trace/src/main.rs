@@ -27,42 +27,6 @@ struct Cli {
     bytes: Vec<String>,
 }
 
-/// Parse a string containing C-style escape sequences into bytes.
-/// Handles \xNN (hex), \0 (null), \n, \r, \t, and \\ (literal backslash).
-fn parse_escape_sequences(s: &str) -> Result<Vec<u8>, String> {
-    let mut out = Vec::new();
-    let mut chars = s.chars().peekable();
-    while let Some(c) = chars.next() {
-        if c != '\\' {
-            // literal byte - only accept printable ASCII here
-            if c.is_ascii() {
-                out.push(c as u8);
-            } else {
-                return Err(format!("unexpected non-ASCII character: {c:?}"));
-            }
-            continue;
-        }
-        match chars.next() {
-            Some('x') => {
-                let hi = chars.next().ok_or("truncated \\x escape")?;
-                let lo = chars.next().ok_or("truncated \\x escape")?;
-                let hex = format!("{hi}{lo}");
-                let byte = u8::from_str_radix(&hex, 16)
-                    .map_err(|_| format!("invalid hex in \\x escape: {hex:?}"))?;
-                out.push(byte);
-            },
-            Some('0') => out.push(0),
-            Some('n') => out.push(b'\n'),
-            Some('r') => out.push(b'\r'),
-            Some('t') => out.push(b'\t'),
-            Some('\\') => out.push(b'\\'),
-            Some(other) => return Err(format!("unknown escape: \\{other}")),
-            None => return Err("trailing backslash".into()),
-        }
-    }
-    Ok(out)
-}
-
 fn main() {
     // Force insim_core to TRACE so every field decode is logged.
     // Override specific targets via RUST_LOG if needed.
@@ -80,39 +44,14 @@ fn main() {
 
     let joined = cli.bytes.join("");
 
-    // Detect format: if the input contains a backslash, treat it as escape sequences.
-    // Otherwise treat it as run-together hex digits.
-    let raw: Vec<u8> = if joined.contains('\\') {
-        match parse_escape_sequences(&joined) {
-            Ok(bytes) => bytes,
-            Err(e) => {
-                eprintln!("error: {e}");
-                std::process::exit(1);
-            },
-        }
-    } else {
-        let hex_input: String = joined.chars().filter(|c| c.is_ascii_hexdigit()).collect();
-
-        if !hex_input.len().is_multiple_of(2) {
-            eprintln!("error: odd number of hex digits");
+    let raw = match insim::core::hex::parse(&joined) {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            eprintln!("error: {e}");
             std::process::exit(1);
-        }
-
-        hex_input
-            .as_bytes()
-            .chunks(2)
-            .map(|pair| {
-                let s = std::str::from_utf8(pair).expect("ascii");
-                u8::from_str_radix(s, 16).expect("valid hex digit")
-            })
-            .collect()
+        },
     };
 
-    if raw.is_empty() {
-        eprintln!("error: no bytes provided");
-        std::process::exit(1);
-    }
-
     eprintln!("--- input ({} bytes): {:02x?}", raw.len(), raw);
     eprintln!("--- decode trace:");
 
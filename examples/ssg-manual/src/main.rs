@@ -2,9 +2,10 @@
 use std::{net::SocketAddr, time::Duration};
 
 use clap::Parser;
-use insim::{Packet, core::Decode, insim::SmallType};
-use outgauge::Outgauge;
-use outsim::OutsimPack;
+use insim::{
+    insim::SmallType,
+    net::demux::{Datagram, Demux},
+};
 use tokio::net::UdpSocket;
 use tracing_subscriber::fmt::format::FmtSpan;
 
@@ -42,8 +43,8 @@ pub async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     let udp = UdpSocket::bind(cli.udp_listen_addr).await?;
-    // Make a buffer for the UdpSocket, we'll reuse this across recv_from calls
-    let mut buf = [0; 1024];
+    // LFS sends Mci/Nlp, outgauge and outsim to the same port, Demux tells them apart
+    let demux = Demux::new();
 
     // Make our TCP connection
     let mut connection = insim::tcp(cli.addr)
@@ -77,25 +78,18 @@ pub async fn main() -> anyhow::Result<()> {
                 let packet = packet?;
                 tracing::info!("{:?}", packet);
             },
-            res = udp.recv_from(&mut buf) => {
-                let (amt, src) = res?;
-
-                if amt == 92 {
-                    // Conventionally it's outgauge
-                    let packet = Outgauge::decode_slice(&buf[..amt])?;
-                    tracing::info!("outgauge: from={:?}, data={:?}", src, packet);
-                } else if amt == 64 {
-                    // Conventionally it's outsim
-                    let packet = OutsimPack::decode_slice(&buf[..amt])?;
-                    tracing::info!("outsim: from={:?}, data={:?}", src, packet);
-                } else if amt > 1 {
+            res = demux.recv_from_async(&udp) => {
+                match res? {
+                    (Datagram::Outgauge(packet), src) => {
+                        tracing::info!("outgauge: from={:?}, data={:?}", src, packet);
+                    },
+                    (Datagram::Outsim(packet), src) => {
+                        tracing::info!("outsim: from={:?}, data={:?}", src, packet);
+                    },
                     // Otherwise it's probably a Mci or Nlp packet
-                    //
-                    // XXX: we need to chop off the first byte, which is the length of the packet.
-                    // That's normally handled internally
-                    // by the insim crate.
-                    let packet = Packet::decode_slice(&buf[1..amt])?;
-                    tracing::info!("insim: from={:?}, data={:?}", src, packet);
+                    (datagram, src) => {
+                        tracing::info!("insim: from={:?}, data={:?}", src, datagram);
+                    },
                 }
             }
         }
@@ -0,0 +1,104 @@
+// This bench binary links insim's full dependency set but only exercises
+// decoding, so unused_crate_dependencies fires for the rest.
+#![allow(
+    missing_docs,
+    missing_debug_implementations,
+    unused_results,
+    unused_crate_dependencies
+)]
+
+use std::hint::black_box;
+
+use bytes::Bytes;
+use criterion::{Criterion, criterion_group, criterion_main};
+use insim::{
+    Packet, PacketRef, VERSION,
+    identifiers::{ConnectionId, PlayerId},
+    insim::{CompCar, Mci, Mso, MsoUserType},
+    net::Codec,
+};
+
+fn frame(packet: Packet) -> Bytes {
+    Codec::new().encode(&packet).unwrap()
+}
+
+/// A full MCI packet, as sent for a large grid.
+fn mci() -> Bytes {
+    frame(
+        Mci {
+            info: (0..8)
+                .map(|i| CompCar {
+                    node: 100 + i,
+                    plid: PlayerId(i as u8),
+                    ..Default::default()
+                })
+                .collect(),
+            ..Default::default()
+        }
+        .into(),
+    )
+}
+
+fn mso() -> Bytes {
+    frame(
+        Mso {
+            ucid: ConnectionId(3),
+            usertype: MsoUserType::User,
+            textstart: 12,
+            msg: "^7Some^1body : ^Lhello world".into(),
+            ..Default::default()
+        }
+        .into(),
+    )
+}
+
+fn decode_owned(frame: &Bytes) -> Packet {
+    PacketRef::new(frame).unwrap().to_packet(VERSION).unwrap()
+}
+
+fn bench_mci(c: &mut Criterion) {
+    let frame = mci();
+
+    c.bench_function("mci owned", |b| {
+        b.iter(|| {
+            let Packet::Mci(mci) = decode_owned(black_box(&frame)) else {
+                unreachable!()
+            };
+            mci.info.iter().map(|c| u32::from(c.node)).sum::<u32>()
+        })
+    });
+
+    c.bench_function("mci borrowed", |b| {
+        b.iter(|| {
+            let Ok(PacketRef::Mci(mci)) = PacketRef::new(black_box(&frame)) else {
+                unreachable!()
+            };
+            mci.iter().map(|c| u32::from(c.node())).sum::<u32>()
+        })
+    });
+}
+
+fn bench_mso(c: &mut Criterion) {
+    let frame = mso();
+
+    c.bench_function("mso owned", |b| {
+        b.iter(|| {
+            let Packet::Mso(mso) = decode_owned(black_box(&frame)) else {
+                unreachable!()
+            };
+            mso.msg_from_textstart().len()
+        })
+    });
+
+    c.bench_function("mso borrowed", |b| {
+        b.iter(|| {
+            let Ok(PacketRef::Mso(mso)) = PacketRef::new(black_box(&frame)) else {
+                unreachable!()
+            };
+            mso.text().to_lossy_string().len()
+        })
+    });
+}
+
+criterion_group!(benches, bench_mci, bench_mso);
+criterion_main!(benches);
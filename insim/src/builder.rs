@@ -2,19 +2,26 @@
 use std::{
     fmt::Debug,
     net::{SocketAddr, ToSocketAddrs},
+    sync::Arc,
     time::Duration,
 };
 
 #[cfg(feature = "blocking")]
 #[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
-use crate::net::blocking_impl::Framed as BlockingFramed;
+use crate::net::blocking_impl::{
+    Framed as BlockingFramed, Handle as BlockingHandle, Subscriber as BlockingSubscriber,
+};
 #[cfg(feature = "tokio")]
 #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
-use crate::net::tokio_impl::Framed as AsyncFramed;
+use crate::net::tokio_impl::{
+    Framed as AsyncFramed, Handle as AsyncHandle, Subscriber as AsyncSubscriber,
+};
 use crate::{
+    VERSION,
     address::Addr,
     identifiers::RequestId,
     insim::{Isi, IsiFlags},
+    net::{Codec, DEFAULT_SUBSCRIBER_CAPACITY, Interest, LatencyMonitor, Metrics, SendQueue},
     result::Result,
 };
 
@@ -53,6 +60,7 @@ enum Proto {
     #[default]
     Tcp,
     Udp,
+    TcpUdp,
 }
 
 #[derive(Debug)]
@@ -71,6 +79,7 @@ pub struct Builder {
     isi_iname: Option<String>,
     isi_reqi: RequestId,
     isi_udpport: Option<u16>,
+    isi_version: u8,
 
     // Choosing to use separate fields with a prefix, rather than an enum because when this was
     // originally implemented it supported LFSW Relay, which no longer exists, and if you were to do
@@ -82,6 +91,11 @@ pub struct Builder {
     tcp_nodelay: bool,
     non_blocking: bool,
     udp_local_address: Option<SocketAddr>,
+
+    send_queue: Option<SendQueue>,
+    latency_monitor: Option<LatencyMonitor>,
+    interest: Interest,
+    metrics: Option<Arc<dyn Metrics>>,
 }
 
 impl Default for Builder {
@@ -104,6 +118,12 @@ impl Default for Builder {
             isi_interval: None,
             isi_reqi: RequestId(1),
             isi_udpport: None,
+            isi_version: VERSION,
+
+            send_queue: None,
+            latency_monitor: None,
+            interest: Interest::ALL,
+            metrics: None,
         }
     }
 }
@@ -133,6 +153,25 @@ impl Builder {
         self
     }
 
+    /// Use a TCP connection for most packets, and a UDP socket for [crate::Packet::Mci] and
+    /// [crate::Packet::Nlp].
+    ///
+    /// If `udp_local_addr` is not provided then we will bind to "0.0.0.0:0" (all addresses,
+    /// random port). The `udpport` sent during the handshake is derived from the bound socket
+    /// unless explicitly set via [Builder::isi_udpport]. Packets from both sockets are merged
+    /// into the single connection returned by `connect_async` or `connect_blocking`, and all
+    /// writes are sent over TCP.
+    pub fn tcp_udp<L: Into<Option<SocketAddr>>, R: Into<Addr>>(
+        mut self,
+        remote_addr: R,
+        udp_local_addr: L,
+    ) -> Self {
+        self.proto = Proto::TcpUdp;
+        self.remote = remote_addr.into();
+        self.udp_local_address = udp_local_addr.into();
+        self
+    }
+
     /// Set the connection timeout.
     pub fn connect_timeout(mut self, duration: Duration) -> Self {
         self.connect_timeout = duration;
@@ -273,20 +312,76 @@ impl Builder {
     /// If you explicitly set this then it will override any values derived from the `udp_local_address`.
     /// You may wish to do this in one of 2 scenarios:
     /// - NAT/PAT (read: port forwarding) reasons
-    /// - You want to receive positional and other information over UDP, whilst using TCP.
-    ///
-    /// If you choose to use a combination of Tcp and Udp (insim protocol dictates that if a udpport is provided
-    /// then [crate::Packet::Mci], [crate::Packet::Nlp], Outgauge and Outsim packets will be sent to the
-    /// udpport) be aware that this library *does not* currently automatically handle this protocol split for
-    /// you. You **must** spawn the relevant UdpSocket by hand. You can find an example of this in
-    /// the examples/ssg-manual/ directory.
+    /// - You want to receive positional and other information over UDP, whilst using TCP, but
+    ///   wish to manage the UdpSocket yourself.
     ///
-    /// This may change in future versions.
+    /// The insim protocol dictates that if a udpport is provided then [crate::Packet::Mci],
+    /// [crate::Packet::Nlp], Outgauge and Outsim packets will be sent to the udpport. If you
+    /// only need InSim packets over UDP, [Builder::tcp_udp] will handle this protocol split for
+    /// you. Otherwise you **must** spawn the relevant UdpSocket by hand. You can find an example of
+    /// this in the examples/ssg-manual/ directory.
     pub fn isi_udpport<P: Into<Option<u16>>>(mut self, udpport: P) -> Self {
         self.isi_udpport = udpport.into();
         self
     }
 
+    /// Set the Insim version to be used in the [crate::Packet::Isi] packet during connection
+    /// handshake, between [crate::MIN_VERSION] and [VERSION]. Defaults to [VERSION].
+    ///
+    /// The connection never speaks a later version than this, even if LFS does. See
+    /// [Codec::with_version].
+    pub fn isi_version(mut self, version: u8) -> Self {
+        self.isi_version = version;
+        self
+    }
+
+    /// Pace and prioritise packets sent by a spawned connection using `queue`. Only used by
+    /// [Builder::spawn] and [Builder::spawn_blocking]. Defaults to no queue, where packets are
+    /// written as soon as possible. Packets still queued during shutdown are discarded.
+    pub fn send_queue<Q: Into<Option<SendQueue>>>(mut self, queue: Q) -> Self {
+        self.send_queue = queue.into();
+        self
+    }
+
+    /// Measure the round trip time of a spawned connection using `monitor`. Only used by
+    /// [Builder::spawn] and [Builder::spawn_blocking], where the statistics are available from
+    /// [AsyncHandle::latency] and [BlockingHandle::latency]. Defaults to no monitor.
+    ///
+    /// The replies to the monitor's pings are not passed to subscribers.
+    pub fn latency_monitor<M: Into<Option<LatencyMonitor>>>(mut self, monitor: M) -> Self {
+        self.latency_monitor = monitor.into();
+        self
+    }
+
+    /// Report traffic on every connection made by this `Builder` to `metrics`. Defaults to none.
+    /// See [Codec::with_metrics].
+    pub fn metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// The [Metrics] traffic is reported to, if any.
+    pub(crate) fn metrics_ref(&self) -> Option<&Arc<dyn Metrics>> {
+        self.metrics.as_ref()
+    }
+
+    /// Only decode packets within `interest`, skipping everything else. Defaults to
+    /// [Interest::ALL]. See [Codec::with_interest].
+    ///
+    /// Skipped packets are never received, including replies to any request you make.
+    pub fn interest(mut self, interest: Interest) -> Self {
+        self.interest = interest;
+        self
+    }
+
+    fn codec(&self) -> Codec {
+        let mut codec = Codec::new()
+            .with_interest(self.interest)
+            .with_version(self.isi_version);
+        codec.set_metrics(self.metrics.clone());
+        codec
+    }
+
     /// Create a [crate::insim::Isi] from this configuration.
     pub fn isi(&self, udpport: Option<u16>) -> Isi {
         Isi {
@@ -301,7 +396,7 @@ impl Builder {
                 .to_owned(),
             prefix: self.isi_prefix.unwrap_or('\0'),
             interval: self.isi_interval.unwrap_or(Duration::ZERO),
-            ..Default::default()
+            version: self.isi_version,
         }
     }
 
@@ -322,7 +417,7 @@ impl Builder {
                 if self.non_blocking {
                     stream.set_nonblocking(true)?;
                 }
-                let mut stream: BlockingFramed = stream.into();
+                let mut stream = BlockingFramed::from_tcp(stream, self.codec());
                 stream.write(self.isi(self.isi_udpport))?;
 
                 Ok(stream)
@@ -341,7 +436,31 @@ impl Builder {
                 let port = self.isi_udpport.unwrap_or(stream.local_addr()?.port());
                 let isi = self.isi(Some(port));
 
-                let mut stream: BlockingFramed = stream.into();
+                let mut stream = BlockingFramed::from_udp(stream, self.codec());
+                stream.write(isi)?;
+
+                Ok(stream)
+            },
+            Proto::TcpUdp => {
+                use crate::net::blocking_impl::SPLIT_POLL_INTERVAL;
+
+                let local = self.udp_local_address.unwrap_or("0.0.0.0:0".parse()?);
+                let socket = std::net::UdpSocket::bind(local)?;
+                socket.set_nonblocking(true)?;
+
+                let stream = tcpstream_connect_to_any(&self.remote, self.connect_timeout)?;
+                stream.set_nodelay(self.tcp_nodelay)?;
+                stream.set_write_timeout(Some(Duration::from_secs(DEFAULT_TIMEOUT_SECS)))?;
+                if self.non_blocking {
+                    stream.set_nonblocking(true)?;
+                } else {
+                    stream.set_read_timeout(Some(SPLIT_POLL_INTERVAL))?;
+                }
+
+                let port = self.isi_udpport.unwrap_or(socket.local_addr()?.port());
+                let isi = self.isi(Some(port));
+
+                let mut stream = BlockingFramed::from_tcp_udp(stream, socket, self.codec());
                 stream.write(isi)?;
 
                 Ok(stream)
@@ -349,6 +468,44 @@ impl Builder {
         }
     }
 
+    /// Handshake over an already established stream, such as an SSH tunnel or serial bridge,
+    /// rather than connecting. The protocol and remote address are ignored.
+    #[cfg(feature = "blocking")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+    pub fn handshake_blocking<S>(&self, stream: S) -> Result<BlockingFramed>
+    where
+        S: std::io::Read + std::io::Write + Send + 'static,
+    {
+        let mut stream = BlockingFramed::from_stream(stream, self.codec());
+        stream.write(self.isi(self.isi_udpport))?;
+        Ok(stream)
+    }
+
+    #[cfg(feature = "tokio")]
+    async fn tcpstream_connect_async(&self) -> std::io::Result<tokio::net::TcpStream> {
+        let connect = async {
+            let addrs = self.remote.to_socket_addrs_async().await?;
+            let mut last_error = None;
+            for addr in addrs {
+                match tokio::net::TcpStream::connect(addr).await {
+                    Ok(stream) => return Ok(stream),
+                    Err(error) => last_error = Some(error),
+                }
+            }
+            Err(last_error.unwrap_or_else(|| {
+                std::io::Error::new(
+                    std::io::ErrorKind::AddrNotAvailable,
+                    "address resolved to no socket addresses",
+                )
+            }))
+        };
+        tokio::time::timeout(self.connect_timeout, connect)
+            .await
+            .map_err(|_| {
+                std::io::Error::new(std::io::ErrorKind::TimedOut, "connection timed out")
+            })?
+    }
+
     /// Attempt to establish (connect and handshake) a valid Insim connection using this
     /// configuration.
     /// The `Builder` is not consumed and may be reused.
@@ -357,30 +514,10 @@ impl Builder {
     pub async fn connect_async(&self) -> Result<AsyncFramed> {
         match self.proto {
             Proto::Tcp => {
-                let connect = async {
-                    let addrs = self.remote.to_socket_addrs_async().await?;
-                    let mut last_error = None;
-                    for addr in addrs {
-                        match tokio::net::TcpStream::connect(addr).await {
-                            Ok(stream) => return Ok(stream),
-                            Err(error) => last_error = Some(error),
-                        }
-                    }
-                    Err(last_error.unwrap_or_else(|| {
-                        std::io::Error::new(
-                            std::io::ErrorKind::AddrNotAvailable,
-                            "address resolved to no socket addresses",
-                        )
-                    }))
-                };
-                let stream = tokio::time::timeout(self.connect_timeout, connect)
-                    .await
-                    .map_err(|_| {
-                        std::io::Error::new(std::io::ErrorKind::TimedOut, "connection timed out")
-                    })??;
+                let stream = self.tcpstream_connect_async().await?;
                 stream.set_nodelay(self.tcp_nodelay)?;
 
-                let mut stream: AsyncFramed = stream.into();
+                let mut stream = AsyncFramed::from_tcp(stream, self.codec());
                 stream.write(self.isi(self.isi_udpport)).await?;
 
                 Ok(stream)
@@ -401,11 +538,274 @@ impl Builder {
                 let port = self.isi_udpport.unwrap_or(stream.local_addr()?.port());
                 let isi = self.isi(Some(port));
 
-                let mut stream: AsyncFramed = stream.into();
+                let mut stream = AsyncFramed::from_udp(stream, self.codec());
+                stream.write(isi).await?;
+
+                Ok(stream)
+            },
+            Proto::TcpUdp => {
+                let local = self.udp_local_address.unwrap_or("0.0.0.0:0".parse()?);
+                let socket = tokio::net::UdpSocket::bind(local).await?;
+
+                let stream = self.tcpstream_connect_async().await?;
+                stream.set_nodelay(self.tcp_nodelay)?;
+
+                let port = self.isi_udpport.unwrap_or(socket.local_addr()?.port());
+                let isi = self.isi(Some(port));
+
+                let mut stream = AsyncFramed::from_tcp_udp(stream, socket, self.codec());
                 stream.write(isi).await?;
 
                 Ok(stream)
             },
         }
     }
+
+    /// Handshake over an already established stream, such as an SSH tunnel or
+    /// [tokio::io::duplex] pipe, rather than connecting. The protocol and remote address are
+    /// ignored.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    pub async fn handshake_async<S>(&self, stream: S) -> Result<AsyncFramed>
+    where
+        S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Send + 'static,
+    {
+        let mut stream = AsyncFramed::from_stream(stream, self.codec());
+        stream.write(self.isi(self.isi_udpport)).await?;
+        Ok(stream)
+    }
+
+    /// Convert this `Builder` into a task which connects using this configuration, and then drives the connection in
+    /// the background. Returns a cloneable [AsyncHandle] for sending packets, alongside the first
+    /// [AsyncSubscriber]. Further subscribers can be created using [AsyncHandle::subscribe].
+    ///
+    /// Connection failures, both initial and later, are reported to every subscriber.
+    /// Must be called from within a tokio runtime.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    pub fn spawn(mut self) -> (AsyncHandle, AsyncSubscriber) {
+        let queue = self.send_queue.take();
+        let latency = self.latency_monitor.take();
+        AsyncHandle::spawn(
+            async move { self.connect_async().await },
+            DEFAULT_SUBSCRIBER_CAPACITY,
+            queue,
+            latency,
+        )
+    }
+
+    /// Convert this `Builder` into a thread which connects using this configuration, and then drives the connection
+    /// in the background. Returns a cloneable [BlockingHandle] for sending packets, alongside
+    /// the first [BlockingSubscriber]. Further subscribers can be created using
+    /// [BlockingHandle::subscribe].
+    ///
+    /// Connection failures, both initial and later, are reported to every subscriber. An error
+    /// is only returned here if the thread could not be spawned.
+    #[cfg(feature = "blocking")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+    pub fn spawn_blocking(mut self) -> Result<(BlockingHandle, BlockingSubscriber)> {
+        let queue = self.send_queue.take();
+        let latency = self.latency_monitor.take();
+        BlockingHandle::spawn(
+            move || self.connect_blocking(),
+            DEFAULT_SUBSCRIBER_CAPACITY,
+            queue,
+            latency,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Packet, insim::TinyType};
+
+    // Not an InSim packet, i.e. Outgauge sharing the UDP port. Must be discarded.
+    const JUNK: [u8; 92] = [0xff; 92];
+    // Tiny, TinyType::Ping, reqi=2
+    const PING: [u8; 4] = [1, 3, 2, 3];
+    // Tiny, TinyType::None, reqi=0
+    const KEEPALIVE: [u8; 4] = [1, 3, 0, 0];
+
+    fn udpport_from_isi(isi: &[u8; 44]) -> u16 {
+        u16::from_le_bytes([isi[4], isi[5]])
+    }
+
+    #[test]
+    fn test_isi_version() {
+        assert_eq!(Builder::default().isi(None).version, VERSION);
+
+        let builder = Builder::default().isi_version(crate::MIN_VERSION);
+        assert_eq!(builder.isi(None).version, crate::MIN_VERSION);
+        assert_eq!(builder.codec().version(), crate::MIN_VERSION);
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_tcp_udp_blocking() {
+        use std::io::Read;
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut isi = [0u8; 44];
+            stream.read_exact(&mut isi).unwrap();
+
+            let udp = std::net::UdpSocket::bind("127.0.0.1:0").unwrap();
+            let dest = ("127.0.0.1", udpport_from_isi(&isi));
+            let _ = udp.send_to(&JUNK, dest).unwrap();
+            let _ = udp.send_to(&PING, dest).unwrap();
+            stream
+        });
+
+        let mut framed = Builder::default()
+            .tcp_udp(addr, "127.0.0.1:0".parse::<SocketAddr>().unwrap())
+            .connect_blocking()
+            .unwrap();
+
+        assert!(matches!(
+            framed.read().unwrap(),
+            Packet::Tiny(crate::insim::Tiny {
+                subt: TinyType::Ping,
+                reqi: RequestId(2)
+            })
+        ));
+        drop(server.join().unwrap());
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_tcp_udp_async() {
+        use tokio::io::AsyncReadExt;
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut isi = [0u8; 44];
+            let _ = stream.read_exact(&mut isi).await.unwrap();
+
+            let udp = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+            let dest = ("127.0.0.1", udpport_from_isi(&isi));
+            let _ = udp.send_to(&JUNK, dest).await.unwrap();
+            let _ = udp.send_to(&PING, dest).await.unwrap();
+            stream
+        });
+
+        let mut framed = Builder::default()
+            .tcp_udp(addr, None)
+            .connect_async()
+            .await
+            .unwrap();
+
+        assert!(matches!(
+            framed.read().await.unwrap(),
+            Packet::Tiny(crate::insim::Tiny {
+                subt: TinyType::Ping,
+                reqi: RequestId(2)
+            })
+        ));
+        drop(server.await.unwrap());
+    }
+
+    #[cfg(feature = "blocking")]
+    #[test]
+    fn test_handshake_blocking() {
+        use std::{
+            io::{Cursor, Read, Write},
+            sync::{Arc, Mutex},
+        };
+
+        struct Pipe {
+            received: Cursor<Vec<u8>>,
+            sent: Arc<Mutex<Vec<u8>>>,
+        }
+
+        impl Read for Pipe {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                self.received.read(buf)
+            }
+        }
+
+        impl Write for Pipe {
+            fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+                self.sent.lock().unwrap().write(buf)
+            }
+
+            fn flush(&mut self) -> std::io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let sent = Arc::new(Mutex::new(Vec::new()));
+        let mut framed = Builder::default()
+            .handshake_blocking(Pipe {
+                received: Cursor::new([KEEPALIVE, PING].concat()),
+                sent: sent.clone(),
+            })
+            .unwrap();
+
+        assert!(matches!(
+            framed.read().unwrap(),
+            Packet::Tiny(crate::insim::Tiny {
+                subt: TinyType::None,
+                ..
+            })
+        ));
+        assert!(matches!(
+            framed.read().unwrap(),
+            Packet::Tiny(crate::insim::Tiny {
+                subt: TinyType::Ping,
+                reqi: RequestId(2)
+            })
+        ));
+        assert!(matches!(framed.read(), Err(crate::Error::Disconnected)));
+
+        // the keepalive is answered once everything buffered has been read
+        let sent = sent.lock().unwrap();
+        assert_eq!(sent[..2], [11, 1]);
+        assert_eq!(sent[44..], KEEPALIVE);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test]
+    async fn test_handshake_async() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let (client, mut server) = tokio::io::duplex(1024);
+        let mut framed = Builder::default().handshake_async(client).await.unwrap();
+
+        let mut isi = [0u8; 44];
+        let _ = server.read_exact(&mut isi).await.unwrap();
+        assert_eq!(isi[..2], [11, 1]);
+
+        server.write_all(&KEEPALIVE).await.unwrap();
+        server.write_all(&PING).await.unwrap();
+        assert!(matches!(
+            framed.read().await.unwrap(),
+            Packet::Tiny(crate::insim::Tiny {
+                subt: TinyType::None,
+                ..
+            })
+        ));
+        assert!(matches!(
+            framed.read().await.unwrap(),
+            Packet::Tiny(crate::insim::Tiny {
+                subt: TinyType::Ping,
+                reqi: RequestId(2)
+            })
+        ));
+
+        let mut keepalive = [0u8; 4];
+        let _ = server.read_exact(&mut keepalive).await.unwrap();
+        assert_eq!(keepalive, KEEPALIVE);
+
+        framed.shutdown().await.unwrap();
+        let mut close = Vec::new();
+        let _ = server.read_to_end(&mut close).await.unwrap();
+        assert_eq!(close, [1, 3, 0, 2]);
+    }
 }
@@ -0,0 +1,574 @@
+//! Record InSim sessions to disk and replay them later.
+//!
+//! A capture is a sequence of raw frames, exactly as produced by
+//! [crate::net::Codec::decode_with_frame] or [crate::net::Codec::encode], each tagged with a monotonic timestamp and the direction it
+//! travelled in. Because the raw frames are stored, a capture can always be decoded again, even
+//! if it was recorded by an application which did not understand every packet.
+//!
+//! ```rust,ignore
+//! use insim::capture::{CaptureWriter, Direction};
+//!
+//! let mut capture = CaptureWriter::new(std::fs::File::create("session.icap")?)?;
+//!
+//! // when using your own IO with Codec
+//! while let Some((packet, frame)) = codec.decode_with_frame()? {
+//!     capture.write_frame(Direction::Incoming, &frame)?;
+//! }
+//!
+//! // or when using Framed
+//! let packet = connection.read().await?;
+//! capture.write(Direction::Incoming, &packet)?;
+//! ```
+//!
+//! [Replay] reads a capture back, either as fast as possible or in real time:
+//!
+//! ```rust,ignore
+//! use insim::capture::{CaptureReader, Direction, Pace, Replay};
+//!
+//! let reader = CaptureReader::new(std::fs::File::open("session.icap")?)?;
+//! for captured in Replay::new(reader, Pace::Fast).direction(Direction::Incoming) {
+//!     let captured = captured?;
+//!     let events = world.apply_packet(&captured.packet);
+//! }
+//! ```
+//!
+//! # Format
+//!
+//! All integers are little endian.
+//!
+//! | Field      | Size | Description                                             |
+//! | ---------- | ---- | ------------------------------------------------------- |
+//! | magic      | 8    | `INSIMCAP`                                              |
+//! | format     | 1    | Capture format version, currently 1                     |
+//! | insimver   | 1    | [crate::VERSION] of the library which wrote the capture |
+//!
+//! Followed by zero or more records:
+//!
+//! | Field      | Size | Description                                     |
+//! | ---------- | ---- | ----------------------------------------------- |
+//! | timestamp  | 8    | Microseconds since the capture started          |
+//! | direction  | 1    | 0 = incoming (from LFS), 1 = outgoing (to LFS)  |
+//! | len        | 2    | Length of the frame                             |
+//! | frame      | len  | Raw frame, including the size byte              |
+use std::{
+    io::{self, Read, Write},
+    time::{Duration, Instant},
+};
+
+use bytes::Bytes;
+
+use crate::{
+    MIN_VERSION, Packet, Result, VERSION,
+    net::codec::{decode_frame, encode_packet, ver_insimver},
+};
+
+const MAGIC: &[u8; 8] = b"INSIMCAP";
+const FORMAT: u8 = 1;
+
+/// Which way a frame travelled.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    /// Received from LFS
+    Incoming,
+    /// Sent to LFS
+    Outgoing,
+}
+
+impl Direction {
+    fn to_u8(self) -> u8 {
+        match self {
+            Direction::Incoming => 0,
+            Direction::Outgoing => 1,
+        }
+    }
+
+    fn from_u8(value: u8) -> io::Result<Self> {
+        match value {
+            0 => Ok(Direction::Incoming),
+            1 => Ok(Direction::Outgoing),
+            other => Err(invalid_data(format!("unknown direction {other}"))),
+        }
+    }
+}
+
+/// A single raw frame from a capture.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    /// Time since the capture started
+    pub timestamp: Duration,
+    /// Which way the frame travelled
+    pub direction: Direction,
+    /// The raw frame, including the size byte
+    pub data: Bytes,
+}
+
+impl Frame {
+    /// Decode the frame into a [Packet], as Insim `version`. See [CaptureReader::version].
+    pub fn decode(&self, version: u8) -> Result<Packet> {
+        decode_frame(self.data.clone(), version)
+    }
+}
+
+/// A decoded packet from a capture.
+#[derive(Debug, Clone)]
+pub struct Captured {
+    /// Time since the capture started
+    pub timestamp: Duration,
+    /// Which way the packet travelled
+    pub direction: Direction,
+    /// The packet
+    pub packet: Packet,
+}
+
+/// Writes a capture.
+#[derive(Debug)]
+pub struct CaptureWriter<W: Write> {
+    inner: W,
+    started: Instant,
+    version: u8,
+}
+
+impl<W: Write> CaptureWriter<W> {
+    /// Create a new capture, writing the header immediately. Timestamps are measured from
+    /// this point.
+    pub fn new(mut inner: W) -> Result<Self> {
+        inner.write_all(MAGIC)?;
+        inner.write_all(&[FORMAT, VERSION])?;
+        Ok(Self {
+            inner,
+            started: Instant::now(),
+            version: VERSION,
+        })
+    }
+
+    /// The Insim version [CaptureWriter::write] encodes packets as. This is [VERSION], until a
+    /// [crate::insim::Ver] announcing an older version is recorded.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Encode and record a [Packet], timestamped now.
+    pub fn write(&mut self, direction: Direction, packet: &Packet) -> Result<()> {
+        let frame = encode_packet(packet, self.version)?;
+        self.write_frame(direction, &frame)
+    }
+
+    /// Record a raw frame, timestamped now. See [crate::net::Codec::decode_with_frame].
+    pub fn write_frame(&mut self, direction: Direction, frame: &[u8]) -> Result<()> {
+        let timestamp = self.started.elapsed();
+        self.write_frame_at(timestamp, direction, frame)
+    }
+
+    /// Record a raw frame with an explicit timestamp, measured from the start of the capture.
+    /// Useful when converting from other formats.
+    pub fn write_frame_at(
+        &mut self,
+        timestamp: Duration,
+        direction: Direction,
+        frame: &[u8],
+    ) -> Result<()> {
+        let len = u16::try_from(frame.len())
+            .map_err(|_| invalid_data(format!("frame too large: {} bytes", frame.len())))?;
+        let micros = u64::try_from(timestamp.as_micros()).unwrap_or(u64::MAX);
+
+        let mut header = [0u8; 11];
+        header[0..8].copy_from_slice(&micros.to_le_bytes());
+        header[8] = direction.to_u8();
+        header[9..11].copy_from_slice(&len.to_le_bytes());
+        self.inner.write_all(&header)?;
+        self.inner.write_all(frame)?;
+        if let Some(version) = negotiated(frame) {
+            self.version = version;
+        }
+        Ok(())
+    }
+
+    /// Flush the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.inner.flush()?;
+        Ok(())
+    }
+
+    /// Unwrap the underlying writer.
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+/// Reads raw [Frame]s from a capture.
+#[derive(Debug)]
+pub struct CaptureReader<R: Read> {
+    inner: R,
+    insimver: u8,
+    version: u8,
+}
+
+impl<R: Read> CaptureReader<R> {
+    /// Open a capture, reading and validating the header.
+    pub fn new(mut inner: R) -> Result<Self> {
+        let mut header = [0u8; 10];
+        inner.read_exact(&mut header)?;
+        if &header[0..8] != MAGIC {
+            return Err(invalid_data("not an insim capture").into());
+        }
+        if header[8] != FORMAT {
+            return Err(invalid_data(format!("unsupported capture format {}", header[8])).into());
+        }
+        Ok(Self {
+            inner,
+            insimver: header[9],
+            version: header[9],
+        })
+    }
+
+    /// The InSim version of the library which wrote this capture.
+    pub fn insimver(&self) -> u8 {
+        self.insimver
+    }
+
+    /// The Insim version the frames most recently read were encoded as. This is
+    /// [CaptureReader::insimver], until a [crate::insim::Ver] announcing an older version is read.
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Read the next frame, or None at the end of the capture.
+    pub fn read_frame(&mut self) -> Result<Option<Frame>> {
+        let mut header = [0u8; 11];
+        // A clean end of file is only permitted between records
+        match self.inner.read(&mut header[..1])? {
+            0 => return Ok(None),
+            _ => self.inner.read_exact(&mut header[1..])?,
+        }
+
+        let mut micros = [0u8; 8];
+        micros.copy_from_slice(&header[0..8]);
+        let timestamp = Duration::from_micros(u64::from_le_bytes(micros));
+        let direction = Direction::from_u8(header[8])?;
+        let len = u16::from_le_bytes([header[9], header[10]]) as usize;
+
+        let mut data = vec![0u8; len];
+        self.inner.read_exact(&mut data)?;
+        if let Some(version) = negotiated(&data) {
+            self.version = version.min(self.insimver);
+        }
+
+        Ok(Some(Frame {
+            timestamp,
+            direction,
+            data: data.into(),
+        }))
+    }
+}
+
+impl<R: Read> Iterator for CaptureReader<R> {
+    type Item = Result<Frame>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read_frame().transpose()
+    }
+}
+
+/// How quickly [Replay] yields packets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pace {
+    /// As fast as possible
+    #[default]
+    Fast,
+    /// With the same gaps between packets as when they were recorded
+    RealTime,
+}
+
+/// Replays a capture as decoded packets.
+///
+/// As an [Iterator] this blocks the current thread when using [Pace::RealTime]. With the tokio
+/// feature, [Replay::next_async] waits asynchronously instead.
+///
+/// A frame which fails to decode is returned as an error, and replay continues with the next
+/// frame. Once an IO error has been returned, replay ends.
+#[derive(Debug)]
+pub struct Replay<R: Read> {
+    reader: CaptureReader<R>,
+    pace: Pace,
+    direction: Option<Direction>,
+    // Timestamp of the first packet yielded
+    first: Option<Duration>,
+    started: Option<Instant>,
+    #[cfg(feature = "tokio")]
+    started_async: Option<tokio::time::Instant>,
+    done: bool,
+}
+
+impl<R: Read> Replay<R> {
+    /// Create a new replay.
+    pub fn new(reader: CaptureReader<R>, pace: Pace) -> Self {
+        Self {
+            reader,
+            pace,
+            direction: None,
+            first: None,
+            started: None,
+            #[cfg(feature = "tokio")]
+            started_async: None,
+            done: false,
+        }
+    }
+
+    /// Only replay packets travelling in this direction. Typically you will want
+    /// [Direction::Incoming] to feed the packets back into your application.
+    pub fn direction(mut self, direction: Direction) -> Self {
+        self.direction = Some(direction);
+        self
+    }
+
+    /// Read the next frame matching the direction filter, returning it along with how long
+    /// after the first frame it should be yielded.
+    fn next_frame(&mut self) -> Option<Result<(Frame, Option<Duration>)>> {
+        if self.done {
+            return None;
+        }
+        loop {
+            let frame = match self.reader.read_frame() {
+                Ok(Some(frame)) => frame,
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                },
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                },
+            };
+            if self.direction.is_some_and(|d| d != frame.direction) {
+                continue;
+            }
+
+            let offset = match self.pace {
+                Pace::Fast => None,
+                Pace::RealTime => {
+                    let first = *self.first.get_or_insert(frame.timestamp);
+                    Some(frame.timestamp.saturating_sub(first))
+                },
+            };
+            return Some(Ok((frame, offset)));
+        }
+    }
+
+    /// Wait asynchronously for the next packet.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    pub async fn next_async(&mut self) -> Option<Result<Captured>> {
+        let (frame, offset) = match self.next_frame()? {
+            Ok(next) => next,
+            Err(e) => return Some(Err(e)),
+        };
+        if let Some(offset) = offset {
+            let started = *self
+                .started_async
+                .get_or_insert_with(tokio::time::Instant::now);
+            tokio::time::sleep_until(started + offset).await;
+        }
+        Some(captured(frame, self.reader.version()))
+    }
+}
+
+impl<R: Read> Iterator for Replay<R> {
+    type Item = Result<Captured>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.next_frame()?.and_then(|(frame, offset)| {
+            if let Some(offset) = offset {
+                let started = *self.started.get_or_insert_with(Instant::now);
+                std::thread::sleep((started + offset).saturating_duration_since(Instant::now()));
+            }
+            captured(frame, self.reader.version())
+        }))
+    }
+}
+
+fn captured(frame: Frame, version: u8) -> Result<Captured> {
+    Ok(Captured {
+        timestamp: frame.timestamp,
+        direction: frame.direction,
+        packet: frame.decode(version)?,
+    })
+}
+
+/// The version negotiated by a [crate::insim::Ver] frame, if `frame` is one which we support.
+fn negotiated(frame: &[u8]) -> Option<u8> {
+    ver_insimver(frame).filter(|version| (MIN_VERSION..=VERSION).contains(version))
+}
+
+fn invalid_data<E>(error: E) -> io::Error
+where
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::{
+        Error,
+        identifiers::{ConnectionId, RequestId},
+        insim::{Ncn, Npl, RiFlags, Set, Tiny, TinyType, Ver},
+        net::Codec,
+    };
+
+    fn capture() -> Vec<u8> {
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        writer
+            .write_frame_at(
+                Duration::ZERO,
+                Direction::Outgoing,
+                &encode_packet(&TinyType::Ncn.into(), VERSION).unwrap(),
+            )
+            .unwrap();
+        writer
+            .write_frame_at(
+                Duration::from_millis(30),
+                Direction::Incoming,
+                &encode_packet(
+                    &Ncn {
+                        ucid: ConnectionId(2),
+                        ..Default::default()
+                    }
+                    .into(),
+                    VERSION,
+                )
+                .unwrap(),
+            )
+            .unwrap();
+        // keepalive, as received from Codec
+        let mut codec = Codec::new();
+        codec.feed(&[1, 3, 0, 0]);
+        let (_, frame) = codec.decode_with_frame().unwrap().unwrap();
+        writer
+            .write_frame_at(Duration::from_millis(60), Direction::Incoming, &frame)
+            .unwrap();
+        writer.into_inner()
+    }
+
+    #[test]
+    fn test_capture_roundtrip() {
+        let buf = capture();
+        assert_eq!(&buf[0..8], b"INSIMCAP");
+
+        let reader = CaptureReader::new(Cursor::new(buf)).unwrap();
+        assert_eq!(reader.insimver(), VERSION);
+        let frames: Vec<Frame> = reader.map(|f| f.unwrap()).collect();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[1].timestamp, Duration::from_millis(30));
+        assert_eq!(frames[1].direction, Direction::Incoming);
+        assert_eq!(&frames[2].data[..], &[1, 3, 0, 0]);
+        assert!(matches!(
+            frames[2].decode(VERSION).unwrap(),
+            Packet::Tiny(Tiny {
+                subt: TinyType::None,
+                reqi: RequestId(0)
+            })
+        ));
+    }
+
+    #[test]
+    fn test_replay_fast_with_direction() {
+        let reader = CaptureReader::new(Cursor::new(capture())).unwrap();
+        let packets: Vec<Captured> = Replay::new(reader, Pace::Fast)
+            .direction(Direction::Incoming)
+            .map(|c| c.unwrap())
+            .collect();
+        assert_eq!(packets.len(), 2);
+        assert!(matches!(&packets[0].packet, Packet::Ncn(ncn) if ncn.ucid == ConnectionId(2)));
+    }
+
+    #[test]
+    fn test_version_follows_ver() {
+        let npl = Npl {
+            riflags: RiFlags::LATE_START,
+            ..Default::default()
+        };
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        writer
+            .write(Direction::Incoming, &npl.clone().into())
+            .unwrap();
+        let ver = Ver {
+            insimver: MIN_VERSION,
+            ..Default::default()
+        };
+        writer.write(Direction::Incoming, &ver.into()).unwrap();
+        assert_eq!(writer.version(), MIN_VERSION);
+        // packets are now encoded as the older version
+        assert!(
+            writer
+                .write(Direction::Incoming, &Set::default().into())
+                .is_err()
+        );
+        let frame = encode_packet(&npl.into(), VERSION).unwrap();
+        writer
+            .write_frame_at(Duration::ZERO, Direction::Incoming, &frame)
+            .unwrap();
+
+        let reader = CaptureReader::new(Cursor::new(writer.into_inner())).unwrap();
+        assert_eq!(reader.version(), VERSION);
+        let packets: Vec<Packet> = Replay::new(reader, Pace::Fast)
+            .map(|c| c.unwrap().packet)
+            .collect();
+        assert!(matches!(&packets[0], Packet::Npl(npl) if npl.riflags == RiFlags::LATE_START));
+        assert!(matches!(&packets[1], Packet::Ver(_)));
+        // riflags are spare before Insim 10
+        assert!(matches!(&packets[2], Packet::Npl(npl) if npl.riflags.is_empty()));
+    }
+
+    #[test]
+    fn test_replay_real_time() {
+        let reader = CaptureReader::new(Cursor::new(capture())).unwrap();
+        let start = Instant::now();
+        assert_eq!(Replay::new(reader, Pace::RealTime).count(), 3);
+        assert!(start.elapsed() >= Duration::from_millis(60));
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_replay_real_time_async() {
+        let reader = CaptureReader::new(Cursor::new(capture())).unwrap();
+        let mut replay = Replay::new(reader, Pace::RealTime);
+        let start = tokio::time::Instant::now();
+        while let Some(captured) = replay.next_async().await {
+            let _ = captured.unwrap();
+        }
+        assert!(start.elapsed() >= Duration::from_millis(60));
+    }
+
+    #[test]
+    fn test_truncated_and_invalid() {
+        assert!(matches!(
+            CaptureReader::new(Cursor::new(b"NOTACAPTURE".to_vec())),
+            Err(Error::IO(_))
+        ));
+
+        let mut buf = capture();
+        let _ = buf.pop();
+        let results: Vec<_> = CaptureReader::new(Cursor::new(buf)).unwrap().collect();
+        assert_eq!(results.len(), 3);
+        assert!(results[2].is_err());
+
+        // a frame which does not decode is reported, and replay continues
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        writer
+            .write_frame_at(
+                Duration::ZERO,
+                Direction::Incoming,
+                &[1, 3, 0, 0, 0, 0, 0, 0],
+            )
+            .unwrap();
+        writer
+            .write_frame_at(Duration::ZERO, Direction::Incoming, &[1, 3, 0, 0])
+            .unwrap();
+        let reader = CaptureReader::new(Cursor::new(writer.into_inner())).unwrap();
+        let results: Vec<_> = Replay::new(reader, Pace::Fast).collect();
+        assert!(matches!(results[0], Err(Error::IncompleteDecode { .. })));
+        assert!(results[1].is_ok());
+    }
+}
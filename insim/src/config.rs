@@ -0,0 +1,415 @@
+//! Connection configuration which can be deserialised, i.e. from a TOML file, and converted into
+//! a [Builder].
+//!
+//! ```toml
+//! protocol = "tcp_udp"
+//! remote = "127.0.0.1:29999"
+//! connect_timeout = 5000
+//! flags = ["mci", "con"]
+//! prefix = "!"
+//! iname = "my-app"
+//! interval = 500
+//! admin_password_env = "INSIM_ADMIN"
+//! ```
+//!
+//! ```rust,ignore
+//! let config = insim::config::ConnectionConfig::from_toml(&std::fs::read_to_string(path)?)?;
+//! let conn = config.builder()?.connect_async().await?;
+//! ```
+use std::{net::SocketAddr, time::Duration};
+
+use crate::{builder::Builder, insim::IsiFlags};
+
+/// Maximum length of [ConnectionConfig::iname], as sent in the [crate::insim::Isi].
+const INAME_MAX_LEN: usize = 15;
+
+/// Maximum length of the admin password, as sent in the [crate::insim::Isi].
+const ADMIN_MAX_LEN: usize = 16;
+
+/// Transport used to connect to LFS. See [Builder::tcp], [Builder::udp] and [Builder::tcp_udp].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Protocol {
+    /// TCP only
+    #[default]
+    Tcp,
+    /// UDP only
+    Udp,
+    /// TCP, with [crate::Packet::Mci] and [crate::Packet::Nlp] over UDP
+    TcpUdp,
+}
+
+/// Error converting a [ConnectionConfig] into a [Builder], or parsing one.
+#[non_exhaustive]
+#[derive(thiserror::Error, Debug)]
+pub enum ConfigError {
+    /// A field has an invalid value
+    #[error("Invalid `{field}`: {reason}")]
+    Invalid {
+        /// Name of the field, as written in the configuration
+        field: &'static str,
+        /// Why the value is invalid
+        reason: String,
+    },
+
+    /// The configuration could not be parsed as TOML
+    #[cfg(feature = "toml")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "toml")))]
+    #[error("Failed to parse TOML: {0}")]
+    Toml(#[from] toml::de::Error),
+}
+
+impl ConfigError {
+    fn invalid(field: &'static str, reason: impl Into<String>) -> Self {
+        Self::Invalid {
+            field,
+            reason: reason.into(),
+        }
+    }
+
+    /// Name of the invalid field, if any.
+    pub fn field(&self) -> Option<&'static str> {
+        match self {
+            Self::Invalid { field, .. } => Some(field),
+            #[cfg(feature = "toml")]
+            Self::Toml(_) => None,
+        }
+    }
+}
+
+/// Everything required to connect to LFS, in a form which can be deserialised. Every field is
+/// optional, defaulting to the same value as [Builder]. Unknown fields are rejected.
+///
+/// Use [ConnectionConfig::builder] to validate and convert into a [Builder].
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ConnectionConfig {
+    /// Transport to connect using
+    pub protocol: Protocol,
+
+    /// Remote address of LFS, as `host:port`
+    pub remote: String,
+
+    /// Local address to bind the UDP socket to, when using [Protocol::Udp] or
+    /// [Protocol::TcpUdp]. Defaults to any address and a random port.
+    pub local: Option<SocketAddr>,
+
+    /// Connection timeout, in milliseconds
+    #[serde(with = "crate::duration_serde")]
+    pub connect_timeout: Duration,
+
+    /// Whether `TCP_NODELAY` is enabled
+    pub tcp_nodelay: bool,
+
+    /// Names of the [IsiFlags] to set, case insensitive, i.e. `["mci", "con"]`
+    pub flags: Vec<String>,
+
+    /// Special host message prefix, a single ASCII character
+    pub prefix: Option<char>,
+
+    /// Name of the program, as shown in LFS
+    pub iname: Option<String>,
+
+    /// Interval between [crate::Packet::Mci] or [crate::Packet::Nlp] packets, in milliseconds
+    #[serde(with = "crate::duration_serde::option")]
+    pub interval: Option<Duration>,
+
+    /// udpport sent in the [crate::insim::Isi], see [Builder::isi_udpport]
+    pub udpport: Option<u16>,
+
+    /// Admin password of the LFS host. Prefer [ConnectionConfig::admin_password_env] to
+    /// avoid storing the password alongside the rest of the configuration.
+    pub admin_password: Option<String>,
+
+    /// Name of an environment variable to read the admin password from
+    pub admin_password_env: Option<String>,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            protocol: Protocol::default(),
+            remote: "127.0.0.1:29999".to_owned(),
+            local: None,
+            connect_timeout: Duration::from_secs(10),
+            tcp_nodelay: true,
+            flags: Vec::new(),
+            prefix: None,
+            iname: None,
+            interval: None,
+            udpport: None,
+            admin_password: None,
+            admin_password_env: None,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    /// Parse from TOML. The result is not validated until [ConnectionConfig::builder] is
+    /// called.
+    #[cfg(feature = "toml")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "toml")))]
+    pub fn from_toml(s: &str) -> Result<Self, ConfigError> {
+        Ok(toml::from_str(s)?)
+    }
+
+    /// Validate and convert into a [Builder], reading the admin password from the environment
+    /// if [ConnectionConfig::admin_password_env] is set.
+    pub fn builder(&self) -> Result<Builder, ConfigError> {
+        self.builder_with_env(|name| std::env::var(name).ok())
+    }
+
+    /// As [ConnectionConfig::builder], looking up environment variables using `env`.
+    pub fn builder_with_env<F>(&self, env: F) -> Result<Builder, ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let remote = self.remote()?;
+        if self.local.is_some() && self.protocol == Protocol::Tcp {
+            return Err(ConfigError::invalid(
+                "local",
+                "only used with the udp or tcp_udp protocol",
+            ));
+        }
+        if self.connect_timeout.is_zero() {
+            return Err(ConfigError::invalid("connect_timeout", "must be non-zero"));
+        }
+        if let Some(prefix) = self.prefix
+            && !prefix.is_ascii()
+        {
+            return Err(ConfigError::invalid(
+                "prefix",
+                format!("{prefix:?} is not an ASCII character"),
+            ));
+        }
+        if let Some(iname) = &self.iname
+            && iname.len() > INAME_MAX_LEN
+        {
+            return Err(ConfigError::invalid(
+                "iname",
+                format!("must be at most {INAME_MAX_LEN} bytes"),
+            ));
+        }
+        if let Some(interval) = self.interval
+            && interval.as_millis() > u16::MAX as u128
+        {
+            return Err(ConfigError::invalid(
+                "interval",
+                format!("must be at most {}ms", u16::MAX),
+            ));
+        }
+
+        let builder = match self.protocol {
+            Protocol::Tcp => Builder::default().tcp(remote),
+            Protocol::Udp => Builder::default().udp(remote, self.local),
+            Protocol::TcpUdp => Builder::default().tcp_udp(remote, self.local),
+        };
+
+        Ok(builder
+            .connect_timeout(self.connect_timeout)
+            .tcp_nodelay(self.tcp_nodelay)
+            .isi_flags(self.isi_flags()?)
+            .isi_prefix(self.prefix)
+            .isi_iname(self.iname.clone())
+            .isi_interval(self.interval)
+            .isi_udpport(self.udpport)
+            .isi_admin_password(self.admin_password(env)?))
+    }
+
+    fn remote(&self) -> Result<String, ConfigError> {
+        if self.remote.parse::<SocketAddr>().is_ok() {
+            return Ok(self.remote.clone());
+        }
+        match self.remote.rsplit_once(':') {
+            Some((host, port)) if !host.is_empty() && port.parse::<u16>().is_ok() => {
+                Ok(self.remote.clone())
+            },
+            _ => Err(ConfigError::invalid(
+                "remote",
+                format!("expected host:port, found {:?}", self.remote),
+            )),
+        }
+    }
+
+    fn isi_flags(&self) -> Result<IsiFlags, ConfigError> {
+        self.flags
+            .iter()
+            .try_fold(IsiFlags::empty(), |flags, name| {
+                IsiFlags::from_name(&name.to_ascii_uppercase())
+                    .map(|flag| flags | flag)
+                    .ok_or_else(|| ConfigError::invalid("flags", format!("unknown flag {name:?}")))
+            })
+    }
+
+    fn admin_password<F>(&self, env: F) -> Result<Option<String>, ConfigError>
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let password = match (&self.admin_password, &self.admin_password_env) {
+            (Some(_), Some(_)) => {
+                return Err(ConfigError::invalid(
+                    "admin_password_env",
+                    "cannot be used together with admin_password",
+                ));
+            },
+            (Some(password), None) => password.clone(),
+            (None, Some(name)) => env(name).ok_or_else(|| {
+                ConfigError::invalid(
+                    "admin_password_env",
+                    format!("environment variable {name:?} is not set"),
+                )
+            })?,
+            (None, None) => return Ok(None),
+        };
+        if password.len() > ADMIN_MAX_LEN {
+            let field = if self.admin_password.is_some() {
+                "admin_password"
+            } else {
+                "admin_password_env"
+            };
+            return Err(ConfigError::invalid(
+                field,
+                format!("must be at most {ADMIN_MAX_LEN} bytes"),
+            ));
+        }
+        Ok(Some(password))
+    }
+}
+
+impl TryFrom<ConnectionConfig> for Builder {
+    type Error = ConfigError;
+
+    fn try_from(value: ConnectionConfig) -> Result<Self, Self::Error> {
+        value.builder()
+    }
+}
+
+impl TryFrom<&ConnectionConfig> for Builder {
+    type Error = ConfigError;
+
+    fn try_from(value: &ConnectionConfig) -> Result<Self, Self::Error> {
+        value.builder()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn no_env(_: &str) -> Option<String> {
+        None
+    }
+
+    fn field(config: ConnectionConfig) -> Option<&'static str> {
+        config.builder_with_env(no_env).unwrap_err().field()
+    }
+
+    #[test]
+    fn test_builder() {
+        let config = ConnectionConfig {
+            flags: vec!["mci".into(), "CON".into()],
+            prefix: Some('!'),
+            iname: Some("test".into()),
+            interval: Some(Duration::from_millis(500)),
+            admin_password_env: Some("ADMIN".into()),
+            ..Default::default()
+        };
+        let builder = config
+            .builder_with_env(|name| (name == "ADMIN").then(|| "secret".to_owned()))
+            .unwrap();
+
+        let isi = builder.isi(None);
+        assert_eq!(isi.flags, IsiFlags::MCI | IsiFlags::CON);
+        assert_eq!(isi.prefix, '!');
+        assert_eq!(isi.iname, "test");
+        assert_eq!(isi.interval, Duration::from_millis(500));
+        assert_eq!(isi.admin, "secret");
+    }
+
+    #[test]
+    fn test_validation_names_field() {
+        assert_eq!(
+            field(ConnectionConfig {
+                remote: "localhost".into(),
+                ..Default::default()
+            }),
+            Some("remote")
+        );
+        assert_eq!(
+            field(ConnectionConfig {
+                local: Some("0.0.0.0:0".parse().unwrap()),
+                ..Default::default()
+            }),
+            Some("local")
+        );
+        assert_eq!(
+            field(ConnectionConfig {
+                flags: vec!["nope".into()],
+                ..Default::default()
+            }),
+            Some("flags")
+        );
+        assert_eq!(
+            field(ConnectionConfig {
+                prefix: Some('é'),
+                ..Default::default()
+            }),
+            Some("prefix")
+        );
+        assert_eq!(
+            field(ConnectionConfig {
+                iname: Some("a very long program name".into()),
+                ..Default::default()
+            }),
+            Some("iname")
+        );
+        assert_eq!(
+            field(ConnectionConfig {
+                interval: Some(Duration::from_secs(100)),
+                ..Default::default()
+            }),
+            Some("interval")
+        );
+        assert_eq!(
+            field(ConnectionConfig {
+                admin_password_env: Some("ADMIN".into()),
+                ..Default::default()
+            }),
+            Some("admin_password_env")
+        );
+        assert_eq!(
+            field(ConnectionConfig {
+                admin_password: Some("a".into()),
+                admin_password_env: Some("ADMIN".into()),
+                ..Default::default()
+            }),
+            Some("admin_password_env")
+        );
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_from_toml() {
+        let config = ConnectionConfig::from_toml(
+            r#"
+            protocol = "tcp_udp"
+            remote = "lfs.example.com:29999"
+            local = "0.0.0.0:30000"
+            connect_timeout = 5000
+            flags = ["mci", "nlp"]
+            interval = 1000
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.protocol, Protocol::TcpUdp);
+        assert_eq!(config.connect_timeout, Duration::from_secs(5));
+        assert_eq!(config.interval, Some(Duration::from_secs(1)));
+        assert!(config.tcp_nodelay);
+        assert!(config.builder_with_env(no_env).is_ok());
+
+        assert!(matches!(
+            ConnectionConfig::from_toml("unknown = 1"),
+            Err(ConfigError::Toml(_))
+        ));
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use bytes::Bytes;
 
 #[non_exhaustive]
@@ -5,15 +7,22 @@ use bytes::Bytes;
 /// Errors that may occur during an InSim connection.
 ///
 /// Most variants are fatal - once returned, the connection is no longer usable and you
-/// should reconnect via [`crate::builder::Builder`]. The exceptions are [`Error::Encode`],
-/// [`Error::VehicleNotAMod`], and [`Error::VehicleNotStandard`], which indicate a problem
-/// with a packet you tried to send and leave the connection intact.
+/// should reconnect via [`crate::builder::Builder`], or let `net::supervised::Supervised`
+/// do it for you. The exceptions are [`Error::Encode`], [`Error::VehicleNotAMod`], and
+/// [`Error::VehicleNotStandard`], which indicate a problem with a packet you tried to send
+/// and leave the connection intact.
 pub enum Error {
     /// Connection is disconnected
     #[error("Disconnected")]
     Disconnected,
 
-    /// Connection has detected in incompatible version
+    /// A spawned connection stopped because of the wrapped error. The same error is shared by
+    /// every handle and subscriber.
+    #[error("Connection stopped: {0}")]
+    Stopped(Arc<Error>),
+
+    /// Connection has detected in incompatible version, outside of [crate::MIN_VERSION] and
+    /// [crate::VERSION]
     #[error("Unsupported Insim version: received {0:?}")]
     IncompatibleVersion(u8),
 
@@ -55,6 +64,11 @@ pub enum Error {
         error: insim_core::DecodeError,
     },
 
+    /// A datagram was not recognised as InSim, OutGauge or OutSim, see
+    /// `net::demux::Demux`
+    #[error("Unrecognised datagram of {} bytes", .0.len())]
+    UnrecognisedDatagram(Bytes),
+
     /// Partial decode
     #[error("Partial decode. Likely invalid packet definition. Decoded {:?}, remaining {:?}", input.as_ref(), remaining.as_ref())]
     IncompleteDecode {
@@ -14,6 +14,13 @@ impl ClickId {
     pub const MAX: u8 = 239;
 }
 
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for ClickId {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self(u.int_in_range(0..=Self::MAX)?))
+    }
+}
+
 impl fmt::Display for ClickId {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}", self.0)
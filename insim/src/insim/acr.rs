@@ -4,6 +4,7 @@ use crate::identifiers::{ConnectionId, RequestId};
 #[repr(u8)]
 #[derive(Debug, Default, Clone, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[non_exhaustive]
 pub enum AcrResult {
@@ -23,6 +24,7 @@ pub enum AcrResult {
 /// - Contains the raw command text and result.
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Acr {
     /// Request identifier echoed by replies.
@@ -41,6 +43,7 @@ pub struct Acr {
 
     /// Command text.
     #[insim(codepage(length = 64, align_to = 4, trailing_nul = true))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<63>))]
     pub text: String,
 }
 
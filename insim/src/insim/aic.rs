@@ -10,6 +10,7 @@ const AIC_MAX_INPUTS: usize = 20;
 
 bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// AI help and assist flags.
     pub struct AiHelpFlags: u16 {
         /// Autogears
@@ -26,6 +27,7 @@ impl_bitflags_from_to_bytes!(AiHelpFlags, u16);
 
 #[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[non_exhaustive]
 /// Toggle mode for AI controls.
@@ -61,6 +63,7 @@ impl From<AiInputToggle> for u16 {
 
 #[derive(Debug, PartialEq, Eq, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[non_exhaustive]
 /// AI input command.
@@ -144,6 +147,7 @@ pub enum AiInputType {
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// AI input control value with optional duration.
 pub struct AiInput {
@@ -153,6 +157,7 @@ pub struct AiInput {
     /// Duration for the input (if supported), in milliseconds.
     #[cfg_attr(feature = "serde", serde(with = "crate::duration_serde::option"))]
     #[cfg_attr(feature = "schemars", schemars(with = "Option<u64>"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_time))]
     pub time: Option<Duration>,
 }
 
@@ -406,6 +411,13 @@ impl AiInput {
     }
 }
 
+/// Hundredths of a second which fit within a u8, where 0 means no time.
+#[cfg(feature = "arbitrary")]
+fn arbitrary_time(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<Option<Duration>> {
+    let centis = u.int_in_range(0..=u8::MAX)?;
+    Ok((centis > 0).then(|| Duration::from_millis(centis as u64 * 10)))
+}
+
 impl Decode for AiInput {
     fn decode(ctx: &mut DecodeContext) -> Result<Self, insim_core::DecodeError> {
         let input = ctx.decode::<u8>("input")?;
@@ -514,6 +526,7 @@ impl Encode for AiInput {
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// AI input control packet.
 ///
@@ -526,6 +539,10 @@ pub struct Aic {
     pub plid: PlayerId,
 
     /// Inputs to send (use [AiInput] helpers to build common inputs).
+    #[cfg_attr(
+        feature = "arbitrary",
+        arbitrary(with = insim_core::arbitrary_helpers::vec::<_, AIC_MAX_INPUTS>)
+    )]
     pub inputs: Vec<AiInput>,
 }
 
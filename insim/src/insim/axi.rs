@@ -6,6 +6,7 @@ use crate::identifiers::RequestId;
 /// - Can be requested via [`TinyType::Axi`](crate::insim::TinyType::Axi).
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Axi {
     /// Request identifier echoed by replies.
@@ -23,6 +24,7 @@ pub struct Axi {
 
     /// Name of the last loaded layout (if loaded locally).
     #[insim(codepage(length = 32))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::optional_string::<32>))]
     pub lname: Option<String>,
 }
 
@@ -8,6 +8,7 @@ pub use insim_core::object::ObjectInfo;
 
 #[derive(Debug, Default, Clone, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(u8)]
 enum PmoActionWire {
@@ -25,6 +26,7 @@ enum PmoActionWire {
 
 bitflags::bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Flags for an [`Axm`] packet.
     pub struct PmoFlags: u8 {
         /// LFS has reached the end of a layout file, or (on [`PmoAction::AddObjects`])
@@ -55,6 +57,7 @@ impl_bitflags_from_to_bytes!(PmoFlags, u8);
 /// `adjusted` indicates whether the adjustment succeeded.
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct GetZEntry {
     /// Position (X, Y, input or output Z).
@@ -69,29 +72,48 @@ pub struct GetZEntry {
 /// live on [`Axm::flags`] rather than inside the variant.
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[non_exhaustive]
 pub enum PmoAction {
     /// Sent by the layout loading system while loading a file.
-    LoadingFile(Vec<ObjectInfo>),
+    LoadingFile(
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::vec::<_, AXM_MAX_OBJECTS>))]
+         Vec<ObjectInfo>,
+    ),
 
     /// Add objects to the layout.
-    AddObjects(Vec<ObjectInfo>),
+    AddObjects(
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::vec::<_, AXM_MAX_OBJECTS>))]
+         Vec<ObjectInfo>,
+    ),
 
     /// Delete objects from the layout.
-    DelObjects(Vec<ObjectInfo>),
+    DelObjects(
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::vec::<_, AXM_MAX_OBJECTS>))]
+         Vec<ObjectInfo>,
+    ),
 
     /// Remove all objects from the layout.
     ClearAll,
 
     /// Reply to [`TinyType::Axm`](crate::insim::TinyType::Axm).
-    TinyAxm(Vec<ObjectInfo>),
+    TinyAxm(
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::vec::<_, AXM_MAX_OBJECTS>))]
+         Vec<ObjectInfo>,
+    ),
 
     /// Reply to [`TtcType::Sel`](crate::insim::TtcType::Sel).
-    TtcSel(Vec<ObjectInfo>),
+    TtcSel(
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::vec::<_, AXM_MAX_OBJECTS>))]
+         Vec<ObjectInfo>,
+    ),
 
     /// Set or report a connection's layout editor selection.
-    Selection(Vec<ObjectInfo>),
+    Selection(
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::vec::<_, AXM_MAX_OBJECTS>))]
+         Vec<ObjectInfo>,
+    ),
 
     /// User pressed 'O' without anything selected; reports current editor position.
     ///
@@ -107,7 +129,10 @@ pub enum PmoAction {
     ///
     /// Send entries with suggested Zbyte values; receive them back with adjusted
     /// Zbyte values and [`GetZEntry::adjusted`] set to indicate success.
-    GetZ(Vec<GetZEntry>),
+    GetZ(
+        #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::vec::<_, AXM_MAX_OBJECTS>))]
+         Vec<GetZEntry>,
+    ),
 }
 
 impl Default for PmoAction {
@@ -122,6 +147,7 @@ impl Default for PmoAction {
 /// [`flags`](Axm::flags); the action and its data are on [`action`](Axm::action).
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Axm {
     /// Request identifier echoed by replies.
@@ -269,7 +295,10 @@ impl Encode for Axm {
 
 #[cfg(test)]
 mod test {
-    use insim_core::object::control::{Control, ControlKind};
+    use insim_core::object::{
+        control::{Control, ControlKind},
+        marshal::RouteChecker,
+    };
 
     use super::*;
 
@@ -324,4 +353,42 @@ mod test {
             }
         )
     }
+
+    #[test]
+    fn test_axm_route_checker() {
+        assert_from_to_bytes!(
+            Axm,
+            [
+                0,   // reqi
+                1,   // numo
+                0,   // ucid
+                1,   // pmoaction (AddObjects)
+                0,   // pmoflags
+                0,   // sp3
+                172, // info[1] - x (1)
+                218, // info[1] - x (2)
+                25,  // info[1] - y (1)
+                136, // info[1] - y (2)
+                8,   // info[1] - zbyte
+                168, // info[1] - flags (floating | radius 10 << 2)
+                255, // info[1] - objectindex (route checker)
+                3,   // info[1] - heading (route index)
+            ],
+            |axm: Axm| {
+                let PmoAction::AddObjects(info) = axm.action else {
+                    panic!("expected AddObjects action");
+                };
+
+                assert!(matches!(
+                    info[0],
+                    ObjectInfo::RouteChecker(RouteChecker {
+                        route: 3,
+                        radius: 10,
+                        floating: true,
+                        ..
+                    })
+                ));
+            }
+        )
+    }
 }
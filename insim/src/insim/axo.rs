@@ -5,6 +5,7 @@ use crate::identifiers::{PlayerId, RequestId};
 /// - Sent when an autocross object is hit.
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct Axo {
     /// Request identifier echoed by replies.
@@ -8,6 +8,7 @@ const BTN_TEXT_ALIGN: usize = 4;
 
 bitflags::bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Bitwise flags used within [Btn], [Btc], and [Btt].
     ///
     /// - Mostly internal, but includes user-visible behavior.
@@ -23,6 +24,7 @@ impl_bitflags_from_to_bytes!(BtnInst, u8);
 /// Colour
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub enum BtnStyleColour {
     /// NotEditable, defaults to light grey
@@ -47,6 +49,7 @@ pub enum BtnStyleColour {
 bitflags::bitflags! {
     /// Button style flags for [Btn].
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     pub struct BtnStyleFlags: u8 {
         /// Clickable button (sends [Btc]).
         const CLICK = (1 << 3);
@@ -68,6 +71,7 @@ impl_bitflags_json_schema!(BtnStyleFlags, "BtnStyleFlag");
 
 #[derive(Debug, Clone, Copy, Default, PartialEq, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Button style configuration (colour + flags).
 pub struct BtnStyle {
@@ -226,6 +230,7 @@ impl Decode for BtnStyle {
 bitflags::bitflags! {
     /// Bitwise flags reported for a button click.
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     pub struct BtnClickFlags: u8 {
         /// Left click
         const LMB = (1 << 0);
@@ -246,6 +251,7 @@ impl_bitflags_from_to_bytes!(BtnClickFlags, u8);
 
 #[derive(Debug, Default, Clone, PartialEq, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(u8)]
 #[non_exhaustive]
@@ -267,6 +273,7 @@ pub enum BfnType {
 
 #[derive(Debug, Clone, Default, PartialEq, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Button management command or notification.
 ///
@@ -338,6 +345,43 @@ pub struct Btn {
     pub text: String,
 }
 
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Btn {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        use insim_core::arbitrary_helpers::string;
+
+        // Caption and text share a single field, separated by \0
+        let (caption, text) = if u.arbitrary()? {
+            (
+                Some(string::<{ (BTN_TEXT_MAX_LEN - 2) / 2 }>(u)?),
+                string::<{ (BTN_TEXT_MAX_LEN - 2) / 2 }>(u)?,
+            )
+        } else {
+            (None, string::<BTN_TEXT_MAX_LEN>(u)?)
+        };
+        Ok(Self {
+            reqi: u.arbitrary()?,
+            ucid: u.arbitrary()?,
+            clickid: u.arbitrary()?,
+            inst: u.arbitrary()?,
+            bstyle: u.arbitrary()?,
+            typein: arbitrary_typein(u)?,
+            l: u.int_in_range(0..=200)?,
+            t: u.int_in_range(0..=200)?,
+            w: u.int_in_range(1..=200)?,
+            h: u.int_in_range(1..=200)?,
+            caption,
+            text,
+        })
+    }
+}
+
+/// Input limits which survive encoding, where 0 means no input.
+#[cfg(feature = "arbitrary")]
+fn arbitrary_typein(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<Option<u8>> {
+    Ok(Some(u.int_in_range(0..=96)?).filter(|typein| *typein > 0))
+}
+
 impl Decode for Btn {
     fn decode(ctx: &mut DecodeContext) -> Result<Self, insim_core::DecodeError> {
         let reqi = ctx.decode::<RequestId>("reqi")?;
@@ -491,6 +535,7 @@ impl_typical_with_request_id!(Btn);
 
 #[derive(Debug, Clone, Default, PartialEq, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Sent when a user clicks a button.
 ///
@@ -513,6 +558,7 @@ pub struct Btc {
 
 #[derive(Debug, Clone, PartialEq, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Sent when a user types into a text entry button.
 ///
@@ -530,9 +576,11 @@ pub struct Btt {
     pub inst: BtnInst,
 
     /// Original input limit from [Btn].
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_typein))]
     pub typein: Option<u8>,
 
     /// Entered text.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<96>))]
     pub text: String,
 }
 
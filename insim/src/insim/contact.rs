@@ -13,6 +13,7 @@ use crate::identifiers::{PlayerId, RequestId};
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Per-car contact details used in [Con].
 pub struct ConInfo {
@@ -26,18 +27,23 @@ pub struct ConInfo {
     pub steer: i8,
 
     /// Throttle input (0-15).
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::up_to::<15>))]
     pub thr: u8,
 
     /// Brake input (0-15).
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::up_to::<15>))]
     pub brk: u8,
 
     /// Clutch input (0-15).
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::up_to::<15>))]
     pub clu: u8,
 
     /// Handbrake input (0-15).
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::up_to::<15>))]
     pub han: u8,
 
     /// Gear selector (0-15).
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::up_to::<15>))]
     pub gearsp: u8,
 
     /// Speed.
@@ -189,6 +195,7 @@ impl Encode for ConInfo {
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Vehicle-to-vehicle contact report.
 ///
@@ -203,6 +210,7 @@ pub struct Con {
     /// Time since session start, in milliseconds (wraps periodically).
     #[cfg_attr(feature = "serde", serde(with = "crate::duration_serde"))]
     #[cfg_attr(feature = "schemars", schemars(with = "u64"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::millis::<u32>))]
     pub time: Duration,
 
     /// Contact information for vehicle A.
@@ -238,6 +246,7 @@ impl Encode for Con {
         ctx.encode("reqi", &self.reqi)?;
         ctx.pad("sp0", 1)?;
         ctx.encode("spclose", &self.spclose)?;
+        ctx.pad("spw", 2)?;
         ctx.encode_duration::<u32>("time", self.time)?;
         ctx.encode("a", &self.a)?;
         ctx.encode("b", &self.b)?;
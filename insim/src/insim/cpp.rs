@@ -9,6 +9,7 @@ use crate::identifiers::{PlayerId, RequestId};
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Camera Position Pack reports the current camera position and state. This packet may also be
 /// sent to control the camera.
@@ -40,6 +41,7 @@ pub struct Cpp {
     /// Time in milliseconds to get there (0 means instant).
     #[cfg_attr(feature = "serde", serde(with = "crate::duration_serde"))]
     #[cfg_attr(feature = "schemars", schemars(with = "u64"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::millis::<u16>))]
     pub time: Duration,
 
     /// State flags to set
@@ -2,6 +2,7 @@ use crate::identifiers::{ConnectionId, RequestId};
 
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Connection rename or plate change notification.
 ///
@@ -15,10 +16,12 @@ pub struct Cpr {
 
     #[insim(codepage(length = 24))]
     /// New player name.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<24>))]
     pub pname: String,
 
     #[insim(codepage(length = 8))]
     /// New number plate.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<8>))]
     pub plate: String,
 }
 
@@ -5,6 +5,7 @@ use crate::identifiers::{PlayerId, RequestId};
 
 bitflags::bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Result confirmation flags for finishes and results.
     pub struct RaceConfirmFlags: u8 {
         /// Mentioned
@@ -67,6 +68,7 @@ impl RaceConfirmFlags {
 
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Provisional finish notification.
 ///
@@ -82,12 +84,14 @@ pub struct Fin {
     /// Total race time in milliseconds.
     #[cfg_attr(feature = "serde", serde(with = "crate::duration_serde"))]
     #[cfg_attr(feature = "schemars", schemars(with = "u64"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::millis::<u32>))]
     pub ttime: Duration,
 
     #[insim(duration = u32, pad_after = 1)]
     /// Best lap time in milliseconds.
     #[cfg_attr(feature = "serde", serde(with = "crate::duration_serde"))]
     #[cfg_attr(feature = "schemars", schemars(with = "u64"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::millis::<u32>))]
     pub btime: Duration,
 
     /// Number of pit stops.
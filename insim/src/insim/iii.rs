@@ -2,6 +2,7 @@ use crate::identifiers::{ConnectionId, PlayerId, RequestId};
 
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Message sent by a user with the `/i` command.
 ///
@@ -21,6 +22,7 @@ pub struct Iii {
 
     /// Message text.
     #[insim(codepage(length = 64, align_to = 4, trailing_nul = true))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<63>))]
     pub msg: String,
 }
 
@@ -9,6 +9,7 @@ const IPB_MAX_BANS: usize = 120;
 
 #[derive(Debug, Clone, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// IP ban list (host only).
 ///
@@ -18,9 +19,16 @@ pub struct Ipb {
     pub reqi: RequestId,
 
     #[cfg_attr(feature = "schemars", schemars(with = "Vec<std::net::Ipv4Addr>"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_banips))]
     banips: IndexSet<Ipv4Addr>,
 }
 
+#[cfg(feature = "arbitrary")]
+fn arbitrary_banips(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<IndexSet<Ipv4Addr>> {
+    let ips: Vec<Ipv4Addr> = insim_core::arbitrary_helpers::vec::<_, IPB_MAX_BANS>(u)?;
+    Ok(ips.into_iter().collect())
+}
+
 impl Ipb {
     /// Returns `true` if an IP is contained in this list.
     pub fn contains(&self, v: &Ipv4Addr) -> bool {
@@ -7,6 +7,7 @@ use crate::{VERSION, WithRequestId, identifiers::RequestId};
 bitflags! {
     /// Flags for the [Isi] options field.
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Flags for [Isi], used to indicate which updates and behaviours to enable.
     pub struct IsiFlags: u16 {
         /// Guest or single player
@@ -39,7 +40,7 @@ bitflags! {
         /// Process join requests
         const REQ_JOIN = (1 << 11);
 
-        /// Receive SET packets from guests who sent their setup to the host
+        /// Receive SET packets from guests who sent their setup to the host. Requires Insim 10.
         const SET = (1 << 12);
     }
 }
@@ -65,6 +66,7 @@ impl_bitflags_from_to_bytes!(IsiFlags, u16);
 
 #[derive(Debug, Clone, Eq, PartialEq, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// InSim init/handshake packet sent to start communication.
 ///
@@ -92,20 +94,24 @@ pub struct Isi {
 
     /// Special host message prefix (single ASCII character).
     /// Messages typed with this prefix are forwarded to InSim and not shown in chat.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::ascii_char))]
     pub prefix: char,
 
     /// Interval between [Nlp](super::Nlp) or [Mci](super::Mci) updates, in milliseconds (0 = disabled).
     #[insim(duration = u16)]
     #[cfg_attr(feature = "serde", serde(with = "crate::duration_serde"))]
     #[cfg_attr(feature = "schemars", schemars(with = "u64"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::millis::<u16>))]
     pub interval: Duration,
 
     /// Admin password (empty if none).
     #[insim(codepage(length = 16))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<16>))]
     pub admin: String,
 
     /// Short program name shown to LFS.
     #[insim(codepage(length = 16, trailing_nul = true))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<15>))]
     pub iname: String,
 }
 
@@ -6,6 +6,7 @@ use crate::identifiers::{ConnectionId, PlayerId, RequestId};
 
 #[derive(Debug, Default, Clone, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(u8)]
 #[non_exhaustive]
@@ -27,6 +28,7 @@ pub enum JrrAction {
 
 #[derive(Debug, Clone)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Start position selection used by [`Jrr`].
 pub enum JrrStartPosition {
@@ -48,10 +50,11 @@ impl Decode for JrrStartPosition {
         let z = ctx.decode::<u8>("z")?;
 
         let flags = ctx.decode::<u8>("flags")?;
-        let index = ctx.decode::<u8>("index")?;
+        ctx.pad("index", 1)?;
         let heading = ctx.decode::<u8>("heading")?;
 
-        if x == 0 && y == 0 && z == 8 && flags == 0 && index == 0 && heading == 0 {
+        // LFS only uses the start position when the 0x80 flag is set
+        if flags & 0x80 == 0 {
             Ok(Self::DefaultStartPosition)
         } else {
             Ok(Self::Custom {
@@ -81,6 +84,7 @@ impl Encode for JrrStartPosition {
 
 #[derive(Debug, Clone, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Join Request Reply
 /// Set the ISF_REQ_JOIN flag in the IS_ISI to receive join requests
@@ -154,4 +158,42 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn test_jrr_default_start_position() {
+        assert_from_to_bytes!(
+            Jrr,
+            [
+                0, // reqi
+                0, // plid
+                2, // ucid
+                1, // jrraction
+                0, // sp2
+                0, // sp3
+                0, // startpos - x (1)
+                0, // startpos - x (2)
+                0, // startpos - y (1)
+                0, // startpos - y (2)
+                0, // startpos - zbyte (1)
+                0, // startpos - flags
+                0, // startpos - index
+                0, // startpos - heading
+            ],
+            |jrr: Jrr| {
+                assert!(matches!(jrr.jrraction, JrrAction::Spawn));
+                assert!(matches!(
+                    jrr.startpos,
+                    JrrStartPosition::DefaultStartPosition
+                ));
+            }
+        );
+    }
+
+    #[test]
+    fn test_jrr_start_position_requires_flag() {
+        // without 0x80 in flags LFS ignores the start position
+        let mut buf = bytes::Bytes::from_static(&[172, 218, 25, 136, 12, 0, 0, 67]);
+        let startpos = JrrStartPosition::decode(&mut DecodeContext::new(&mut buf)).unwrap();
+        assert!(matches!(startpos, JrrStartPosition::DefaultStartPosition));
+    }
 }
@@ -12,6 +12,7 @@ const MAX_MAL_SIZE: usize = 120;
 
 #[derive(Debug, Clone, Default, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Restrict which mods can be used.
 ///
@@ -27,9 +28,16 @@ pub struct Mal {
         feature = "schemars",
         schemars(with = "Vec<insim_core::vehicle::Vehicle>")
     )]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = arbitrary_mods))]
     allowed_mods: IndexSet<Vehicle>,
 }
 
+#[cfg(feature = "arbitrary")]
+fn arbitrary_mods(u: &mut arbitrary::Unstructured<'_>) -> arbitrary::Result<IndexSet<Vehicle>> {
+    let ids: Vec<u32> = insim_core::arbitrary_helpers::vec::<_, MAX_MAL_SIZE>(u)?;
+    Ok(ids.into_iter().map(Vehicle::Mod).collect())
+}
+
 impl Mal {
     /// Returns `true` if a mod is contained in this packet.
     pub fn contains(&self, v: &Vehicle) -> bool {
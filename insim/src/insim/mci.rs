@@ -8,6 +8,7 @@ use crate::identifiers::{PlayerId, RequestId};
 
 bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Additional state for a car in [Mci].
     pub struct CompCarInfo: u8 {
         /// This car is in the way of a driver who is a lap ahead
@@ -50,6 +51,7 @@ impl_bitflags_from_to_bytes!(CompCarInfo, u8);
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Per-car telemetry entry used by [Mci].
 pub struct CompCar {
@@ -149,6 +151,7 @@ impl Encode for CompCar {
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Multi-car telemetry updates.
 ///
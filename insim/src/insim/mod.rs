@@ -69,8 +69,8 @@ mod ver;
 mod vtn;
 
 pub use acr::{Acr, AcrResult};
-pub use aic::{AiInput, AiInputToggle, AiInputType, Aic};
-pub use aii::{AiFlags, Aii};
+pub use aic::{AiHelpFlags, AiInput, AiInputToggle, AiInputType, Aic};
+pub use aii::{AiFlags, Aii, OsMain};
 pub use axi::Axi;
 pub use axm::{Axm, GetZEntry, ObjectInfo, PmoAction, PmoFlags};
 pub use axo::Axo;
@@ -123,7 +123,10 @@ pub use rip::{Rip, RipError, RipOptions};
 pub use rst::{LapTimingInfo, RaceFlags, Rst};
 pub use scc::Scc;
 pub use sch::{Sch, SchFlags};
-pub use set::{CentreDiffType, DiffType, Set, SetupFlags, TyreBrand, VehicleSetup};
+pub use set::{
+    CentreDiffType, DiffType, Set, SetFile, SetFileError, SetupChange, SetupFlags, TyreBrand,
+    VehicleSetup,
+};
 pub use sfp::Sfp;
 pub use slc::Slc;
 pub use small::{LclFlags, LcsFlags, Small, SmallType};
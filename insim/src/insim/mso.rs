@@ -8,6 +8,7 @@ use crate::identifiers::{ConnectionId, PlayerId, RequestId};
     Debug, Default, Clone, Eq, PartialEq, PartialOrd, Ord, insim_core::Decode, insim_core::Encode,
 )]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(u8)]
 #[non_exhaustive]
@@ -63,6 +64,21 @@ impl Mso {
     }
 }
 
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for Mso {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        let msg = insim_core::arbitrary_helpers::string::<{ MSO_MSG_MAX_LEN - 1 }>(u)?;
+        Ok(Self {
+            reqi: u.arbitrary()?,
+            ucid: u.arbitrary()?,
+            plid: u.arbitrary()?,
+            usertype: u.arbitrary()?,
+            textstart: u.int_in_range(0..=msg.len() as u8)?,
+            msg,
+        })
+    }
+}
+
 impl Decode for Mso {
     fn decode(ctx: &mut DecodeContext) -> Result<Self, insim_core::DecodeError> {
         let reqi = ctx.decode::<RequestId>("reqi")?;
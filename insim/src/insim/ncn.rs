@@ -3,6 +3,7 @@ use crate::identifiers::{ConnectionId, RequestId};
 bitflags::bitflags! {
     /// Additional facts about this connection. Used within [Ncn].
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     pub struct NcnFlags: u8 {
         /// User is remote
         const REMOTE = (1 << 2);
@@ -19,6 +20,7 @@ impl_bitflags_from_to_bytes!(NcnFlags, u8);
 
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Connection joined notification.
 ///
@@ -33,10 +35,12 @@ pub struct Ncn {
 
     /// LFS.net username.
     #[insim(codepage(length = 24))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<24>))]
     pub uname: String,
 
     #[insim(codepage(length = 24))]
     /// Player nickname.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<24>))]
     pub pname: String,
 
     /// Whether the connection has admin privileges.
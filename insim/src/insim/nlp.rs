@@ -6,6 +6,7 @@ const NLP_MAX_CARS: usize = 48;
 
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Node/lap snapshot for a player.
 pub struct NodeLapInfo {
@@ -24,6 +25,7 @@ pub struct NodeLapInfo {
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Node/lap updates without positional coordinates.
 ///
@@ -34,6 +36,10 @@ pub struct Nlp {
     pub reqi: RequestId,
 
     /// Node, lap, and position for each player.
+    #[cfg_attr(
+        feature = "arbitrary",
+        arbitrary(with = insim_core::arbitrary_helpers::vec::<_, NLP_MAX_CARS>)
+    )]
     pub info: Vec<NodeLapInfo>,
 }
 
@@ -46,6 +52,10 @@ impl Decode for Nlp {
             info.push(ctx.decode::<NodeLapInfo>("info")?);
             nump -= 1;
         }
+        // an odd number of players is padded to a multiple of 4 bytes
+        if info.len() % 2 == 1 {
+            ctx.pad("pad", 2)?;
+        }
         Ok(Self { reqi, info })
     }
 }
@@ -66,6 +76,9 @@ impl Encode for Nlp {
         for i in self.info.iter() {
             ctx.encode("info", i)?;
         }
+        if nump % 2 == 1 {
+            ctx.pad("pad", 2)?;
+        }
 
         Ok(())
     }
@@ -101,4 +114,26 @@ mod test {
             }
         );
     }
+
+    #[test]
+    fn test_nlp_odd_padding() {
+        assert_from_to_bytes!(
+            Nlp,
+            [
+                1,  // reqi
+                1,  // nump
+                26, // info[1] - node (1)
+                1,  // info[1] - node (2)
+                14, // info[1] - lap (1)
+                0,  // info[1] - lap (2)
+                5,  // info[1] - plid
+                18, // info[1] - position
+                0,  // pad
+                0,  // pad
+            ],
+            |nlp: Nlp| {
+                assert_eq!(nlp.info.len(), 1);
+            }
+        );
+    }
 }
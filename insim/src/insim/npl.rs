@@ -6,6 +6,7 @@ use crate::identifiers::{ConnectionId, PlayerId, RequestId};
 
 #[derive(Debug, Default, Clone, PartialEq, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(u8)]
 #[non_exhaustive]
@@ -42,6 +43,7 @@ pub enum TyreCompound {
 
 bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Describes the setup of a player and the various helpers that may be enabled, such as
     /// auto-clutch, etc.
     pub struct PlayerFlags: u16 {
@@ -91,6 +93,7 @@ impl_bitflags_from_to_bytes!(PlayerFlags, u16);
 
 bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Setup Flags
     pub struct SetFlags: u8 {
         /// Symmetric wheels
@@ -113,6 +116,7 @@ generate_bitflag_helpers!(SetFlags,
 
 bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Player model and type information
     pub struct PlayerType: u8 {
         /// Female, if not set assume male
@@ -136,6 +140,7 @@ generate_bitflag_helpers!(
 
 bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Passenger flags
     pub struct Passengers: u8 {
         /// Front male, opposite side from driver
@@ -162,6 +167,7 @@ impl_bitflags_from_to_bytes!(Passengers, u8);
 
 bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Racer Info flags
     pub struct RiFlags: u8 {
         /// Late start.
@@ -212,6 +218,7 @@ impl RiFlags {
 
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Player joined race notification.
 ///
@@ -235,10 +242,12 @@ pub struct Npl {
 
     #[insim(codepage(length = 24))]
     /// Player nickname.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<24>))]
     pub pname: String,
 
     #[insim(codepage(length = 8))]
     /// Number plate.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<8>))]
     pub plate: String,
 
     /// Vehicle used.
@@ -246,6 +255,7 @@ pub struct Npl {
 
     #[insim(codepage(length = 16))]
     /// Skin name.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<16>))]
     pub sname: String,
 
     /// Tyre compound per wheel.
@@ -268,8 +278,8 @@ pub struct Npl {
     /// Front tyre width adjustment.
     pub fwadj: u8,
 
-    #[insim(pad_after = 1)]
-    /// Racer info flags.
+    #[insim(pad_after = 1, since = 10)]
+    /// Racer info flags. Requires Insim 10, and is always empty for older versions.
     pub riflags: RiFlags,
 
     /// Setup flags.
@@ -291,9 +301,32 @@ pub struct Npl {
 #[cfg(test)]
 mod test {
     use bytes::{BufMut, BytesMut};
+    use insim_core::{DecodeContext, EncodeContext};
 
     use super::*;
 
+    #[test]
+    fn test_npl_riflags_require_insim_10() {
+        let npl = Npl {
+            riflags: RiFlags::LATE_START,
+            ..Default::default()
+        };
+        let roundtrip = |encode_as: u8, decode_as: u8| {
+            let mut buf = BytesMut::new();
+            npl.encode(&mut EncodeContext::new(&mut buf).with_version(encode_as))
+                .unwrap();
+            let mut buf = buf.freeze();
+            Npl::decode(&mut DecodeContext::new(&mut buf).with_version(decode_as))
+                .unwrap()
+                .riflags
+        };
+
+        assert_eq!(roundtrip(10, 10), RiFlags::LATE_START);
+        // the byte is spare before Insim 10, so it is neither written nor read
+        assert_eq!(roundtrip(10, 9), RiFlags::empty());
+        assert_eq!(roundtrip(9, 10), RiFlags::empty());
+    }
+
     #[test]
     fn test_npl_xrt() {
         let mut raw = BytesMut::new();
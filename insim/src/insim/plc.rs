@@ -208,6 +208,13 @@ impl PlcAllowedCarsSet {
     }
 }
 
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for PlcAllowedCarsSet {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        Ok(Self::from_bits_truncate(u.arbitrary()?))
+    }
+}
+
 impl<'a> IntoIterator for &'a PlcAllowedCarsSet {
     type Item = &'a Vehicle;
     type IntoIter = IndexSetIter<'a, Vehicle>;
@@ -219,6 +226,7 @@ impl<'a> IntoIterator for &'a PlcAllowedCarsSet {
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Restrict which standard vehicles a connection may select.
 ///
@@ -6,6 +6,7 @@ const PLH_MAX_PLAYERS: usize = 48;
 
 bitflags::bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Flags to indicate which handicap fields are set.
     pub struct PlayerHandicapFlags: u8 {
          const MASS = (1 << 0);
@@ -19,6 +20,7 @@ impl_bitflags_from_to_bytes!(PlayerHandicapFlags, u8);
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Handicap settings for a single player.
 pub struct PlayerHandicap {
@@ -29,9 +31,11 @@ pub struct PlayerHandicap {
     pub flags: PlayerHandicapFlags,
 
     /// Added mass (requires MASS flag).
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::up_to::<200>))]
     pub h_mass: u8,
 
     /// Intake restriction (requires TRES flag).
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::up_to::<50>))]
     pub h_tres: u8,
 }
 
@@ -80,6 +84,7 @@ impl Encode for PlayerHandicap {
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Player handicap updates.
 ///
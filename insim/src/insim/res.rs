@@ -7,6 +7,7 @@ use crate::identifiers::{PlayerId, RequestId};
 
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Confirmed race or qualifying result.
 ///
@@ -20,14 +21,17 @@ pub struct Res {
 
     #[insim(codepage(length = 24))]
     /// LFS.net username.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<24>))]
     pub uname: String,
 
     #[insim(codepage(length = 24))]
     /// Player nickname.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<24>))]
     pub pname: String,
 
     #[insim(codepage(length = 8))]
     /// Number plate.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<8>))]
     pub plate: String,
 
     /// Vehicle used for the result.
@@ -37,12 +41,14 @@ pub struct Res {
     /// Total time in milliseconds.
     #[cfg_attr(feature = "serde", serde(with = "crate::duration_serde"))]
     #[cfg_attr(feature = "schemars", schemars(with = "u64"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::millis::<u32>))]
     pub ttime: Duration,
 
     #[insim(duration = u32, pad_after = 1)]
     /// Best lap time in milliseconds.
     #[cfg_attr(feature = "serde", serde(with = "crate::duration_serde"))]
     #[cfg_attr(feature = "schemars", schemars(with = "u64"))]
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::millis::<u32>))]
     pub btime: Duration,
 
     /// Number of pit stops.
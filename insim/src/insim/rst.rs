@@ -6,6 +6,7 @@ use crate::identifiers::RequestId;
 
 bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Server and race configuration flags.
     pub struct RaceFlags: u16 {
         /// Can vote
@@ -58,6 +59,7 @@ impl_bitflags_from_to_bytes!(RaceFlags, u16);
 
 #[derive(Default, Debug, Clone, Copy, PartialEq, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Lap timing configuration for the current session.
 pub enum LapTimingInfo {
@@ -105,6 +107,7 @@ impl Encode for LapTimingInfo {
 
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Race start information and session configuration.
 ///
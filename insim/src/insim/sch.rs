@@ -5,6 +5,7 @@ use crate::identifiers::RequestId;
 bitflags! {
     /// Modifier flags used with [Sch].
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     pub struct SchFlags: u8 {
         /// Shift
         const SHIFT = (1 << 0);
@@ -25,6 +26,7 @@ impl_bitflags_from_to_bytes!(SchFlags, u8);
 
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Send a single key press.
 ///
@@ -35,6 +37,7 @@ pub struct Sch {
     pub reqi: RequestId,
 
     /// Character to send.
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::ascii_char))]
     pub charb: char,
 
     /// Key modifiers (shift/ctrl).
@@ -1,12 +1,19 @@
-use bytes::BytesMut;
+use std::{
+    fmt, fs,
+    io::{Read, Write},
+    path::Path,
+};
+
+use bytes::{Bytes, BytesMut};
 use digest::Digest;
-use insim_core::{Encode, EncodeContext, vehicle::Vehicle};
+use insim_core::{Decode, DecodeContext, Encode, EncodeContext, vehicle::Vehicle};
 
 use super::{Passengers, TyreCompound};
 use crate::identifiers::{PlayerId, RequestId};
 
 bitflags::bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Setup option flags.
     pub struct SetupFlags: u8 {
         /// Patch X setup format (set=1) vs. older format (set=0).
@@ -24,6 +31,7 @@ impl_bitflags_from_to_bytes!(SetupFlags, u8);
 
 #[derive(Debug, Default, Clone, PartialEq, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(u8)]
 #[non_exhaustive]
@@ -44,6 +52,7 @@ pub enum TyreBrand {
 
 #[derive(Debug, Default, Clone, PartialEq, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(u8)]
 #[non_exhaustive]
@@ -58,6 +67,7 @@ pub enum CentreDiffType {
 
 #[derive(Debug, Default, Clone, PartialEq, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(u8)]
 #[non_exhaustive]
@@ -76,6 +86,7 @@ pub enum DiffType {
 
 #[derive(Debug, Default, Clone, PartialEq, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Vehicle setup.
 ///
@@ -275,9 +286,172 @@ pub struct VehicleSetup {
     pub front_right_tyre_pressure: u16,
 }
 
+/// A field that differs between two [VehicleSetup]s, see [VehicleSetup::diff].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SetupChange {
+    /// Name of the field, e.g. `rear_wing_angle` or `gear_ratios[7]`
+    pub field: &'static str,
+
+    /// Value in the original setup
+    pub old: String,
+
+    /// Value in the changed setup
+    pub new: String,
+}
+
+impl fmt::Display for SetupChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {} -> {}", self.field, self.old, self.new)
+    }
+}
+
+macro_rules! diff_fields {
+    ($old:expr, $new:expr, $changes:expr, [$($field:ident),+ $(,)?]) => {
+        $(
+            if $old.$field != $new.$field {
+                $changes.push(SetupChange {
+                    field: stringify!($field),
+                    old: format!("{:?}", $old.$field),
+                    new: format!("{:?}", $new.$field),
+                });
+            }
+        )+
+    };
+}
+
 impl VehicleSetup {
     const SET_PAYLOAD_LEN: usize = 120;
 
+    const GEAR_RATIO_FIELDS: [&'static str; 8] = [
+        "gear_ratios[0]",
+        "gear_ratios[1]",
+        "gear_ratios[2]",
+        "gear_ratios[3]",
+        "gear_ratios[4]",
+        "gear_ratios[5]",
+        "gear_ratios[6]",
+        "gear_ratios[7]",
+    ];
+
+    /// Fields which differ from `other`, in SET file order. Gear ratios are compared
+    /// individually.
+    pub fn diff(&self, other: &Self) -> Vec<SetupChange> {
+        let mut changes = Vec::new();
+        diff_fields!(
+            self,
+            other,
+            changes,
+            [
+                flags,
+                unknown,
+                handicap_mass_position,
+                tyre_brand,
+                brake_strength,
+                rear_wing_angle,
+                front_wing_angle,
+                voluntary_handicap_mass,
+                voluntary_intake_restriction,
+                max_steering_lock,
+                parallel_steering,
+                brake_balance,
+                engine_brake_reduction,
+                centre_diff_type,
+                centre_diff_viscous_torque,
+                centre_diff_torque_split,
+            ]
+        );
+        for (i, (old, new)) in self.gear_ratios.iter().zip(&other.gear_ratios).enumerate() {
+            if old != new {
+                changes.push(SetupChange {
+                    field: Self::GEAR_RATIO_FIELDS[i],
+                    old: old.to_string(),
+                    new: new.to_string(),
+                });
+            }
+        }
+        diff_fields!(
+            self,
+            other,
+            changes,
+            [
+                passengers,
+                car_config,
+                tc_slip,
+                tc_engage_speed,
+                rear_ride_height,
+                rear_spring_stiffness,
+                rear_bump_damping,
+                rear_rebound_damping,
+                rear_anti_roll_bar,
+                handbrake_strength,
+                rear_toe,
+                rear_caster,
+                rear_tyre_type,
+                rear_tyre_warmer_temp,
+                rear_left_camber,
+                rear_right_camber,
+                rear_tyre_size,
+                rear_diff_clutch_preload,
+                rear_diff_type,
+                rear_viscous_torque,
+                rear_power_locking,
+                rear_coast_locking,
+                rear_left_tyre_pressure,
+                rear_right_tyre_pressure,
+                front_ride_height,
+                front_spring_stiffness,
+                front_bump_damping,
+                front_rebound_damping,
+                front_anti_roll_bar,
+                front_toe,
+                front_caster,
+                front_tyre_type,
+                front_tyre_warmer_temp,
+                front_left_camber,
+                front_right_camber,
+                front_tyre_size,
+                front_diff_clutch_preload,
+                front_diff_type,
+                front_viscous_torque,
+                front_power_locking,
+                front_coast_locking,
+                front_left_tyre_pressure,
+                front_right_tyre_pressure,
+            ]
+        );
+        changes
+    }
+
+    /// Encode in SET file payload order.
+    fn to_file_payload(&self) -> [u8; Self::SET_PAYLOAD_LEN] {
+        let mut buf = BytesMut::with_capacity(Self::SET_PAYLOAD_LEN);
+        let mut ctx = EncodeContext::new(&mut buf);
+        self.encode(&mut ctx)
+            .expect("VehicleSetup encode is infallible");
+
+        // Reorder gears from packet order [g1..g6, g7, FDR] to file order [g7, FDR, g1..g6].
+        let mut payload = [0u8; Self::SET_PAYLOAD_LEN];
+        payload.copy_from_slice(&buf);
+        payload[20..22].copy_from_slice(&buf[32..34]); // g7
+        payload[22..24].copy_from_slice(&buf[34..36]); // FDR
+        payload[24..36].copy_from_slice(&buf[20..32]); // g1..g6
+        payload
+    }
+
+    /// Decode from SET file payload order.
+    fn from_file_payload(
+        payload: &[u8; Self::SET_PAYLOAD_LEN],
+    ) -> Result<Self, insim_core::DecodeError> {
+        // Reorder gears from file order [g7, FDR, g1..g6] to packet order [g1..g6, g7, FDR].
+        let mut buf = BytesMut::from(&payload[..]);
+        buf[20..32].copy_from_slice(&payload[24..36]); // g1..g6
+        buf[32..34].copy_from_slice(&payload[20..22]); // g7
+        buf[34..36].copy_from_slice(&payload[22..24]); // FDR
+
+        let mut buf = buf.freeze();
+        Self::decode(&mut DecodeContext::new(&mut buf))
+    }
+
     /// Digest of this setup encoded in SET file payload order.
     ///
     /// The result is equivalent to hashing bytes 12 onwards of the corresponding SET file on
@@ -299,28 +473,143 @@ impl VehicleSetup {
     /// let hash = sha2::Sha256::digest(&bytes[12..]);
     /// ```
     pub fn digest<D: Digest>(&self) -> digest::Output<D> {
-        let mut buf = BytesMut::with_capacity(Self::SET_PAYLOAD_LEN);
-        let mut ctx = EncodeContext::new(&mut buf);
-        self.encode(&mut ctx)
-            .expect("VehicleSetup encode is infallible");
+        D::digest(self.to_file_payload())
+    }
+}
 
-        // Reorder gears from packet order [g1..g6, g7, FDR] to file order [g7, FDR, g1..g6].
-        let mut payload = [0u8; 120];
-        payload.copy_from_slice(&buf);
-        payload[20..22].copy_from_slice(&buf[32..34]); // g7
-        payload[22..24].copy_from_slice(&buf[34..36]); // FDR
-        payload[24..36].copy_from_slice(&buf[20..32]); // g1..g6
+#[non_exhaustive]
+#[derive(thiserror::Error, Debug)]
+/// Errors that may occur reading or writing a [SetFile].
+pub enum SetFileError {
+    /// The file does not start with `SRSETT`
+    #[error("Not a SET file, found magic {0:?}")]
+    InvalidMagic(Vec<u8>),
+
+    /// The file is a version other than [SetFile::VERSION]
+    #[error("Unsupported SET file version {0}")]
+    UnsupportedVersion(u8),
+
+    /// The file ends before the end of the setup
+    #[error("SET file is {0} bytes, expected at least {len}", len = SetFile::LEN)]
+    TooShort(usize),
+
+    /// IO Error
+    #[error("IO error occurred: {0}")]
+    IO(#[from] std::io::Error),
+
+    /// Decode Error
+    #[error("Decode error: {0}")]
+    Decode(#[from] insim_core::DecodeError),
+}
 
-        D::digest(payload)
+#[derive(Debug, Clone, PartialEq)]
+/// LFS setup file, as saved in `data/setups`.
+///
+/// The setup itself is a [VehicleSetup], the same as sent in a [Set] packet. Setups collected
+/// with [IsiFlags::SET](crate::insim::IsiFlags::SET) can be written to disk with
+/// [SetFile::new] and [SetFile::write].
+pub struct SetFile {
+    /// Vehicle setup.
+    pub setup: VehicleSetup,
+
+    /// Any bytes following the setup, kept so that a file can be written back unchanged.
+    pub extra: Bytes,
+
+    // Header as read, including bytes which are not otherwise interpreted
+    header: [u8; Self::HEADER_LEN],
+}
+
+impl SetFile {
+    const MAGIC: &[u8; 6] = b"SRSETT";
+
+    const HEADER_LEN: usize = 12;
+
+    /// Length of a SET file, without any [SetFile::extra] bytes.
+    pub const LEN: usize = Self::HEADER_LEN + VehicleSetup::SET_PAYLOAD_LEN;
+
+    /// Latest supported file format version.
+    pub const VERSION: u8 = 252;
+
+    /// File format revision written by [SetFile::new].
+    pub const REVISION: u8 = 2;
+
+    /// New SET file, in the latest version, for `setup`.
+    pub fn new(setup: VehicleSetup) -> Self {
+        let mut header = [0; Self::HEADER_LEN];
+        header[0..6].copy_from_slice(Self::MAGIC);
+        header[7] = Self::VERSION;
+        header[8] = Self::REVISION;
+        Self {
+            setup,
+            extra: Bytes::new(),
+            header,
+        }
+    }
+
+    /// File format version, always [SetFile::VERSION].
+    pub fn version(&self) -> u8 {
+        self.header[7]
+    }
+
+    /// File format revision.
+    pub fn revision(&self) -> u8 {
+        self.header[8]
+    }
+
+    /// Read and parse a SET file.
+    pub fn read<R: Read>(mut reader: R) -> Result<Self, SetFileError> {
+        let mut data = Vec::with_capacity(Self::LEN);
+        let _ = reader.read_to_end(&mut data)?;
+        if data.len() < Self::LEN {
+            if !data.starts_with(&Self::MAGIC[..data.len().min(Self::MAGIC.len())]) {
+                return Err(SetFileError::InvalidMagic(
+                    data[..data.len().min(6)].to_vec(),
+                ));
+            }
+            return Err(SetFileError::TooShort(data.len()));
+        }
+
+        let (header, rest) = data.split_at(Self::HEADER_LEN);
+        if &header[0..6] != Self::MAGIC {
+            return Err(SetFileError::InvalidMagic(header[0..6].to_vec()));
+        }
+        let version = header[7];
+        if version != Self::VERSION {
+            return Err(SetFileError::UnsupportedVersion(version));
+        }
+
+        let (payload, extra) = rest.split_at(VehicleSetup::SET_PAYLOAD_LEN);
+        let payload = payload.try_into().expect("split at SET_PAYLOAD_LEN");
+        Ok(Self {
+            setup: VehicleSetup::from_file_payload(payload)?,
+            extra: Bytes::copy_from_slice(extra),
+            header: header.try_into().expect("split at HEADER_LEN"),
+        })
+    }
+
+    /// Write a SET file.
+    pub fn write<W: Write>(&self, mut writer: W) -> Result<usize, SetFileError> {
+        writer.write_all(&self.header)?;
+        writer.write_all(&self.setup.to_file_payload())?;
+        writer.write_all(&self.extra)?;
+        Ok(Self::LEN + self.extra.len())
+    }
+
+    /// Read and parse a SET file from `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, SetFileError> {
+        let file = fs::File::open(path)?;
+        Self::read(file)
     }
 }
 
 #[derive(Debug, Clone, Default, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Player sent setup to host
 ///
 /// - Sent when SET is enabled in [IsiFlags](crate::insim::IsiFlags).
+/// - Requires Insim 10.
 pub struct Set {
     /// Request identifier echoed by replies.
     pub reqi: RequestId,
@@ -376,6 +665,122 @@ mod test {
         );
     }
 
+    fn example_setup() -> VehicleSetup {
+        VehicleSetup {
+            flags: SetupFlags::ABS | SetupFlags::PATCH_X,
+            tyre_brand: TyreBrand::Michelin,
+            brake_strength: 1200.0,
+            rear_wing_angle: 12,
+            gear_ratios: [1, 2, 3, 4, 5, 6, 7, 8],
+            rear_tyre_type: TyreCompound::R2,
+            front_left_tyre_pressure: 160,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_set_file_round_trip() {
+        let file = SetFile::new(example_setup());
+        let mut bytes = Vec::new();
+        assert_eq!(file.write(&mut bytes).unwrap(), SetFile::LEN);
+        assert_eq!(bytes.len(), SetFile::LEN);
+        assert_eq!(&bytes[0..6], b"SRSETT");
+        assert_eq!(bytes[7], SetFile::VERSION);
+
+        // Gears are stored as [g7, FDR, g1..g6]
+        assert_eq!(&bytes[32..36], &[7, 0, 8, 0]);
+        assert_eq!(&bytes[36..38], &[1, 0]);
+
+        let read = SetFile::read(&bytes[..]).unwrap();
+        assert_eq!(read, file);
+        assert_eq!(
+            read.setup.digest::<sha2::Sha256>(),
+            <sha2::Sha256 as digest::Digest>::digest(&bytes[12..])
+        );
+
+        // Trailing bytes are kept
+        bytes.extend_from_slice(&[1, 2, 3]);
+        let read = SetFile::read(&bytes[..]).unwrap();
+        assert_eq!(read.extra.as_ref(), &[1, 2, 3]);
+        let mut written = Vec::new();
+        assert_eq!(read.write(&mut written).unwrap(), SetFile::LEN + 3);
+        assert_eq!(written, bytes);
+    }
+
+    #[test]
+    fn test_set_file_keeps_header() {
+        let mut bytes = Vec::new();
+        let _ = SetFile::new(example_setup()).write(&mut bytes).unwrap();
+        bytes[6] = 1;
+        bytes[9..12].copy_from_slice(&[4, 5, 6]);
+
+        let mut written = Vec::new();
+        let _ = SetFile::read(&bytes[..])
+            .unwrap()
+            .write(&mut written)
+            .unwrap();
+        assert_eq!(written, bytes);
+    }
+
+    #[test]
+    fn test_set_file_errors() {
+        let mut bytes = Vec::new();
+        let _ = SetFile::new(VehicleSetup::default())
+            .write(&mut bytes)
+            .unwrap();
+
+        assert!(matches!(
+            SetFile::read(&bytes[..100]),
+            Err(SetFileError::TooShort(100))
+        ));
+
+        let mut invalid = bytes.clone();
+        invalid[0..6].copy_from_slice(b"LFSPTH");
+        assert!(matches!(
+            SetFile::read(&invalid[..]),
+            Err(SetFileError::InvalidMagic(magic)) if magic == b"LFSPTH"
+        ));
+        assert!(matches!(
+            SetFile::read(&b"LFSPTH"[..]),
+            Err(SetFileError::InvalidMagic(_))
+        ));
+
+        let mut newer = bytes.clone();
+        newer[7] = 253;
+        assert!(matches!(
+            SetFile::read(&newer[..]),
+            Err(SetFileError::UnsupportedVersion(253))
+        ));
+
+        let mut older = bytes.clone();
+        older[7] = 251;
+        assert!(matches!(
+            SetFile::read(&older[..]),
+            Err(SetFileError::UnsupportedVersion(251))
+        ));
+    }
+
+    #[test]
+    fn test_diff() {
+        let old = example_setup();
+        assert!(old.diff(&old).is_empty());
+
+        let mut new = old.clone();
+        new.rear_wing_angle = 14;
+        new.gear_ratios[7] = 9;
+        new.rear_tyre_type = TyreCompound::R3;
+
+        let changes = old.diff(&new);
+        assert_eq!(
+            changes.iter().map(ToString::to_string).collect::<Vec<_>>(),
+            [
+                "rear_wing_angle: 12 -> 14",
+                "gear_ratios[7]: 8 -> 9",
+                "rear_tyre_type: R2 -> R3",
+            ]
+        );
+    }
+
     #[test]
     fn test_set() {
         assert_from_to_bytes!(
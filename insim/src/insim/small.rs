@@ -11,6 +11,7 @@ use crate::{
 
 bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Local car switches flags.
     ///
     /// - Kept for backwards compatibility; prefer [LclFlags].
@@ -77,6 +78,7 @@ impl_bitflags_json_schema!(LcsFlags, "LcsFlag");
 
 bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Local car lights flags.
     ///
     /// - Used with [`SmallType::Lcl`].
@@ -130,6 +132,7 @@ impl_bitflags_json_schema!(LclFlags, "LclFlag");
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[non_exhaustive]
 /// Subtype for the [Small] packet.
@@ -329,6 +332,7 @@ impl Encode for SmallType {
 
 #[derive(Debug, Clone, Default, PartialEq, Eq, insim_core::Decode, insim_core::Encode)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// General purpose packet carrying a subtype and an integer value.
 ///
@@ -4,6 +4,7 @@ use crate::identifiers::RequestId;
 
 #[derive(Debug, Clone, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Version information about the connected LFS instance.
 ///
@@ -17,6 +18,7 @@ pub struct Ver {
     pub version: GameVersion,
 
     /// Product identifier (e.g., DEMO/S1/S2/S3).
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<6>))]
     pub product: String,
 
     /// InSim protocol version reported by LFS.
@@ -1,6 +1,9 @@
 #![doc = include_str!("../README.md")]
 #![cfg_attr(test, deny(warnings, unreachable_pub))]
 
+#[cfg(test)]
+extern crate criterion as _; // Needed for cargo bench.
+
 #[cfg(any(feature = "blocking", feature = "tokio"))]
 use std::net::SocketAddr;
 
@@ -16,14 +19,23 @@ pub mod address;
 #[cfg(any(feature = "blocking", feature = "tokio"))]
 /// Builder for establishing an InSim connection.
 pub mod builder;
+pub mod capture;
+#[cfg(all(feature = "serde", any(feature = "blocking", feature = "tokio")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "serde")))]
+pub mod config;
 /// Error type for InSim connection failures.
 pub mod error;
 pub mod identifiers;
 pub mod insim;
 pub mod net;
 pub mod packet;
+pub mod packet_ref;
+pub mod request;
 #[doc(hidden)]
 pub mod result;
+#[cfg(all(feature = "tokio", any(test, feature = "testing")))]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
 
 #[doc = include_str!("../docs/book.md")]
 pub mod _book {}
@@ -31,6 +43,10 @@ pub mod _book {}
 /// The Insim Protocol Version Number supported by this library
 pub const VERSION: u8 = 10;
 
+/// The oldest Insim Protocol Version Number supported by this library. Anything between this
+/// and [VERSION] is accepted, see [crate::net::Codec::version].
+pub const MIN_VERSION: u8 = 9;
+
 /// Why 255 * 4? Because the size of a packet is a u8, with a max byte size of 255.
 /// In "compressed" mode the raw size is multiplied by 4.
 pub(crate) const MAX_SIZE_PACKET: usize = (u8::MAX as usize) * 4;
@@ -43,6 +59,7 @@ pub use error::Error;
 /// Rexport insim_core
 pub use insim_core as core;
 pub use packet::{Packet, WithRequestId};
+pub use packet_ref::PacketRef;
 pub use result::Result;
 
 /// Shortcut method to create a TCP connection
@@ -86,3 +103,31 @@ pub fn udp<L: Into<Option<SocketAddr>>, R: Into<address::Addr>>(
 ) -> builder::Builder {
     builder::Builder::default().udp(remote_addr, local_addr)
 }
+
+/// Shortcut method to create a TCP connection, which additionally receives
+/// [Packet::Mci] and [Packet::Nlp] over UDP.
+/// If udp_local_addr is not provided then we will bind to "0.0.0.0:0" (all addresses, random port).
+///
+/// # Examples
+///
+/// Supports both blocking and tokio. Swap about `connect_async` for `connect` and remove the
+/// `.await` annotations.
+///
+/// ```rust,ignore
+/// let conn = insim::tcp_udp("127.0.0.1:29999", None)
+///     .isi_flag_mci(true)
+///     .isi_interval(std::time::Duration::from_millis(500))
+///     .connect_async()
+///     .await?;
+/// loop {
+///     let packet = conn.read().await?;
+///     println!("{:?}", packet);
+/// }
+/// ```
+#[cfg(any(feature = "blocking", feature = "tokio"))]
+pub fn tcp_udp<L: Into<Option<SocketAddr>>, R: Into<address::Addr>>(
+    remote_addr: R,
+    udp_local_addr: L,
+) -> builder::Builder {
+    builder::Builder::default().tcp_udp(remote_addr, udp_local_addr)
+}
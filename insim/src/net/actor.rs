@@ -0,0 +1,114 @@
+//! State shared between the tokio and blocking connection handles.
+
+use std::{
+    sync::{Arc, Mutex, OnceLock},
+    time::Instant,
+};
+
+use crate::{
+    Error, Packet, Result,
+    net::{LatencyMonitor, LatencyStats, QueueMetrics, SendQueue},
+};
+
+/// Why a spawned connection stopped.
+#[derive(Debug)]
+pub(crate) enum Stopped {
+    /// A handle asked for the connection to be shutdown, or every handle was dropped.
+    Shutdown,
+    /// The connection failed. Every handle and subscriber observes the same error.
+    Failed(Arc<Error>),
+}
+
+/// Set exactly once, by the actor, as it exits.
+pub(crate) type StopReason = Arc<OnceLock<Stopped>>;
+
+impl Stopped {
+    pub(crate) fn from_result(result: Result<()>) -> Self {
+        match result {
+            Ok(()) => Stopped::Shutdown,
+            Err(e) => Stopped::Failed(Arc::new(e)),
+        }
+    }
+}
+
+/// The error a handle or subscriber returns once the connection has stopped.
+pub(crate) fn stopped_error(reason: &OnceLock<Stopped>) -> Error {
+    match reason.get() {
+        Some(Stopped::Failed(e)) => Error::Stopped(e.clone()),
+        Some(Stopped::Shutdown) | None => Error::Disconnected,
+    }
+}
+
+/// Metrics of the [SendQueue] driven by the actor, published after every change.
+pub(crate) type SharedMetrics = Arc<Mutex<QueueMetrics>>;
+
+pub(crate) fn shared_metrics(queue: Option<&SendQueue>) -> Option<SharedMetrics> {
+    queue.map(|q| Arc::new(Mutex::new(q.metrics())))
+}
+
+pub(crate) fn publish_metrics(shared: Option<&SharedMetrics>, queue: Option<&SendQueue>) {
+    if let (Some(shared), Some(queue)) = (shared, queue) {
+        *shared.lock().unwrap_or_else(|e| e.into_inner()) = queue.metrics();
+    }
+}
+
+pub(crate) fn read_metrics(shared: Option<&SharedMetrics>) -> Option<QueueMetrics> {
+    shared.map(|m| *m.lock().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Statistics of the [LatencyMonitor] driven by the actor, published after every change.
+pub(crate) type SharedLatency = Arc<Mutex<LatencyStats>>;
+
+/// A [LatencyMonitor] driven by the actor.
+#[derive(Debug)]
+pub(crate) struct Latency {
+    monitor: LatencyMonitor,
+    shared: SharedLatency,
+}
+
+impl Latency {
+    pub(crate) fn new(monitor: LatencyMonitor) -> Self {
+        let shared = Arc::new(Mutex::new(monitor.stats()));
+        Self { monitor, shared }
+    }
+
+    pub(crate) fn shared(&self) -> SharedLatency {
+        self.shared.clone()
+    }
+
+    /// A ping to send, if one is due.
+    pub(crate) fn poll(&mut self, now: Instant) -> Option<Packet> {
+        let stalled = self.monitor.is_stalled();
+        let ping = self.monitor.poll(now);
+        if self.monitor.is_stalled() && !stalled {
+            tracing::warn!("connection stalled, no reply to ping");
+        }
+        self.publish();
+        ping
+    }
+
+    /// Record `packet` if it is the reply to a ping, returning true if so.
+    pub(crate) fn handle(&mut self, packet: &Packet, now: Instant) -> bool {
+        let stalled = self.monitor.is_stalled();
+        if !self.monitor.handle(packet, now) {
+            return false;
+        }
+        if stalled {
+            tracing::info!("connection recovered from stall");
+        }
+        self.publish();
+        true
+    }
+
+    pub(crate) fn poll_at(&self, now: Instant) -> Option<Instant> {
+        self.monitor.poll_at(now)
+    }
+
+    fn publish(&self) {
+        *self.shared.lock().unwrap_or_else(|e| e.into_inner()) = self.monitor.stats();
+    }
+}
+
+pub(crate) fn read_latency(shared: Option<&SharedLatency>) -> Option<LatencyStats> {
+    shared.map(|m| *m.lock().unwrap_or_else(|e| e.into_inner()))
+}
@@ -0,0 +1,434 @@
+use std::{
+    sync::{
+        Arc, Mutex, OnceLock,
+        mpsc::{
+            self, Receiver, RecvError, RecvTimeoutError, SyncSender, TryRecvError, TrySendError,
+        },
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+use super::Framed;
+use crate::{
+    Error, Packet, Result,
+    net::{
+        LatencyMonitor, LatencyStats, QueueMetrics, SendQueue,
+        actor::{
+            Latency, SharedLatency, SharedMetrics, StopReason, Stopped, publish_metrics,
+            read_latency, read_metrics, shared_metrics, stopped_error,
+        },
+    },
+    request::{Pending, Request, RequestIds},
+};
+
+/// How long the connection thread blocks waiting on the network before checking for packets
+/// to send. This bounds the additional latency of [Handle::send], and the accuracy of any
+/// [SendQueue] pacing.
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+#[derive(Debug)]
+enum Command {
+    Packet(Packet),
+    Shutdown(SyncSender<Result<()>>),
+}
+
+type Subscribers = Arc<Mutex<Vec<SyncSender<Packet>>>>;
+
+/// A cloneable handle to a connection running on its own thread.
+///
+/// Create one with [crate::builder::Builder::spawn_blocking], or [Handle::new] for an existing
+/// [Framed]. The thread owns the connection, takes care of keepalives, and broadcasts every
+/// packet it receives to all [Subscriber]s. It stops when asked via [Handle::shutdown], when
+/// every `Handle` has been dropped, or when the connection fails - in which case every
+/// subscriber receives the error as [Error::Stopped].
+#[derive(Debug, Clone)]
+pub struct Handle {
+    tx: mpsc::Sender<Command>,
+    subscribers: Subscribers,
+    capacity: usize,
+    stopped: StopReason,
+    reqis: RequestIds,
+    metrics: Option<SharedMetrics>,
+    latency: Option<SharedLatency>,
+}
+
+impl Handle {
+    /// Spawn a thread to drive an established connection. Each [Subscriber] may fall up to
+    /// `capacity` packets behind before packets are dropped for it.
+    pub fn new(framed: Framed, capacity: usize) -> Result<(Self, Subscriber)> {
+        Self::spawn(move || Ok(framed), capacity, None, None)
+    }
+
+    /// As [Handle::new], but packets are sent through `queue`.
+    pub fn with_queue(
+        framed: Framed,
+        capacity: usize,
+        queue: SendQueue,
+    ) -> Result<(Self, Subscriber)> {
+        Self::spawn(move || Ok(framed), capacity, Some(queue), None)
+    }
+
+    /// Spawn a thread which first establishes the connection using `connect`. Failing to
+    /// connect is reported to subscribers in the same way as losing the connection.
+    pub(crate) fn spawn<F>(
+        connect: F,
+        capacity: usize,
+        queue: Option<SendQueue>,
+        latency: Option<LatencyMonitor>,
+    ) -> Result<(Self, Subscriber)>
+    where
+        F: FnOnce() -> Result<Framed> + Send + 'static,
+    {
+        let (tx, rx) = mpsc::channel();
+        let metrics = shared_metrics(queue.as_ref());
+        let mut latency = latency.map(Latency::new);
+        let handle = Self {
+            tx,
+            subscribers: Arc::new(Mutex::new(Vec::new())),
+            capacity,
+            stopped: Arc::new(OnceLock::new()),
+            reqis: RequestIds::new(),
+            metrics: metrics.clone(),
+            latency: latency.as_ref().map(Latency::shared),
+        };
+        let subscriber = handle.subscribe();
+
+        let subscribers = handle.subscribers.clone();
+        let stopped = handle.stopped.clone();
+        let _ = thread::Builder::new().name("insim".into()).spawn(move || {
+            let result = connect().and_then(|framed| {
+                framed.set_poll_interval(POLL_INTERVAL)?;
+                let mut outbound = Outbound { queue, metrics };
+                run(
+                    framed,
+                    &rx,
+                    &subscribers,
+                    &stopped,
+                    &mut outbound,
+                    &mut latency,
+                )
+            });
+            if let Err(e) = &result {
+                tracing::debug!(error = ?e, "connection stopped");
+            }
+            let _ = stopped.set(Stopped::from_result(result));
+            // Handles observe the command channel closing, but only after the reason for
+            // stopping has been set. Close it before waking subscribers, so that anything
+            // reacting to a subscriber stopping also finds the handle stopped.
+            drop(rx);
+            lock(&subscribers).clear();
+        })?;
+
+        Ok((handle, subscriber))
+    }
+
+    /// Queue a packet to be written to the connection. This does not block.
+    ///
+    /// Returns [Error::Disconnected] if the connection was shutdown, or [Error::Stopped] if it
+    /// failed.
+    pub fn send<P: Into<Packet>>(&self, packet: P) -> Result<()> {
+        self.tx
+            .send(Command::Packet(packet.into()))
+            .map_err(|_| stopped_error(&self.stopped))
+    }
+
+    /// Subscribe to every packet received from this point onwards.
+    pub fn subscribe(&self) -> Subscriber {
+        let (tx, rx) = mpsc::sync_channel(self.capacity);
+        let mut subscribers = lock(&self.subscribers);
+        // Once stopped, nothing will clear the list again. Dropping the sender means the
+        // subscriber immediately observes why.
+        if self.stopped.get().is_none() {
+            subscribers.push(tx);
+        }
+        Subscriber {
+            rx,
+            stopped: self.stopped.clone(),
+        }
+    }
+
+    /// Send a [Request], using a newly allocated [crate::identifiers::RequestId], and block
+    /// until every reply has been received.
+    ///
+    /// Returns [Error::Timeout] if the replies are not received within `timeout`.
+    pub fn request<R: Request>(&self, request: R, timeout: Duration) -> Result<Vec<R::Reply>> {
+        // subscribe before sending, so that we cannot miss a reply
+        let subscriber = self.subscribe();
+        let mut pending = Pending::new(request, self.reqis.next());
+        for packet in pending.packets() {
+            self.send(packet)?;
+        }
+
+        let deadline = Instant::now() + timeout;
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            match subscriber.recv_timeout(remaining)? {
+                Some(packet) if pending.handle(&packet) => return Ok(pending.finish()),
+                Some(_) => {},
+                None => {
+                    return Err(Error::Timeout(format!(
+                        "No reply to {:?} within {timeout:?}",
+                        pending.request()
+                    )));
+                },
+            }
+        }
+    }
+
+    /// Send [crate::insim::TinyType::Close] and shutdown the connection, blocking until
+    /// complete. Every subscriber will subsequently receive [Error::Disconnected].
+    pub fn shutdown(&self) -> Result<()> {
+        let (tx, rx) = mpsc::sync_channel(1);
+        if self.tx.send(Command::Shutdown(tx)).is_err() {
+            // Already stopped
+            return Ok(());
+        }
+        rx.recv().unwrap_or(Ok(()))
+    }
+
+    /// Has the connection stopped?
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.get().is_some()
+    }
+
+    /// Metrics of the [SendQueue], or `None` if this connection does not use one.
+    pub fn queue_metrics(&self) -> Option<QueueMetrics> {
+        read_metrics(self.metrics.as_ref())
+    }
+
+    /// Round trip time statistics, or `None` if this connection does not use a
+    /// [LatencyMonitor].
+    pub fn latency(&self) -> Option<LatencyStats> {
+        read_latency(self.latency.as_ref())
+    }
+}
+
+/// Receives every packet from a connection driven by a [Handle].
+#[derive(Debug)]
+pub struct Subscriber {
+    rx: Receiver<Packet>,
+    stopped: StopReason,
+}
+
+impl Subscriber {
+    /// Block until the next packet is received.
+    ///
+    /// Once the connection has stopped, and any remaining packets have been received, returns
+    /// [Error::Disconnected] if it was shutdown or [Error::Stopped] if it failed.
+    pub fn recv(&self) -> Result<Packet> {
+        self.rx
+            .recv()
+            .map_err(|RecvError| stopped_error(&self.stopped))
+    }
+
+    /// Block until the next packet is received, or `timeout` elapses.
+    pub fn recv_timeout(&self, timeout: Duration) -> Result<Option<Packet>> {
+        match self.rx.recv_timeout(timeout) {
+            Ok(packet) => Ok(Some(packet)),
+            Err(RecvTimeoutError::Timeout) => Ok(None),
+            Err(RecvTimeoutError::Disconnected) => Err(stopped_error(&self.stopped)),
+        }
+    }
+
+    /// Return the next packet if one has already been received, without blocking.
+    pub fn try_recv(&self) -> Result<Option<Packet>> {
+        match self.rx.try_recv() {
+            Ok(packet) => Ok(Some(packet)),
+            Err(TryRecvError::Empty) => Ok(None),
+            Err(TryRecvError::Disconnected) => Err(stopped_error(&self.stopped)),
+        }
+    }
+}
+
+fn lock(subscribers: &Subscribers) -> std::sync::MutexGuard<'_, Vec<SyncSender<Packet>>> {
+    subscribers.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+fn broadcast(subscribers: &Subscribers, packet: &Packet) {
+    lock(subscribers).retain(|tx| match tx.try_send(packet.clone()) {
+        Ok(()) => true,
+        Err(TrySendError::Full(_)) => {
+            tracing::warn!("subscriber lagged, dropping packet");
+            true
+        },
+        Err(TrySendError::Disconnected(_)) => false,
+    });
+}
+
+#[derive(Debug)]
+struct Outbound {
+    queue: Option<SendQueue>,
+    metrics: Option<SharedMetrics>,
+}
+
+impl Outbound {
+    fn send(&mut self, framed: &mut Framed, packet: Packet) -> Result<()> {
+        match &mut self.queue {
+            Some(queue) => {
+                queue.push(packet);
+                Ok(())
+            },
+            None => write(framed, packet),
+        }
+    }
+
+    fn flush(&mut self, framed: &mut Framed) -> Result<()> {
+        if let Some(queue) = &mut self.queue {
+            while let Some(packet) = queue.pop(Instant::now()) {
+                write(framed, packet)?;
+            }
+        }
+        publish_metrics(self.metrics.as_ref(), self.queue.as_ref());
+        Ok(())
+    }
+}
+
+fn write(framed: &mut Framed, packet: Packet) -> Result<()> {
+    match framed.write(packet) {
+        Ok(()) => Ok(()),
+        // A packet which cannot be encoded does not affect the connection
+        Err(e @ Error::Encode(_)) => {
+            tracing::warn!(error = ?e, "dropping packet");
+            Ok(())
+        },
+        Err(e) => Err(e),
+    }
+}
+
+fn run(
+    mut framed: Framed,
+    rx: &Receiver<Command>,
+    subscribers: &Subscribers,
+    stopped: &OnceLock<Stopped>,
+    outbound: &mut Outbound,
+    latency: &mut Option<Latency>,
+) -> Result<()> {
+    loop {
+        loop {
+            match rx.try_recv() {
+                Ok(Command::Packet(packet)) => outbound.send(&mut framed, packet)?,
+                Ok(Command::Shutdown(reply)) => {
+                    let result = framed.shutdown();
+                    let _ = stopped.set(Stopped::Shutdown);
+                    let _ = reply.send(result);
+                    return Ok(());
+                },
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => return framed.shutdown(),
+            }
+        }
+        outbound.flush(&mut framed)?;
+
+        // Pings bypass any queue, so that they measure only the connection
+        if let Some(ping) = latency.as_mut().and_then(|l| l.poll(Instant::now())) {
+            write(&mut framed, ping)?;
+        }
+
+        if let Some(packet) = framed.try_read()?
+            && !latency
+                .as_mut()
+                .is_some_and(|l| l.handle(&packet, Instant::now()))
+        {
+            broadcast(subscribers, &packet);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{WithRequestId, identifiers::RequestId, insim::TinyType};
+
+    #[test]
+    fn test_handle_blocking() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (ready_tx, ready_rx) = mpsc::channel::<()>();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut isi = [0u8; 44];
+            stream.read_exact(&mut isi).unwrap();
+            // once every subscriber exists, ping the client and expect a ping back from the
+            // handle
+            ready_rx.recv().unwrap();
+            stream.write_all(&[1, 3, 2, 3]).unwrap();
+            let mut ping = [0u8; 4];
+            stream.read_exact(&mut ping).unwrap();
+            assert_eq!(ping, [1, 3, 4, 3]);
+        });
+
+        let (handle, first) = crate::tcp(addr).spawn_blocking().unwrap();
+        let second = handle.subscribe();
+        ready_tx.send(()).unwrap();
+
+        for sub in [&first, &second] {
+            assert!(matches!(
+                sub.recv().unwrap(),
+                Packet::Tiny(crate::insim::Tiny {
+                    subt: TinyType::Ping,
+                    ..
+                })
+            ));
+        }
+
+        handle
+            .clone()
+            .send(TinyType::Ping.with_request_id(RequestId(4)))
+            .unwrap();
+        server.join().unwrap();
+
+        // the server hung up, so every subscriber should now see the connection stop
+        assert!(matches!(first.recv(), Err(Error::Stopped(_))));
+        assert!(matches!(second.recv(), Err(Error::Stopped(_))));
+        assert!(matches!(handle.subscribe().recv(), Err(Error::Stopped(_))));
+        assert!(matches!(
+            handle.send(TinyType::Ping),
+            Err(Error::Stopped(_))
+        ));
+    }
+
+    #[test]
+    fn test_handle_latency_blocking() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut isi = [0u8; 44];
+            stream.read_exact(&mut isi).unwrap();
+            let mut ping = [0u8; 4];
+            stream.read_exact(&mut ping).unwrap();
+            assert_eq!(ping, [1, 3, LatencyMonitor::REQUEST_ID.0, 3]);
+            stream
+                .write_all(&[1, 3, LatencyMonitor::REQUEST_ID.0, 4, 1, 3, 1, 4])
+                .unwrap();
+            stream
+        });
+
+        let (handle, sub) = crate::tcp(addr)
+            .latency_monitor(LatencyMonitor::new(Duration::from_secs(60)))
+            .spawn_blocking()
+            .unwrap();
+
+        // only the unrelated reply is received
+        assert!(matches!(
+            sub.recv().unwrap(),
+            Packet::Tiny(crate::insim::Tiny {
+                subt: TinyType::Reply,
+                reqi: RequestId(1)
+            })
+        ));
+        assert_eq!(handle.latency().unwrap().samples, 1);
+
+        let _stream = server.join().unwrap();
+        handle.shutdown().unwrap();
+    }
+}
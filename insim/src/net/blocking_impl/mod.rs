@@ -0,0 +1,355 @@
+use std::{
+    fmt,
+    io::{self, Read, Write},
+    net::{TcpStream, UdpSocket},
+    time::Duration,
+};
+
+use super::Codec;
+use crate::{Error, MAX_SIZE_PACKET, Packet, Result, insim::TinyType};
+
+mod actor;
+
+pub use actor::{Handle, Subscriber};
+
+/// How long a TCP + UDP [Framed] blocks waiting on the TcpStream before checking the UdpSocket
+/// again. This bounds the additional latency of packets received over UDP.
+pub const SPLIT_POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+/// Any stream which can be both read from and written to.
+trait ReadWrite: Read + Write + Send {}
+
+impl<T: Read + Write + Send> ReadWrite for T {}
+
+/// Any stream, see [Framed::from_stream] and [Framed::from_split].
+enum BoxedStream {
+    Stream(Box<dyn ReadWrite>),
+    Split {
+        read: Box<dyn Read + Send>,
+        write: Box<dyn Write + Send>,
+    },
+}
+
+impl fmt::Debug for BoxedStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BoxedStream")
+    }
+}
+
+impl Read for BoxedStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match self {
+            BoxedStream::Stream(stream) => stream.read(buf),
+            BoxedStream::Split { read, .. } => read.read(buf),
+        }
+    }
+}
+
+impl Write for BoxedStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            BoxedStream::Stream(stream) => stream.write(buf),
+            BoxedStream::Split { write, .. } => write.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            BoxedStream::Stream(stream) => stream.flush(),
+            BoxedStream::Split { write, .. } => write.flush(),
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Transport {
+    Tcp(TcpStream),
+    Udp(UdpSocket),
+    Stream(BoxedStream),
+    TcpUdp {
+        stream: TcpStream,
+        socket: UdpSocket,
+        datagrams: Codec,
+    },
+}
+
+#[derive(Debug)]
+/// A convenience wrapper around Udp, Tcp or any other stream, using Codec to drive the IO
+pub struct Framed {
+    inner: Transport,
+    codec: Codec,
+    scratch: [u8; MAX_SIZE_PACKET], // reused across reads
+}
+
+impl Framed {
+    /// Create a new Framed from a TcpStream
+    pub fn from_tcp(stream: TcpStream, codec: Codec) -> Self {
+        Self {
+            inner: Transport::Tcp(stream),
+            codec,
+            scratch: [0; MAX_SIZE_PACKET],
+        }
+    }
+
+    /// Create a new Framed from a UdpSocket
+    pub fn from_udp(socket: UdpSocket, codec: Codec) -> Self {
+        Self {
+            inner: Transport::Udp(socket),
+            codec,
+            scratch: [0; MAX_SIZE_PACKET],
+        }
+    }
+
+    /// Create a new Framed from any other stream, i.e. an SSH tunnel or serial bridge.
+    ///
+    /// Keepalives and timeouts are handled exactly as with [Framed::from_tcp], but timeouts
+    /// can only be noticed between reads. The stream should return an error, such as
+    /// [io::ErrorKind::TimedOut], rather than block for longer than
+    /// [crate::net::DEFAULT_TIMEOUT_SECS].
+    pub fn from_stream<S>(stream: S, codec: Codec) -> Self
+    where
+        S: Read + Write + Send + 'static,
+    {
+        Self {
+            inner: Transport::Stream(BoxedStream::Stream(Box::new(stream))),
+            codec,
+            scratch: [0; MAX_SIZE_PACKET],
+        }
+    }
+
+    /// Create a new Framed from separate read and write halves of a stream, i.e. the stdout and
+    /// stdin of a child process. See [Framed::from_stream].
+    pub fn from_split<R, W>(read: R, write: W, codec: Codec) -> Self
+    where
+        R: Read + Send + 'static,
+        W: Write + Send + 'static,
+    {
+        Self {
+            inner: Transport::Stream(BoxedStream::Split {
+                read: Box::new(read),
+                write: Box::new(write),
+            }),
+            codec,
+            scratch: [0; MAX_SIZE_PACKET],
+        }
+    }
+
+    /// Create a new Framed which writes to, and reads from, a TcpStream, but additionally reads
+    /// packets from a UdpSocket.
+    ///
+    /// This is the TCP + UDP protocol split, where LFS sends [crate::Packet::Mci] and
+    /// [crate::Packet::Nlp] to the `udpport` given in the [crate::insim::Isi] handshake, and
+    /// everything else over TCP. The UdpSocket does not need to be connected. Any datagram which
+    /// is not an InSim packet (i.e. Outgauge or Outsim sharing the port) is discarded. The
+    /// [crate::net::Interest] of `codec` applies to both.
+    ///
+    /// Both sockets are polled in turn, so the TcpStream must have a short read timeout (see
+    /// [SPLIT_POLL_INTERVAL]) or be non-blocking, and the UdpSocket must be non-blocking.
+    /// [crate::builder::Builder::tcp_udp] handles this for you.
+    pub fn from_tcp_udp(stream: TcpStream, socket: UdpSocket, codec: Codec) -> Self {
+        Self {
+            inner: Transport::TcpUdp {
+                stream,
+                socket,
+                datagrams: codec.datagram_codec(),
+            },
+            codec,
+            scratch: [0; MAX_SIZE_PACKET],
+        }
+    }
+
+    fn read_inner(&mut self) -> io::Result<usize> {
+        let n = match &mut self.inner {
+            Transport::Tcp(stream) => stream.read(&mut self.scratch)?,
+            Transport::Stream(stream) => stream.read(&mut self.scratch)?,
+            Transport::Udp(socket) => socket.recv(&mut self.scratch)?,
+            Transport::TcpUdp { .. } => unreachable!("TcpUdp is read by read_split"),
+        };
+        let dst = self.codec.buf_mut();
+        dst.reserve(n);
+        dst.extend_from_slice(&self.scratch[..n]);
+        Ok(n)
+    }
+
+    /// Poll both halves of a TcpUdp transport once, feeding whatever is received into the
+    /// relevant codec. Returns false if nothing was received.
+    fn read_split(&mut self) -> Result<bool> {
+        let Transport::TcpUdp {
+            stream,
+            socket,
+            datagrams,
+        } = &mut self.inner
+        else {
+            unreachable!("read_split is only used with TcpUdp");
+        };
+
+        match socket.recv_from(&mut self.scratch) {
+            Ok((n, _)) => {
+                datagrams.feed(&self.scratch[..n]);
+                return Ok(true);
+            },
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => {},
+            Err(e) => return Err(e.into()),
+        }
+
+        match stream.read(&mut self.scratch) {
+            Ok(0) => Err(Error::Disconnected),
+            Ok(n) => {
+                self.codec.feed(&self.scratch[..n]);
+                Ok(true)
+            },
+            // A read timeout means we're blocking and should poll both sockets again. Without
+            // one the stream is non-blocking and nothing is available on either socket.
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) && stream.read_timeout()?.is_some() =>
+            {
+                Ok(false)
+            },
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Return any packet already buffered, checking for timeouts and answering keepalives.
+    fn next_buffered(&mut self) -> Result<Option<Packet>> {
+        if self.codec.reached_timeout() {
+            return Err(Error::Timeout(
+                "Timeout exceeded, no keepalive or packet received".into(),
+            ));
+        }
+        if let Some(packet) = self.codec.decode()? {
+            return Ok(Some(packet));
+        }
+        if let Transport::TcpUdp { datagrams, .. } = &mut self.inner {
+            // Datagrams use whichever version was negotiated over TCP
+            datagrams.set_version(self.codec.version());
+            if let Some(packet) = datagrams.decode_datagram() {
+                return Ok(Some(packet));
+            }
+        }
+        if let Some(keepalive) = self.codec.keepalive() {
+            self.write(keepalive)?;
+        }
+        Ok(None)
+    }
+
+    /// Read from the inner network once. Returns false if nothing was received.
+    fn fill(&mut self) -> Result<bool> {
+        if matches!(self.inner, Transport::TcpUdp { .. }) {
+            return self.read_split();
+        }
+        let n = self.read_inner()?;
+        if n == 0 {
+            return Err(Error::Disconnected);
+        }
+        Ok(true)
+    }
+
+    /// Wait for a packet from the inner network.
+    pub fn read(&mut self) -> Result<Packet> {
+        loop {
+            if let Some(packet) = self.next_buffered()? {
+                return Ok(packet);
+            }
+            let _ = self.fill()?;
+        }
+    }
+
+    /// Read a packet from the inner network, returning `None` rather than an error if the
+    /// read timed out or would block.
+    pub(crate) fn try_read(&mut self) -> Result<Option<Packet>> {
+        loop {
+            if let Some(packet) = self.next_buffered()? {
+                return Ok(Some(packet));
+            }
+            match self.fill() {
+                Ok(true) => continue,
+                Ok(false) => return Ok(None),
+                Err(Error::IO(e))
+                    if matches!(
+                        e.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None);
+                },
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Make reads block for at most `interval`, so that [Framed::try_read] can be interleaved
+    /// with other work on the same thread.
+    pub(crate) fn set_poll_interval(&self, interval: Duration) -> io::Result<()> {
+        match &self.inner {
+            Transport::Tcp(stream) | Transport::TcpUdp { stream, .. } => {
+                stream.set_nonblocking(false)?;
+                stream.set_read_timeout(Some(interval))
+            },
+            Transport::Udp(socket) => {
+                socket.set_nonblocking(false)?;
+                socket.set_read_timeout(Some(interval))
+            },
+            Transport::Stream(_) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                "cannot set a read timeout on a generic stream",
+            )),
+        }
+    }
+
+    /// The Insim version in use. This is negotiated when LFS sends a [crate::insim::Ver],
+    /// see [Codec::version].
+    pub fn version(&self) -> u8 {
+        self.codec.version()
+    }
+
+    /// Write a packet to the inner network.
+    pub fn write<P: Into<Packet>>(&mut self, packet: P) -> Result<()> {
+        let buf = self.codec.encode(&packet.into())?;
+        if buf.is_empty() {
+            return Ok(());
+        }
+        match &mut self.inner {
+            Transport::Tcp(stream) | Transport::TcpUdp { stream, .. } => stream.write_all(&buf)?,
+            Transport::Stream(stream) => {
+                stream.write_all(&buf)?;
+                stream.flush()?;
+            },
+            Transport::Udp(socket) => {
+                let n = socket.send(&buf)?;
+                if n != buf.len() {
+                    return Err(io::Error::new(
+                        io::ErrorKind::WriteZero,
+                        format!("short UDP send: {n} < {}", buf.len()),
+                    )
+                    .into());
+                }
+            },
+        }
+        Ok(())
+    }
+
+    /// Shutdown the inner network. For blocking this currently just a flush
+    pub fn shutdown(&mut self) -> Result<()> {
+        self.write(TinyType::Close)?;
+        if let Transport::Tcp(stream) | Transport::TcpUdp { stream, .. } = &mut self.inner {
+            let _ = stream.shutdown(std::net::Shutdown::Both);
+        }
+
+        Ok(())
+    }
+}
+
+impl From<TcpStream> for Framed {
+    fn from(value: TcpStream) -> Self {
+        Self::from_tcp(value, Codec::new())
+    }
+}
+
+impl From<UdpSocket> for Framed {
+    fn from(value: UdpSocket) -> Self {
+        Self::from_udp(value, Codec::new())
+    }
+}
@@ -1,14 +1,15 @@
 use std::{
     fmt,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use insim_core::{Decode, DecodeContext, DecodeErrorKind, Encode, EncodeContext, EncodeErrorKind};
 
-use super::DEFAULT_TIMEOUT_SECS;
+use super::{DEFAULT_TIMEOUT_SECS, Metrics};
 use crate::{
-    DEFAULT_BUFFER_CAPACITY, Error, MAX_SIZE_PACKET, VERSION, WithRequestId,
+    DEFAULT_BUFFER_CAPACITY, Error, MAX_SIZE_PACKET, MIN_VERSION, VERSION, WithRequestId,
     identifiers::RequestId,
     insim::{Tiny, TinyType, Ver},
     packet::Packet,
@@ -30,8 +31,169 @@ impl fmt::Display for HexDisplay<'_> {
 }
 
 const MAX_PACKET_SIZE: usize = 1020;
+
+/// Offset of [Ver::insimver] within a frame
+const VER_INSIMVER: usize = 18;
 const MIN_PACKET_SIZE: usize = 4;
 
+/// If `frame`, including the length prefix, is a [Ver], the Insim version it announces.
+pub(crate) fn ver_insimver(frame: &[u8]) -> Option<u8> {
+    match frame.get(1) {
+        Some(&Ver::DISCRIMINATOR) => frame.get(VER_INSIMVER).copied(),
+        _ => None,
+    }
+}
+
+/// Encode a [Packet] into [Bytes], including the length prefix, for the given Insim version.
+///
+/// Encoding is stateless. This is shared by [Codec::encode] and any writer which does not own a
+/// [Codec].
+#[tracing::instrument(err)]
+pub(crate) fn encode_packet(msg: &Packet, version: u8) -> Result<Bytes> {
+    let mut buf = BytesMut::with_capacity(msg.size_hint());
+
+    // add a placeholder for the size of the packet
+    buf.put_u8(0);
+
+    // encode the message
+    let mut ctx = EncodeContext::new(&mut buf).with_version(version);
+    msg.encode(&mut ctx)?;
+
+    let n = encode_length(buf.len())?;
+
+    // populate the size
+    buf[0] = n;
+
+    tracing::debug!(bytes = %HexDisplay(&buf), "encoded packet");
+
+    Ok(buf.freeze())
+}
+
+/// Decode a single complete frame, including the length prefix, into a [Packet] for the given
+/// Insim version.
+///
+/// Like [encode_packet] this is stateless, and does not apply any of the connection handling
+/// performed by [Codec::decode].
+pub(crate) fn decode_frame(frame: Bytes, version: u8) -> Result<Packet> {
+    tracing::trace!(bytes = %HexDisplay(&frame), "raw packet");
+
+    // cloning Bytes is cheap:
+    // Bytes values facilitate zero-copy network programming by allowing multiple Bytes objects to point to the same underlying memory.
+    let original = frame.clone();
+    let original_len = original.len();
+
+    // skip over the size field now that we know we have a full packet
+    // none of the packet definitions include the size
+    let mut data = frame;
+    data.advance(1);
+
+    let mut ctx = DecodeContext::new(&mut data).with_version(version);
+    match Packet::decode(&mut ctx) {
+        Ok(packet) => {
+            tracing::debug!(?packet, "decoded packet");
+            if ctx.buf.remaining() > 0 {
+                return Err(Error::IncompleteDecode {
+                    input: original,
+                    remaining: data,
+                });
+            }
+            Ok(packet)
+        },
+        Err(e) => Err(crate::Error::Decode {
+            offset: original_len - data.remaining(),
+            error: e,
+            input: original,
+        }),
+    }
+}
+
+/// Given a single packet in dst, encode it's length, and ensure that it does not
+/// exceed maximum limits
+#[tracing::instrument(level = "trace", ret, err)]
+fn encode_length(len: usize) -> Result<u8> {
+    if !(MIN_PACKET_SIZE..=MAX_PACKET_SIZE).contains(&len) {
+        return Err(EncodeErrorKind::OutOfRange {
+            min: MIN_PACKET_SIZE,
+            max: MAX_PACKET_SIZE,
+            found: len,
+        }
+        .context("Failed to encode packet length")
+        .into());
+    }
+
+    let n = if let Some(0) = len.checked_rem(4) {
+        len / 4
+    } else {
+        return Err(EncodeErrorKind::OutOfRange {
+            min: MIN_PACKET_SIZE,
+            max: MAX_PACKET_SIZE,
+            found: len,
+        }
+        .context("Packet length is not divisible by 4! This is probably a programming error.")
+        .into());
+    };
+
+    Ok(n as u8)
+}
+
+/// A set of packet types, identified by their discriminator, e.g. [crate::insim::Mci::DISCRIMINATOR].
+///
+/// Frames for packets outside of the set are not decoded by a [Codec]. Keepalives and the
+/// [Ver] version check are always handled, regardless of the set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Interest([u64; 4]);
+
+impl Default for Interest {
+    fn default() -> Self {
+        Self::ALL
+    }
+}
+
+impl Interest {
+    /// Every packet type.
+    pub const ALL: Self = Self([u64::MAX; 4]);
+
+    /// No packet types.
+    pub const NONE: Self = Self([0; 4]);
+
+    /// Add a packet type to the set.
+    pub const fn with(mut self, discriminator: u8) -> Self {
+        self.0[(discriminator / 64) as usize] |= 1 << (discriminator % 64);
+        self
+    }
+
+    /// Remove a packet type from the set.
+    pub const fn without(mut self, discriminator: u8) -> Self {
+        self.0[(discriminator / 64) as usize] &= !(1 << (discriminator % 64));
+        self
+    }
+
+    /// Is this packet type within the set?
+    pub const fn contains(&self, discriminator: u8) -> bool {
+        self.0[(discriminator / 64) as usize] & (1 << (discriminator % 64)) != 0
+    }
+}
+
+impl FromIterator<u8> for Interest {
+    fn from_iter<T: IntoIterator<Item = u8>>(iter: T) -> Self {
+        iter.into_iter().fold(Self::NONE, Self::with)
+    }
+}
+
+/// A frame decoded by [Codec::decode_filtered].
+#[derive(Debug, Clone)]
+pub enum Decoded {
+    /// A packet within the [Interest] set.
+    Packet(Packet),
+    /// A frame outside of the [Interest] set, which was not decoded.
+    Skipped {
+        /// Packet type identifier
+        discriminator: u8,
+        /// Length of the frame in bytes, including the length prefix
+        len: usize,
+    },
+}
+
 /// Handles the encoding and decoding of Insim packets to and from raw bytes.
 /// It automatically handles the encoding of the total size of the packet, and the packet
 /// type/identifier.
@@ -42,11 +204,19 @@ const MIN_PACKET_SIZE: usize = 4;
 /// With your own IO it is your responsibility to ensure that your read and write timeouts under
 /// blocking implementations are appropriately set. We recommend
 /// [crate::net::DEFAULT_TIMEOUT_SECS].
+///
+/// Any Insim version between [MIN_VERSION] and [VERSION] is accepted. The version is negotiated
+/// from the [Ver] received, as the lower of the announced version and the version this codec
+/// was configured to speak, and is used to encode and decode every packet thereafter.
 #[derive(Debug)]
 pub struct Codec {
     timeout_at: Instant,
     keepalive: bool,
     buffer: BytesMut,
+    interest: Interest,
+    version: u8,
+    max_version: u8,
+    metrics: Option<Arc<dyn Metrics>>,
 }
 
 impl Default for Codec {
@@ -62,29 +232,88 @@ impl Codec {
             timeout_at: Instant::now() + Duration::from_secs(DEFAULT_TIMEOUT_SECS),
             keepalive: false,
             buffer: BytesMut::with_capacity(DEFAULT_BUFFER_CAPACITY),
+            interest: Interest::ALL,
+            version: VERSION,
+            max_version: VERSION,
+            metrics: None,
         }
     }
 
-    /// Encode a [Packet] into [Bytes].
-    #[tracing::instrument(skip(self), err)]
-    pub fn encode(&self, msg: &Packet) -> Result<Bytes> {
-        let mut buf = BytesMut::with_capacity(msg.size_hint());
+    /// A new codec for datagrams received alongside this one, i.e. over UDP when using the TCP
+    /// + UDP split. It shares the same version, interest and metrics.
+    #[cfg(any(feature = "tokio", feature = "blocking"))]
+    pub(crate) fn datagram_codec(&self) -> Self {
+        Self {
+            interest: self.interest,
+            version: self.version,
+            max_version: self.max_version,
+            metrics: self.metrics.clone(),
+            ..Self::new()
+        }
+    }
+
+    /// Speak at most `version`, which should match the [crate::insim::Isi::version] sent.
+    /// Until a [Ver] is received the remote end is assumed to speak it too. Defaults to
+    /// [VERSION].
+    pub fn with_version(mut self, version: u8) -> Self {
+        self.version = version;
+        self.max_version = version;
+        self
+    }
+
+    /// Replace the Insim version in use.
+    pub fn set_version(&mut self, version: u8) {
+        self.version = version;
+    }
+
+    /// The Insim version in use. This is [VERSION] until a [Ver] has been received, unless
+    /// otherwise configured using [Codec::with_version].
+    pub fn version(&self) -> u8 {
+        self.version
+    }
+
+    /// Only decode packets within `interest`. Defaults to [Interest::ALL].
+    pub fn with_interest(mut self, interest: Interest) -> Self {
+        self.interest = interest;
+        self
+    }
 
-        // add a placeholder for the size of the packet
-        buf.put_u8(0);
+    /// Replace the set of packets which are decoded.
+    pub fn set_interest(&mut self, interest: Interest) {
+        self.interest = interest;
+    }
 
-        // encode the message
-        let mut ctx = EncodeContext::new(&mut buf);
-        msg.encode(&mut ctx)?;
+    /// The set of packets which are decoded.
+    pub fn interest(&self) -> Interest {
+        self.interest
+    }
 
-        let n = self.encode_length(buf.len())?;
+    /// Report traffic to `metrics`. Defaults to none.
+    pub fn with_metrics(mut self, metrics: Arc<dyn Metrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
 
-        // populate the size
-        buf[0] = n;
+    /// Replace the [Metrics] traffic is reported to.
+    pub fn set_metrics(&mut self, metrics: Option<Arc<dyn Metrics>>) {
+        self.metrics = metrics;
+    }
 
-        tracing::debug!(bytes = %HexDisplay(&buf), "encoded packet");
+    /// The [Metrics] traffic is reported to, if any.
+    pub fn metrics(&self) -> Option<&Arc<dyn Metrics>> {
+        self.metrics.as_ref()
+    }
 
-        Ok(buf.freeze())
+    /// Encode a [Packet] into [Bytes].
+    ///
+    /// Packets, or flags, which are not supported by the Insim version in use are rejected with
+    /// an [Error::Encode].
+    pub fn encode(&self, msg: &Packet) -> Result<Bytes> {
+        let buf = encode_packet(msg, self.version)?;
+        if let Some(metrics) = &self.metrics {
+            metrics.sent(msg, buf.len());
+        }
+        Ok(buf)
     }
 
     /// Feed the codec with bytes
@@ -111,9 +340,48 @@ impl Codec {
         &mut self.buffer
     }
 
-    /// Decode any complete packet in the buffer into a [Packet]
-    #[tracing::instrument(skip_all, fields(buf_len = self.buffer.len()), err)]
+    /// Decode any complete packet in the buffer into a [Packet]. Packets outside of the
+    /// [Interest] set are silently skipped.
     pub fn decode(&mut self) -> Result<Option<Packet>> {
+        Ok(self.decode_with_frame()?.map(|(packet, _)| packet))
+    }
+
+    /// Decode any complete packet in the buffer into a [Packet], also returning the raw frame it
+    /// was decoded from. This is useful for recording a session, see [crate::capture]. Packets
+    /// outside of the [Interest] set are silently skipped.
+    pub fn decode_with_frame(&mut self) -> Result<Option<(Packet, Bytes)>> {
+        while let Some(frame) = self.next_frame()? {
+            if self.interest.contains(frame[1]) {
+                let packet = self.decode_frame(frame.clone())?;
+                return Ok(Some((packet, frame)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Decode the next complete frame in the buffer, returning a [Decoded::Skipped] marker
+    /// rather than decoding packets outside of the [Interest] set.
+    pub fn decode_filtered(&mut self) -> Result<Option<Decoded>> {
+        let Some(frame) = self.next_frame()? else {
+            return Ok(None);
+        };
+        if self.interest.contains(frame[1]) {
+            Ok(Some(Decoded::Packet(self.decode_frame(frame)?)))
+        } else {
+            Ok(Some(Decoded::Skipped {
+                discriminator: frame[1],
+                len: frame.len(),
+            }))
+        }
+    }
+
+    /// Split any complete frame, including the length prefix, from the buffer without decoding
+    /// it. Keepalives and the [Ver] version check are still handled, as with [Codec::decode].
+    ///
+    /// Use with [crate::PacketRef] to avoid decoding packets, or fields, which you do not need.
+    /// The [Interest] set does not apply.
+    #[tracing::instrument(skip_all, fields(buf_len = self.buffer.len()), err)]
+    pub fn next_frame(&mut self) -> Result<Option<Bytes>> {
         if self.buffer.is_empty() {
             return Ok(None);
         }
@@ -125,56 +393,63 @@ impl Codec {
             },
         };
 
-        let mut data = self.buffer.split_to(n).freeze();
-
-        tracing::trace!(bytes = %HexDisplay(&data), "raw packet");
-
+        let frame = self.buffer.split_to(n).freeze();
         self.timeout_at = Instant::now() + Duration::from_secs(DEFAULT_TIMEOUT_SECS);
 
-        // cloning Bytes is cheap:
-        // Bytes values facilitate zero-copy network programming by allowing multiple Bytes objects to point to the same underlying memory.
-        let original = data.clone();
-        let original_len = original.len();
-
-        // skip over the size field now that we know we have a full packet
-        // none of the packet definitions include the size
-        data.advance(1);
-
-        let mut ctx = DecodeContext::new(&mut data);
-        let packet = Packet::decode(&mut ctx);
-        match packet {
-            Ok(packet) => {
-                tracing::debug!(?packet, "decoded packet");
-                if ctx.buf.remaining() > 0 {
-                    return Err(Error::IncompleteDecode {
-                        input: original,
-                        remaining: data,
-                    });
+        match frame[1] {
+            Tiny::DISCRIMINATOR if frame[2..] == [0, TinyType::None as u8] => {
+                self.keepalive = true;
+                if let Some(metrics) = &self.metrics {
+                    metrics.keepalive();
                 }
-
-                match packet {
-                    Packet::Tiny(Tiny {
-                        subt: TinyType::None,
-                        reqi: RequestId(0),
-                    }) => {
-                        self.keepalive = true;
-                    },
-                    Packet::Ver(Ver { insimver, .. }) if insimver != VERSION => {
+            },
+            Ver::DISCRIMINATOR => {
+                if let Some(insimver) = ver_insimver(&frame) {
+                    if !(MIN_VERSION..=VERSION).contains(&insimver) {
                         return Err(Error::IncompatibleVersion(insimver));
-                    },
-                    _ => {},
+                    }
+                    self.version = insimver.min(self.max_version);
                 }
+            },
+            _ => {},
+        }
 
-                Ok(Some(packet))
+        Ok(Some(frame))
+    }
+
+    /// Decode the datagram held in the buffer, for a codec that is only ever fed one whole UDP
+    /// datagram at a time.
+    /// Unlike a TCP stream a datagram can never be "completed" by a later read, so anything that
+    /// does not decode as an InSim packet is discarded rather than returned as an error. This
+    /// allows Outgauge and Outsim to share the same UDP port as InSim. Any bytes following the
+    /// packet are discarded too, so that they cannot be mistaken for the start of the next
+    /// datagram.
+    #[cfg(any(feature = "tokio", feature = "blocking"))]
+    pub(crate) fn decode_datagram(&mut self) -> Option<Packet> {
+        let result = self.decode();
+        self.buffer.clear();
+        match result {
+            Ok(packet) => packet,
+            Err(error) => {
+                tracing::debug!(?error, "discarding datagram which is not an InSim packet");
+                None
             },
-            Err(e) => Err(crate::Error::Decode {
-                offset: original_len - data.remaining(),
-                error: e,
-                input: original,
-            }),
         }
     }
 
+    /// Decode a frame, reporting the result to any [Metrics].
+    fn decode_frame(&self, frame: Bytes) -> Result<Packet> {
+        let len = frame.len();
+        let result = decode_frame(frame, self.version);
+        if let Some(metrics) = &self.metrics {
+            match &result {
+                Ok(packet) => metrics.received(packet, len),
+                Err(e) => metrics.decode_error(e),
+            }
+        }
+        result
+    }
+
     /// Return the next timeout
     pub fn timeout(&self) -> Instant {
         self.timeout_at
@@ -195,35 +470,6 @@ impl Codec {
         }
     }
 
-    /// Given a single packet in dst, encode it's length, and ensure that it does not
-    /// exceed maximum limits
-    #[tracing::instrument(skip(self), level = "trace", ret, err)]
-    fn encode_length(&self, len: usize) -> Result<u8> {
-        if !(MIN_PACKET_SIZE..=MAX_PACKET_SIZE).contains(&len) {
-            return Err(EncodeErrorKind::OutOfRange {
-                min: MIN_PACKET_SIZE,
-                max: MAX_PACKET_SIZE,
-                found: len,
-            }
-            .context("Failed to encode packet length")
-            .into());
-        }
-
-        let n = if let Some(0) = len.checked_rem(4) {
-            len / 4
-        } else {
-            return Err(EncodeErrorKind::OutOfRange {
-                min: MIN_PACKET_SIZE,
-                max: MAX_PACKET_SIZE,
-                found: len,
-            }
-            .context("Packet length is not divisible by 4! This is probably a programming error.")
-            .into());
-        };
-
-        Ok(n as u8)
-    }
-
     /// Decode the length of the next packet in the buffer src, ensuring that it does
     /// not exceed limits.
     #[tracing::instrument(skip_all, fields(src_len = src.len()), level = "trace", ret, err)]
@@ -272,7 +518,7 @@ mod tests {
     use super::*;
     use crate::{
         identifiers::RequestId,
-        insim::{Tiny, TinyType},
+        insim::{Isi, IsiFlags, Set, Tiny, TinyType},
         packet::Packet,
     };
 
@@ -315,4 +561,165 @@ mod tests {
 
         assert_eq!(&mock[..], &buf.unwrap()[..])
     }
+
+    #[test]
+    fn test_interest() {
+        use crate::insim::{Mci, Mso};
+
+        let interest: Interest = [Mci::DISCRIMINATOR, Tiny::DISCRIMINATOR]
+            .into_iter()
+            .collect();
+        assert!(interest.contains(Mci::DISCRIMINATOR));
+        assert!(!interest.contains(Mso::DISCRIMINATOR));
+        assert!(
+            !interest
+                .without(Mci::DISCRIMINATOR)
+                .contains(Mci::DISCRIMINATOR)
+        );
+        assert!(Interest::ALL.contains(255));
+        assert!(!Interest::NONE.contains(0));
+
+        let mut codec = Codec::new().with_interest(Interest::NONE.with(Mci::DISCRIMINATOR));
+        for packet in [
+            Packet::from(Mso::default()),
+            TinyType::None.into(),
+            Mci::default().into(),
+        ] {
+            codec.feed(&encode_packet(&packet, VERSION).unwrap());
+        }
+
+        assert!(matches!(
+            codec.decode_filtered().unwrap(),
+            Some(Decoded::Skipped {
+                discriminator: Mso::DISCRIMINATOR,
+                len: 12,
+            })
+        ));
+        // keepalives are still handled, even when skipped
+        assert!(matches!(codec.decode().unwrap(), Some(Packet::Mci(_))));
+        assert!(codec.keepalive().is_some());
+        assert!(codec.decode().unwrap().is_none());
+    }
+
+    #[cfg(any(feature = "tokio", feature = "blocking"))]
+    #[test]
+    fn test_decode_datagram_discards_trailing_bytes() {
+        let mut codec = Codec::new().datagram_codec();
+
+        // a ping, followed by the start of what looks like another packet
+        codec.feed(&[1, 3, 2, 3, 2, 3]);
+        assert!(matches!(
+            codec.decode_datagram(),
+            Some(Packet::Tiny(Tiny {
+                subt: TinyType::Ping,
+                ..
+            }))
+        ));
+
+        codec.feed(&[1, 3, 4, 3]);
+        assert!(matches!(
+            codec.decode_datagram(),
+            Some(Packet::Tiny(Tiny {
+                reqi: RequestId(4),
+                ..
+            }))
+        ));
+        assert!(codec.decode_datagram().is_none());
+    }
+
+    #[test]
+    fn test_version_negotiation() {
+        let mut codec = Codec::new();
+        assert_eq!(codec.version(), VERSION);
+        assert!(codec.encode(&Set::default().into()).is_ok());
+
+        codec.feed(
+            &encode_packet(
+                &Ver {
+                    insimver: MIN_VERSION,
+                    ..Default::default()
+                }
+                .into(),
+                VERSION,
+            )
+            .unwrap(),
+        );
+        assert!(matches!(codec.decode().unwrap(), Some(Packet::Ver(_))));
+        assert_eq!(codec.version(), MIN_VERSION);
+
+        // packets, and flags, introduced by a later version are rejected in both directions
+        assert!(matches!(
+            codec.encode(&Set::default().into()),
+            Err(Error::Encode(_))
+        ));
+        assert!(matches!(
+            codec.encode(&Isi::from(IsiFlags::SET).into()),
+            Err(Error::Encode(_))
+        ));
+        assert!(codec.encode(&Isi::from(IsiFlags::MCI).into()).is_ok());
+
+        codec.feed(&encode_packet(&Set::default().into(), VERSION).unwrap());
+        assert!(matches!(codec.decode(), Err(Error::Decode { .. })));
+
+        // never speak a later version than configured, even if LFS does
+        let mut codec = Codec::new().with_version(MIN_VERSION);
+        codec.feed(
+            &encode_packet(
+                &Ver {
+                    insimver: VERSION,
+                    ..Default::default()
+                }
+                .into(),
+                VERSION,
+            )
+            .unwrap(),
+        );
+        assert!(matches!(codec.decode().unwrap(), Some(Packet::Ver(_))));
+        assert_eq!(codec.version(), MIN_VERSION);
+    }
+
+    #[test]
+    fn test_interest_still_validates_version() {
+        let mut codec = Codec::new().with_interest(Interest::NONE);
+        codec.feed(
+            &encode_packet(
+                &Ver {
+                    insimver: MIN_VERSION - 1,
+                    ..Default::default()
+                }
+                .into(),
+                VERSION,
+            )
+            .unwrap(),
+        );
+        assert!(matches!(
+            codec.decode(),
+            Err(Error::IncompatibleVersion(v)) if v == MIN_VERSION - 1
+        ));
+    }
+
+    #[test]
+    fn test_metrics() {
+        use crate::{insim::Mso, net::MemoryMetrics};
+
+        let metrics = MemoryMetrics::new();
+        let mut codec = Codec::new().with_metrics(metrics.clone());
+        codec.feed(&encode_packet(&TinyType::None.into(), VERSION).unwrap());
+        codec.feed(&encode_packet(&Mso::default().into(), VERSION).unwrap());
+        // Tiny with an unknown subtype
+        codec.feed(&[1, 3, 0, 255]);
+
+        assert!(matches!(codec.decode().unwrap(), Some(Packet::Tiny(_))));
+        assert!(matches!(codec.decode().unwrap(), Some(Packet::Mso(_))));
+        assert!(codec.decode().is_err());
+        let _ = codec.encode(&TinyType::Ping.into()).unwrap();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.keepalives, 1);
+        assert_eq!(snapshot.received["Tiny"].packets, 1);
+        assert_eq!(snapshot.received["Mso"].bytes, 12);
+        assert_eq!(snapshot.total_received().bytes, 16);
+        assert_eq!(snapshot.sent["Tiny"].packets, 1);
+        assert_eq!(snapshot.decode_errors["NoVariantMatch"], 1);
+    }
 }
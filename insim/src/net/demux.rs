@@ -0,0 +1,293 @@
+//! Receive InSim, OutGauge and OutSim on a single UDP socket, see [Demux].
+#[cfg(any(feature = "blocking", feature = "tokio"))]
+use std::net::SocketAddr;
+
+use bytes::Bytes;
+use insim_core::{Decode, DecodeContext};
+use outgauge::Outgauge;
+use outsim::{
+    OutsimPack,
+    outsimpack2::{OutSimOpts, OutsimPack2},
+};
+
+use crate::{Error, Packet, Result, VERSION, insim::Nlp, net::codec::decode_frame};
+
+/// Sizes of an [Outgauge], without and with the optional ID.
+const OUTGAUGE_SIZES: [usize; 2] = [92, 96];
+
+/// Sizes of an [OutsimPack], without and with the optional ID.
+const OUTSIM_SIZES: [usize; 2] = [64, 68];
+
+/// A datagram classified by [Demux].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Datagram {
+    /// An InSim packet, i.e. [Packet::Mci] or [Packet::Nlp]
+    Insim(Packet),
+    /// OutGauge
+    Outgauge(Outgauge),
+    /// OutSim, when no `OutSim Opts` are configured in LFS
+    Outsim(OutsimPack),
+    /// OutSim, when `OutSim Opts` are configured in LFS, see [Demux::outsim_opts]
+    Outsim2(Box<OutsimPack2>),
+}
+
+/// Classifies datagrams received on the udpport given to LFS, see
+/// [crate::builder::Builder::isi_udpport]. LFS sends [Packet::Mci] and [Packet::Nlp], OutGauge
+/// and OutSim to the same port, none of which identify themselves, so each datagram is
+/// classified by the following, in order:
+///
+/// 1. [OutsimPack2], if [Demux::outsim_opts] is set and the datagram is the size those options
+///    produce. If the options include [OutSimOpts::HEADER] it must also start with `LFST`.
+/// 2. [Outgauge], if the datagram is 92 or 96 bytes.
+/// 3. [OutsimPack], if [Demux::outsim_opts] is not set and the datagram is 64 or 68 bytes.
+/// 4. InSim, if the size byte matches the datagram and it decodes completely.
+///
+/// OutGauge and OutSim start with a timestamp, which can look like an InSim header, so their
+/// sizes are checked first. The exception is an [Packet::Nlp] for 10 or 15 players, which is
+/// the same size as OutSim or OutGauge with an ID, and is treated as InSim if its header
+/// matches its size exactly.
+///
+/// ```rust,ignore
+/// let socket = tokio::net::UdpSocket::bind("0.0.0.0:30000").await?;
+/// let demux = Demux::new();
+/// loop {
+///     match demux.recv_from_async(&socket).await?.0 {
+///         Datagram::Insim(packet) => println!("{packet:?}"),
+///         Datagram::Outgauge(outgauge) => println!("{outgauge:?}"),
+///         _ => {},
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Demux {
+    version: Option<u8>,
+    outsim_opts: Option<OutSimOpts>,
+}
+
+impl Demux {
+    /// New `Demux`, expecting [OutsimPack] rather than [OutsimPack2].
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// InSim version to decode packets as. Defaults to [VERSION].
+    pub fn version(mut self, version: u8) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// The `OutSim Opts` configured in LFS's cfg.txt. When set, OutSim is decoded as
+    /// [OutsimPack2] using these options, rather than as [OutsimPack].
+    pub fn outsim_opts<O: Into<Option<OutSimOpts>>>(mut self, opts: O) -> Self {
+        self.outsim_opts = opts.into();
+        self
+    }
+
+    /// Classify and decode a single datagram.
+    pub fn classify(&self, datagram: &[u8]) -> Result<Datagram> {
+        let len = datagram.len();
+
+        if let Some(opts) = &self.outsim_opts
+            && len == opts.packet_size()
+            && (!opts.contains(OutSimOpts::HEADER) || datagram.starts_with(b"LFST"))
+        {
+            let mut buf = Bytes::copy_from_slice(datagram);
+            return OutsimPack2::decode_with_options(&mut DecodeContext::new(&mut buf), opts)
+                .map(|pack| Datagram::Outsim2(Box::new(pack)))
+                .map_err(|error| decode_error(datagram, &buf, error));
+        }
+
+        if OUTGAUGE_SIZES.contains(&len) && !is_nlp(datagram) {
+            let mut buf = Bytes::copy_from_slice(datagram);
+            return Outgauge::decode(&mut DecodeContext::new(&mut buf))
+                .map(Datagram::Outgauge)
+                .map_err(|error| decode_error(datagram, &buf, error));
+        }
+
+        if self.outsim_opts.is_none() && OUTSIM_SIZES.contains(&len) && !is_nlp(datagram) {
+            let mut buf = Bytes::copy_from_slice(datagram);
+            return OutsimPack::decode(&mut DecodeContext::new(&mut buf))
+                .map(Datagram::Outsim)
+                .map_err(|error| decode_error(datagram, &buf, error));
+        }
+
+        if len >= 4 && datagram[0] as usize * 4 == len {
+            let frame = Bytes::copy_from_slice(datagram);
+            if let Ok(packet) = decode_frame(frame, self.version.unwrap_or(VERSION)) {
+                return Ok(Datagram::Insim(packet));
+            }
+        }
+
+        Err(Error::UnrecognisedDatagram(Bytes::copy_from_slice(
+            datagram,
+        )))
+    }
+
+    /// Receive and classify a single datagram from `socket`.
+    #[cfg(feature = "blocking")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
+    pub fn recv_from(&self, socket: &std::net::UdpSocket) -> Result<(Datagram, SocketAddr)> {
+        let mut buf = [0; crate::MAX_SIZE_PACKET];
+        let (len, from) = socket.recv_from(&mut buf)?;
+        Ok((self.classify(&buf[..len])?, from))
+    }
+
+    /// Receive and classify a single datagram from `socket`.
+    #[cfg(feature = "tokio")]
+    #[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+    pub async fn recv_from_async(
+        &self,
+        socket: &tokio::net::UdpSocket,
+    ) -> Result<(Datagram, SocketAddr)> {
+        let mut buf = [0; crate::MAX_SIZE_PACKET];
+        let (len, from) = socket.recv_from(&mut buf).await?;
+        Ok((self.classify(&buf[..len])?, from))
+    }
+}
+
+/// Is `datagram` an [Packet::Nlp], whose size byte and player count both match its length?
+fn is_nlp(datagram: &[u8]) -> bool {
+    let [size, ty, _, nump, ..] = *datagram else {
+        return false;
+    };
+    ty == Nlp::DISCRIMINATOR
+        && size as usize * 4 == datagram.len()
+        && (4 + nump as usize * 6).next_multiple_of(4) == datagram.len()
+}
+
+fn decode_error(datagram: &[u8], remaining: &Bytes, error: insim_core::DecodeError) -> Error {
+    Error::Decode {
+        offset: datagram.len() - remaining.len(),
+        input: Bytes::copy_from_slice(datagram),
+        error,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use insim_core::{Encode, EncodeContext};
+
+    use super::*;
+    use crate::{
+        identifiers::PlayerId,
+        insim::{CompCar, Mci, Mso, NodeLapInfo},
+        net::Codec,
+    };
+
+    fn encode<T: Encode>(value: &T) -> Vec<u8> {
+        let mut buf = BytesMut::new();
+        value.encode(&mut EncodeContext::new(&mut buf)).unwrap();
+        buf.to_vec()
+    }
+
+    #[test]
+    fn test_classify() {
+        let demux = Demux::new();
+
+        let mci = Codec::new()
+            .encode(
+                &Mci {
+                    info: vec![CompCar {
+                        plid: PlayerId(3),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                }
+                .into(),
+            )
+            .unwrap();
+        assert!(matches!(
+            demux.classify(&mci),
+            Ok(Datagram::Insim(Packet::Mci(Mci { info, .. }))) if info[0].plid == PlayerId(3)
+        ));
+
+        let outgauge = encode(&Outgauge {
+            time: std::time::Duration::from_millis(23),
+            car: insim_core::vehicle::Vehicle::Xrt,
+            rpm: 2000.0,
+            ..Default::default()
+        });
+        assert_eq!(outgauge.len(), 92);
+        assert!(matches!(
+            demux.classify(&outgauge),
+            Ok(Datagram::Outgauge(Outgauge { rpm: 2000.0, .. }))
+        ));
+
+        let outsim = encode(&OutsimPack {
+            heading: 1.5,
+            ..Default::default()
+        });
+        assert!(matches!(
+            demux.classify(&outsim),
+            Ok(Datagram::Outsim(OutsimPack { heading: 1.5, .. }))
+        ));
+
+        assert!(matches!(
+            demux.classify(&[1, 2, 3]),
+            Err(Error::UnrecognisedDatagram(_))
+        ));
+    }
+
+    #[test]
+    fn test_classify_sizes_before_insim() {
+        let demux = Demux::new();
+        let header = |len: usize| [(len / 4) as u8, Mso::DISCRIMINATOR, 0, 0];
+
+        // A timestamp which happens to look like an Mso header
+        let mut outgauge = encode(&Outgauge::default());
+        outgauge[..4].copy_from_slice(&header(92));
+        assert_eq!(outgauge[0], 23);
+        assert!(matches!(
+            demux.classify(&outgauge),
+            Ok(Datagram::Outgauge(_))
+        ));
+
+        let mut outsim = encode(&OutsimPack::default());
+        outsim[..4].copy_from_slice(&header(64));
+        assert_eq!(outsim[0], 16);
+        assert!(matches!(demux.classify(&outsim), Ok(Datagram::Outsim(_))));
+
+        // Nlp for 10 and 15 players are the same sizes as OutSim and OutGauge with an ID
+        for (count, len) in [(10, 64), (15, 96)] {
+            let nlp = Codec::new()
+                .encode(
+                    &Nlp {
+                        info: vec![NodeLapInfo::default(); count],
+                        ..Default::default()
+                    }
+                    .into(),
+                )
+                .unwrap();
+            assert_eq!(nlp.len(), len);
+            assert!(matches!(
+                demux.classify(&nlp),
+                Ok(Datagram::Insim(Packet::Nlp(Nlp { info, .. }))) if info.len() == count
+            ));
+        }
+    }
+
+    #[test]
+    fn test_classify_outsim_opts() {
+        let opts = OutSimOpts::HEADER | OutSimOpts::TIME | OutSimOpts::MAIN;
+        let demux = Demux::new().outsim_opts(opts);
+
+        let pack = OutsimPack2 {
+            header: Some("LFST".into()),
+            time: Some(std::time::Duration::from_millis(100)),
+            ..Default::default()
+        };
+        let mut buf = BytesMut::new();
+        pack.encode_with_options(&mut EncodeContext::new(&mut buf), &opts)
+            .unwrap();
+        assert!(matches!(
+            demux.classify(&buf),
+            Ok(Datagram::Outsim2(pack)) if pack.time == Some(std::time::Duration::from_millis(100))
+        ));
+
+        // Only OutsimPack2 is expected once opts are set
+        let outsim = encode(&OutsimPack::default());
+        assert!(demux.classify(&outsim).is_err());
+    }
+}
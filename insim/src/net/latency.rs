@@ -0,0 +1,248 @@
+//! Round trip latency monitoring, using [TinyType::Ping] and [TinyType::Reply].
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Packet, WithRequestId,
+    identifiers::RequestId,
+    insim::{Tiny, TinyType},
+};
+
+/// Rolling round trip time statistics, from a [LatencyMonitor].
+///
+/// Every duration is zero until the first reply has been received.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct LatencyStats {
+    /// Number of round trips the statistics are calculated from.
+    pub samples: usize,
+    /// Most recent round trip time.
+    pub last: Duration,
+    /// Shortest round trip time.
+    pub min: Duration,
+    /// Mean round trip time.
+    pub avg: Duration,
+    /// 95th percentile round trip time.
+    pub p95: Duration,
+    /// Longest round trip time.
+    pub max: Duration,
+    /// Total number of pings sent.
+    pub pings: u64,
+    /// Is a ping overdue? See [LatencyMonitor::stall_after].
+    pub stalled: bool,
+}
+
+/// Measures the round trip time of a connection, by periodically sending a [TinyType::Ping]
+/// using [LatencyMonitor::REQUEST_ID] and timing the matching [TinyType::Reply].
+///
+/// Only one ping is outstanding at a time. If the reply takes longer than
+/// [LatencyMonitor::stall_after] the connection is considered stalled until it arrives. This is
+/// independent of, and typically much sooner than, the connection timing out with
+/// [crate::Error::Timeout].
+///
+/// This is sans-io. Use [crate::builder::Builder::latency_monitor] to have a spawned
+/// connection drive it for you, and hide the replies from subscribers.
+#[derive(Debug, Clone)]
+pub struct LatencyMonitor {
+    interval: Duration,
+    stall_after: Duration,
+    window: usize,
+    samples: VecDeque<Duration>,
+    next_ping: Option<Instant>,
+    outstanding: Option<Instant>,
+    pings: u64,
+    stalled: bool,
+}
+
+impl LatencyMonitor {
+    /// The [RequestId] used for pings. Avoid using this for your own requests.
+    ///
+    /// This is outside of the range used by [crate::request::RequestIds].
+    pub const REQUEST_ID: RequestId = RequestId(127);
+
+    /// Create a monitor which pings every `interval`, keeps the last 100 round trip times and
+    /// considers the connection stalled after 5 seconds without a reply.
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            stall_after: Duration::from_secs(5),
+            window: 100,
+            samples: VecDeque::new(),
+            next_ping: None,
+            outstanding: None,
+            pings: 0,
+            stalled: false,
+        }
+    }
+
+    /// Number of round trip times the statistics are calculated from. Defaults to 100.
+    pub fn window(mut self, window: usize) -> Self {
+        self.window = window.max(1);
+        self
+    }
+
+    /// How long to wait for a reply before considering the connection stalled. Defaults to 5
+    /// seconds.
+    pub fn stall_after(mut self, stall_after: Duration) -> Self {
+        self.stall_after = stall_after;
+        self
+    }
+
+    /// Update the stalled state, and return a ping if one should be sent at `now`.
+    pub fn poll(&mut self, now: Instant) -> Option<Packet> {
+        if let Some(sent) = self.outstanding {
+            self.stalled = now.saturating_duration_since(sent) >= self.stall_after;
+            return None;
+        }
+        if self.next_ping.is_some_and(|next| next > now) {
+            return None;
+        }
+        self.outstanding = Some(now);
+        self.next_ping = Some(now + self.interval);
+        self.pings += 1;
+        Some(TinyType::Ping.with_request_id(Self::REQUEST_ID).into())
+    }
+
+    /// Record `packet` if it is the reply to our ping, returning true if so. Such packets should
+    /// not be passed on.
+    pub fn handle(&mut self, packet: &Packet, now: Instant) -> bool {
+        let Packet::Tiny(Tiny {
+            subt: TinyType::Reply,
+            reqi,
+        }) = packet
+        else {
+            return false;
+        };
+        if *reqi != Self::REQUEST_ID {
+            return false;
+        }
+        if let Some(sent) = self.outstanding.take() {
+            if self.samples.len() == self.window {
+                let _ = self.samples.pop_front();
+            }
+            self.samples.push_back(now.saturating_duration_since(sent));
+            self.stalled = false;
+        }
+        true
+    }
+
+    /// When [LatencyMonitor::poll] next needs to be called, or `None` if nothing will happen
+    /// until the outstanding reply is received.
+    pub fn poll_at(&self, now: Instant) -> Option<Instant> {
+        match (self.outstanding, self.next_ping) {
+            (Some(_), _) if self.stalled => None,
+            (Some(sent), _) => Some(sent + self.stall_after),
+            (None, next) => Some(next.unwrap_or(now)),
+        }
+    }
+
+    /// Is the connection stalled, as of the last call to [LatencyMonitor::poll]?
+    pub fn is_stalled(&self) -> bool {
+        self.stalled
+    }
+
+    /// Statistics calculated from the most recent round trip times.
+    pub fn stats(&self) -> LatencyStats {
+        let stats = LatencyStats {
+            samples: self.samples.len(),
+            pings: self.pings,
+            stalled: self.stalled,
+            ..Default::default()
+        };
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let (Some(&min), Some(&max), Some(&last)) =
+            (sorted.first(), sorted.last(), self.samples.back())
+        else {
+            return stats;
+        };
+        // nearest rank
+        let p95 = sorted[(sorted.len() * 95).div_ceil(100) - 1];
+        let avg = sorted.iter().sum::<Duration>() / sorted.len() as u32;
+        LatencyStats {
+            last,
+            min,
+            avg,
+            p95,
+            max,
+            ..stats
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reply(reqi: RequestId) -> Packet {
+        TinyType::Reply.with_request_id(reqi).into()
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let start = Instant::now();
+        let mut monitor = LatencyMonitor::new(Duration::from_secs(1)).window(20);
+
+        for i in 1..=25u64 {
+            let now = start + Duration::from_secs(i);
+            assert!(matches!(
+                monitor.poll(now),
+                Some(Packet::Tiny(Tiny {
+                    subt: TinyType::Ping,
+                    reqi: LatencyMonitor::REQUEST_ID
+                }))
+            ));
+            // only one ping is outstanding at a time
+            assert!(monitor.poll(now).is_none());
+            // replies to anyone else are not ours
+            assert!(!monitor.handle(&reply(RequestId(1)), now));
+            assert!(monitor.handle(
+                &reply(LatencyMonitor::REQUEST_ID),
+                now + Duration::from_millis(i)
+            ));
+            assert_eq!(monitor.poll_at(now), Some(now + Duration::from_secs(1)));
+        }
+
+        // only the last 20 samples, 6ms to 25ms, are kept
+        let stats = monitor.stats();
+        assert_eq!(stats.samples, 20);
+        assert_eq!(stats.pings, 25);
+        assert_eq!(stats.last, Duration::from_millis(25));
+        assert_eq!(stats.min, Duration::from_millis(6));
+        assert_eq!(stats.max, Duration::from_millis(25));
+        assert_eq!(stats.avg, Duration::from_micros(15_500));
+        assert_eq!(stats.p95, Duration::from_millis(24));
+        assert!(!stats.stalled);
+    }
+
+    #[test]
+    fn test_stalled() {
+        let now = Instant::now();
+        let mut monitor =
+            LatencyMonitor::new(Duration::from_secs(1)).stall_after(Duration::from_secs(2));
+        assert_eq!(monitor.stats(), LatencyStats::default());
+
+        assert!(monitor.poll(now).is_some());
+        assert_eq!(monitor.poll_at(now), Some(now + Duration::from_secs(2)));
+        assert!(monitor.poll(now + Duration::from_secs(1)).is_none());
+        assert!(!monitor.is_stalled());
+
+        // no further pings are sent whilst stalled
+        assert!(monitor.poll(now + Duration::from_secs(2)).is_none());
+        assert!(monitor.is_stalled());
+        assert!(monitor.stats().stalled);
+        assert_eq!(monitor.poll_at(now), None);
+        assert!(monitor.poll(now + Duration::from_secs(3)).is_none());
+
+        // a late reply still counts
+        assert!(monitor.handle(
+            &reply(LatencyMonitor::REQUEST_ID),
+            now + Duration::from_secs(4)
+        ));
+        assert!(!monitor.is_stalled());
+        assert_eq!(monitor.stats().max, Duration::from_secs(4));
+        assert!(monitor.poll(now + Duration::from_secs(4)).is_some());
+    }
+}
@@ -0,0 +1,182 @@
+//! Traffic metrics, see [Metrics].
+
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, Mutex},
+};
+
+use crate::{Error, Packet};
+
+/// Receives traffic events from a [crate::net::Codec], and therefore from both the tokio and
+/// blocking `Framed`. Implement this to export to your own monitoring, or use
+/// [MemoryMetrics].
+///
+/// Every method has an empty default implementation. Methods are called inline whilst reading
+/// and writing, so should be cheap and must not block.
+pub trait Metrics: fmt::Debug + Send + Sync {
+    /// A packet was received, in a frame of `len` bytes.
+    fn received(&self, packet: &Packet, len: usize) {
+        let _ = (packet, len);
+    }
+
+    /// A packet was encoded to be sent, in a frame of `len` bytes.
+    fn sent(&self, packet: &Packet, len: usize) {
+        let _ = (packet, len);
+    }
+
+    /// A frame could not be decoded. `error` is either [Error::Decode] or
+    /// [Error::IncompleteDecode].
+    fn decode_error(&self, error: &Error) {
+        let _ = error;
+    }
+
+    /// A keepalive was received from LFS.
+    fn keepalive(&self) {}
+
+    /// A supervised connection was re-established, see `Builder::supervised`.
+    fn reconnected(&self) {}
+}
+
+/// Number of packets, and their total size.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Traffic {
+    /// Number of packets.
+    pub packets: u64,
+    /// Total size of the packets in bytes, including the length prefix.
+    pub bytes: u64,
+}
+
+impl Traffic {
+    fn add(&mut self, len: usize) {
+        self.packets += 1;
+        self.bytes += len as u64;
+    }
+}
+
+/// Snapshot of a [MemoryMetrics].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MetricsSnapshot {
+    /// Packets received, by [Packet::name].
+    pub received: BTreeMap<&'static str, Traffic>,
+    /// Packets sent, by [Packet::name].
+    pub sent: BTreeMap<&'static str, Traffic>,
+    /// Decode errors, by [insim_core::DecodeErrorKind::name] of the innermost error, or
+    /// `"IncompleteDecode"`.
+    pub decode_errors: BTreeMap<&'static str, u64>,
+    /// Keepalives received.
+    pub keepalives: u64,
+    /// Times the connection was re-established.
+    pub reconnects: u64,
+}
+
+impl MetricsSnapshot {
+    /// Total of every packet received.
+    pub fn total_received(&self) -> Traffic {
+        total(&self.received)
+    }
+
+    /// Total of every packet sent.
+    pub fn total_sent(&self) -> Traffic {
+        total(&self.sent)
+    }
+}
+
+fn total(traffic: &BTreeMap<&'static str, Traffic>) -> Traffic {
+    traffic.values().fold(Traffic::default(), |a, b| Traffic {
+        packets: a.packets + b.packets,
+        bytes: a.bytes + b.bytes,
+    })
+}
+
+/// [Metrics] which are counted in memory, and read using [MemoryMetrics::snapshot].
+///
+/// ```rust
+/// use insim::net::{Codec, MemoryMetrics};
+///
+/// let metrics = MemoryMetrics::new();
+/// let codec = Codec::new().with_metrics(metrics.clone());
+/// // later
+/// println!("{:?}", metrics.snapshot().total_received());
+/// ```
+#[derive(Debug, Default)]
+pub struct MemoryMetrics(Mutex<MetricsSnapshot>);
+
+impl MemoryMetrics {
+    /// Create new, empty, metrics.
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self::default())
+    }
+
+    /// A copy of the current counts.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.lock().clone()
+    }
+
+    /// Reset every count to zero.
+    pub fn reset(&self) {
+        *self.lock() = MetricsSnapshot::default();
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MetricsSnapshot> {
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Metrics for MemoryMetrics {
+    fn received(&self, packet: &Packet, len: usize) {
+        self.lock()
+            .received
+            .entry(packet.name())
+            .or_default()
+            .add(len);
+    }
+
+    fn sent(&self, packet: &Packet, len: usize) {
+        self.lock().sent.entry(packet.name()).or_default().add(len);
+    }
+
+    fn decode_error(&self, error: &Error) {
+        let kind = match error {
+            Error::Decode { error, .. } => error.root_kind().name(),
+            Error::IncompleteDecode { .. } => "IncompleteDecode",
+            _ => "Other",
+        };
+        *self.lock().decode_errors.entry(kind).or_default() += 1;
+    }
+
+    fn keepalive(&self) {
+        self.lock().keepalives += 1;
+    }
+
+    fn reconnected(&self) {
+        self.lock().reconnects += 1;
+    }
+}
+
+/// [Metrics] which emits a `tracing` event, at trace level with the target `insim::metrics`,
+/// for every packet and a warning for every decode error.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TracingMetrics;
+
+impl Metrics for TracingMetrics {
+    fn received(&self, packet: &Packet, len: usize) {
+        tracing::trace!(target: "insim::metrics", packet = packet.name(), len, "received");
+    }
+
+    fn sent(&self, packet: &Packet, len: usize) {
+        tracing::trace!(target: "insim::metrics", packet = packet.name(), len, "sent");
+    }
+
+    fn decode_error(&self, error: &Error) {
+        tracing::warn!(target: "insim::metrics", %error, "decode error");
+    }
+
+    fn keepalive(&self) {
+        tracing::trace!(target: "insim::metrics", "keepalive");
+    }
+
+    fn reconnected(&self) {
+        tracing::info!(target: "insim::metrics", "reconnected");
+    }
+}
@@ -10,9 +10,29 @@ pub mod tokio_impl;
 #[cfg_attr(docsrs, doc(cfg(feature = "blocking")))]
 pub mod blocking_impl;
 
+#[cfg(feature = "tokio")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tokio")))]
+pub mod supervised;
+
+#[cfg(feature = "demux")]
+#[cfg_attr(docsrs, doc(cfg(feature = "demux")))]
+pub mod demux;
+
+#[cfg(any(feature = "tokio", feature = "blocking"))]
+pub(crate) mod actor;
 pub(crate) mod codec;
+pub(crate) mod latency;
+pub(crate) mod metrics;
+pub(crate) mod queue;
 
-pub use codec::Codec;
+pub use codec::{Codec, Decoded, Interest};
+pub use latency::{LatencyMonitor, LatencyStats};
+pub use metrics::{MemoryMetrics, Metrics, MetricsSnapshot, TracingMetrics, Traffic};
+pub use queue::{Priority, QueueMetrics, SendQueue};
 
 /// If no data is received within this period of seconds, consider the Insim connection to be lost.
 pub const DEFAULT_TIMEOUT_SECS: u64 = 70;
+
+/// How many packets a subscriber to a spawned connection may fall behind by before it starts
+/// missing packets.
+pub const DEFAULT_SUBSCRIBER_CAPACITY: usize = 512;
@@ -0,0 +1,361 @@
+//! Paced, prioritised outbound packet queue.
+
+use std::{
+    collections::VecDeque,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    Packet,
+    identifiers::{ClickId, ConnectionId},
+    insim::{Bfn, BfnType, Btn},
+};
+
+/// Outbound priority class of a [Packet]. Higher priority packets are always sent before lower
+/// priority packets, regardless of the order in which they were queued.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Priority {
+    /// Commands, such as [crate::insim::Mst] starting with `/`, and every other packet which
+    /// is neither a button nor chat.
+    Admin,
+    /// [crate::insim::Btn] and [crate::insim::Bfn].
+    Button,
+    /// Chat and messages, i.e. [crate::insim::Mst], [crate::insim::Msx],
+    /// [crate::insim::Mtc] and [crate::insim::Msl].
+    Chat,
+}
+
+impl Priority {
+    const ALL: [Priority; 3] = [Priority::Admin, Priority::Button, Priority::Chat];
+
+    /// Classify a packet.
+    pub fn of(packet: &Packet) -> Self {
+        match packet {
+            Packet::Mst(mst) if mst.msg.starts_with('/') => Self::Admin,
+            Packet::Mst(_) | Packet::Msx(_) | Packet::Mtc(_) | Packet::Msl(_) => Self::Chat,
+            Packet::Btn(_) | Packet::Bfn(_) => Self::Button,
+            _ => Self::Admin,
+        }
+    }
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+/// Snapshot of a [SendQueue].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueueMetrics {
+    /// Packets currently queued, indexed by [Priority].
+    pub depth: [usize; 3],
+    /// Highest total number of packets queued at any one time.
+    pub peak: usize,
+    /// Total number of packets sent.
+    pub sent: u64,
+    /// Total number of packets which were superseded by a later packet before being sent.
+    pub coalesced: u64,
+}
+
+impl QueueMetrics {
+    /// Total number of packets currently queued.
+    pub fn total(&self) -> usize {
+        self.depth.iter().sum()
+    }
+
+    /// Number of packets currently queued with the given priority.
+    pub fn depth(&self, priority: Priority) -> usize {
+        self.depth[priority.index()]
+    }
+}
+
+/// An outbound queue, which paces packets to avoid tripping LFS's flood protection.
+///
+/// Packets are released at no more than the configured rate, after an initial burst, with the
+/// highest [Priority] first. Whilst a [Btn] is queued it is replaced by any later [Btn] for the
+/// same [ClickId] and [ConnectionId], and removed entirely by a later [Bfn] which deletes it.
+///
+/// This is sans-io. Use [crate::builder::Builder::send_queue] to have a spawned connection
+/// drive it for you.
+#[derive(Debug, Clone)]
+pub struct SendQueue {
+    queues: [VecDeque<Packet>; 3],
+    interval: Duration,
+    burst: u32,
+    // Generic cell rate algorithm, the time at which the bucket is empty again
+    tat: Option<Instant>,
+    metrics: QueueMetrics,
+}
+
+impl SendQueue {
+    /// Create a queue which sends at most `per_second` packets per second, once any burst has
+    /// been used. A rate of zero is treated as one.
+    pub fn new(per_second: u32) -> Self {
+        Self {
+            queues: Default::default(),
+            interval: Duration::from_secs(1) / per_second.max(1),
+            burst: 1,
+            tat: None,
+            metrics: QueueMetrics::default(),
+        }
+    }
+
+    /// Allow up to `burst` packets to be sent immediately after a quiet period. Defaults to 1.
+    pub fn burst(mut self, burst: u32) -> Self {
+        self.burst = burst.max(1);
+        self
+    }
+
+    /// Queue a packet.
+    pub fn push<P: Into<Packet>>(&mut self, packet: P) {
+        let packet = packet.into();
+        let priority = Priority::of(&packet);
+        let queue = &mut self.queues[priority.index()];
+
+        let before = queue.len();
+        match &packet {
+            Packet::Btn(btn) => {
+                if let Some(queued) = superseded_btn(queue, btn) {
+                    *queued = packet;
+                    self.metrics.coalesced += 1;
+                    return;
+                }
+            },
+            Packet::Bfn(bfn) => {
+                if let Some(range) = deleted_range(bfn) {
+                    queue.retain(|queued| match queued {
+                        Packet::Btn(btn) => !range.covers(btn.ucid, btn.clickid),
+                        Packet::Bfn(other) => other != bfn,
+                        _ => true,
+                    });
+                }
+            },
+            _ => {},
+        }
+        self.metrics.coalesced += (before - queue.len()) as u64;
+
+        queue.push_back(packet);
+        self.update_depth();
+    }
+
+    /// Remove the next packet, if one is queued and pacing allows it to be sent at `now`.
+    pub fn pop(&mut self, now: Instant) -> Option<Packet> {
+        if self.ready_at(now)? > now {
+            return None;
+        }
+        let packet = self.queues.iter_mut().find_map(|q| q.pop_front())?;
+        self.tat = Some(self.tat.map_or(now, |tat| tat.max(now)) + self.interval);
+        self.metrics.sent += 1;
+        self.update_depth();
+        Some(packet)
+    }
+
+    /// When the next packet may be sent, or `None` if the queue is empty.
+    pub fn ready_at(&self, now: Instant) -> Option<Instant> {
+        if self.is_empty() {
+            return None;
+        }
+        let allowance = self.interval * (self.burst - 1);
+        Some(match self.tat {
+            Some(tat) => tat.checked_sub(allowance).unwrap_or(now).max(now),
+            None => now,
+        })
+    }
+
+    /// Number of packets queued.
+    pub fn len(&self) -> usize {
+        self.queues.iter().map(VecDeque::len).sum()
+    }
+
+    /// Is the queue empty?
+    pub fn is_empty(&self) -> bool {
+        self.queues.iter().all(VecDeque::is_empty)
+    }
+
+    /// Discard every queued packet, returning how many there were.
+    pub fn clear(&mut self) -> usize {
+        let len = self.len();
+        self.queues.iter_mut().for_each(VecDeque::clear);
+        self.update_depth();
+        len
+    }
+
+    /// Current queue depth, and totals since creation.
+    pub fn metrics(&self) -> QueueMetrics {
+        self.metrics
+    }
+
+    fn update_depth(&mut self) {
+        for priority in Priority::ALL {
+            self.metrics.depth[priority.index()] = self.queues[priority.index()].len();
+        }
+        self.metrics.peak = self.metrics.peak.max(self.metrics.total());
+    }
+}
+
+/// The queued [Btn] which `btn` replaces. A [Btn] queued before a [Bfn] which deletes it is
+/// never superseded, so that the button is deleted and then recreated.
+fn superseded_btn<'a>(queue: &'a mut VecDeque<Packet>, btn: &Btn) -> Option<&'a mut Packet> {
+    for queued in queue.iter_mut().rev() {
+        match queued {
+            Packet::Btn(other) if other.ucid == btn.ucid && other.clickid == btn.clickid => {
+                return Some(queued);
+            },
+            Packet::Bfn(bfn)
+                if deleted_range(bfn).is_some_and(|r| r.covers(btn.ucid, btn.clickid)) =>
+            {
+                return None;
+            },
+            _ => {},
+        }
+    }
+    None
+}
+
+#[derive(Debug, Clone, Copy)]
+struct DeletedRange {
+    ucid: ConnectionId,
+    from: ClickId,
+    to: ClickId,
+}
+
+impl DeletedRange {
+    fn covers(&self, ucid: ConnectionId, clickid: ClickId) -> bool {
+        (self.ucid == ConnectionId::ALL || self.ucid == ucid)
+            && (self.from..=self.to).contains(&clickid)
+    }
+}
+
+/// Buttons removed by a [Bfn] sent to LFS.
+fn deleted_range(bfn: &Bfn) -> Option<DeletedRange> {
+    match bfn.subt {
+        BfnType::DelBtn => Some(DeletedRange {
+            ucid: bfn.ucid,
+            from: bfn.clickid,
+            to: ClickId(bfn.clickmax.max(bfn.clickid.0)),
+        }),
+        BfnType::Clear => Some(DeletedRange {
+            ucid: bfn.ucid,
+            from: ClickId(0),
+            to: ClickId(u8::MAX),
+        }),
+        BfnType::UserClear | BfnType::BtnRequest => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::insim::{Mst, Mtc, TinyType};
+
+    fn btn(ucid: u8, clickid: u8, text: &str) -> Btn {
+        Btn {
+            ucid: ConnectionId(ucid),
+            clickid: ClickId(clickid),
+            text: text.into(),
+            ..Default::default()
+        }
+    }
+
+    fn drain(queue: &mut SendQueue, now: Instant) -> Vec<Packet> {
+        std::iter::from_fn(|| queue.pop(now)).collect()
+    }
+
+    #[test]
+    fn test_priorities() {
+        let mut queue = SendQueue::new(10).burst(10);
+        queue.push(Mtc {
+            text: "hello".into(),
+            ..Default::default()
+        });
+        queue.push(btn(1, 1, "a"));
+        queue.push(Mst {
+            msg: "/end".into(),
+            ..Default::default()
+        });
+        queue.push(TinyType::Ncn);
+
+        let metrics = queue.metrics();
+        assert_eq!(metrics.depth(Priority::Admin), 2);
+        assert_eq!(metrics.depth(Priority::Button), 1);
+        assert_eq!(metrics.depth(Priority::Chat), 1);
+
+        let sent: Vec<Priority> = drain(&mut queue, Instant::now())
+            .iter()
+            .map(Priority::of)
+            .collect();
+        assert_eq!(
+            sent,
+            [
+                Priority::Admin,
+                Priority::Admin,
+                Priority::Button,
+                Priority::Chat
+            ]
+        );
+        assert_eq!(queue.metrics().sent, 4);
+        assert_eq!(queue.metrics().peak, 4);
+        assert_eq!(queue.metrics().total(), 0);
+    }
+
+    #[test]
+    fn test_pacing() {
+        let now = Instant::now();
+        let mut queue = SendQueue::new(10).burst(2);
+        for i in 0..4 {
+            queue.push(btn(0, i, "x"));
+        }
+
+        // burst
+        assert!(queue.pop(now).is_some());
+        assert!(queue.pop(now).is_some());
+        assert!(queue.pop(now).is_none());
+        assert_eq!(queue.ready_at(now), Some(now + Duration::from_millis(100)));
+
+        let later = now + Duration::from_millis(100);
+        assert!(queue.pop(later).is_some());
+        assert!(queue.pop(later).is_none());
+        assert!(queue.pop(later + Duration::from_millis(100)).is_some());
+        assert_eq!(queue.ready_at(later), None);
+    }
+
+    #[test]
+    fn test_coalescing() {
+        let mut queue = SendQueue::new(10).burst(100);
+        queue.push(btn(1, 1, "a"));
+        queue.push(btn(2, 1, "b"));
+        queue.push(btn(1, 1, "c"));
+        assert_eq!(queue.len(), 2);
+
+        // deleting a range removes queued buttons within it, for that connection
+        queue.push(btn(1, 2, "d"));
+        queue.push(btn(1, 5, "e"));
+        queue.push(Bfn {
+            subt: BfnType::DelBtn,
+            ucid: ConnectionId(1),
+            clickid: ClickId(1),
+            clickmax: 3,
+            ..Default::default()
+        });
+        // ... but a button recreated afterwards is not merged into an earlier one
+        queue.push(btn(1, 1, "f"));
+        queue.push(btn(1, 1, "g"));
+
+        let sent: Vec<(u8, u8, String)> = drain(&mut queue, Instant::now())
+            .into_iter()
+            .filter_map(|p| match p {
+                Packet::Btn(b) => Some((b.ucid.0, b.clickid.0, b.text)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            sent,
+            [
+                (2, 1, "b".to_string()),
+                (1, 5, "e".to_string()),
+                (1, 1, "g".to_string())
+            ]
+        );
+        assert_eq!(queue.metrics().coalesced, 4);
+        assert_eq!(queue.metrics().sent, 4);
+    }
+}
@@ -0,0 +1,381 @@
+//! A self-healing connection built on top of [Builder::connect_async].
+//!
+//! [Supervised] owns a [Builder] and the currently established
+//! [Framed]. When the connection is lost (LFS restarted, the
+//! network dropped, the keepalive timed out) it waits according to a [Backoff] policy,
+//! reconnects, replays the [Isi](crate::insim::Isi) handshake and optionally re-issues a list
+//! of startup [TinyType] requests. Lifecycle changes are surfaced to the caller as
+//! [SupervisedEvent]s interleaved with the normal packet stream.
+//!
+//! ```rust,ignore
+//! use insim::{insim::TinyType, net::supervised::SupervisedEvent};
+//!
+//! let mut conn = insim::tcp("127.0.0.1:29999")
+//!     .isi_iname("supervised".to_owned())
+//!     .supervised()
+//!     .startup_requests([TinyType::Ncn, TinyType::Npl]);
+//!
+//! loop {
+//!     match conn.read().await? {
+//!         SupervisedEvent::Packet(packet) => println!("{:?}", packet),
+//!         SupervisedEvent::Reconnecting { attempt, delay, error } => {
+//!             println!("lost connection ({error}), attempt {attempt} in {delay:?}")
+//!         },
+//!         other => println!("{:?}", other),
+//!     }
+//! }
+//! ```
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use super::tokio_impl::Framed;
+use crate::{
+    Error, Packet, Result, WithRequestId, builder::Builder, identifiers::RequestId, insim::TinyType,
+};
+
+/// Exponential backoff policy used by [Supervised] between connection attempts.
+#[derive(Debug, Clone)]
+pub struct Backoff {
+    /// Delay before the first reconnection attempt.
+    pub initial: Duration,
+    /// Upper bound on the delay between attempts.
+    pub max: Duration,
+    /// Factor the delay is multiplied by after each failed attempt.
+    pub multiplier: u32,
+    /// Give up after this many consecutive failed attempts. `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for Backoff {
+    fn default() -> Self {
+        Self {
+            initial: Duration::from_secs(1),
+            max: Duration::from_secs(30),
+            multiplier: 2,
+            max_attempts: None,
+        }
+    }
+}
+
+impl Backoff {
+    /// Use the same delay between every attempt.
+    pub fn fixed(delay: Duration) -> Self {
+        Self {
+            initial: delay,
+            max: delay,
+            multiplier: 1,
+            max_attempts: None,
+        }
+    }
+
+    /// Delay to wait before the given (1-based) attempt.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let factor = self
+            .multiplier
+            .max(1)
+            .saturating_pow(attempt.saturating_sub(1));
+        self.initial.saturating_mul(factor).min(self.max)
+    }
+}
+
+/// Events produced by [Supervised::read].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum SupervisedEvent {
+    /// A packet was received from LFS.
+    Packet(Packet),
+    /// The initial connection and handshake succeeded.
+    Connected,
+    /// The connection was lost, or an attempt to re-establish it failed. The next attempt
+    /// will be made after `delay`.
+    Reconnecting {
+        /// 1-based number of the upcoming attempt.
+        attempt: u32,
+        /// How long until the upcoming attempt.
+        delay: Duration,
+        /// The error that caused the connection to be considered lost.
+        error: Error,
+    },
+    /// The connection was re-established, the handshake replayed and any startup requests
+    /// sent.
+    Reconnected {
+        /// How many attempts were required.
+        attempts: u32,
+    },
+}
+
+#[derive(Debug)]
+enum State {
+    Connected(Box<Framed>),
+    Waiting { attempt: u32, until: Instant },
+}
+
+/// A connection which automatically reconnects, with backoff, when lost.
+///
+/// Create one with [Builder::supervised]. Nothing happens until the first call to
+/// [Supervised::read], which establishes the initial connection and returns
+/// [SupervisedEvent::Connected]. Initial connection failures are retried using the same
+/// [Backoff] policy as later reconnections.
+///
+/// [Supervised::read] is cancel-safe whilst connected and whilst waiting between attempts. If
+/// the future is dropped part way through a connection attempt, that attempt is simply
+/// restarted on the next call.
+#[derive(Debug)]
+pub struct Supervised {
+    builder: Builder,
+    backoff: Backoff,
+    startup_requests: Vec<TinyType>,
+    startup_reqi: RequestId,
+    state: State,
+    has_connected: bool,
+}
+
+impl Supervised {
+    /// Create a new supervised connection from a [Builder].
+    pub fn new(builder: Builder) -> Self {
+        Self {
+            builder,
+            backoff: Backoff::default(),
+            startup_requests: Vec::new(),
+            startup_reqi: RequestId(1),
+            state: State::Waiting {
+                attempt: 0,
+                until: Instant::now(),
+            },
+            has_connected: false,
+        }
+    }
+
+    /// Set the [Backoff] policy used between connection attempts.
+    pub fn backoff(mut self, backoff: Backoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Set the [TinyType] requests to send after every successful (re)connection, i.e.
+    /// [TinyType::Ncn], [TinyType::Npl], [TinyType::Ism] or [TinyType::Rst], so that state
+    /// mirrored by the caller can be rebuilt.
+    pub fn startup_requests<I: IntoIterator<Item = TinyType>>(mut self, requests: I) -> Self {
+        self.startup_requests = requests.into_iter().collect();
+        self
+    }
+
+    /// Set the [RequestId] used for the startup requests. Defaults to 1.
+    pub fn startup_reqi(mut self, reqi: RequestId) -> Self {
+        self.startup_reqi = reqi;
+        self
+    }
+
+    /// Is there currently an established connection?
+    pub fn is_connected(&self) -> bool {
+        matches!(self.state, State::Connected(_))
+    }
+
+    /// Wait for the next packet or lifecycle event.
+    ///
+    /// Returns an error only if the error is not recoverable by reconnecting (i.e.
+    /// [Error::IncompatibleVersion]), or the [Backoff::max_attempts] limit is reached.
+    pub async fn read(&mut self) -> Result<SupervisedEvent> {
+        match &mut self.state {
+            State::Connected(framed) => match framed.read().await {
+                Ok(packet) => Ok(SupervisedEvent::Packet(packet)),
+                Err(error) => self.lost(1, error),
+            },
+            State::Waiting { attempt, until } => {
+                let attempt = *attempt;
+                tokio::time::sleep_until(*until).await;
+
+                let mut framed = match self.builder.connect_async().await {
+                    Ok(framed) => framed,
+                    Err(error) => return self.lost(attempt + 1, error),
+                };
+                for request in self.startup_requests.iter().cloned() {
+                    if let Err(error) = framed
+                        .write(request.with_request_id(self.startup_reqi))
+                        .await
+                    {
+                        return self.lost(attempt + 1, error);
+                    }
+                }
+
+                self.state = State::Connected(Box::new(framed));
+                if !self.has_connected {
+                    self.has_connected = true;
+                    return Ok(SupervisedEvent::Connected);
+                }
+                if let Some(metrics) = self.builder.metrics_ref() {
+                    metrics.reconnected();
+                }
+                Ok(SupervisedEvent::Reconnected {
+                    attempts: attempt.max(1),
+                })
+            },
+        }
+    }
+
+    /// Write a packet to the current connection.
+    ///
+    /// Returns [Error::Disconnected] if there is no established connection. Packets are not
+    /// queued whilst disconnected. If the write fails because the connection has gone away,
+    /// the next call to [Supervised::read] will observe the same failure and begin
+    /// reconnecting.
+    pub async fn write<P: Into<Packet>>(&mut self, packet: P) -> Result<()> {
+        match &mut self.state {
+            State::Connected(framed) => framed.write(packet).await,
+            State::Waiting { .. } => Err(Error::Disconnected),
+        }
+    }
+
+    /// Flush and shutdown the current connection, if any. The next call to
+    /// [Supervised::read] will reconnect.
+    pub async fn shutdown(&mut self) -> Result<()> {
+        let state = std::mem::replace(
+            &mut self.state,
+            State::Waiting {
+                attempt: 0,
+                until: Instant::now(),
+            },
+        );
+        if let State::Connected(mut framed) = state {
+            framed.shutdown().await?;
+        }
+        Ok(())
+    }
+
+    fn lost(&mut self, attempt: u32, error: Error) -> Result<SupervisedEvent> {
+        if !is_recoverable(&error) || self.backoff.max_attempts.is_some_and(|max| attempt > max) {
+            self.state = State::Waiting {
+                attempt: 0,
+                until: Instant::now(),
+            };
+            return Err(error);
+        }
+
+        let delay = self.backoff.delay(attempt);
+        self.state = State::Waiting {
+            attempt,
+            until: Instant::now() + delay,
+        };
+        Ok(SupervisedEvent::Reconnecting {
+            attempt,
+            delay,
+            error,
+        })
+    }
+}
+
+fn is_recoverable(error: &Error) -> bool {
+    !matches!(
+        error,
+        Error::IncompatibleVersion(_) | Error::AddrParseError(_)
+    )
+}
+
+impl Builder {
+    /// Convert this `Builder` into a [Supervised] connection which reconnects automatically.
+    /// No connection is made until [Supervised::read] is first called.
+    pub fn supervised(self) -> Supervised {
+        Supervised::new(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{io::AsyncReadExt, net::TcpListener};
+
+    use super::*;
+
+    #[test]
+    fn test_backoff_delay() {
+        let backoff = Backoff {
+            initial: Duration::from_millis(100),
+            max: Duration::from_millis(500),
+            multiplier: 2,
+            max_attempts: None,
+        };
+        assert_eq!(backoff.delay(1), Duration::from_millis(100));
+        assert_eq!(backoff.delay(2), Duration::from_millis(200));
+        assert_eq!(backoff.delay(3), Duration::from_millis(400));
+        assert_eq!(backoff.delay(4), Duration::from_millis(500));
+        assert_eq!(backoff.delay(u32::MAX), Duration::from_millis(500));
+    }
+
+    #[tokio::test]
+    async fn test_reconnects_and_replays_startup_requests() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            // expect the handshake and startup requests on both connections, dropping the
+            // first to force a reconnect
+            for _ in 0..2 {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let mut isi = [0u8; 44];
+                let _ = stream.read_exact(&mut isi).await.unwrap();
+                assert_eq!(isi[1], 1);
+                let mut tiny = [0u8; 8];
+                let _ = stream.read_exact(&mut tiny).await.unwrap();
+                assert_eq!(tiny, [1, 3, 1, 13, 1, 3, 1, 14]);
+            }
+        });
+
+        let metrics = crate::net::MemoryMetrics::new();
+        let mut conn = crate::tcp(addr)
+            .metrics(metrics.clone())
+            .supervised()
+            .backoff(Backoff::fixed(Duration::from_millis(10)))
+            .startup_requests([TinyType::Ncn, TinyType::Npl]);
+
+        assert!(matches!(
+            conn.read().await.unwrap(),
+            SupervisedEvent::Connected
+        ));
+        assert!(conn.is_connected());
+        assert!(matches!(
+            conn.read().await.unwrap(),
+            SupervisedEvent::Reconnecting {
+                attempt: 1,
+                error: Error::Disconnected,
+                ..
+            }
+        ));
+        assert!(matches!(
+            conn.write(TinyType::Ping).await,
+            Err(Error::Disconnected)
+        ));
+        assert!(matches!(
+            conn.read().await.unwrap(),
+            SupervisedEvent::Reconnected { attempts: 1 }
+        ));
+
+        server.await.unwrap();
+
+        let snapshot = metrics.snapshot();
+        assert_eq!(snapshot.reconnects, 1);
+        assert_eq!(snapshot.sent["Isi"].packets, 2);
+        assert_eq!(snapshot.sent["Tiny"].packets, 4);
+        assert_eq!(snapshot.total_sent().bytes, 2 * 44 + 4 * 4);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_attempts() {
+        // bind and immediately drop to find a port that refuses connections
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let mut conn = crate::tcp(addr).supervised().backoff(Backoff {
+            max_attempts: Some(1),
+            ..Backoff::fixed(Duration::from_millis(1))
+        });
+
+        assert!(matches!(
+            conn.read().await.unwrap(),
+            SupervisedEvent::Reconnecting { attempt: 1, .. }
+        ));
+        assert!(conn.read().await.is_err());
+    }
+}
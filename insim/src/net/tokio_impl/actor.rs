@@ -0,0 +1,570 @@
+use std::{
+    future::Future,
+    sync::{Arc, OnceLock},
+    time::Duration,
+};
+
+use tokio::sync::{
+    broadcast::{self, error::RecvError},
+    mpsc, oneshot,
+};
+
+use super::Framed;
+use crate::{
+    Error, Packet, Result,
+    net::{
+        LatencyMonitor, LatencyStats, QueueMetrics, SendQueue,
+        actor::{
+            Latency, SharedLatency, SharedMetrics, StopReason, Stopped, publish_metrics,
+            read_latency, read_metrics, shared_metrics, stopped_error,
+        },
+    },
+    request::{Pending, Request, RequestIds},
+};
+
+#[derive(Debug)]
+enum Command {
+    Packet(Packet),
+    Shutdown(oneshot::Sender<Result<()>>),
+}
+
+/// A cloneable handle to a connection running on its own task.
+///
+/// Create one with [crate::builder::Builder::spawn], or [Handle::new] for an existing
+/// [Framed]. The task owns the connection, takes care of keepalives, and broadcasts every
+/// packet it receives to all [Subscriber]s. It stops when asked via [Handle::shutdown], when
+/// every `Handle` has been dropped, or when the connection fails - in which case every
+/// subscriber receives the error as [Error::Stopped].
+#[derive(Debug, Clone)]
+pub struct Handle {
+    tx: mpsc::UnboundedSender<Command>,
+    // Only the task holds a strong sender, so that subscribers observe it exiting
+    events: broadcast::WeakSender<Packet>,
+    stopped: StopReason,
+    reqis: RequestIds,
+    metrics: Option<SharedMetrics>,
+    latency: Option<SharedLatency>,
+}
+
+impl Handle {
+    /// Spawn a task to drive an established connection. Each [Subscriber] may fall up to
+    /// `capacity` packets behind before it starts missing packets.
+    ///
+    /// Must be called from within a tokio runtime.
+    pub fn new(framed: Framed, capacity: usize) -> (Self, Subscriber) {
+        Self::spawn(async move { Ok(framed) }, capacity, None, None)
+    }
+
+    /// As [Handle::new], but packets are sent through `queue`.
+    pub fn with_queue(framed: Framed, capacity: usize, queue: SendQueue) -> (Self, Subscriber) {
+        Self::spawn(async move { Ok(framed) }, capacity, Some(queue), None)
+    }
+
+    /// Spawn a task which first establishes the connection using `connect`. Failing to
+    /// connect is reported to subscribers in the same way as losing the connection.
+    pub(crate) fn spawn<F>(
+        connect: F,
+        capacity: usize,
+        queue: Option<SendQueue>,
+        latency: Option<LatencyMonitor>,
+    ) -> (Self, Subscriber)
+    where
+        F: Future<Output = Result<Framed>> + Send + 'static,
+    {
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let (events, first) = broadcast::channel(capacity);
+        let stopped: StopReason = Arc::new(OnceLock::new());
+        let metrics = shared_metrics(queue.as_ref());
+        let mut latency = latency.map(Latency::new);
+
+        let handle = Self {
+            tx,
+            events: events.downgrade(),
+            stopped: stopped.clone(),
+            reqis: RequestIds::new(),
+            metrics: metrics.clone(),
+            latency: latency.as_ref().map(Latency::shared),
+        };
+        let subscriber = Subscriber {
+            rx: Some(first),
+            stopped: stopped.clone(),
+        };
+
+        drop(tokio::spawn(async move {
+            let result = match connect.await {
+                Ok(framed) => {
+                    let mut outbound = Outbound { queue, metrics };
+                    run(
+                        framed,
+                        &mut rx,
+                        &events,
+                        &stopped,
+                        &mut outbound,
+                        &mut latency,
+                    )
+                    .await
+                },
+                Err(e) => Err(e),
+            };
+            if let Err(e) = &result {
+                tracing::debug!(error = ?e, "connection stopped");
+            }
+            let _ = stopped.set(Stopped::from_result(result));
+            // Handles observe the command channel closing, but only after the reason for
+            // stopping has been set. Close it before dropping the only strong sender, which
+            // wakes every subscriber, so that anything reacting to a subscriber stopping also
+            // finds the handle stopped.
+            drop(rx);
+            drop(events);
+        }));
+
+        (handle, subscriber)
+    }
+
+    /// Queue a packet to be written to the connection. This does not block.
+    ///
+    /// Returns [Error::Disconnected] if the connection was shutdown, or [Error::Stopped] if it
+    /// failed.
+    pub fn send<P: Into<Packet>>(&self, packet: P) -> Result<()> {
+        self.tx
+            .send(Command::Packet(packet.into()))
+            .map_err(|_| stopped_error(&self.stopped))
+    }
+
+    /// Subscribe to every packet received from this point onwards.
+    pub fn subscribe(&self) -> Subscriber {
+        Subscriber {
+            rx: self.events.upgrade().map(|events| events.subscribe()),
+            stopped: self.stopped.clone(),
+        }
+    }
+
+    /// Send a [Request], using a newly allocated [crate::identifiers::RequestId], and wait for
+    /// every reply.
+    ///
+    /// Returns [Error::Timeout] if the replies are not received within `timeout`.
+    pub async fn request<R: Request>(
+        &self,
+        request: R,
+        timeout: Duration,
+    ) -> Result<Vec<R::Reply>> {
+        // subscribe before sending, so that we cannot miss a reply
+        let mut subscriber = self.subscribe();
+        let mut pending = Pending::new(request, self.reqis.next());
+        for packet in pending.packets() {
+            self.send(packet)?;
+        }
+
+        let replies = async {
+            loop {
+                if pending.handle(&subscriber.recv().await?) {
+                    return Ok::<_, Error>(());
+                }
+            }
+        };
+        match tokio::time::timeout(timeout, replies).await {
+            Ok(result) => result?,
+            Err(_) => {
+                return Err(Error::Timeout(format!(
+                    "No reply to {:?} within {timeout:?}",
+                    pending.request()
+                )));
+            },
+        }
+        Ok(pending.finish())
+    }
+
+    /// Send [crate::insim::TinyType::Close] and shutdown the connection, waiting until
+    /// complete. Every subscriber will subsequently receive [Error::Disconnected].
+    pub async fn shutdown(&self) -> Result<()> {
+        let (tx, rx) = oneshot::channel();
+        if self.tx.send(Command::Shutdown(tx)).is_err() {
+            // Already stopped
+            return Ok(());
+        }
+        rx.await.unwrap_or(Ok(()))
+    }
+
+    /// Has the connection stopped?
+    pub fn is_stopped(&self) -> bool {
+        self.stopped.get().is_some()
+    }
+
+    /// Metrics of the [SendQueue], or `None` if this connection does not use one.
+    pub fn queue_metrics(&self) -> Option<QueueMetrics> {
+        read_metrics(self.metrics.as_ref())
+    }
+
+    /// Round trip time statistics, or `None` if this connection does not use a
+    /// [LatencyMonitor].
+    pub fn latency(&self) -> Option<LatencyStats> {
+        read_latency(self.latency.as_ref())
+    }
+}
+
+/// Receives every packet from a connection driven by a [Handle].
+#[derive(Debug)]
+pub struct Subscriber {
+    // None if the connection had already stopped when subscribing
+    rx: Option<broadcast::Receiver<Packet>>,
+    stopped: StopReason,
+}
+
+impl Subscriber {
+    /// Wait for the next packet.
+    ///
+    /// If this subscriber falls more than the channel capacity behind, the oldest packets are
+    /// skipped and a warning is logged.
+    /// Once the connection has stopped, and any remaining packets have been received, returns
+    /// [Error::Disconnected] if it was shutdown or [Error::Stopped] if it failed.
+    ///
+    /// Cancel-safe.
+    pub async fn recv(&mut self) -> Result<Packet> {
+        let Some(rx) = &mut self.rx else {
+            return Err(stopped_error(&self.stopped));
+        };
+        loop {
+            match rx.recv().await {
+                Ok(packet) => return Ok(packet),
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "subscriber lagged, skipping packets");
+                },
+                Err(RecvError::Closed) => return Err(stopped_error(&self.stopped)),
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Outbound {
+    queue: Option<SendQueue>,
+    metrics: Option<SharedMetrics>,
+}
+
+impl Outbound {
+    fn publish(&self) {
+        publish_metrics(self.metrics.as_ref(), self.queue.as_ref());
+    }
+}
+
+async fn write(framed: &mut Framed, packet: Packet) -> Result<()> {
+    match framed.write(packet).await {
+        Ok(()) => Ok(()),
+        // A packet which cannot be encoded does not affect the connection
+        Err(e @ Error::Encode(_)) => {
+            tracing::warn!(error = ?e, "dropping packet");
+            Ok(())
+        },
+        Err(e) => Err(e),
+    }
+}
+
+async fn run(
+    mut framed: Framed,
+    rx: &mut mpsc::UnboundedReceiver<Command>,
+    events: &broadcast::Sender<Packet>,
+    stopped: &OnceLock<Stopped>,
+    outbound: &mut Outbound,
+    latency: &mut Option<Latency>,
+) -> Result<()> {
+    loop {
+        let now = std::time::Instant::now();
+        let ready = outbound.queue.as_ref().and_then(|q| q.ready_at(now));
+        let ping_at = latency.as_ref().and_then(|l| l.poll_at(now));
+
+        tokio::select! {
+            packet = framed.read() => {
+                let packet = packet?;
+                if latency.as_mut().is_some_and(|l| l.handle(&packet, std::time::Instant::now())) {
+                    continue;
+                }
+                // No subscribers is not an error, they may subscribe later
+                let _ = events.send(packet);
+            },
+            _ = sleep_until(ping_at), if ping_at.is_some() => {
+                // Pings bypass any queue, so that they measure only the connection
+                if let Some(ping) = latency.as_mut().and_then(|l| l.poll(std::time::Instant::now())) {
+                    write(&mut framed, ping).await?;
+                }
+            },
+            _ = sleep_until(ready), if ready.is_some() => {
+                if let Some(queue) = &mut outbound.queue {
+                    while let Some(packet) = queue.pop(std::time::Instant::now()) {
+                        write(&mut framed, packet).await?;
+                    }
+                }
+                outbound.publish();
+            },
+            command = rx.recv() => match command {
+                Some(Command::Packet(packet)) => match &mut outbound.queue {
+                    Some(queue) => {
+                        queue.push(packet);
+                        outbound.publish();
+                    },
+                    None => write(&mut framed, packet).await?,
+                },
+                Some(Command::Shutdown(reply)) => {
+                    let result = framed.shutdown().await;
+                    let _ = stopped.set(Stopped::Shutdown);
+                    let _ = reply.send(result);
+                    return Ok(());
+                },
+                None => return framed.shutdown().await,
+            },
+        }
+    }
+}
+
+async fn sleep_until(instant: Option<std::time::Instant>) {
+    if let Some(instant) = instant {
+        tokio::time::sleep_until(instant.into()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{
+        WithRequestId,
+        identifiers::{ConnectionId, RequestId},
+        insim::{Tiny, TinyType},
+        request::{Connections, Version},
+    };
+
+    #[tokio::test]
+    async fn test_handle_async() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut isi = [0u8; 44];
+            let _ = stream.read_exact(&mut isi).await.unwrap();
+            // ping the client, expect a ping back from the handle, followed by a close
+            stream.write_all(&[1, 3, 2, 3]).await.unwrap();
+            let mut buf = [0u8; 8];
+            let _ = stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [1, 3, 4, 3, 1, 3, 0, 2]);
+        });
+
+        let (handle, mut first) = crate::tcp(addr).spawn();
+        let mut second = handle.subscribe();
+
+        for sub in [&mut first, &mut second] {
+            assert!(matches!(
+                sub.recv().await.unwrap(),
+                Packet::Tiny(Tiny {
+                    subt: TinyType::Ping,
+                    ..
+                })
+            ));
+        }
+
+        handle
+            .clone()
+            .send(TinyType::Ping.with_request_id(RequestId(4)))
+            .unwrap();
+        handle.shutdown().await.unwrap();
+        server.await.unwrap();
+
+        assert!(handle.is_stopped());
+        assert!(matches!(first.recv().await, Err(Error::Disconnected)));
+        assert!(matches!(second.recv().await, Err(Error::Disconnected)));
+        assert!(matches!(
+            handle.subscribe().recv().await,
+            Err(Error::Disconnected)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_handle_latency() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut isi = [0u8; 44];
+            let _ = stream.read_exact(&mut isi).await.unwrap();
+            // a ping is sent immediately, answer it and then reply to someone else
+            let mut ping = [0u8; 4];
+            let _ = stream.read_exact(&mut ping).await.unwrap();
+            assert_eq!(ping, [1, 3, LatencyMonitor::REQUEST_ID.0, 3]);
+            stream
+                .write_all(&[1, 3, LatencyMonitor::REQUEST_ID.0, 4, 1, 3, 1, 4])
+                .await
+                .unwrap();
+            stream
+        });
+
+        let (handle, mut sub) = crate::tcp(addr)
+            .latency_monitor(LatencyMonitor::new(Duration::from_secs(60)))
+            .spawn();
+
+        // only the unrelated reply is received
+        assert!(matches!(
+            sub.recv().await.unwrap(),
+            Packet::Tiny(Tiny {
+                subt: TinyType::Reply,
+                reqi: RequestId(1)
+            })
+        ));
+        let stats = handle.latency().unwrap();
+        assert_eq!(stats.samples, 1);
+        assert_eq!(stats.pings, 1);
+        assert!(!stats.stalled);
+
+        let _stream = server.await.unwrap();
+        handle.shutdown().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_handle_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut isi = [0u8; 44];
+            let _ = stream.read_exact(&mut isi).await.unwrap();
+            // Ncn request, followed by a ping using the same RequestId
+            let mut buf = [0u8; 8];
+            let _ = stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [1, 3, 128, 13, 1, 3, 128, 3]);
+
+            for packet in [
+                Packet::from(crate::insim::Ncn {
+                    reqi: RequestId(128),
+                    ucid: ConnectionId(0),
+                    ..Default::default()
+                }),
+                // unrelated packets are ignored
+                TinyType::Reply.with_request_id(RequestId(1)).into(),
+                Packet::from(crate::insim::Ncn {
+                    reqi: RequestId(128),
+                    ucid: ConnectionId(3),
+                    ..Default::default()
+                }),
+                TinyType::Reply.with_request_id(RequestId(128)).into(),
+            ] {
+                stream
+                    .write_all(&crate::net::codec::encode_packet(&packet, crate::VERSION).unwrap())
+                    .await
+                    .unwrap();
+            }
+
+            // never reply to the second request
+            let mut buf = [0u8; 4];
+            let _ = stream.read_exact(&mut buf).await.unwrap();
+            assert_eq!(buf, [1, 3, 129, 1]);
+            stream
+        });
+
+        let (handle, _) = crate::tcp(addr).spawn();
+
+        let replies = handle
+            .request(Connections, Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[1].ucid, ConnectionId(3));
+
+        assert!(matches!(
+            handle.request(Version, Duration::from_millis(50)).await,
+            Err(Error::Timeout(_))
+        ));
+
+        drop(server.await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_handle_send_queue() {
+        use crate::{
+            identifiers::ClickId,
+            insim::{Btn, Mst},
+            testing::FakeHost,
+        };
+
+        let host = FakeHost::new().listen_tcp().await.unwrap();
+        let (handle, _sub) = crate::tcp(host.local_addr())
+            .send_queue(SendQueue::new(20))
+            .spawn();
+        host.wait_for_connections(1, Duration::from_secs(5))
+            .await
+            .unwrap();
+
+        // The first packet uses up the burst, everything else is queued behind it
+        handle.send(TinyType::Ping).unwrap();
+        handle
+            .send(Mst {
+                msg: "hello".into(),
+                ..Default::default()
+            })
+            .unwrap();
+        for text in ["a", "b", "c"] {
+            handle
+                .send(Btn {
+                    clickid: ClickId(1),
+                    text: text.into(),
+                    w: 10,
+                    h: 10,
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        handle
+            .send(Mst {
+                msg: "/end".into(),
+                ..Default::default()
+            })
+            .unwrap();
+
+        let _ = host
+            .wait_for(
+                |p| matches!(p, Packet::Mst(mst) if mst.msg == "hello"),
+                Duration::from_secs(5),
+            )
+            .await
+            .unwrap();
+        let sent: Vec<String> = host
+            .received()
+            .into_iter()
+            .filter_map(|p| match p {
+                Packet::Tiny(Tiny {
+                    subt: TinyType::Ping,
+                    ..
+                }) => Some("ping".to_string()),
+                Packet::Mst(mst) => Some(mst.msg),
+                Packet::Btn(btn) => Some(btn.text),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(sent, ["ping", "/end", "c", "hello"]);
+
+        let metrics = handle.queue_metrics().unwrap();
+        assert_eq!(metrics.coalesced, 2);
+        assert_eq!(metrics.sent, 4);
+        assert_eq!(metrics.total(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_handle_connect_failure() {
+        // bind and immediately drop to find a port that refuses connections
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .await
+            .unwrap()
+            .local_addr()
+            .unwrap();
+
+        let (handle, mut sub) = crate::tcp(addr).spawn();
+        assert!(matches!(sub.recv().await, Err(Error::Stopped(_))));
+        assert!(matches!(
+            handle.send(TinyType::Ping),
+            Err(Error::Stopped(_))
+        ));
+    }
+}
@@ -0,0 +1,322 @@
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{Sink, Stream};
+use tokio::{
+    io::{AsyncRead, AsyncWrite},
+    net::{TcpStream, UdpSocket},
+};
+
+use super::Codec;
+use crate::{Error, Packet, Result};
+
+mod actor;
+mod read;
+mod write;
+
+pub use actor::{Handle, Subscriber};
+pub use read::FramedRead;
+use read::{BoxedRead, Reader};
+pub use write::FramedWrite;
+use write::{BoxedWrite, SharedWriteState, Writer};
+
+/// A convenience wrapper around Udp, Tcp or any other stream, using Codec to drive the IO
+///
+/// `Framed` implements [futures::Stream] and [futures::Sink], and may be divided into a
+/// [FramedRead] and [FramedWrite] using [Framed::split] or [Framed::into_split]. Both halves
+/// cooperate on the connection upkeep: keepalives are handled by the read half and written via
+/// the same outbound queue as the write half.
+#[derive(Debug)]
+pub struct Framed {
+    read: FramedRead,
+    write: FramedWrite,
+}
+
+impl Framed {
+    fn new(reader: Reader, writer: Writer, codec: Codec) -> Self {
+        let state = SharedWriteState::new(writer, codec.version(), codec.metrics().cloned());
+        Self {
+            read: FramedRead {
+                reader,
+                codec,
+                writer: state.clone(),
+                sleep: None,
+                error: None,
+            },
+            write: FramedWrite { state },
+        }
+    }
+
+    /// Create a new Framed from a TcpStream
+    pub fn from_tcp(stream: TcpStream, codec: Codec) -> Self {
+        let (read, write) = stream.into_split();
+        Self::new(Reader::Tcp(read), Writer::Tcp(write), codec)
+    }
+
+    /// Create a new Framed from a UdpSocket
+    pub fn from_udp(socket: UdpSocket, codec: Codec) -> Self {
+        let socket = Arc::new(socket);
+        Self::new(Reader::Udp(socket.clone()), Writer::Udp(socket), codec)
+    }
+
+    /// Create a new Framed from any other stream, i.e. an SSH tunnel, serial bridge or
+    /// [tokio::io::duplex] pipe.
+    ///
+    /// Keepalives and timeouts are handled exactly as with [Framed::from_tcp].
+    pub fn from_stream<S>(stream: S, codec: Codec) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let (read, write) = tokio::io::split(stream);
+        Self::from_split(read, write, codec)
+    }
+
+    /// Create a new Framed from separate read and write halves of a stream, i.e. the stdout and
+    /// stdin of a child process.
+    pub fn from_split<R, W>(read: R, write: W, codec: Codec) -> Self
+    where
+        R: AsyncRead + Send + 'static,
+        W: AsyncWrite + Send + 'static,
+    {
+        Self::new(
+            Reader::Stream(BoxedRead(Box::pin(read))),
+            Writer::Stream(BoxedWrite(Box::pin(write))),
+            codec,
+        )
+    }
+
+    /// Create a new Framed which writes to, and reads from, a TcpStream, but additionally reads
+    /// packets from a UdpSocket.
+    ///
+    /// This is the TCP + UDP protocol split, where LFS sends [crate::Packet::Mci] and
+    /// [crate::Packet::Nlp] to the `udpport` given in the [crate::insim::Isi] handshake, and
+    /// everything else over TCP. The UdpSocket does not need to be connected. Any datagram which
+    /// is not an InSim packet (i.e. Outgauge or Outsim sharing the port) is discarded. The
+    /// [crate::net::Interest] of `codec` applies to both.
+    pub fn from_tcp_udp(stream: TcpStream, socket: UdpSocket, codec: Codec) -> Self {
+        let (read, write) = stream.into_split();
+        Self::new(
+            Reader::TcpUdp {
+                stream: read,
+                socket,
+                datagrams: codec.datagram_codec(),
+            },
+            Writer::Tcp(write),
+            codec,
+        )
+    }
+
+    /// Asynchronously wait for a packet from the inner network.
+    ///
+    /// Cancel-safe: all in-flight state (decoded bytes, pending outbound bytes, keepalive flag)
+    /// lives on `self`. Dropping the returned future from a `tokio::select!` loses no progress.
+    pub async fn read(&mut self) -> Result<Packet> {
+        self.read.read().await
+    }
+
+    /// Asynchronously write a packet to the inner network.
+    pub async fn write<P: Into<Packet>>(&mut self, packet: P) -> Result<()> {
+        self.write.write(packet).await
+    }
+
+    /// Asynchronously send [crate::insim::TinyType::Close], flush the inner network and
+    /// shutdown.
+    pub async fn shutdown(&mut self) -> Result<()> {
+        self.write.shutdown().await
+    }
+
+    /// The Insim version in use. This is negotiated when LFS sends a [crate::insim::Ver],
+    /// see [Codec::version].
+    pub fn version(&self) -> u8 {
+        self.read.version()
+    }
+
+    /// Borrow the read and write halves, i.e. to use them concurrently from within a single
+    /// `tokio::select!`.
+    pub fn split(&mut self) -> (&mut FramedRead, &mut FramedWrite) {
+        (&mut self.read, &mut self.write)
+    }
+
+    /// Split into owned read and write halves, which may be moved into separate tasks.
+    /// The [FramedWrite] may be cloned to allow many tasks to write.
+    ///
+    /// The [FramedRead] must continue to be polled, otherwise keepalives will not be answered
+    /// and LFS will eventually drop the connection.
+    pub fn into_split(self) -> (FramedRead, FramedWrite) {
+        (self.read, self.write)
+    }
+}
+
+impl Stream for Framed {
+    type Item = Result<Packet>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.get_mut().read).poll_next(cx)
+    }
+}
+
+impl Sink<Packet> for Framed {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().write).poll_ready(cx)
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Packet) -> Result<()> {
+        Pin::new(&mut self.get_mut().write).start_send(item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().write).poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        Pin::new(&mut self.get_mut().write).poll_close(cx)
+    }
+}
+
+impl From<TcpStream> for Framed {
+    fn from(value: TcpStream) -> Self {
+        Self::from_tcp(value, Codec::new())
+    }
+}
+
+impl From<UdpSocket> for Framed {
+    fn from(value: UdpSocket) -> Self {
+        Self::from_udp(value, Codec::new())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use futures::{SinkExt, StreamExt};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::{
+        WithRequestId,
+        identifiers::RequestId,
+        insim::{Tiny, TinyType},
+    };
+
+    const KEEPALIVE: [u8; 4] = [1, 3, 0, 0];
+    const PING: [u8; 4] = [1, 3, 2, 3];
+
+    async fn pair() -> (Framed, TcpStream) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (client, server) = tokio::join!(TcpStream::connect(addr), listener.accept());
+        (
+            Framed::from_tcp(client.unwrap(), Codec::new()),
+            server.unwrap().0,
+        )
+    }
+
+    #[tokio::test]
+    async fn test_into_split_keepalive_and_concurrent_write() {
+        let (framed, mut server) = pair().await;
+        let (mut read, write) = framed.into_split();
+
+        // the read half answers keepalives through the write half's queue
+        server.write_all(&KEEPALIVE).await.unwrap();
+        let packet = read.read().await.unwrap();
+        assert!(matches!(
+            packet,
+            Packet::Tiny(Tiny {
+                subt: TinyType::None,
+                reqi: RequestId(0)
+            })
+        ));
+
+        let reader = tokio::spawn(async move {
+            while let Some(packet) = read.next().await {
+                let _ = packet.unwrap();
+            }
+        });
+
+        let mut writer = write.clone();
+        tokio::spawn(async move {
+            writer
+                .write(TinyType::Ping.with_request_id(RequestId(2)))
+                .await
+                .unwrap();
+        })
+        .await
+        .unwrap();
+
+        let mut buf = [0u8; 8];
+        let _ = tokio::time::timeout(Duration::from_secs(5), server.read_exact(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(buf[..4], KEEPALIVE);
+        assert_eq!(buf[4..], PING);
+
+        drop(server);
+        reader.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_packet_returned_before_upkeep_error() {
+        let (input, mut server) = tokio::io::duplex(64);
+        let (output, closed) = tokio::io::duplex(64);
+        drop(closed);
+        let mut framed = Framed::from_split(input, output, Codec::new());
+
+        // answering the keepalive fails, but the keepalive is still returned first
+        server.write_all(&KEEPALIVE).await.unwrap();
+        assert!(matches!(
+            framed.read().await,
+            Ok(Packet::Tiny(Tiny {
+                subt: TinyType::None,
+                ..
+            }))
+        ));
+        assert!(matches!(framed.read().await, Err(Error::IO(_))));
+    }
+
+    #[tokio::test]
+    async fn test_stream_and_sink() {
+        let (mut framed, mut server) = pair().await;
+
+        framed
+            .send(TinyType::Ping.with_request_id(RequestId(2)).into())
+            .await
+            .unwrap();
+        let mut buf = [0u8; 4];
+        let _ = server.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, PING);
+
+        server.write_all(&PING).await.unwrap();
+        drop(server);
+        assert!(matches!(
+            framed.next().await,
+            Some(Ok(Packet::Tiny(Tiny {
+                subt: TinyType::Ping,
+                reqi: RequestId(2)
+            })))
+        ));
+        assert!(framed.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_write_after_shutdown() {
+        let (framed, _server) = pair().await;
+        let (_read, mut write) = framed.into_split();
+        let mut other = write.clone();
+
+        write.shutdown().await.unwrap();
+        assert!(matches!(
+            other.write(TinyType::Ping).await,
+            Err(Error::Disconnected)
+        ));
+    }
+}
@@ -0,0 +1,218 @@
+use std::{
+    fmt,
+    future::Future,
+    io,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll, ready},
+    time::Instant,
+};
+
+use futures::Stream;
+use tokio::{
+    io::AsyncRead,
+    net::{UdpSocket, tcp::OwnedReadHalf},
+    time::Sleep,
+};
+use tokio_util::io::poll_read_buf;
+
+use super::write::SharedWriteState;
+use crate::{Error, Packet, Result, net::Codec};
+
+/// The read half of any stream, see [super::Framed::from_stream].
+pub(super) struct BoxedRead(pub(super) Pin<Box<dyn AsyncRead + Send>>);
+
+impl fmt::Debug for BoxedRead {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BoxedRead")
+    }
+}
+
+#[derive(Debug)]
+pub(super) enum Reader {
+    Tcp(OwnedReadHalf),
+    Stream(BoxedRead),
+    Udp(Arc<UdpSocket>),
+    TcpUdp {
+        stream: OwnedReadHalf,
+        socket: UdpSocket,
+        datagrams: Codec,
+    },
+}
+
+impl Reader {
+    /// Poll the network for more bytes, feeding them into the relevant codec.
+    fn poll_fill(&mut self, cx: &mut Context<'_>, codec: &mut Codec) -> Poll<Result<()>> {
+        match self {
+            Reader::Tcp(stream) => poll_fill_stream(Pin::new(stream), cx, codec),
+            Reader::Stream(stream) => poll_fill_stream(stream.0.as_mut(), cx, codec),
+            Reader::Udp(socket) => {
+                let n = ready!(poll_recv(socket, cx, codec))?;
+                if n == 0 {
+                    return Poll::Ready(Err(Error::Disconnected));
+                }
+                Poll::Ready(Ok(()))
+            },
+            Reader::TcpUdp {
+                stream,
+                socket,
+                datagrams,
+            } => {
+                if let Poll::Ready(res) = poll_recv(socket, cx, datagrams) {
+                    return Poll::Ready(res.map(|_| ()));
+                }
+                poll_fill_stream(Pin::new(stream), cx, codec)
+            },
+        }
+    }
+}
+
+fn poll_fill_stream<R: AsyncRead + ?Sized>(
+    stream: Pin<&mut R>,
+    cx: &mut Context<'_>,
+    codec: &mut Codec,
+) -> Poll<Result<()>> {
+    match ready!(poll_read_buf(stream, cx, codec.buf_mut())) {
+        Ok(0) => Poll::Ready(Err(Error::Disconnected)),
+        Ok(_) => Poll::Ready(Ok(())),
+        Err(e) => Poll::Ready(Err(e.into())),
+    }
+}
+
+fn poll_recv(socket: &UdpSocket, cx: &mut Context<'_>, codec: &mut Codec) -> Poll<Result<usize>> {
+    loop {
+        ready!(socket.poll_recv_ready(cx))?;
+        match socket.try_recv_buf_from(codec.buf_mut()) {
+            Ok((n, _)) => return Poll::Ready(Ok(n)),
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+            Err(e) => return Poll::Ready(Err(e.into())),
+        }
+    }
+}
+
+/// The read half of a [super::Framed].
+///
+/// Obtained via [super::Framed::split] or [super::Framed::into_split], and implements
+/// [futures::Stream]. The read half remains responsible for the connection upkeep: keepalives
+/// are queued onto the shared outbound queue of the matching [super::FramedWrite] and flushed
+/// whilst reading, so you must continue to poll it even if you only care about writing. Reading
+/// fails with [Error::Timeout] if nothing is received, or nothing queued can be written, for
+/// [crate::net::DEFAULT_TIMEOUT_SECS].
+#[derive(Debug)]
+pub struct FramedRead {
+    pub(super) reader: Reader,
+    pub(super) codec: Codec,
+    pub(super) writer: SharedWriteState,
+    pub(super) sleep: Option<Pin<Box<Sleep>>>,
+    // An upkeep error found whilst returning a packet, reported by the next poll
+    pub(super) error: Option<Error>,
+}
+
+impl FramedRead {
+    /// Asynchronously wait for a packet from the inner network.
+    ///
+    /// Cancel-safe: all in-flight state (decoded bytes, pending outbound bytes, keepalive flag)
+    /// lives on `self`. Dropping the returned future from a `tokio::select!` loses no progress.
+    pub async fn read(&mut self) -> Result<Packet> {
+        std::future::poll_fn(|cx| self.poll_read(cx)).await
+    }
+
+    /// Poll for the next packet from the inner network.
+    pub fn poll_read(&mut self, cx: &mut Context<'_>) -> Poll<Result<Packet>> {
+        if let Some(e) = self.error.take() {
+            return Poll::Ready(Err(e));
+        }
+        loop {
+            if self.codec.reached_timeout() {
+                return Poll::Ready(Err(Error::Timeout(
+                    "Timeout exceeded, no keepalive or packet received".into(),
+                )));
+            }
+            if let Some(packet) = self.codec.decode()? {
+                // Queue any keepalive immediately, so that it is ordered ahead of anything the
+                // caller writes in response to this packet. The packet has already been
+                // consumed, so any error waits for the next poll.
+                if let Err(e) = self.poll_upkeep(cx) {
+                    self.error = Some(e);
+                }
+                return Poll::Ready(Ok(packet));
+            }
+            if let Reader::TcpUdp { datagrams, .. } = &mut self.reader
+                && let Some(packet) = datagrams.decode_datagram()
+            {
+                return Poll::Ready(Ok(packet));
+            }
+
+            self.poll_upkeep(cx)?;
+
+            match self.reader.poll_fill(cx, &mut self.codec) {
+                Poll::Ready(Ok(())) => continue,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => {},
+            }
+
+            let deadline = match self.writer.lock().write_deadline() {
+                Some(write) => write.min(self.codec.timeout()),
+                None => self.codec.timeout(),
+            }
+            .into();
+            let sleep = self
+                .sleep
+                .get_or_insert_with(|| Box::pin(tokio::time::sleep_until(deadline)));
+            if sleep.deadline() != deadline {
+                sleep.as_mut().reset(deadline);
+            }
+            if sleep.as_mut().poll(cx).is_ready() {
+                // loop around to report the timeout
+                continue;
+            }
+            return Poll::Pending;
+        }
+    }
+
+    /// The Insim version in use, see [Codec::version].
+    pub fn version(&self) -> u8 {
+        self.codec.version()
+    }
+
+    /// Queue any pending keepalive and opportunistically flush the outbound queue. If the flush
+    /// is pending we will be woken when the socket is writable.
+    fn poll_upkeep(&mut self, cx: &mut Context<'_>) -> Result<()> {
+        let mut writer = self.writer.lock();
+        // Share any newly negotiated version with the write half, and the datagram codec
+        writer.version = self.codec.version();
+        if let Reader::TcpUdp { datagrams, .. } = &mut self.reader {
+            datagrams.set_version(writer.version);
+        }
+        if let Some(ka) = self.codec.keepalive() {
+            // If the write half has been shutdown there's no-one to send this to.
+            let _ = writer.push(self.codec.encode(&ka)?);
+        }
+        if !writer.is_empty()
+            && let Poll::Ready(Err(e)) = writer.poll_flush(cx)
+        {
+            return Err(e);
+        }
+        if writer
+            .write_deadline()
+            .is_some_and(|deadline| deadline <= Instant::now())
+        {
+            return Err(Error::Timeout(
+                "Timeout exceeded, unable to write queued packets".into(),
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl Stream for FramedRead {
+    type Item = Result<Packet>;
+
+    /// Returns `None` once the connection has been closed, otherwise every packet or error.
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match ready!(self.get_mut().poll_read(cx)) {
+            Err(Error::Disconnected) => Poll::Ready(None),
+            res => Poll::Ready(Some(res)),
+        }
+    }
+}
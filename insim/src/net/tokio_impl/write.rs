@@ -0,0 +1,284 @@
+use std::{
+    collections::VecDeque,
+    fmt, io,
+    pin::Pin,
+    sync::{Arc, Mutex, MutexGuard},
+    task::{Context, Poll, Waker, ready},
+    time::{Duration, Instant},
+};
+
+use bytes::{Buf, Bytes};
+use futures::Sink;
+use tokio::{
+    io::AsyncWrite,
+    net::{UdpSocket, tcp::OwnedWriteHalf},
+};
+
+use crate::{
+    DEFAULT_BUFFER_CAPACITY, Error, Packet, Result,
+    insim::TinyType,
+    net::{DEFAULT_TIMEOUT_SECS, Metrics, codec::encode_packet},
+};
+
+/// The write half of any stream, see [super::Framed::from_stream].
+pub(super) struct BoxedWrite(pub(super) Pin<Box<dyn AsyncWrite + Send>>);
+
+impl fmt::Debug for BoxedWrite {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("BoxedWrite")
+    }
+}
+
+#[derive(Debug)]
+pub(super) enum Writer {
+    Tcp(OwnedWriteHalf),
+    Udp(Arc<UdpSocket>),
+    Stream(BoxedWrite),
+}
+
+/// Outbound state shared between a [FramedWrite] (and its clones) and the matching
+/// [super::FramedRead], which needs to queue keepalives.
+///
+/// The lock is only ever held for the duration of a single poll, never across an await point.
+#[derive(Debug)]
+pub(super) struct WriteState {
+    writer: Writer,
+    queue: VecDeque<Bytes>,
+    queued_bytes: usize,
+    // Tasks waiting on a flush. The underlying socket only remembers the most recently
+    // registered waker, so whoever completes (or fails) a flush wakes everyone else.
+    waiters: Vec<Waker>,
+    // When the queue last stopped draining, so that a peer which stops reading is noticed
+    stalled_since: Option<Instant>,
+    closed: bool,
+    // Insim version negotiated by the read half
+    pub(super) version: u8,
+    metrics: Option<Arc<dyn Metrics>>,
+}
+
+impl WriteState {
+    /// Encode and queue a packet.
+    fn push_packet(&mut self, packet: &Packet) -> Result<()> {
+        let buf = encode_packet(packet, self.version)?;
+        if let Some(metrics) = &self.metrics {
+            metrics.sent(packet, buf.len());
+        }
+        self.push(buf)
+    }
+
+    /// Queue an already encoded packet.
+    pub(super) fn push(&mut self, buf: Bytes) -> Result<()> {
+        if self.closed {
+            return Err(Error::Disconnected);
+        }
+        if !buf.is_empty() {
+            self.queued_bytes += buf.len();
+            self.queue.push_back(buf);
+        }
+        Ok(())
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+
+    fn register(&mut self, waker: &Waker) {
+        if !self.waiters.iter().any(|w| w.will_wake(waker)) {
+            self.waiters.push(waker.clone());
+        }
+    }
+
+    fn wake_all(&mut self) {
+        for waker in self.waiters.drain(..) {
+            waker.wake();
+        }
+    }
+
+    /// When the queued bytes will be considered undeliverable, if they are not draining.
+    pub(super) fn write_deadline(&self) -> Option<Instant> {
+        self.stalled_since
+            .map(|since| since + Duration::from_secs(DEFAULT_TIMEOUT_SECS))
+    }
+
+    /// Attempt to write everything queued to the network.
+    pub(super) fn poll_flush(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let res = self.poll_flush_inner(cx);
+        match res {
+            Poll::Pending => {
+                let _ = self.stalled_since.get_or_insert_with(Instant::now);
+                self.register(cx.waker());
+            },
+            Poll::Ready(_) => {
+                self.stalled_since = None;
+                self.wake_all();
+            },
+        }
+        res
+    }
+
+    fn poll_flush_inner(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        while let Some(front) = self.queue.front_mut() {
+            let n = match &mut self.writer {
+                Writer::Tcp(stream) => ready!(poll_write_stream(
+                    Pin::new(stream),
+                    cx,
+                    front,
+                    self.queued_bytes
+                ))?,
+                Writer::Stream(stream) => ready!(poll_write_stream(
+                    stream.0.as_mut(),
+                    cx,
+                    front,
+                    self.queued_bytes
+                ))?,
+                Writer::Udp(socket) => match socket.poll_send(cx, front) {
+                    // UDP should be whole datagram or error, but we still guard against short
+                    // sends.
+                    Poll::Ready(Ok(n)) if n != front.len() => {
+                        return Poll::Ready(Err(io::Error::new(
+                            io::ErrorKind::WriteZero,
+                            format!("short UDP send: {n} < {}", front.len()),
+                        )
+                        .into()));
+                    },
+                    Poll::Ready(Ok(n)) => n,
+                    Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                    Poll::Pending => return Poll::Pending,
+                },
+            };
+
+            front.advance(n);
+            self.queued_bytes -= n;
+            self.stalled_since = None;
+            if !front.has_remaining() {
+                let _ = self.queue.pop_front();
+            }
+        }
+
+        match &mut self.writer {
+            Writer::Tcp(stream) => Pin::new(stream).poll_flush(cx).map_err(Error::from),
+            Writer::Stream(stream) => stream.0.as_mut().poll_flush(cx).map_err(Error::from),
+            Writer::Udp(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_close(&mut self, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        if !self.closed {
+            self.push_packet(&TinyType::Close.into())?;
+            self.closed = true;
+        }
+
+        match self.poll_flush(cx) {
+            Poll::Ready(Ok(())) => {},
+            other => return other,
+        }
+
+        match &mut self.writer {
+            Writer::Tcp(stream) => Pin::new(stream).poll_shutdown(cx).map_err(Error::from),
+            Writer::Stream(stream) => stream.0.as_mut().poll_shutdown(cx).map_err(Error::from),
+            Writer::Udp(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+/// Write part of `buf` to a stream, treating a zero length write as an error.
+fn poll_write_stream<W: AsyncWrite + ?Sized>(
+    stream: Pin<&mut W>,
+    cx: &mut Context<'_>,
+    buf: &[u8],
+    pending: usize,
+) -> Poll<Result<usize>> {
+    match ready!(stream.poll_write(cx, buf)) {
+        Ok(0) => Poll::Ready(Err(io::Error::new(
+            io::ErrorKind::WriteZero,
+            format!("short stream send: 0 bytes written, {pending} pending"),
+        )
+        .into())),
+        Ok(n) => Poll::Ready(Ok(n)),
+        Err(e) => Poll::Ready(Err(e.into())),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub(super) struct SharedWriteState(Arc<Mutex<WriteState>>);
+
+impl SharedWriteState {
+    pub(super) fn new(writer: Writer, version: u8, metrics: Option<Arc<dyn Metrics>>) -> Self {
+        Self(Arc::new(Mutex::new(WriteState {
+            writer,
+            queue: VecDeque::new(),
+            queued_bytes: 0,
+            waiters: Vec::new(),
+            stalled_since: None,
+            closed: false,
+            version,
+            metrics,
+        })))
+    }
+
+    pub(super) fn lock(&self) -> MutexGuard<'_, WriteState> {
+        // We never panic whilst holding the lock, but if a caller's waker does we would rather
+        // carry on than poison every other handle.
+        self.0.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// The write half of a [super::Framed].
+///
+/// Obtained via [super::Framed::split] or [super::Framed::into_split]. `FramedWrite` is cheap to
+/// clone, allowing many tasks to write concurrently, and implements [futures::Sink].
+/// Writes from every clone, and keepalives queued by the matching [super::FramedRead], share a
+/// single outbound queue so packets are never interleaved mid-frame.
+#[derive(Debug, Clone)]
+pub struct FramedWrite {
+    pub(super) state: SharedWriteState,
+}
+
+impl FramedWrite {
+    /// Asynchronously write a packet to the inner network.
+    ///
+    /// Cancel-safe in the sense that once this has been polled the packet has been queued. If
+    /// the future is dropped before the queue is flushed, the remaining bytes are sent by the
+    /// next write or read.
+    pub async fn write<P: Into<Packet>>(&mut self, packet: P) -> Result<()> {
+        self.state.lock().push_packet(&packet.into())?;
+        self.flush().await
+    }
+
+    /// Asynchronously flush any queued packets to the inner network.
+    pub async fn flush(&mut self) -> Result<()> {
+        std::future::poll_fn(|cx| self.state.lock().poll_flush(cx)).await
+    }
+
+    /// Asynchronously send [TinyType::Close], flush the inner network and shutdown.
+    ///
+    /// Any further writes, from any clone, will return [Error::Disconnected].
+    pub async fn shutdown(&mut self) -> Result<()> {
+        std::future::poll_fn(|cx| self.state.lock().poll_close(cx)).await
+    }
+}
+
+impl Sink<Packet> for FramedWrite {
+    type Error = Error;
+
+    fn poll_ready(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        let mut state = self.state.lock();
+        if state.queued_bytes >= DEFAULT_BUFFER_CAPACITY {
+            state.poll_flush(cx)
+        } else {
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    fn start_send(self: Pin<&mut Self>, item: Packet) -> Result<()> {
+        self.state.lock().push_packet(&item)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.state.lock().poll_flush(cx)
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<()>> {
+        self.state.lock().poll_close(cx)
+    }
+}
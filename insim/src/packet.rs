@@ -24,7 +24,7 @@ use std::fmt::Debug;
 
 use insim_core::{Decode, Encode};
 
-use crate::insim::*;
+use crate::{MIN_VERSION, insim::*};
 
 /// Helper method to assist in converting the inner part of a [Packet] variant into [Packet] with a
 /// request identifier set. Mostly useful for things like [Packet::Tiny].
@@ -68,6 +68,7 @@ macro_rules! define_packet {
         /// told something about LFS), or both.
         #[derive(Debug, Clone, from_variants::FromVariants)]
         #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+        #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
         #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
         #[cfg_attr(feature = "serde", serde(tag = "type"))]
         #[non_exhaustive]
@@ -78,6 +79,8 @@ macro_rules! define_packet {
             )*
             #[cfg(feature = "allow-unknown-packet")]
             /// Raw binary capture of an unknown packet.
+            // Unknown packets only round trip for unknown discriminators, so are never generated
+            #[cfg_attr(feature = "arbitrary", arbitrary(skip))]
             Unknown(Raw)
         }
 
@@ -98,9 +101,70 @@ macro_rules! define_packet {
             }
         }
 
+        $(
+            impl $variant {
+                /// Packet type identifier, as used by [crate::net::Interest].
+                pub const DISCRIMINATOR: u8 = $disc;
+            }
+        )*
+
+        impl Packet {
+            /// Name of the packet variant, i.e. `"Ncn"`. This matches the `type` tag used by the
+            /// `serde` feature.
+            pub fn name(&self) -> &'static str {
+                match self {
+                    $(
+                        Self::$variant(_) => stringify!($variant),
+                    )*
+                    #[cfg(feature = "allow-unknown-packet")]
+                    Self::Unknown(_) => "Unknown",
+                }
+            }
+
+            /// Packet type identifier.
+            pub fn discriminator(&self) -> u8 {
+                match self {
+                    $(
+                        Self::$variant(_) => $disc,
+                    )*
+                    #[cfg(feature = "allow-unknown-packet")]
+                    Self::Unknown(raw) => raw.discriminator,
+                }
+            }
+
+            /// The [crate::identifiers::RequestId] of this packet. Replies echo the
+            /// identifier of the request which caused them, otherwise this is usually 0.
+            pub fn reqi(&self) -> crate::identifiers::RequestId {
+                match self {
+                    $(
+                        Self::$variant(inner) => inner.reqi,
+                    )*
+                    #[cfg(feature = "allow-unknown-packet")]
+                    Self::Unknown(_) => crate::identifiers::RequestId(0),
+                }
+            }
+
+            /// Replace the [crate::identifiers::RequestId] of this packet in place.
+            pub fn set_reqi<R: Into<crate::identifiers::RequestId>>(&mut self, reqi: R) {
+                let reqi = reqi.into();
+                match self {
+                    $(
+                        Self::$variant(inner) => inner.reqi = reqi,
+                    )*
+                    #[cfg(feature = "allow-unknown-packet")]
+                    Self::Unknown(_) => {},
+                }
+            }
+        }
+
         impl Decode for Packet {
             fn decode(ctx: &mut insim_core::DecodeContext) -> Result<Self, insim_core::DecodeError> {
                 let discriminator = ctx.decode::<u8>("discriminator")?;
+                let required = min_version(discriminator);
+                if let Some(found) = ctx.version() && found < required {
+                    return Err(insim_core::DecodeErrorKind::UnsupportedVersion { required, found }
+                        .context("Packet is not supported by this Insim version"));
+                }
                 match discriminator {
                     $(
                         $disc => Ok(Self::$variant(<$variant>::decode(ctx)?)),
@@ -129,6 +193,11 @@ macro_rules! define_packet {
 
         impl Encode for Packet {
             fn encode(&self, ctx: &mut insim_core::EncodeContext) -> Result<(), insim_core::EncodeError> {
+                let required = self.min_version();
+                if let Some(found) = ctx.version() && found < required {
+                    return Err(insim_core::EncodeErrorKind::UnsupportedVersion { required, found }
+                        .context("Packet is not supported by this Insim version"));
+                }
                 match self {
                     $(
                         Self::$variant(inner) => {
@@ -309,6 +378,14 @@ define_packet! (
     Set = 70
 );
 
+/// The oldest Insim version which supports a packet type.
+const fn min_version(discriminator: u8) -> u8 {
+    match discriminator {
+        Set::DISCRIMINATOR => 10,
+        _ => MIN_VERSION,
+    }
+}
+
 impl Default for Packet {
     /// A Tiny with Type of None was selected as the default as it's a non-damaging packet
     /// (keepalive)
@@ -408,6 +485,15 @@ impl Packet {
             _ => 4, // a sensible default for everything else
         }
     }
+
+    /// The oldest Insim version which supports this packet, including any fields or flags that
+    /// it uses.
+    pub fn min_version(&self) -> u8 {
+        match self {
+            Packet::Isi(isi) if isi.flags.contains(IsiFlags::SET) => 10,
+            _ => min_version(self.discriminator()),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -450,4 +536,10 @@ mod test {
         let res = Packet::decode(&mut ctx);
         assert!(res.is_err());
     }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_round_trip() {
+        insim_core::arbitrary_helpers::round_trip::<Packet>(4096, 20_000, Some(crate::VERSION));
+    }
 }
@@ -0,0 +1,527 @@
+//! Borrowed, lazily decoded, views over raw packet frames.
+//!
+//! Decoding every [Packet] allocates for any packet containing a list or a string. For high
+//! rate packets, such as [Mci] with a short [crate::insim::Isi::interval], this can be
+//! significant. [PacketRef] instead borrows the frame returned by [crate::net::Codec::next_frame],
+//! and only decodes the fields which are read.
+//!
+//! ```rust,ignore
+//! while let Some(frame) = codec.next_frame()? {
+//!     match PacketRef::new(&frame)? {
+//!         PacketRef::Mci(mci) => {
+//!             for car in mci.iter() {
+//!                 println!("{:?} {:?}", car.plid(), car.speed());
+//!             }
+//!         },
+//!         other => handle(other.to_packet(codec.version())?),
+//!     }
+//! }
+//! ```
+use bytes::Bytes;
+use insim_core::{
+    DecodeErrorKind, angvel::AngVelI16, coordinate::Coordinate, heading::HeadingU16,
+    speed::SpeedU16, string::codepages::CodepageStr,
+};
+
+use crate::{
+    Error, Packet, Result,
+    identifiers::{ConnectionId, PlayerId, RequestId},
+    insim::{CompCar, CompCarInfo, Iii, Mci, Mso, MsoUserType, Nlp, NodeLapInfo},
+    net::codec::decode_frame,
+};
+
+const MSO: u8 = Mso::DISCRIMINATOR;
+const III: u8 = Iii::DISCRIMINATOR;
+const NLP: u8 = Nlp::DISCRIMINATOR;
+const MCI: u8 = Mci::DISCRIMINATOR;
+
+/// Offset of the first byte following the header, within a frame.
+const BODY: usize = 4;
+const COMPCAR_LEN: usize = 28;
+const NODELAP_LEN: usize = 6;
+
+/// A borrowed view over a single, complete, frame.
+#[derive(Debug, Clone, Copy)]
+#[non_exhaustive]
+pub enum PacketRef<'a> {
+    /// [crate::insim::Mso]
+    Mso(MsoRef<'a>),
+    /// [crate::insim::Iii]
+    Iii(IiiRef<'a>),
+    /// [crate::insim::Nlp]
+    Nlp(NlpRef<'a>),
+    /// [crate::insim::Mci]
+    Mci(MciRef<'a>),
+    /// Any other packet, which has no borrowed representation. Use [PacketRef::to_packet].
+    Other(OtherRef<'a>),
+}
+
+impl<'a> PacketRef<'a> {
+    /// Borrow a frame, including the length prefix, as returned by
+    /// [crate::net::Codec::next_frame].
+    ///
+    /// Only the length of the frame is checked. Fields are decoded when read.
+    pub fn new(frame: &'a Bytes) -> Result<Self> {
+        if frame.len() < BODY || usize::from(frame[0]) * 4 != frame.len() {
+            return Err(invalid(frame, 0, "Frame length does not match size"));
+        }
+
+        let (count, each) = match frame[1] {
+            MSO | III => (1, 8),
+            NLP => (usize::from(frame[3]), NODELAP_LEN),
+            MCI => (usize::from(frame[3]), COMPCAR_LEN),
+            _ => (0, 0),
+        };
+        if frame.len() < BODY + count * each {
+            return Err(invalid(frame, BODY, "Frame too short"));
+        }
+
+        Ok(match frame[1] {
+            MSO => Self::Mso(MsoRef(frame)),
+            III => Self::Iii(IiiRef(frame)),
+            NLP => Self::Nlp(NlpRef(frame)),
+            MCI => Self::Mci(MciRef(frame)),
+            _ => Self::Other(OtherRef(frame)),
+        })
+    }
+
+    /// The raw frame, including the length prefix.
+    pub fn frame(&self) -> &'a Bytes {
+        match self {
+            Self::Mso(MsoRef(frame))
+            | Self::Iii(IiiRef(frame))
+            | Self::Nlp(NlpRef(frame))
+            | Self::Mci(MciRef(frame))
+            | Self::Other(OtherRef(frame)) => frame,
+        }
+    }
+
+    /// The packet type identifier.
+    pub fn discriminator(&self) -> u8 {
+        self.frame()[1]
+    }
+
+    /// The [RequestId] of this packet.
+    pub fn reqi(&self) -> RequestId {
+        RequestId(self.frame()[2])
+    }
+
+    /// Fully decode into an owned [Packet], as Insim `version`, typically
+    /// [crate::net::Codec::version]. This shares, rather than copies, the frame.
+    pub fn to_packet(&self, version: u8) -> Result<Packet> {
+        decode_frame(self.frame().clone(), version)
+    }
+}
+
+impl<'a> TryFrom<&'a Bytes> for PacketRef<'a> {
+    type Error = Error;
+
+    fn try_from(frame: &'a Bytes) -> Result<Self> {
+        Self::new(frame)
+    }
+}
+
+fn invalid(frame: &Bytes, offset: usize, context: &'static str) -> Error {
+    Error::Decode {
+        offset,
+        input: frame.clone(),
+        error: DecodeErrorKind::UnexpectedEof.context(context),
+    }
+}
+
+fn u16_at(buf: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([buf[at], buf[at + 1]])
+}
+
+fn i32_at(buf: &[u8], at: usize) -> i32 {
+    i32::from_le_bytes([buf[at], buf[at + 1], buf[at + 2], buf[at + 3]])
+}
+
+/// Borrowed [crate::insim::Mso].
+#[derive(Debug, Clone, Copy)]
+pub struct MsoRef<'a>(&'a Bytes);
+
+impl<'a> MsoRef<'a> {
+    /// Connection that sent the message (0 = host).
+    pub fn ucid(&self) -> ConnectionId {
+        ConnectionId(self.0[4])
+    }
+
+    /// Player that sent the message (0 = use `ucid`).
+    pub fn plid(&self) -> PlayerId {
+        PlayerId(self.0[5])
+    }
+
+    /// Message origin and visibility.
+    pub fn usertype(&self) -> Option<MsoUserType> {
+        match self.0[6] {
+            0 => Some(MsoUserType::System),
+            1 => Some(MsoUserType::User),
+            2 => Some(MsoUserType::Prefix),
+            3 => Some(MsoUserType::O),
+            _ => None,
+        }
+    }
+
+    /// The full message, including any name prefix.
+    pub fn msg(&self) -> CodepageStr<'a> {
+        CodepageStr::new(&self.0[8..])
+    }
+
+    /// The name prefix, before the user-entered text.
+    pub fn name(&self) -> CodepageStr<'a> {
+        CodepageStr::new(&self.0[8..self.textstart()])
+    }
+
+    /// The user-entered text.
+    pub fn text(&self) -> CodepageStr<'a> {
+        CodepageStr::new(&self.0[self.textstart()..])
+    }
+
+    fn textstart(&self) -> usize {
+        (8 + usize::from(self.0[7])).min(self.0.len())
+    }
+}
+
+/// Borrowed [crate::insim::Iii].
+#[derive(Debug, Clone, Copy)]
+pub struct IiiRef<'a>(&'a Bytes);
+
+impl<'a> IiiRef<'a> {
+    /// Connection that sent the message.
+    pub fn ucid(&self) -> ConnectionId {
+        ConnectionId(self.0[4])
+    }
+
+    /// Player that sent the message.
+    pub fn plid(&self) -> PlayerId {
+        PlayerId(self.0[5])
+    }
+
+    /// The message.
+    pub fn msg(&self) -> CodepageStr<'a> {
+        CodepageStr::new(&self.0[8..])
+    }
+}
+
+/// Borrowed [crate::insim::Nlp].
+#[derive(Debug, Clone, Copy)]
+pub struct NlpRef<'a>(&'a Bytes);
+
+impl<'a> NlpRef<'a> {
+    /// Number of players.
+    pub fn len(&self) -> usize {
+        usize::from(self.0[3])
+    }
+
+    /// Are there no players?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over each player, without allocating.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = NodeLapInfo> + 'a {
+        let frame = self.0;
+        (0..self.len()).map(move |i| {
+            let at = BODY + i * NODELAP_LEN;
+            NodeLapInfo {
+                node: u16_at(frame, at),
+                lap: u16_at(frame, at + 2),
+                plid: PlayerId(frame[at + 4]),
+                position: frame[at + 5],
+            }
+        })
+    }
+
+    /// Convert into an owned [Nlp].
+    pub fn to_owned(&self) -> Nlp {
+        Nlp {
+            reqi: RequestId(self.0[2]),
+            info: self.iter().collect(),
+        }
+    }
+}
+
+/// Borrowed [crate::insim::Mci].
+#[derive(Debug, Clone, Copy)]
+pub struct MciRef<'a>(&'a Bytes);
+
+impl<'a> MciRef<'a> {
+    /// Number of cars.
+    pub fn len(&self) -> usize {
+        usize::from(self.0[3])
+    }
+
+    /// Are there no cars?
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Iterate over each car, without allocating or decoding.
+    pub fn iter(&self) -> impl ExactSizeIterator<Item = CompCarRef<'a>> + 'a {
+        let frame: &'a [u8] = self.0;
+        (0..self.len()).map(move |i| {
+            let at = BODY + i * COMPCAR_LEN;
+            CompCarRef(&frame[at..at + COMPCAR_LEN])
+        })
+    }
+
+    /// Is this the first MCI packet in this set of MCI packets?
+    pub fn is_first(&self) -> bool {
+        self.iter().any(|c| c.info().is_first())
+    }
+
+    /// Is this the last MCI packet in this set of MCI packets?
+    pub fn is_last(&self) -> bool {
+        self.iter().any(|c| c.info().is_last())
+    }
+
+    /// Convert into an owned [Mci].
+    pub fn to_owned(&self) -> Mci {
+        Mci {
+            reqi: RequestId(self.0[2]),
+            info: self.iter().map(|c| c.to_owned()).collect(),
+        }
+    }
+}
+
+/// Borrowed [CompCar]. Each field is decoded when read.
+#[derive(Debug, Clone, Copy)]
+pub struct CompCarRef<'a>(&'a [u8]);
+
+impl CompCarRef<'_> {
+    /// Index of the last node the player passed.
+    pub fn node(&self) -> u16 {
+        u16_at(self.0, 0)
+    }
+
+    /// Current lap number.
+    pub fn lap(&self) -> u16 {
+        u16_at(self.0, 2)
+    }
+
+    /// Player identifier.
+    pub fn plid(&self) -> PlayerId {
+        PlayerId(self.0[4])
+    }
+
+    /// Race position.
+    pub fn position(&self) -> u8 {
+        self.0[5]
+    }
+
+    /// Additional state flags.
+    pub fn info(&self) -> CompCarInfo {
+        CompCarInfo::from_bits_truncate(self.0[6])
+    }
+
+    /// World position.
+    pub fn xyz(&self) -> Coordinate {
+        Coordinate {
+            x: i32_at(self.0, 8),
+            y: i32_at(self.0, 12),
+            z: i32_at(self.0, 16),
+        }
+    }
+
+    /// Speed.
+    pub fn speed(&self) -> SpeedU16 {
+        SpeedU16::from_raw(u16_at(self.0, 20))
+    }
+
+    /// Direction of motion (heading of velocity).
+    pub fn direction(&self) -> HeadingU16 {
+        HeadingU16::from_raw(u16_at(self.0, 22))
+    }
+
+    /// Car facing direction.
+    pub fn heading(&self) -> HeadingU16 {
+        HeadingU16::from_raw(u16_at(self.0, 24))
+    }
+
+    /// Angular velocity of the car.
+    pub fn angvel(&self) -> AngVelI16 {
+        AngVelI16::from_raw(u16_at(self.0, 26) as i16)
+    }
+
+    /// Convert into an owned [CompCar].
+    pub fn to_owned(&self) -> CompCar {
+        CompCar {
+            node: self.node(),
+            lap: self.lap(),
+            plid: self.plid(),
+            position: self.position(),
+            info: self.info(),
+            xyz: self.xyz(),
+            speed: self.speed(),
+            direction: self.direction(),
+            heading: self.heading(),
+            angvel: self.angvel(),
+        }
+    }
+}
+
+/// Any packet without a borrowed representation.
+#[derive(Debug, Clone, Copy)]
+pub struct OtherRef<'a>(&'a Bytes);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        VERSION,
+        insim::{Tiny, TinyType},
+        net::codec::encode_packet,
+    };
+
+    fn mci() -> Mci {
+        Mci {
+            reqi: RequestId(3),
+            info: (1..=3)
+                .map(|i| CompCar {
+                    node: 300 + u16::from(i),
+                    lap: 2,
+                    plid: PlayerId(i),
+                    position: i,
+                    info: if i == 1 {
+                        CompCarInfo::FIRST
+                    } else {
+                        CompCarInfo::empty()
+                    },
+                    xyz: Coordinate {
+                        x: -65536 * i32::from(i),
+                        y: 12,
+                        z: 65536,
+                    },
+                    speed: SpeedU16::from_raw(1000),
+                    direction: HeadingU16::from_raw(16384),
+                    heading: HeadingU16::from_raw(32768),
+                    angvel: AngVelI16::from_raw(-100),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_discriminators() {
+        for packet in [
+            Packet::from(Mso::default()),
+            Iii::default().into(),
+            Nlp::default().into(),
+            Mci::default().into(),
+        ] {
+            assert_eq!(
+                encode_packet(&packet, VERSION).unwrap()[1],
+                packet.discriminator()
+            );
+        }
+    }
+
+    #[test]
+    fn test_mci_ref_matches_owned() {
+        let frame = encode_packet(&mci().into(), VERSION).unwrap();
+        let PacketRef::Mci(mci_ref) = PacketRef::new(&frame).unwrap() else {
+            panic!("expected Mci");
+        };
+        assert_eq!(mci_ref.len(), 3);
+        assert!(mci_ref.is_first());
+        assert!(!mci_ref.is_last());
+
+        let Packet::Mci(owned) = decode_frame(frame.clone(), VERSION).unwrap() else {
+            panic!("expected Mci");
+        };
+        let borrowed = mci_ref.to_owned();
+        assert_eq!(borrowed.reqi, owned.reqi);
+        for (b, o) in borrowed.info.iter().zip(owned.info.iter()) {
+            assert_eq!(format!("{b:?}"), format!("{o:?}"));
+        }
+        assert_eq!(
+            mci_ref.iter().map(|c| c.plid()).collect::<Vec<_>>(),
+            [PlayerId(1), PlayerId(2), PlayerId(3)]
+        );
+    }
+
+    #[test]
+    fn test_nlp_ref() {
+        let nlp = Nlp {
+            reqi: RequestId(1),
+            info: vec![
+                NodeLapInfo {
+                    node: 258,
+                    lap: 14,
+                    plid: PlayerId(5),
+                    position: 18,
+                },
+                NodeLapInfo::default(),
+            ],
+        };
+        let frame = encode_packet(&nlp.into(), VERSION).unwrap();
+        let PacketRef::Nlp(nlp) = PacketRef::new(&frame).unwrap() else {
+            panic!("expected Nlp");
+        };
+        let first = nlp.iter().next().unwrap();
+        assert_eq!(first.node, 258);
+        assert_eq!(first.position, 18);
+        assert_eq!(nlp.to_owned().info.len(), 2);
+    }
+
+    #[test]
+    fn test_mso_ref() {
+        let mso = Mso {
+            ucid: ConnectionId(2),
+            usertype: MsoUserType::User,
+            textstart: 7,
+            msg: "^1Bob: hello".into(),
+            ..Default::default()
+        };
+        let frame = encode_packet(&mso.into(), VERSION).unwrap();
+        let PacketRef::Mso(mso) = PacketRef::new(&frame).unwrap() else {
+            panic!("expected Mso");
+        };
+        assert_eq!(mso.ucid(), ConnectionId(2));
+        assert_eq!(mso.usertype(), Some(MsoUserType::User));
+        assert_eq!(mso.name().to_lossy_string(), "^1Bob: ");
+        assert_eq!(mso.text().to_lossy_string(), "hello");
+        assert_eq!(mso.msg().to_lossy_string(), "^1Bob: hello");
+    }
+
+    #[test]
+    fn test_codec_next_frame() {
+        let mut codec = crate::net::Codec::new();
+        codec.feed(&encode_packet(&TinyType::None.into(), VERSION).unwrap());
+        codec.feed(&encode_packet(&mci().into(), VERSION).unwrap());
+
+        let keepalive = codec.next_frame().unwrap().unwrap();
+        assert!(matches!(
+            PacketRef::new(&keepalive).unwrap(),
+            PacketRef::Other(_)
+        ));
+        assert!(codec.keepalive().is_some());
+
+        let frame = codec.next_frame().unwrap().unwrap();
+        let packet = PacketRef::new(&frame).unwrap();
+        assert_eq!(packet.reqi(), RequestId(3));
+        assert!(matches!(packet, PacketRef::Mci(mci) if mci.len() == 3));
+        assert!(codec.next_frame().unwrap().is_none());
+    }
+
+    #[test]
+    fn test_other_and_invalid() {
+        let frame = encode_packet(&TinyType::Ping.into(), VERSION).unwrap();
+        let packet = PacketRef::new(&frame).unwrap();
+        assert!(matches!(packet, PacketRef::Other(_)));
+        assert!(matches!(
+            packet.to_packet(VERSION).unwrap(),
+            Packet::Tiny(Tiny {
+                subt: TinyType::Ping,
+                ..
+            })
+        ));
+
+        // claims 3 cars, but has none
+        let frame = Bytes::from_static(&[1, MCI, 0, 3]);
+        assert!(matches!(PacketRef::new(&frame), Err(Error::Decode { .. })));
+        let frame = Bytes::from_static(&[2, MCI, 0, 0]);
+        assert!(PacketRef::new(&frame).is_err());
+    }
+}
@@ -0,0 +1,508 @@
+//! Correlate requests with their replies.
+//!
+//! Many [Tiny] and [Small] requests are answered by
+//! one or more packets carrying the same [RequestId]. The [Request] trait describes which
+//! packets answer each request, and [Pending] collects them from the packet stream.
+//!
+//! Requests which are answered by a variable number of packets (i.e. one [Ncn] per
+//! connection) are followed by a [TinyType::Ping] using the same [RequestId]. LFS answers
+//! packets in order, so the matching [TinyType::Reply] marks the end of the replies. This
+//! means a request with no replies, such as [Players] with nobody on track, completes with an
+//! empty set rather than timing out.
+//!
+//! If you are using a spawned connection, `Handle::request` takes care of all of this for you:
+//!
+//! ```rust,ignore
+//! use insim::request::Connections;
+//!
+//! let (handle, _) = insim::tcp("127.0.0.1:29999").spawn();
+//! for ncn in handle.request(Connections, Duration::from_secs(5)).await? {
+//!     println!("{} - {}", ncn.ucid, ncn.uname);
+//! }
+//! ```
+use std::{
+    fmt::Debug,
+    sync::{
+        Arc,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+
+use crate::{
+    Packet, WithRequestId,
+    identifiers::{PlayerId, RequestId},
+    insim::{
+        Aii, Axi, Axm, Cpp, Ipb, Ism, Mal, Mci, Nci, Ncn, Nlp, Npl, Plh, Reo, Res, Rip, Rst, Slc,
+        Small, SmallType, Ssh, Sta, Tiny, TinyType, Ver,
+    },
+};
+
+/// How many packets LFS answers a [Request] with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expect {
+    /// Exactly one packet.
+    One,
+    /// Zero or more packets.
+    Many,
+}
+
+/// A request which LFS answers with packets carrying the same [RequestId].
+pub trait Request: Debug {
+    /// The type of each reply.
+    type Reply: Debug;
+
+    /// How many replies LFS sends.
+    const EXPECT: Expect;
+
+    /// Build the request packet.
+    fn packet(&self, reqi: RequestId) -> Packet;
+
+    /// If `packet` answers this request, sent using `reqi`, return the reply.
+    fn reply(&self, reqi: RequestId, packet: &Packet) -> Option<Self::Reply>;
+}
+
+/// Collects the replies to a single [Request].
+///
+/// This is the "sans-io" core used by the spawned connection handles. You can use it with
+/// your own IO by writing every packet from [Pending::packets] and then passing each packet
+/// received to [Pending::handle] until it returns true.
+#[derive(Debug)]
+pub struct Pending<R: Request> {
+    request: R,
+    reqi: RequestId,
+    replies: Vec<R::Reply>,
+}
+
+impl<R: Request> Pending<R> {
+    /// Start a new request, using `reqi` to correlate the replies.
+    /// `reqi` must be non-zero, and should not be in use by any other outstanding request.
+    pub fn new(request: R, reqi: RequestId) -> Self {
+        Self {
+            request,
+            reqi,
+            replies: Vec::new(),
+        }
+    }
+
+    /// The request.
+    pub fn request(&self) -> &R {
+        &self.request
+    }
+
+    /// The [RequestId] used to correlate replies.
+    pub fn reqi(&self) -> RequestId {
+        self.reqi
+    }
+
+    /// The packets to send, in order.
+    pub fn packets(&self) -> Vec<Packet> {
+        let mut packets = vec![self.request.packet(self.reqi)];
+        if R::EXPECT == Expect::Many {
+            packets.push(TinyType::Ping.with_request_id(self.reqi).into());
+        }
+        packets
+    }
+
+    /// Offer a received packet. Returns true once every reply has been received.
+    pub fn handle(&mut self, packet: &Packet) -> bool {
+        if let Some(reply) = self.request.reply(self.reqi, packet) {
+            self.replies.push(reply);
+            return R::EXPECT == Expect::One;
+        }
+
+        R::EXPECT == Expect::Many
+            && matches!(
+                packet,
+                Packet::Tiny(Tiny {
+                    subt: TinyType::Reply,
+                    reqi,
+                }) if *reqi == self.reqi
+            )
+    }
+
+    /// Consume, returning the replies received so far.
+    pub fn finish(self) -> Vec<R::Reply> {
+        self.replies
+    }
+}
+
+/// Allocates [RequestId]s for [Request]s, cycling through [RequestIds::FIRST] to 255.
+///
+/// Clones share the same sequence. Avoid using values in this range for your own requests.
+/// [crate::net::LatencyMonitor::REQUEST_ID] is reserved separately.
+#[derive(Debug, Clone)]
+pub struct RequestIds(Arc<AtomicU8>);
+
+impl Default for RequestIds {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RequestIds {
+    /// The first [RequestId] allocated.
+    pub const FIRST: u8 = 128;
+
+    /// Create a new sequence.
+    pub fn new() -> Self {
+        Self(Arc::new(AtomicU8::new(Self::FIRST)))
+    }
+
+    /// Allocate the next [RequestId].
+    pub fn next(&self) -> RequestId {
+        let next = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |n| {
+                Some(n.checked_add(1).unwrap_or(Self::FIRST))
+            })
+            .unwrap_or(Self::FIRST);
+        RequestId(next)
+    }
+}
+
+macro_rules! tiny_request {
+    (
+        $(#[$attr:meta])*
+        $name:ident: $subt:ident => $reply:ident $(if $pat:pat)?, $expect:ident
+    ) => {
+        $(#[$attr])*
+        #[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+        pub struct $name;
+
+        impl Request for $name {
+            type Reply = $reply;
+            const EXPECT: Expect = Expect::$expect;
+
+            fn packet(&self, reqi: RequestId) -> Packet {
+                TinyType::$subt.with_request_id(reqi).into()
+            }
+
+            fn reply(&self, reqi: RequestId, packet: &Packet) -> Option<Self::Reply> {
+                match packet {
+                    Packet::$reply(reply) if reply.reqi == reqi $(&& matches!(reply.subt, $pat))? => {
+                        Some(reply.clone())
+                    },
+                    _ => None,
+                }
+            }
+        }
+    };
+}
+
+tiny_request!(
+    /// Request version information, using [TinyType::Ver]. Answered by a single [Ver].
+    Version: Ver => Ver, One
+);
+tiny_request!(
+    /// Ping LFS, using [TinyType::Ping]. Answered by a single [TinyType::Reply].
+    Ping: Ping => Tiny if TinyType::Reply, One
+);
+tiny_request!(
+    /// Request the camera position, using [TinyType::Scp]. Answered by a single [Cpp].
+    Camera: Scp => Cpp, One
+);
+tiny_request!(
+    /// Request the game state, using [TinyType::Sst]. Answered by a single [Sta].
+    State: Sst => Sta, One
+);
+tiny_request!(
+    /// Request the race time, using [TinyType::Gtm]. Answered by a single [SmallType::Rtp].
+    RaceTime: Gtm => Small if SmallType::Rtp(_), One
+);
+tiny_request!(
+    /// Request multiplayer information, using [TinyType::Ism]. Answered by an [Ism], or
+    /// nothing if not in multiplayer.
+    Multiplayer: Ism => Ism, Many
+);
+tiny_request!(
+    /// Request every connection, using [TinyType::Ncn]. Answered by one [Ncn] per connection,
+    /// including the host.
+    Connections: Ncn => Ncn, Many
+);
+tiny_request!(
+    /// Request every player, using [TinyType::Npl]. Answered by one [Npl] per player.
+    Players: Npl => Npl, Many
+);
+tiny_request!(
+    /// Request the results, using [TinyType::Res]. Answered by one [Res] per result.
+    Results: Res => Res, Many
+);
+tiny_request!(
+    /// Request node and lap information, using [TinyType::Nlp]. Answered by an [Nlp], if
+    /// anyone is on track.
+    NodeLap: Nlp => Nlp, Many
+);
+tiny_request!(
+    /// Request multi car information, using [TinyType::Mci]. Answered by one [Mci] per 16
+    /// cars on track.
+    MultiCarInfo: Mci => Mci, Many
+);
+tiny_request!(
+    /// Request the grid order, using [TinyType::Reo]. Answered by a single [Reo].
+    Reorder: Reo => Reo, One
+);
+tiny_request!(
+    /// Request race start information, using [TinyType::Rst]. Answered by an [Rst], or
+    /// nothing if no race is in progress.
+    RaceStart: Rst => Rst, Many
+);
+tiny_request!(
+    /// Request autocross layout information, using [TinyType::Axi]. Answered by a single
+    /// [Axi].
+    Autocross: Axi => Axi, One
+);
+tiny_request!(
+    /// Request replay information, using [TinyType::Rip]. Answered by a single [Rip].
+    Replay: Rip => Rip, One
+);
+tiny_request!(
+    /// Request connection information (host only), using [TinyType::Nci]. Answered by one
+    /// [Nci] per connection.
+    ConnectionInfo: Nci => Nci, Many
+);
+tiny_request!(
+    /// Request the allowed cars, using [TinyType::Alc]. Answered by a single
+    /// [SmallType::Alc].
+    AllowedCars: Alc => Small if SmallType::Alc(_), One
+);
+tiny_request!(
+    /// Request the full layout, using [TinyType::Axm]. Answered by one or more [Axm].
+    Layout: Axm => Axm, Many
+);
+tiny_request!(
+    /// Request every selected car, using [TinyType::Slc]. Answered by one [Slc] per
+    /// connection.
+    SelectedCars: Slc => Slc, Many
+);
+tiny_request!(
+    /// Request the allowed mods, using [TinyType::Mal]. Answered by a single [Mal].
+    AllowedMods: Mal => Mal, One
+);
+tiny_request!(
+    /// Request player handicaps, using [TinyType::Plh]. Answered by a single [Plh].
+    Handicaps: Plh => Plh, One
+);
+tiny_request!(
+    /// Request IP bans, using [TinyType::Ipb]. Answered by a single [Ipb].
+    Bans: Ipb => Ipb, One
+);
+tiny_request!(
+    /// Request the local car lights, using [TinyType::Lcl]. Answered by a
+    /// [SmallType::Lcl], if driving.
+    LocalLights: Lcl => Small if SmallType::Lcl(_), Many
+);
+
+/// Request information about a local AI, using [SmallType::Aii]. Answered by an [Aii], if
+/// the player is a local AI.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct AiInfo(pub PlayerId);
+
+impl Request for AiInfo {
+    type Reply = Aii;
+    const EXPECT: Expect = Expect::Many;
+
+    fn packet(&self, reqi: RequestId) -> Packet {
+        Small {
+            reqi,
+            subt: SmallType::Aii(self.0),
+        }
+        .into()
+    }
+
+    fn reply(&self, reqi: RequestId, packet: &Packet) -> Option<Self::Reply> {
+        match packet {
+            Packet::Aii(aii) if aii.reqi == reqi && aii.plid == self.0 => Some(aii.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Load, seek or pause a replay, using a [Rip]. Answered by a single [Rip] once complete,
+/// which may take some time when seeking.
+#[derive(Debug, Clone, Default)]
+pub struct ReplayControl(pub Rip);
+
+impl Request for ReplayControl {
+    type Reply = Rip;
+    const EXPECT: Expect = Expect::One;
+
+    fn packet(&self, reqi: RequestId) -> Packet {
+        Rip {
+            reqi,
+            ..self.0.clone()
+        }
+        .into()
+    }
+
+    fn reply(&self, reqi: RequestId, packet: &Packet) -> Option<Self::Reply> {
+        match packet {
+            Packet::Rip(rip) if rip.reqi == reqi => Some(rip.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// Take a screenshot, using an [Ssh] with the given name, or an automatically generated name
+/// if empty. Answered by a single [Ssh].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Screenshot(pub String);
+
+impl Request for Screenshot {
+    type Reply = Ssh;
+    const EXPECT: Expect = Expect::One;
+
+    fn packet(&self, reqi: RequestId) -> Packet {
+        Ssh {
+            reqi,
+            name: self.0.clone(),
+            ..Default::default()
+        }
+        .into()
+    }
+
+    fn reply(&self, reqi: RequestId, packet: &Packet) -> Option<Self::Reply> {
+        match packet {
+            Packet::Ssh(ssh) if ssh.reqi == reqi => Some(ssh.clone()),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::identifiers::ConnectionId;
+
+    fn reply(reqi: u8) -> Packet {
+        TinyType::Reply.with_request_id(reqi).into()
+    }
+
+    #[test]
+    fn test_many_until_ping_reply() {
+        let mut pending = Pending::new(Connections, RequestId(200));
+        let packets = pending.packets();
+        assert_eq!(packets.len(), 2);
+        assert!(matches!(
+            packets[0],
+            Packet::Tiny(Tiny {
+                subt: TinyType::Ncn,
+                reqi: RequestId(200)
+            })
+        ));
+        assert!(matches!(
+            packets[1],
+            Packet::Tiny(Tiny {
+                subt: TinyType::Ping,
+                reqi: RequestId(200)
+            })
+        ));
+
+        let ncn = |reqi: u8, ucid: u8| -> Packet {
+            Ncn {
+                reqi: RequestId(reqi),
+                ucid: ConnectionId(ucid),
+                ..Default::default()
+            }
+            .into()
+        };
+
+        assert!(!pending.handle(&ncn(200, 0)));
+        // unrelated request
+        assert!(!pending.handle(&ncn(0, 5)));
+        assert!(!pending.handle(&reply(1)));
+        assert!(!pending.handle(&ncn(200, 1)));
+        assert!(pending.handle(&reply(200)));
+
+        let replies = pending.finish();
+        assert_eq!(replies.len(), 2);
+        assert_eq!(replies[1].ucid, ConnectionId(1));
+    }
+
+    #[test]
+    fn test_many_empty() {
+        let mut pending = Pending::new(Players, RequestId(130));
+        assert!(pending.handle(&reply(130)));
+        assert!(pending.finish().is_empty());
+    }
+
+    #[test]
+    fn test_race_start_without_race() {
+        let mut pending = Pending::new(RaceStart, RequestId(132));
+        assert_eq!(pending.packets().len(), 2);
+        // LFS only answers during a race, so the ping's reply arrives alone
+        assert!(pending.handle(&reply(132)));
+        assert!(pending.finish().is_empty());
+
+        let mut pending = Pending::new(RaceStart, RequestId(133));
+        let rst = Rst {
+            reqi: RequestId(133),
+            ..Default::default()
+        };
+        assert!(!pending.handle(&rst.into()));
+        assert!(pending.handle(&reply(133)));
+        assert_eq!(pending.finish().len(), 1);
+    }
+
+    #[test]
+    fn test_one() {
+        let mut pending = Pending::new(RaceTime, RequestId(131));
+        assert_eq!(pending.packets().len(), 1);
+        // wrong subtype
+        assert!(
+            !pending.handle(
+                &SmallType::Nli(Default::default())
+                    .with_request_id(131)
+                    .into()
+            )
+        );
+        assert!(
+            pending.handle(
+                &SmallType::Rtp(std::time::Duration::from_secs(1))
+                    .with_request_id(131)
+                    .into()
+            )
+        );
+        assert_eq!(pending.finish().len(), 1);
+    }
+
+    #[test]
+    fn test_replay_control() {
+        let request = ReplayControl(Rip {
+            ctime: std::time::Duration::from_secs(10),
+            paused: true,
+            ..Default::default()
+        });
+        let mut pending = Pending::new(request, RequestId(132));
+        assert!(matches!(
+            &pending.packets()[..],
+            [Packet::Rip(Rip {
+                reqi: RequestId(132),
+                paused: true,
+                ..
+            })]
+        ));
+        assert!(!pending.handle(&Rip::default().into()));
+        assert!(
+            pending.handle(
+                &Rip {
+                    reqi: RequestId(132),
+                    ..Default::default()
+                }
+                .into()
+            )
+        );
+        assert_eq!(pending.finish().len(), 1);
+    }
+
+    #[test]
+    fn test_request_ids_wrap() {
+        let ids = RequestIds::new();
+        let other = ids.clone();
+        assert_eq!(ids.next(), RequestId(RequestIds::FIRST));
+        assert_eq!(other.next(), RequestId(RequestIds::FIRST + 1));
+        for _ in (RequestIds::FIRST + 2)..=u8::MAX {
+            let _ = ids.next();
+        }
+        assert_eq!(ids.next(), RequestId(RequestIds::FIRST));
+    }
+}
@@ -0,0 +1,646 @@
+//! An in-process fake LFS InSim host, for testing without a running copy of LFS.
+//!
+//! [FakeHost] listens on a local TCP or UDP port and behaves enough like LFS for most clients:
+//!
+//! - The first packet must be an [Isi] using a version between [crate::MIN_VERSION] and
+//!   [crate::VERSION] (and the admin password, if configured). Anything else is rejected and the
+//!   connection closed.
+//! - If [Isi::reqi] is non-zero a [Ver] is sent in reply.
+//! - Packets are encoded using the lower of [Isi::version] and [FakeHost::insimver].
+//! - A keepalive [TinyType::None] is sent periodically.
+//! - [TinyType::Ping] is answered with [TinyType::Reply].
+//! - Everything the client sends, including the [Isi], is recorded.
+//! - Each connection plays a [Script] of packets, delays and disconnects.
+//!
+//! ```rust,ignore
+//! use insim::testing::{FakeHost, Script};
+//!
+//! let host = FakeHost::new()
+//!     .script(Script::new().wait_for(|p| matches!(p, Packet::Mst(_))).send(mso).disconnect())
+//!     .listen_tcp()
+//!     .await?;
+//!
+//! let mut framed = insim::tcp(host.local_addr()).connect_async().await?;
+//! ```
+//!
+//! For UDP a "connection" begins with each [Isi] received, and a disconnect means the host
+//! stops sending to the client until the next [Isi].
+use std::{collections::VecDeque, fmt, io, net::SocketAddr, pin::Pin, sync::Arc, time::Duration};
+
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, UdpSocket},
+    sync::watch,
+    task::JoinHandle,
+    time::Sleep,
+};
+
+use crate::{
+    Error, MIN_VERSION, Packet, Result, VERSION, WithRequestId,
+    identifiers::RequestId,
+    insim::{Isi, Tiny, TinyType, Ver},
+    net::Codec,
+};
+
+/// How long a client has to send the [Isi] before being disconnected.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Predicate = Box<dyn Fn(&Packet) -> bool + Send + Sync>;
+type Responder = Box<dyn Fn(&Packet) -> Option<Packet> + Send + Sync>;
+
+enum Step {
+    Send(Packet),
+    Delay(Duration),
+    WaitFor(Predicate),
+    Reply(Responder),
+    Disconnect,
+}
+
+impl fmt::Debug for Step {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Step::Send(packet) => f.debug_tuple("Send").field(packet).finish(),
+            Step::Delay(delay) => f.debug_tuple("Delay").field(delay).finish(),
+            Step::WaitFor(_) => f.write_str("WaitFor"),
+            Step::Reply(_) => f.write_str("Reply"),
+            Step::Disconnect => f.write_str("Disconnect"),
+        }
+    }
+}
+
+/// A sequence of actions played to a client once the handshake has completed.
+///
+/// Once the script has finished the connection remains open, with keepalives continuing to be
+/// sent, until the client disconnects.
+#[derive(Debug, Default)]
+pub struct Script {
+    steps: VecDeque<Step>,
+}
+
+impl Script {
+    /// Create a new, empty, script.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Send a packet to the client.
+    pub fn send<P: Into<Packet>>(mut self, packet: P) -> Self {
+        self.steps.push_back(Step::Send(packet.into()));
+        self
+    }
+
+    /// Wait before the next step.
+    pub fn delay(mut self, delay: Duration) -> Self {
+        self.steps.push_back(Step::Delay(delay));
+        self
+    }
+
+    /// Wait until the client sends a packet matching `predicate`.
+    pub fn wait_for<F>(mut self, predicate: F) -> Self
+    where
+        F: Fn(&Packet) -> bool + Send + Sync + 'static,
+    {
+        self.steps.push_back(Step::WaitFor(Box::new(predicate)));
+        self
+    }
+
+    /// Wait until `responder` returns a reply to a packet sent by the client, then send it.
+    ///
+    /// Useful for answering requests with the [RequestId] the client chose.
+    pub fn reply<F>(mut self, responder: F) -> Self
+    where
+        F: Fn(&Packet) -> Option<Packet> + Send + Sync + 'static,
+    {
+        self.steps.push_back(Step::Reply(Box::new(responder)));
+        self
+    }
+
+    /// Close the connection.
+    pub fn disconnect(mut self) -> Self {
+        self.steps.push_back(Step::Disconnect);
+        self
+    }
+}
+
+#[derive(Debug, Default)]
+struct Recorded {
+    packets: Vec<Packet>,
+    connections: usize,
+    errors: Vec<String>,
+}
+
+/// Configuration for, and a handle to, a running fake InSim host.
+///
+/// The host stops when this is dropped.
+#[derive(Debug)]
+pub struct FakeHost {
+    keepalive_interval: Duration,
+    admin_password: Option<String>,
+    insimver: u8,
+    scripts: VecDeque<Script>,
+    addr: Option<SocketAddr>,
+    recorded: Arc<watch::Sender<Recorded>>,
+    task: Option<JoinHandle<()>>,
+}
+
+impl Default for FakeHost {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FakeHost {
+    /// Create a new host. Nothing happens until [FakeHost::listen_tcp] or
+    /// [FakeHost::listen_udp] is called.
+    pub fn new() -> Self {
+        Self {
+            keepalive_interval: Duration::from_secs(30),
+            admin_password: None,
+            insimver: VERSION,
+            scripts: VecDeque::new(),
+            addr: None,
+            recorded: Arc::new(watch::Sender::new(Recorded::default())),
+            task: None,
+        }
+    }
+
+    /// How often to send a keepalive. Defaults to 30 seconds.
+    pub fn keepalive_interval(mut self, interval: Duration) -> Self {
+        self.keepalive_interval = interval;
+        self
+    }
+
+    /// Require clients to send this admin password.
+    pub fn admin_password<S: Into<String>>(mut self, password: S) -> Self {
+        self.admin_password = Some(password.into());
+        self
+    }
+
+    /// The InSim version reported in the [Ver] reply, and spoken to clients which support it.
+    /// Defaults to [crate::VERSION].
+    pub fn insimver(mut self, insimver: u8) -> Self {
+        self.insimver = insimver;
+        self
+    }
+
+    /// Add a script, played to the next connection without one. Connections after the
+    /// last script has been used receive an empty script.
+    pub fn script(mut self, script: Script) -> Self {
+        self.scripts.push_back(script);
+        self
+    }
+
+    /// Start listening for TCP connections on a random local port.
+    pub async fn listen_tcp(mut self) -> Result<Self> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        self.addr = Some(listener.local_addr()?);
+        let mut host = self.host();
+        self.task = Some(tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let mut io = Io::Tcp(stream);
+                host.session(&mut io).await;
+            }
+        }));
+        Ok(self)
+    }
+
+    /// Start listening for UDP packets on a random local port.
+    pub async fn listen_udp(mut self) -> Result<Self> {
+        let socket = UdpSocket::bind("127.0.0.1:0").await?;
+        self.addr = Some(socket.local_addr()?);
+        let mut host = self.host();
+        self.task = Some(tokio::spawn(async move {
+            let mut io = Io::Udp { socket, peer: None };
+            loop {
+                host.session(&mut io).await;
+                if let Io::Udp { peer, .. } = &mut io {
+                    *peer = None;
+                }
+            }
+        }));
+        Ok(self)
+    }
+
+    fn host(&mut self) -> Host {
+        Host {
+            keepalive_interval: self.keepalive_interval,
+            admin_password: self.admin_password.clone(),
+            insimver: self.insimver,
+            scripts: std::mem::take(&mut self.scripts),
+            recorded: self.recorded.clone(),
+        }
+    }
+
+    /// The address the host is listening on.
+    ///
+    /// # Panics
+    ///
+    /// If the host is not yet listening.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.addr.expect("FakeHost is not listening")
+    }
+
+    /// Every packet received from every client, in order.
+    pub fn received(&self) -> Vec<Packet> {
+        self.recorded.borrow().packets.clone()
+    }
+
+    /// How many clients have completed the handshake.
+    pub fn connections(&self) -> usize {
+        self.recorded.borrow().connections
+    }
+
+    /// Why any clients were rejected or disconnected with an error.
+    pub fn errors(&self) -> Vec<String> {
+        self.recorded.borrow().errors.clone()
+    }
+
+    /// Wait until a packet matching `predicate` has been received, returning it.
+    pub async fn wait_for<F>(&self, predicate: F, timeout: Duration) -> Result<Packet>
+    where
+        F: Fn(&Packet) -> bool,
+    {
+        let mut rx = self.recorded.subscribe();
+        let found = rx.wait_for(|r| r.packets.iter().any(&predicate));
+        match tokio::time::timeout(timeout, found).await {
+            Ok(Ok(recorded)) => Ok(recorded
+                .packets
+                .iter()
+                .find(|p| predicate(p))
+                .cloned()
+                .unwrap_or_default()),
+            Ok(Err(_)) => Err(Error::Disconnected),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Wait until at least `n` clients have completed the handshake.
+    pub async fn wait_for_connections(&self, n: usize, timeout: Duration) -> Result<()> {
+        let mut rx = self.recorded.subscribe();
+        match tokio::time::timeout(timeout, rx.wait_for(|r| r.connections >= n)).await {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(_)) => Err(Error::Disconnected),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+impl Drop for FakeHost {
+    fn drop(&mut self) {
+        if let Some(task) = self.task.take() {
+            task.abort();
+        }
+    }
+}
+
+#[derive(Debug)]
+enum Io {
+    Tcp(TcpStream),
+    Udp {
+        socket: UdpSocket,
+        peer: Option<SocketAddr>,
+    },
+}
+
+impl Io {
+    /// Read the next packet. Cancel-safe.
+    async fn read(&mut self, codec: &mut Codec) -> Result<Packet> {
+        loop {
+            if let Some(packet) = codec.decode()? {
+                return Ok(packet);
+            }
+            match self {
+                Io::Tcp(stream) => {
+                    if stream.read_buf(codec.buf_mut()).await? == 0 {
+                        return Err(Error::Disconnected);
+                    }
+                },
+                Io::Udp { socket, peer } => {
+                    let (_, from) = socket.recv_buf_from(codec.buf_mut()).await?;
+                    *peer = Some(from);
+                },
+            }
+        }
+    }
+
+    /// Write a packet, using the version negotiated by `codec`.
+    async fn write(&mut self, codec: &Codec, packet: &Packet) -> Result<()> {
+        let buf = codec.encode(packet)?;
+        match self {
+            Io::Tcp(stream) => stream.write_all(&buf).await?,
+            Io::Udp {
+                socket,
+                peer: Some(peer),
+            } => {
+                let _ = socket.send_to(&buf, *peer).await?;
+            },
+            Io::Udp { peer: None, .. } => return Err(Error::Disconnected),
+        }
+        Ok(())
+    }
+}
+
+struct Host {
+    keepalive_interval: Duration,
+    admin_password: Option<String>,
+    insimver: u8,
+    scripts: VecDeque<Script>,
+    recorded: Arc<watch::Sender<Recorded>>,
+}
+
+impl Host {
+    fn record(&self, packet: Packet) {
+        self.recorded.send_modify(|r| r.packets.push(packet));
+    }
+
+    async fn session(&mut self, io: &mut Io) {
+        let script = self.scripts.pop_front().unwrap_or_default();
+        match self.run(io, script).await {
+            Ok(()) | Err(Error::Disconnected) => {},
+            Err(e) => {
+                tracing::debug!(error = ?e, "fake host closing connection");
+                self.recorded.send_modify(|r| r.errors.push(e.to_string()));
+            },
+        }
+        if let Io::Tcp(stream) = io {
+            let _ = stream.shutdown().await;
+        }
+    }
+
+    async fn handshake(&self, io: &mut Io, codec: &mut Codec) -> Result<()> {
+        let isi = match tokio::time::timeout(HANDSHAKE_TIMEOUT, io.read(codec)).await?? {
+            Packet::Isi(isi) => isi,
+            other => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("expected Isi, received {other:?}"),
+                )
+                .into());
+            },
+        };
+        self.record(Packet::Isi(isi.clone()));
+
+        let Isi {
+            reqi,
+            version,
+            admin,
+            ..
+        } = isi;
+        if !(MIN_VERSION..=VERSION).contains(&version) {
+            return Err(Error::IncompatibleVersion(version));
+        }
+        // An unsupported insimver is left for the client to reject, after receiving the Ver
+        codec.set_version(version.min(self.insimver).max(MIN_VERSION));
+        if let Some(password) = &self.admin_password
+            && *password != admin
+        {
+            return Err(
+                io::Error::new(io::ErrorKind::PermissionDenied, "admin password mismatch").into(),
+            );
+        }
+
+        if reqi != RequestId(0) {
+            io.write(
+                codec,
+                &Ver {
+                    reqi,
+                    product: "S3".into(),
+                    insimver: self.insimver,
+                    ..Default::default()
+                }
+                .into(),
+            )
+            .await?;
+        }
+        self.recorded.send_modify(|r| r.connections += 1);
+        Ok(())
+    }
+
+    async fn run(&self, io: &mut Io, mut script: Script) -> Result<()> {
+        let mut codec = Codec::new();
+        self.handshake(io, &mut codec).await?;
+
+        let mut keepalive = tokio::time::interval_at(
+            tokio::time::Instant::now() + self.keepalive_interval,
+            self.keepalive_interval,
+        );
+        let mut waiting: Option<Predicate> = None;
+        let mut replying: Option<Responder> = None;
+        let mut delay: Option<Pin<Box<Sleep>>> = None;
+
+        loop {
+            while waiting.is_none() && replying.is_none() && delay.is_none() {
+                match script.steps.pop_front() {
+                    Some(Step::Send(packet)) => io.write(&codec, &packet).await?,
+                    Some(Step::Delay(d)) => delay = Some(Box::pin(tokio::time::sleep(d))),
+                    Some(Step::WaitFor(predicate)) => waiting = Some(predicate),
+                    Some(Step::Reply(responder)) => replying = Some(responder),
+                    Some(Step::Disconnect) => return Ok(()),
+                    None => break,
+                }
+            }
+
+            tokio::select! {
+                _ = keepalive.tick() => {
+                    io.write(&codec, &TinyType::None.into()).await?;
+                },
+                _ = async { delay.as_mut().expect("guarded by if").await }, if delay.is_some() => {
+                    delay = None;
+                },
+                packet = io.read(&mut codec) => {
+                    let packet = packet?;
+                    if let Packet::Tiny(Tiny { subt: TinyType::Ping, reqi }) = packet {
+                        io.write(&codec, &TinyType::Reply.with_request_id(reqi).into()).await?;
+                    }
+                    if waiting.as_ref().is_some_and(|predicate| predicate(&packet)) {
+                        waiting = None;
+                    }
+                    if let Some(reply) = replying.as_ref().and_then(|responder| responder(&packet)) {
+                        io.write(&codec, &reply).await?;
+                        replying = None;
+                    }
+                    self.record(packet);
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::insim::{Mso, Mst};
+
+    #[tokio::test]
+    async fn test_fake_host_tcp() {
+        let host = FakeHost::new()
+            .keepalive_interval(Duration::from_millis(20))
+            .script(
+                Script::new()
+                    .wait_for(|p| matches!(p, Packet::Mst(_)))
+                    .delay(Duration::from_millis(10))
+                    .send(Mso {
+                        msg: "pong".into(),
+                        ..Default::default()
+                    })
+                    .disconnect(),
+            )
+            .listen_tcp()
+            .await
+            .unwrap();
+
+        let mut framed = crate::tcp(host.local_addr()).connect_async().await.unwrap();
+        assert!(matches!(framed.read().await.unwrap(), Packet::Ver(_)));
+        // keepalive
+        assert!(matches!(
+            framed.read().await.unwrap(),
+            Packet::Tiny(Tiny {
+                subt: TinyType::None,
+                ..
+            })
+        ));
+
+        framed
+            .write(Mst {
+                msg: "ping".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        loop {
+            match framed.read().await {
+                Ok(Packet::Mso(mso)) => {
+                    assert_eq!(mso.msg, "pong");
+                    break;
+                },
+                Ok(_) => continue,
+                Err(e) => panic!("unexpected error {e:?}"),
+            }
+        }
+        assert!(matches!(framed.read().await, Err(Error::Disconnected)));
+
+        // the keepalive reply was recorded
+        let _ = host
+            .wait_for(
+                |p| {
+                    matches!(
+                        p,
+                        Packet::Tiny(Tiny {
+                            subt: TinyType::None,
+                            ..
+                        })
+                    )
+                },
+                Duration::from_secs(1),
+            )
+            .await
+            .unwrap();
+        let received = host.received();
+        assert!(matches!(received[0], Packet::Isi(_)));
+        assert_eq!(host.connections(), 1);
+        assert!(host.errors().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_fake_host_udp_ping() {
+        let host = FakeHost::new().listen_udp().await.unwrap();
+
+        let mut framed = crate::udp(host.local_addr(), None)
+            .connect_async()
+            .await
+            .unwrap();
+        assert!(matches!(framed.read().await.unwrap(), Packet::Ver(_)));
+        framed
+            .write(TinyType::Ping.with_request_id(7))
+            .await
+            .unwrap();
+        assert!(matches!(
+            framed.read().await.unwrap(),
+            Packet::Tiny(Tiny {
+                subt: TinyType::Reply,
+                reqi: RequestId(7)
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fake_host_rejects_password() {
+        let host = FakeHost::new()
+            .admin_password("secret")
+            .listen_tcp()
+            .await
+            .unwrap();
+
+        let mut framed = crate::tcp(host.local_addr())
+            .isi_admin_password(Some("wrong".into()))
+            .connect_async()
+            .await
+            .unwrap();
+        assert!(framed.read().await.is_err());
+        assert_eq!(host.connections(), 0);
+        assert_eq!(host.errors().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_fake_host_incompatible_version() {
+        let host = FakeHost::new().insimver(8).listen_tcp().await.unwrap();
+
+        let mut framed = crate::tcp(host.local_addr()).connect_async().await.unwrap();
+        assert!(matches!(
+            framed.read().await,
+            Err(Error::IncompatibleVersion(8))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fake_host_older_version() {
+        let host = FakeHost::new().insimver(9).listen_tcp().await.unwrap();
+
+        let (mut read, mut write) = crate::tcp(host.local_addr())
+            .connect_async()
+            .await
+            .unwrap()
+            .into_split();
+        assert_eq!(read.version(), crate::VERSION);
+        assert!(matches!(read.read().await.unwrap(), Packet::Ver(_)));
+        assert_eq!(read.version(), 9);
+
+        // Set was introduced in Insim 10, and must not be sent to an older host
+        assert!(matches!(
+            write.write(crate::insim::Set::default()).await,
+            Err(Error::Encode(_))
+        ));
+        write.write(TinyType::Ping).await.unwrap();
+        assert!(matches!(
+            read.read().await.unwrap(),
+            Packet::Tiny(Tiny {
+                subt: TinyType::Reply,
+                ..
+            })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_fake_host_older_client() {
+        let host = FakeHost::new().listen_tcp().await.unwrap();
+
+        let (mut read, mut write) = crate::tcp(host.local_addr())
+            .isi_version(9)
+            .connect_async()
+            .await
+            .unwrap()
+            .into_split();
+        assert!(matches!(read.read().await.unwrap(), Packet::Ver(_)));
+        assert_eq!(read.version(), 9);
+        write.write(TinyType::Ping).await.unwrap();
+        assert!(matches!(
+            read.read().await.unwrap(),
+            Packet::Tiny(Tiny {
+                subt: TinyType::Reply,
+                ..
+            })
+        ));
+        assert_eq!(host.connections(), 1);
+        assert!(host.errors().is_empty());
+    }
+}
@@ -0,0 +1,117 @@
+//! Choosing which packets to print.
+use clap::Args;
+use insim::Packet;
+use serde_json::Value;
+
+#[derive(Debug, Default, Args)]
+/// Filters, applied to every packet received. Every filter given must match.
+pub(crate) struct Filter {
+    #[arg(long = "type", value_delimiter = ',', global = true)]
+    /// Only print these packet types, i.e. `--type ncn,npl`
+    types: Vec<String>,
+
+    #[arg(long = "exclude", value_delimiter = ',', global = true)]
+    /// Never print these packet types, i.e. `--exclude mci,tiny`
+    exclude: Vec<String>,
+
+    #[arg(long, global = true)]
+    /// Only print packets about this player, including within Mci and Nlp
+    plid: Option<u8>,
+
+    #[arg(long, global = true)]
+    /// Only print packets about this connection
+    ucid: Option<u8>,
+}
+
+impl Filter {
+    /// Should `packet`, which serialises to `json`, be printed?
+    pub(crate) fn matches(&self, packet: &Packet, json: &Value) -> bool {
+        let name = packet.name();
+        if !self.types.is_empty() && !self.types.iter().any(|t| t.eq_ignore_ascii_case(name)) {
+            return false;
+        }
+        if self.exclude.iter().any(|t| t.eq_ignore_ascii_case(name)) {
+            return false;
+        }
+        self.plid.is_none_or(|plid| contains(json, "plid", plid))
+            && self.ucid.is_none_or(|ucid| contains(json, "ucid", ucid))
+    }
+
+    /// Does any packet require its JSON representation to be filtered?
+    pub(crate) fn needs_json(&self) -> bool {
+        self.plid.is_some() || self.ucid.is_some()
+    }
+}
+
+/// Does `json`, or any object nested within it, have `field` equal to `value`?
+fn contains(json: &Value, field: &str, value: u8) -> bool {
+    match json {
+        Value::Object(object) => object.iter().any(|(key, v)| {
+            (key == field && v.as_u64() == Some(value as u64)) || contains(v, field, value)
+        }),
+        Value::Array(array) => array.iter().any(|v| contains(v, field, value)),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insim::{
+        identifiers::{ConnectionId, PlayerId},
+        insim::{CompCar, Mci, Ncn, TinyType},
+    };
+
+    use super::*;
+
+    fn matches(filter: &Filter, packet: Packet) -> bool {
+        let json = serde_json::to_value(&packet).unwrap();
+        filter.matches(&packet, &json)
+    }
+
+    #[test]
+    fn test_filter() {
+        let ncn = Ncn {
+            ucid: ConnectionId(3),
+            ..Default::default()
+        };
+        let mci = Mci {
+            info: vec![
+                CompCar {
+                    plid: PlayerId(1),
+                    ..Default::default()
+                },
+                CompCar {
+                    plid: PlayerId(7),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+
+        let all = Filter::default();
+        assert!(matches(&all, ncn.clone().into()));
+
+        let types = Filter {
+            types: vec!["ncn".into(), "mci".into()],
+            exclude: vec!["MCI".into()],
+            ..Default::default()
+        };
+        assert!(matches(&types, ncn.clone().into()));
+        assert!(!matches(&types, mci.clone().into()));
+        assert!(!matches(&types, TinyType::Ncn.into()));
+
+        let ucid = Filter {
+            ucid: Some(3),
+            ..Default::default()
+        };
+        assert!(matches(&ucid, ncn.into()));
+        assert!(!matches(&ucid, mci.clone().into()));
+
+        let plid = Filter {
+            plid: Some(7),
+            ..Default::default()
+        };
+        assert!(plid.needs_json());
+        assert!(matches(&plid, mci.into()));
+    }
+}
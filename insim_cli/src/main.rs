@@ -0,0 +1,222 @@
+//! Inspect an LFS server over Insim, and send it packets, from the command line.
+//!
+//! ```text
+//! insim watch --flags mci,nlp --type ncn,npl
+//! insim --format json watch --plid 3
+//! insim send tiny ncn --watch --type ncn
+//! insim send mst "/laps 5"
+//! insim send '{"type":"Mst","reqi":0,"msg":"hello"}'
+//! insim decode 01 03 02 03
+//! ```
+use std::{
+    io::{BufRead, Write},
+    net::SocketAddr,
+    time::Duration,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use insim::{Packet, builder::Builder, insim::IsiFlags, net::tokio_impl::Framed};
+use serde_json::Value;
+
+mod filter;
+mod parse;
+
+use filter::Filter;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[command(flatten)]
+    connection: Connection,
+
+    #[arg(long, value_enum, default_value_t = Format::Text, global = true)]
+    /// How to print packets
+    format: Format,
+
+    #[command(flatten)]
+    filter: Filter,
+
+    #[command(subcommand)]
+    /// Defaults to `watch`
+    command: Option<Command>,
+}
+
+#[derive(Args)]
+struct Connection {
+    #[arg(long, default_value = "127.0.0.1:29999", global = true)]
+    /// host:port of LFS to connect to
+    host: String,
+
+    #[arg(long, global = true)]
+    /// Connect using UDP, rather than TCP
+    udp: bool,
+
+    #[arg(long, env = "INSIM_ADMIN", global = true)]
+    /// Admin password of the LFS host
+    admin: Option<String>,
+
+    #[arg(long, value_delimiter = ',', global = true)]
+    /// Isi flags to set, i.e. `--flags mci,nlp,con`
+    flags: Vec<String>,
+
+    #[arg(long, global = true)]
+    /// Interval between Mci or Nlp packets, in milliseconds
+    interval: Option<u64>,
+
+    #[arg(long, default_value = "insim-cli", global = true)]
+    /// Name of this program, as shown in LFS
+    iname: String,
+
+    #[arg(long, global = true)]
+    /// Address to receive UDP packets on, when using `--udp`. Defaults to any free port
+    bind: Option<SocketAddr>,
+}
+
+impl Connection {
+    fn builder(&self) -> Result<Builder, String> {
+        let mut flags = IsiFlags::empty();
+        for name in &self.flags {
+            flags |= IsiFlags::from_name(&name.to_ascii_uppercase())
+                .ok_or_else(|| format!("unknown Isi flag {name:?}"))?;
+        }
+        let builder = if self.udp {
+            insim::udp(self.host.clone(), self.bind)
+        } else {
+            insim::tcp(self.host.clone())
+        };
+        Ok(builder
+            .isi_admin_password(self.admin.clone())
+            .isi_iname(self.iname.clone())
+            .isi_flags(flags)
+            .isi_interval(self.interval.map(Duration::from_millis)))
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Format {
+    /// One line per packet
+    Text,
+    /// Multiple, indented, lines per packet
+    Pretty,
+    /// One JSON object per line
+    Json,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Print every packet received
+    Watch,
+    /// Send a packet, then disconnect unless `--watch` is given.
+    #[command(after_help = format!("Packets:\n{}\n\nUse `-` to read packets from stdin, one per line.", parse::SHORTHANDS))]
+    Send {
+        #[arg(long)]
+        /// Continue printing packets after sending
+        watch: bool,
+
+        #[arg(required = true, num_args = 1.., allow_hyphen_values = true)]
+        /// Packet to send, as shorthand, hex or JSON
+        packet: Vec<String>,
+    },
+    /// Decode a packet from its on-wire bytes, including the size byte, without connecting
+    Decode {
+        #[arg(required = true, num_args = 1..)]
+        /// Bytes as hex, i.e. `01 03 02 03`, or escape sequences, i.e. `\x01\x03\x02\x03`
+        bytes: Vec<String>,
+    },
+}
+
+/// Print a packet if it matches the filter.
+fn print(packet: &Packet, format: Format, filter: &Filter) -> Result<(), String> {
+    let json = match format {
+        Format::Json => Some(to_json(packet)?),
+        _ if filter.needs_json() => Some(to_json(packet)?),
+        _ => None,
+    };
+    if !filter.matches(packet, json.as_ref().unwrap_or(&Value::Null)) {
+        return Ok(());
+    }
+    let mut stdout = std::io::stdout().lock();
+    let written = match (format, json) {
+        (Format::Json, Some(json)) => writeln!(stdout, "{json}"),
+        (Format::Pretty, _) => writeln!(stdout, "{packet:#?}"),
+        _ => writeln!(stdout, "{packet:?}"),
+    };
+    written.map_err(|e| e.to_string())
+}
+
+fn to_json(packet: &Packet) -> Result<Value, String> {
+    serde_json::to_value(packet).map_err(|e| format!("failed to serialise packet: {e}"))
+}
+
+/// Print packets until the connection is lost.
+async fn watch(framed: &mut Framed, format: Format, filter: &Filter) -> Result<(), String> {
+    loop {
+        let packet = framed.read().await.map_err(|e| e.to_string())?;
+        print(&packet, format, filter)?;
+    }
+}
+
+/// The packets to send, read from stdin if `packet` is `-`.
+fn packets(packet: &[String]) -> Result<Vec<Packet>, String> {
+    if packet != ["-"] {
+        return Ok(vec![parse::packet(packet)?]);
+    }
+    let mut packets = Vec::new();
+    for line in std::io::stdin().lock().lines() {
+        let line = line.map_err(|e| e.to_string())?;
+        let words = parse::words(&line)?;
+        if !words.is_empty() {
+            packets.push(parse::packet(&words)?);
+        }
+    }
+    Ok(packets)
+}
+
+async fn run(cli: Cli) -> Result<(), String> {
+    let command = cli.command.unwrap_or(Command::Watch);
+    let packets = match &command {
+        Command::Decode { bytes } => {
+            let packet = parse::frame(&parse::bytes(&bytes.join(""))?)?;
+            return print(&packet, cli.format, &Filter::default());
+        },
+        Command::Send { packet, .. } => packets(packet)?,
+        Command::Watch => Vec::new(),
+    };
+
+    let mut framed = cli
+        .connection
+        .builder()?
+        .connect_async()
+        .await
+        .map_err(|e| e.to_string())?;
+    for packet in packets {
+        framed.write(packet).await.map_err(|e| e.to_string())?;
+    }
+
+    match command {
+        Command::Send { watch: false, .. } => framed.shutdown().await.map_err(|e| e.to_string()),
+        _ => watch(&mut framed, cli.format, &cli.filter).await,
+    }
+}
+
+fn setup_tracing_subscriber() {
+    // Setup with a default log level of WARN if RUST_LOG is unset, to keep stdout clean
+    tracing_subscriber::fmt::fmt()
+        .with_writer(std::io::stderr)
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::builder()
+                .with_default_directive(tracing_subscriber::filter::LevelFilter::WARN.into())
+                .from_env_lossy(),
+        )
+        .init();
+}
+
+#[tokio::main]
+async fn main() {
+    setup_tracing_subscriber();
+
+    if let Err(e) = run(Cli::parse()).await {
+        eprintln!("error: {e}");
+        std::process::exit(1);
+    }
+}
@@ -0,0 +1,220 @@
+//! Parsing packets from the command line.
+use insim::{
+    Packet, WithRequestId,
+    identifiers::{ConnectionId, RequestId},
+    insim::{Msl, Mst, Msx, Mtc, Small, SmallType, TinyType},
+    net::Codec,
+};
+
+/// Shorthands accepted by [packet], for help text.
+pub(crate) const SHORTHANDS: &str = "\
+    tiny <subtype>       i.e. `tiny ncn`\n\
+    small nli <ms>       set the Nlp/Mci interval\n\
+    mst <text>           message or command, i.e. `mst \"/laps 5\"`\n\
+    msx <text>           extended length message\n\
+    msl <text>           message to the local computer only\n\
+    mtc <ucid> <text>    message to a connection\n\
+    hex <bytes>          raw bytes, including the size byte\n\
+    {...}                JSON, as printed by `--format json`";
+
+/// Parse a packet from words, either shorthand, JSON or hex. See [SHORTHANDS].
+pub(crate) fn packet<S: AsRef<str>>(words: &[S]) -> Result<Packet, String> {
+    let words: Vec<&str> = words.iter().map(AsRef::as_ref).collect();
+    let Some((&first, rest)) = words.split_first() else {
+        return Err("no packet given".into());
+    };
+    if first.trim_start().starts_with('{') {
+        return serde_json::from_str(&words.join(" ")).map_err(|e| format!("invalid JSON: {e}"));
+    }
+
+    let text = || {
+        if rest.is_empty() {
+            Err(format!("{first} requires some text"))
+        } else {
+            Ok(rest.join(" "))
+        }
+    };
+    match first.to_ascii_lowercase().as_str() {
+        "tiny" => {
+            let [subt] = rest else {
+                return Err("usage: tiny <subtype>".into());
+            };
+            // Without a RequestId LFS ignores most requests
+            Ok(tiny_type(subt)?.with_request_id(RequestId(1)).into())
+        },
+        "small" => match rest {
+            [subt, ms] if subt.eq_ignore_ascii_case("nli") => {
+                let ms = ms.parse().map_err(|_| format!("invalid interval {ms:?}"))?;
+                Ok(Small {
+                    reqi: RequestId(1),
+                    subt: SmallType::Nli(std::time::Duration::from_millis(ms)),
+                }
+                .into())
+            },
+            _ => Err("usage: small nli <ms>".into()),
+        },
+        "mst" => Ok(Mst {
+            msg: text()?,
+            ..Default::default()
+        }
+        .into()),
+        "msx" => Ok(Msx {
+            msg: text()?,
+            ..Default::default()
+        }
+        .into()),
+        "msl" => Ok(Msl {
+            msg: text()?,
+            ..Default::default()
+        }
+        .into()),
+        "mtc" => {
+            let Some((ucid, text)) = rest.split_first().filter(|(_, text)| !text.is_empty()) else {
+                return Err("usage: mtc <ucid> <text>".into());
+            };
+            let ucid = ucid.parse().map_err(|_| format!("invalid ucid {ucid:?}"))?;
+            Ok(Mtc {
+                ucid: ConnectionId(ucid),
+                text: text.join(" "),
+                ..Default::default()
+            }
+            .into())
+        },
+        "hex" => frame(&bytes(&rest.join(""))?),
+        other => Err(format!(
+            "unknown packet {other:?}, expected one of:\n{SHORTHANDS}"
+        )),
+    }
+}
+
+/// Decode a single packet from its on-wire bytes, including the size byte.
+pub(crate) fn frame(bytes: &[u8]) -> Result<Packet, String> {
+    let mut codec = Codec::new();
+    codec.feed(bytes);
+    match codec.decode() {
+        Ok(Some(packet)) => Ok(packet),
+        Ok(None) => Err(format!(
+            "incomplete packet, the size byte requires {} bytes but {} were given",
+            bytes[0] as usize * 4,
+            bytes.len()
+        )),
+        Err(e) => Err(format!("invalid packet: {e}")),
+    }
+}
+
+/// Parse a [TinyType] case insensitively, i.e. `ncn`.
+fn tiny_type(name: &str) -> Result<TinyType, String> {
+    let mut chars = name.chars();
+    let name: String = chars
+        .next()
+        .map(|c| c.to_ascii_uppercase())
+        .into_iter()
+        .chain(chars.map(|c| c.to_ascii_lowercase()))
+        .collect();
+    serde_json::from_value(serde_json::Value::String(name.clone()))
+        .map_err(|_| format!("unknown tiny subtype {name:?}"))
+}
+
+/// Split a line into words, respecting double quotes, i.e. `mst "/laps 5"`.
+pub(crate) fn words(line: &str) -> Result<Vec<String>, String> {
+    let line = line.trim();
+    if line.starts_with('{') {
+        return Ok(vec![line.to_owned()]);
+    }
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quoted = false;
+    let mut chars = line.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => quoted = !quoted,
+            '\\' if quoted => word.extend(chars.next()),
+            c if c.is_whitespace() && !quoted => {
+                if !word.is_empty() {
+                    words.push(std::mem::take(&mut word));
+                }
+            },
+            c => word.push(c),
+        }
+    }
+    if quoted {
+        return Err("unterminated quote".into());
+    }
+    if !word.is_empty() {
+        words.push(word);
+    }
+    Ok(words)
+}
+
+/// Parse bytes given as hex digits or C-style escape sequences, see [insim::core::hex::parse].
+pub(crate) fn bytes(input: &str) -> Result<Vec<u8>, String> {
+    insim::core::hex::parse(input).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use insim::insim::Tiny;
+
+    use super::*;
+
+    #[test]
+    fn test_shorthand() {
+        assert!(matches!(
+            packet(&["tiny", "NCN"]),
+            Ok(Packet::Tiny(Tiny {
+                subt: TinyType::Ncn,
+                reqi: RequestId(1)
+            }))
+        ));
+        assert!(matches!(
+            packet(&["mst", "/laps", "5"]),
+            Ok(Packet::Mst(Mst { msg, .. })) if msg == "/laps 5"
+        ));
+        assert!(matches!(
+            packet(&["mtc", "3", "hello"]),
+            Ok(Packet::Mtc(Mtc { ucid: ConnectionId(3), text, .. })) if text == "hello"
+        ));
+        assert!(matches!(
+            packet(&["small", "nli", "500"]),
+            Ok(Packet::Small(Small {
+                subt: SmallType::Nli(d),
+                ..
+            })) if d.as_millis() == 500
+        ));
+        assert!(matches!(
+            packet(&["hex", "01 03 02 03"]),
+            Ok(Packet::Tiny(Tiny {
+                subt: TinyType::Ping,
+                reqi: RequestId(2)
+            }))
+        ));
+        assert!(matches!(
+            packet(&[r#"{"type":"Msl","reqi":0,"sound":"Silent","msg":"hi"}"#]),
+            Ok(Packet::Msl(Msl { msg, .. })) if msg == "hi"
+        ));
+
+        assert!(packet::<&str>(&[]).is_err());
+        assert!(packet(&["tiny", "nope"]).is_err());
+        assert!(packet(&["mst"]).is_err());
+        assert!(packet(&["mtc", "3"]).is_err());
+        assert!(packet(&["hex", "01"]).is_err());
+        assert!(packet(&["bogus"]).is_err());
+    }
+
+    #[test]
+    fn test_words() {
+        assert_eq!(
+            words(r#"  mst "/laps 5"  "#).unwrap(),
+            vec!["mst".to_string(), "/laps 5".to_string()]
+        );
+        assert_eq!(
+            words(r#"mst "say \"hi\"""#).unwrap(),
+            vec!["mst".to_string(), r#"say "hi""#.to_string()]
+        );
+        assert_eq!(
+            words(r#"{"type": "Mst"}"#).unwrap(),
+            vec![r#"{"type": "Mst"}"#.to_string()]
+        );
+        assert!(words(r#"mst "open"#).is_err());
+    }
+}
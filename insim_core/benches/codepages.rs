@@ -10,7 +10,7 @@
 use std::hint::black_box;
 
 use criterion::{Criterion, criterion_group, criterion_main};
-use insim_core::string::codepages::{to_lossy_bytes, to_lossy_string};
+use insim_core::string::codepages::{CodepageStr, to_lossy_bytes, to_lossy_string};
 
 fn bench_to_lossy_string(c: &mut Criterion) {
     let input: &[u8] = &[
@@ -52,11 +52,20 @@ fn bench_to_lossy_bytes_complex(c: &mut Criterion) {
     });
 }
 
+fn bench_codepage_str_ascii(c: &mut Criterion) {
+    let input: &[u8] = b"^7Hello world!\0\0\0";
+
+    c.bench_function("codepage_str_ascii", |b| {
+        b.iter(|| CodepageStr::new(black_box(input)).to_lossy_string().len())
+    });
+}
+
 criterion_group!(
     benches,
     bench_to_lossy_string,
     bench_to_lossy_bytes_ascii,
     bench_to_lossy_bytes_simple,
-    bench_to_lossy_bytes_complex
+    bench_to_lossy_bytes_complex,
+    bench_codepage_str_ascii
 );
 criterion_main!(benches);
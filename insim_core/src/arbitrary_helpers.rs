@@ -0,0 +1,116 @@
+//! Generators for `arbitrary::Arbitrary` implementations, constrained to values which encode
+//! legally and decode back to the same value.
+//!
+//! Use with `#[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<24>))]`
+//! on any field whose [crate::Encode] implementation restricts its range.
+
+use std::{fmt::Debug, time::Duration};
+
+use arbitrary::{Arbitrary, Result, Unstructured};
+use bytes::{Buf, BytesMut};
+
+use crate::{Decode, DecodeContext, Encode, EncodeContext};
+
+/// Printable ASCII, excluding `^` which begins LFS colour and codepage markers, of at most `MAX`
+/// bytes. Such strings are identical whether encoded as ASCII or as a codepage string.
+pub fn string<const MAX: usize>(u: &mut Unstructured<'_>) -> Result<String> {
+    let len = u.int_in_range(0..=MAX)?;
+    (0..len)
+        .map(|_| {
+            let c = u.int_in_range(b' '..=b'~')?;
+            Ok(if c == b'^' { '_' } else { c as char })
+        })
+        .collect()
+}
+
+/// A `u8` of at most `MAX`, i.e. for values packed into fewer bits.
+pub fn up_to<const MAX: u8>(u: &mut Unstructured<'_>) -> Result<u8> {
+    u.int_in_range(0..=MAX)
+}
+
+/// Any ASCII character, as encoded by `char`.
+pub fn ascii_char(u: &mut Unstructured<'_>) -> Result<char> {
+    Ok(u.int_in_range(0..=0x7f_u8)? as char)
+}
+
+/// As [string], but optional. Empty strings encode as absent, so are never generated.
+pub fn optional_string<const MAX: usize>(u: &mut Unstructured<'_>) -> Result<Option<String>> {
+    if MAX == 0 || !u.arbitrary::<bool>()? {
+        return Ok(None);
+    }
+    let mut s = string::<MAX>(u)?;
+    if s.is_empty() {
+        s.push(u.int_in_range(b'a'..=b'z')? as char);
+    }
+    Ok(Some(s))
+}
+
+/// At most `MAX` arbitrary items.
+pub fn vec<'a, T: Arbitrary<'a>, const MAX: usize>(u: &mut Unstructured<'a>) -> Result<Vec<T>> {
+    let len = u.int_in_range(0..=MAX)?;
+    (0..len).map(|_| T::arbitrary(u)).collect()
+}
+
+/// A whole number of milliseconds which fits within `T`, as encoded by
+/// [crate::EncodeContext::encode_duration].
+pub fn millis<'a, T: Arbitrary<'a> + Into<u64>>(u: &mut Unstructured<'a>) -> Result<Duration> {
+    Ok(Duration::from_millis(T::arbitrary(u)?.into()))
+}
+
+/// Generate `iterations` values of `T`, each from `len` bytes of pseudo random data, and check
+/// that each encodes, decodes using every byte, and encodes back to the same bytes. Panics
+/// describing the first value which does not.
+///
+/// The data is generated using a fixed seed, so that failures are reproducible. Intended for
+/// tests.
+pub fn round_trip<T>(len: usize, iterations: usize, version: Option<u8>)
+where
+    T: for<'a> Arbitrary<'a> + Encode + Decode + Debug,
+{
+    let encode = |value: &T| {
+        let mut buf = BytesMut::new();
+        let mut ctx = EncodeContext::new(&mut buf);
+        if let Some(version) = version {
+            ctx = ctx.with_version(version);
+        }
+        value
+            .encode(&mut ctx)
+            .unwrap_or_else(|e| panic!("failed to encode {value:?}: {e}"));
+        buf.freeze()
+    };
+
+    // xorshift
+    let mut state = 0x2545_f491_4f6c_dd1d_u64;
+    let mut data = vec![0u8; len];
+
+    for _ in 0..iterations {
+        for byte in data.iter_mut() {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            *byte = state as u8;
+        }
+        let Ok(value) = T::arbitrary(&mut Unstructured::new(&data)) else {
+            continue;
+        };
+
+        let encoded = encode(&value);
+        let mut buf = encoded.clone();
+        let mut ctx = DecodeContext::new(&mut buf);
+        if let Some(version) = version {
+            ctx = ctx.with_version(version);
+        }
+        let decoded =
+            T::decode(&mut ctx).unwrap_or_else(|e| panic!("failed to decode {value:?}: {e}"));
+        assert_eq!(
+            buf.remaining(),
+            0,
+            "{value:?} decoded as {decoded:?} without using every byte"
+        );
+        assert_eq!(
+            encoded,
+            encode(&decoded),
+            "{value:?} decoded as {decoded:?}"
+        );
+    }
+}
@@ -6,6 +6,7 @@ bitflags::bitflags! {
     /// - Bitflags can be combined and queried with `.contains`.
     /// - Typically reported in telemetry packets.
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     pub struct DashLights: u32 {
         /// Shift light
         const SHIFT = 1;
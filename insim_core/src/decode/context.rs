@@ -7,12 +7,24 @@ use crate::hex::HexDisplay;
 pub struct DecodeContext<'a> {
     /// The underlying buffer being decoded
     pub buf: &'a mut bytes::Bytes,
+    version: Option<u8>,
 }
 
 impl<'a> DecodeContext<'a> {
     /// New
     pub fn new(buf: &'a mut bytes::Bytes) -> Self {
-        Self { buf }
+        Self { buf, version: None }
+    }
+
+    /// Set the protocol version of the data being decoded.
+    pub fn with_version(mut self, version: u8) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// The protocol version of the data being decoded, if known.
+    pub fn version(&self) -> Option<u8> {
+        self.version
     }
 
     /// The core execution wrapper. Handles:
@@ -25,6 +25,14 @@ impl DecodeError {
             },
         }
     }
+
+    /// The innermost kind of error, skipping over any [DecodeErrorKind::Nested].
+    pub fn root_kind(&self) -> &DecodeErrorKind {
+        match &self.kind {
+            DecodeErrorKind::Nested { source } => source.root_kind(),
+            kind => kind,
+        }
+    }
 }
 
 impl std::fmt::Display for DecodeError {
@@ -106,6 +114,15 @@ pub enum DecodeErrorKind {
     #[error("Expected \0 character")]
     ExpectedNull,
 
+    /// Not supported by the protocol version in use
+    #[error("Requires protocol version {required}, found: {found}")]
+    UnsupportedVersion {
+        /// Minimum version required
+        required: u8,
+        /// Version in use
+        found: u8,
+    },
+
     /// Nested error - designed to preserve the full chain of errors
     #[error("{source}")]
     Nested {
@@ -116,6 +133,20 @@ pub enum DecodeErrorKind {
 }
 
 impl DecodeErrorKind {
+    /// Name of this kind of error, without any details. Useful as a metric label.
+    pub fn name(&self) -> &'static str {
+        match self {
+            DecodeErrorKind::UnexpectedEof => "UnexpectedEof",
+            DecodeErrorKind::BadMagic { .. } => "BadMagic",
+            DecodeErrorKind::NoVariantMatch { .. } => "NoVariantMatch",
+            DecodeErrorKind::GameVersionParseError(_) => "GameVersionParseError",
+            DecodeErrorKind::OutOfRange { .. } => "OutOfRange",
+            DecodeErrorKind::ExpectedNull => "ExpectedNull",
+            DecodeErrorKind::UnsupportedVersion { .. } => "UnsupportedVersion",
+            DecodeErrorKind::Nested { .. } => "Nested",
+        }
+    }
+
     /// Add context to this error
     pub fn context(self, ctx: impl Into<Cow<'static, str>>) -> DecodeError {
         DecodeError {
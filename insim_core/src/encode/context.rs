@@ -7,12 +7,24 @@ use crate::hex::HexDisplay;
 pub struct EncodeContext<'a> {
     /// The underlying buffer being encoded into
     pub buf: &'a mut bytes::BytesMut,
+    version: Option<u8>,
 }
 
 impl<'a> EncodeContext<'a> {
     /// New
     pub fn new(buf: &'a mut bytes::BytesMut) -> Self {
-        Self { buf }
+        Self { buf, version: None }
+    }
+
+    /// Set the protocol version of the data being encoded.
+    pub fn with_version(mut self, version: u8) -> Self {
+        self.version = Some(version);
+        self
+    }
+
+    /// The protocol version of the data being encoded, if known.
+    pub fn version(&self) -> Option<u8> {
+        self.version
     }
 
     fn op<F>(&mut self, name: &'static str, is_prim: bool, f: F) -> Result<(), super::EncodeError>
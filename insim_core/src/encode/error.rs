@@ -98,6 +98,15 @@ pub enum EncodeErrorKind {
         found: usize,
     },
 
+    /// Not supported by the protocol version in use
+    #[error("Requires protocol version {required}, found: {found}")]
+    UnsupportedVersion {
+        /// Minimum version required
+        required: u8,
+        /// Version in use
+        found: u8,
+    },
+
     /// Nested error - designed to preserve the full chain of errors
     #[error("{source}")]
     Nested {
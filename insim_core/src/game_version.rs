@@ -106,6 +106,20 @@ impl Default for GameVersion {
     }
 }
 
+#[cfg(feature = "arbitrary")]
+impl<'a> arbitrary::Arbitrary<'a> for GameVersion {
+    fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+        // Limited to versions which fit within the 8 bytes LFS allows, i.e. `0.7F15`
+        let major = u.int_in_range(0..=9_u8)?;
+        let patch = u.int_in_range(0..=99_usize)?;
+        Ok(Self {
+            major: format!("0.{major}").parse().expect("valid float"),
+            minor: u.int_in_range(b'A'..=b'Z')? as char,
+            patch: (patch > 0).then_some(patch),
+        })
+    }
+}
+
 impl Display for GameVersion {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         if let Some(patch) = self.patch {
@@ -1,5 +1,104 @@
+//! Tools for reading raw bytes written by hand, i.e. on the command line.
+
 use std::fmt;
 
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+/// Possible errors when parsing bytes
+pub enum HexParseError {
+    /// No bytes were given
+    #[error("No bytes provided")]
+    Empty,
+
+    /// A character which is not a hex digit
+    #[error("Invalid hex digit {0:?}")]
+    InvalidDigit(char),
+
+    /// Hex digits must come in pairs
+    #[error("Odd number of hex digits")]
+    OddLength,
+
+    /// A literal character outside of ASCII
+    #[error("Unexpected non-ASCII character {0:?}")]
+    NonAscii(char),
+
+    /// An escape sequence which is not supported
+    #[error("Unknown escape: \\{0}")]
+    UnknownEscape(char),
+
+    /// A `\x` escape without two hex digits following it
+    #[error("Invalid hex in \\x escape: {0:?}")]
+    InvalidHexEscape(String),
+
+    /// A backslash at the end of the input
+    #[error("Trailing backslash")]
+    TrailingBackslash,
+}
+
+/// Parse bytes given as run-together hex digits, optionally separated by whitespace, or as
+/// C-style escape sequences if the input contains a backslash.
+///
+/// Accepts `01 03 02 03`, `01030203` or `\x01\x03\x02\x03`.
+pub fn parse(input: &str) -> Result<Vec<u8>, HexParseError> {
+    let raw = if input.contains('\\') {
+        unescape(input)?
+    } else {
+        let hex: Vec<char> = input.chars().filter(|c| !c.is_whitespace()).collect();
+        if let Some(c) = hex.iter().find(|c| !c.is_ascii_hexdigit()) {
+            return Err(HexParseError::InvalidDigit(*c));
+        }
+        if !hex.len().is_multiple_of(2) {
+            return Err(HexParseError::OddLength);
+        }
+        hex.chunks(2)
+            .map(|pair| {
+                let s: String = pair.iter().collect();
+                u8::from_str_radix(&s, 16).expect("valid hex digit")
+            })
+            .collect()
+    };
+    if raw.is_empty() {
+        return Err(HexParseError::Empty);
+    }
+    Ok(raw)
+}
+
+/// Parse a string containing C-style escape sequences into bytes.
+/// Handles \xNN (hex), \0 (null), \n, \r, \t, and \\ (literal backslash).
+pub fn unescape(input: &str) -> Result<Vec<u8>, HexParseError> {
+    let mut out = Vec::new();
+    let mut chars = input.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            // literal byte - only accept ASCII here
+            if c.is_ascii() {
+                out.push(c as u8);
+            } else {
+                return Err(HexParseError::NonAscii(c));
+            }
+            continue;
+        }
+        match chars.next() {
+            Some('x') => {
+                let hex: String = chars.by_ref().take(2).collect();
+                let byte = if hex.len() == 2 {
+                    u8::from_str_radix(&hex, 16).ok()
+                } else {
+                    None
+                };
+                out.push(byte.ok_or(HexParseError::InvalidHexEscape(hex))?);
+            },
+            Some('0') => out.push(0),
+            Some('n') => out.push(b'\n'),
+            Some('r') => out.push(b'\r'),
+            Some('t') => out.push(b'\t'),
+            Some('\\') => out.push(b'\\'),
+            Some(other) => return Err(HexParseError::UnknownEscape(other)),
+            None => return Err(HexParseError::TrailingBackslash),
+        }
+    }
+    Ok(out)
+}
+
 pub(crate) struct HexDisplay<'a>(pub(crate) &'a [u8]);
 
 impl fmt::Display for HexDisplay<'_> {
@@ -13,3 +112,34 @@ impl fmt::Display for HexDisplay<'_> {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(parse("01 03 02 03").unwrap(), vec![1, 3, 2, 3]);
+        assert_eq!(parse("01030203").unwrap(), vec![1, 3, 2, 3]);
+        assert_eq!(parse(r"\x01\x03\x02\x03").unwrap(), vec![1, 3, 2, 3]);
+        assert_eq!(parse("0"), Err(HexParseError::OddLength));
+        assert_eq!(parse("zz"), Err(HexParseError::InvalidDigit('z')));
+        assert_eq!(parse(""), Err(HexParseError::Empty));
+    }
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(unescape(r"a\0\n\r\t\\\xff").unwrap(), b"a\0\n\r\t\\\xff");
+        assert_eq!(
+            unescape(r"\x1"),
+            Err(HexParseError::InvalidHexEscape("1".into()))
+        );
+        assert_eq!(
+            unescape(r"\xzz"),
+            Err(HexParseError::InvalidHexEscape("zz".into()))
+        );
+        assert_eq!(unescape(r"\q"), Err(HexParseError::UnknownEscape('q')));
+        assert_eq!(unescape("\\"), Err(HexParseError::TrailingBackslash));
+        assert_eq!(unescape("é"), Err(HexParseError::NonAscii('é')));
+    }
+}
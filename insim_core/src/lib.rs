@@ -4,6 +4,8 @@
 extern crate criterion as _; // Needed for cargo bench. BARF.
 
 pub mod angvel;
+#[cfg(feature = "arbitrary")]
+pub mod arbitrary_helpers;
 #[cfg(feature = "serde")]
 pub mod bitflags_serde;
 pub mod coordinate;
@@ -13,7 +15,7 @@ pub mod encode;
 pub mod game_version;
 pub mod gear;
 pub mod heading;
-pub(crate) mod hex;
+pub mod hex;
 pub mod identifiers;
 pub mod license;
 pub mod object;
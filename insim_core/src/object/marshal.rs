@@ -6,6 +6,7 @@ use crate::{
 
 #[derive(Debug, Clone, PartialEq, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Marshal
 pub struct Marshal {
@@ -67,6 +68,7 @@ impl ObjectInfoInner for Marshal {
 
 #[derive(Debug, Default, Clone, Copy, Hash, PartialEq, PartialOrd, Ord, Eq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 #[repr(u8)]
 #[allow(missing_docs)]
@@ -94,11 +96,13 @@ impl TryFrom<u8> for MarshalKind {
 /// Marshall Circle / Restricted Area
 #[derive(Debug, Clone, PartialEq, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RestrictedArea {
     /// Position
     pub xyz: ObjectCoordinate,
     /// Radius
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::up_to::<31>))]
     pub radius: u8,
     /// floating
     pub floating: bool,
@@ -142,6 +146,7 @@ impl ObjectInfoInner for RestrictedArea {
 /// Route Check
 #[derive(Debug, Clone, PartialEq, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 pub struct RouteChecker {
     /// Position
@@ -149,6 +154,7 @@ pub struct RouteChecker {
     /// Route index (stored in heading byte on wire)
     pub route: u8,
     /// Radius
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = crate::arbitrary_helpers::up_to::<31>))]
     pub radius: u8,
     /// floating
     pub floating: bool,
@@ -171,7 +177,7 @@ impl RouteChecker {
 impl ObjectInfoInner for RouteChecker {
     fn flags(&self) -> u8 {
         let mut flags = 0;
-        flags |= (self.radius << 2) & 0b11111;
+        flags |= (self.radius & 0b11111) << 2;
         if self.floating {
             flags |= 0x80;
         }
@@ -187,6 +193,6 @@ impl ObjectInfoInner for RouteChecker {
     }
 
     fn heading_objectinfo_wire(&self) -> u8 {
-        self.radius
+        self.route
     }
 }
@@ -71,6 +71,7 @@ trait ObjectInfoInner {
 
 #[derive(Debug, Clone, Copy)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 #[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
 /// Raw layout object wire fields.
 pub struct Raw {
@@ -136,6 +137,22 @@ macro_rules! define_object_info {
             Unknown(Raw),
         }
 
+        #[cfg(feature = "arbitrary")]
+        impl<'a> arbitrary::Arbitrary<'a> for ObjectInfo {
+            fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+                // Choose by index, so that Unknown is never generated with a known index
+                match u.arbitrary::<u8>()? {
+                    $(
+                        $index => Ok(ObjectInfo::$variant(u.arbitrary()?)),
+                    )+
+                    index => Ok(ObjectInfo::Unknown(Raw {
+                        index,
+                        ..u.arbitrary()?
+                    })),
+                }
+            }
+        }
+
         impl Decode for ObjectInfo {
             fn decode(ctx: &mut DecodeContext) -> Result<Self, DecodeError> {
                 let x = ctx.decode::<i16>("x")?;
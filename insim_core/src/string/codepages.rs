@@ -272,10 +272,55 @@ pub fn to_lossy_string(input: &'_ [u8]) -> Cow<'_, str> {
     result.into()
 }
 
+/// A borrowed, undecoded, codepage string. Decoding is deferred until required, and does not
+/// allocate when the string is plain ASCII.
+///
+/// Anything from the first \0 onwards is ignored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct CodepageStr<'a>(&'a [u8]);
+
+impl<'a> CodepageStr<'a> {
+    /// Wrap raw bytes received from LFS.
+    pub fn new(raw: &'a [u8]) -> Self {
+        Self(super::strip_trailing_nul(raw))
+    }
+
+    /// The raw bytes, excluding any trailing \0.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+
+    /// Is this string empty?
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Decode, as [to_lossy_string].
+    pub fn to_lossy_string(&self) -> Cow<'a, str> {
+        to_lossy_string(self.0)
+    }
+}
+
+impl std::fmt::Display for CodepageStr<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.to_lossy_string())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_codepage_str() {
+        let raw = CodepageStr::new(b"Hello\0\0\0");
+        assert_eq!(raw.as_bytes(), b"Hello");
+        assert!(matches!(raw.to_lossy_string(), Cow::Borrowed("Hello")));
+
+        let raw = CodepageStr::new(&[94, 69, 248, 0]);
+        assert_eq!(raw.to_string(), "ř");
+    }
+
     #[test]
     fn test_codepage_hello_world() {
         let output = to_lossy_bytes("Hello");
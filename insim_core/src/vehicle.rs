@@ -41,6 +41,23 @@ macro_rules! define_vehicles {
             }
         }
 
+        #[cfg(feature = "arbitrary")]
+        impl<'a> arbitrary::Arbitrary<'a> for Vehicle {
+            fn arbitrary(u: &mut arbitrary::Unstructured<'a>) -> arbitrary::Result<Self> {
+                const BUILTIN: &[Vehicle] = &[$(Vehicle::$variant,)* Vehicle::Unknown];
+                if !u.ratio(1, 4)? {
+                    return u.choose(BUILTIN).copied();
+                }
+                // Mod ids are 6 hex digits. Those which would encode as a builtin code, or
+                // all zeros, cannot be told apart when decoding.
+                let id: u32 = u.int_in_range(1..=0xFF_FFFF)?;
+                if id.to_le_bytes()[..3].iter().all(|c| c.is_ascii_alphanumeric()) {
+                    return Err(arbitrary::Error::IncorrectFormat);
+                }
+                Ok(Vehicle::Mod(id))
+            }
+        }
+
         impl Vehicle {
             /// This is a built-in vehicle?
             pub fn is_builtin(&self) -> bool {
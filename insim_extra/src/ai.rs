@@ -0,0 +1,764 @@
+//! Closed-loop AI driver.
+//!
+//! [`AiDriver`] follows a [`Path`], steering with pure pursuit and holding the target speed of
+//! each [`Waypoint`]. It is driven entirely by [`Aii`] feedback: [`AiDriver::start`] asks LFS
+//! to send an [`Aii`] every [`AiDriverConfig::interval`], and each one passed to
+//! [`AiDriver::update`] returns the [`Aic`] to send in reply. No `Sender` or framework
+//! dependency, so recorded or simulated [`Aii`] can be replayed through
+//! [`AiDriver::controls`] in tests.
+//!
+//! ```rust,ignore
+//! let path = Path::from_pth(&Pth::from_path("BL1.pth")?, 20.0);
+//! let mut driver = AiDriver::new(plid, path, AiDriverConfig::default());
+//! insim.send(driver.start()).await?;
+//! while let Some(packet) = insim.read().await? {
+//!     if let Packet::Aii(aii) = packet
+//!         && let Some(aic) = driver.update(&aii)
+//!     {
+//!         insim.send(aic).await?;
+//!     }
+//! }
+//! ```
+
+use std::time::Duration;
+
+use insim::{
+    identifiers::PlayerId,
+    insim::{AiFlags, AiHelpFlags, AiInput, AiInputToggle, AiInputType, Aic, Aii},
+};
+
+/// Scale of [`insim::core::coordinate::Coordinate`] and PTH node coordinates, 65536 = 1 metre.
+const COORDINATE_SCALE: f32 = 65536.0;
+
+/// How many waypoints ahead of the last nearest waypoint to search for the next.
+const SEARCH_WINDOW: usize = 64;
+
+/// [`Aii`] gear for neutral. 0 is reverse and 2 is first.
+const GEAR_NEUTRAL: u8 = 1;
+
+/// How long a gear change is held for.
+const SHIFT_HOLD: Duration = Duration::from_millis(100);
+
+/// A point on a [`Path`], in metres, with the speed to drive at from here.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Waypoint {
+    /// X, in metres
+    pub x: f32,
+    /// Y, in metres
+    pub y: f32,
+    /// Target speed, in metres per second
+    pub speed: f32,
+}
+
+impl Waypoint {
+    fn distance_squared(&self, x: f32, y: f32) -> f32 {
+        (self.x - x).powi(2) + (self.y - y).powi(2)
+    }
+}
+
+/// Waypoints for an [`AiDriver`] to follow.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Path {
+    waypoints: Vec<Waypoint>,
+    closed: bool,
+}
+
+impl Path {
+    /// New path. A closed path loops from the last waypoint back to the first, an open path
+    /// finishes at the last waypoint.
+    pub fn new(waypoints: Vec<Waypoint>, closed: bool) -> Self {
+        Self { waypoints, closed }
+    }
+
+    /// Open path through `points`, in metres, with a constant target speed in metres per
+    /// second. The target speed of the last point is 0, so that the car stops there.
+    pub fn from_points<I>(points: I, speed: f32) -> Self
+    where
+        I: IntoIterator<Item = (f32, f32)>,
+    {
+        let mut waypoints: Vec<Waypoint> = points
+            .into_iter()
+            .map(|(x, y)| Waypoint { x, y, speed })
+            .collect();
+        if let Some(last) = waypoints.last_mut() {
+            last.speed = 0.0;
+        }
+        Self::new(waypoints, false)
+    }
+
+    /// Closed path through the nodes of a PTH file, with a constant target speed in metres
+    /// per second.
+    pub fn from_pth(pth: &insim_pth::Pth, speed: f32) -> Self {
+        let waypoints = pth
+            .iter_nodes()
+            .map(|node| {
+                let center = node.get_center(Some(COORDINATE_SCALE));
+                Waypoint {
+                    x: center.x,
+                    y: center.y,
+                    speed,
+                }
+            })
+            .collect();
+        Self::new(waypoints, true)
+    }
+
+    /// Replace the target speed of every waypoint, e.g. to slow down for corners.
+    pub fn with_speeds<F>(mut self, mut f: F) -> Self
+    where
+        F: FnMut(usize, &Waypoint) -> f32,
+    {
+        for (i, waypoint) in self.waypoints.iter_mut().enumerate() {
+            waypoint.speed = f(i, waypoint);
+        }
+        self
+    }
+
+    /// Waypoints
+    pub fn waypoints(&self) -> &[Waypoint] {
+        &self.waypoints
+    }
+
+    /// Whether the path loops back to the first waypoint
+    pub fn is_closed(&self) -> bool {
+        self.closed
+    }
+
+    fn next(&self, i: usize) -> Option<usize> {
+        if i + 1 < self.waypoints.len() {
+            Some(i + 1)
+        } else if self.closed && !self.waypoints.is_empty() {
+            Some(0)
+        } else {
+            None
+        }
+    }
+
+    /// Index of the waypoint nearest to `(x, y)`, searching `window` waypoints from `from`,
+    /// or the whole path if `from` is `None`.
+    fn nearest(&self, x: f32, y: f32, from: Option<usize>, window: usize) -> Option<usize> {
+        let candidates: Box<dyn Iterator<Item = usize>> = match from {
+            Some(from) => {
+                Box::new(std::iter::successors(Some(from), |&i| self.next(i)).take(window + 1))
+            },
+            None => Box::new(0..self.waypoints.len()),
+        };
+        candidates.min_by(|&a, &b| {
+            self.waypoints[a]
+                .distance_squared(x, y)
+                .total_cmp(&self.waypoints[b].distance_squared(x, y))
+        })
+    }
+
+    /// Fastest speed at `(x, y)`, near waypoint `from`, that can still slow to the target
+    /// speed of every waypoint within `distance` metres, braking at `decel` metres per second
+    /// squared.
+    fn speed_limit(&self, from: usize, x: f32, y: f32, distance: f32, decel: f32) -> f32 {
+        let mut limit = self.waypoints[from].speed;
+        let mut travelled = self.waypoints[from].distance_squared(x, y).sqrt();
+        let mut i = from;
+        while travelled <= distance
+            && let Some(j) = self.next(i)
+            && j != from
+        {
+            let (a, b) = (&self.waypoints[i], &self.waypoints[j]);
+            travelled += a.distance_squared(b.x, b.y).sqrt();
+            limit = limit.min((b.speed.powi(2) + 2.0 * decel * travelled).sqrt());
+            i = j;
+        }
+        limit
+    }
+
+    /// The point `distance` metres along the path from waypoint `from`.
+    fn point_along(&self, from: usize, distance: f32) -> (f32, f32) {
+        let mut remaining = distance;
+        let mut i = from;
+        while let Some(j) = self.next(i) {
+            let (a, b) = (&self.waypoints[i], &self.waypoints[j]);
+            let length = a.distance_squared(b.x, b.y).sqrt();
+            if length >= remaining && length > 0.0 {
+                let t = remaining / length;
+                return (a.x + (b.x - a.x) * t, a.y + (b.y - a.y) * t);
+            }
+            remaining -= length;
+            i = j;
+            if i == from {
+                break;
+            }
+        }
+        (self.waypoints[i].x, self.waypoints[i].y)
+    }
+}
+
+/// Tuning for an [`AiDriver`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct AiDriverConfig {
+    /// How often LFS should send an [`Aii`], and so how often controls are sent. At most
+    /// 2.55 seconds, in steps of 10 milliseconds. Defaults to 100 milliseconds.
+    pub interval: Duration,
+    /// Distance between the front and rear axles, in metres. Defaults to 2.5.
+    pub wheelbase: f32,
+    /// Angle of the front wheels at full lock, in radians. Defaults to 0.5.
+    pub max_steer_angle: f32,
+    /// Minimum distance to look ahead along the path, in metres. Defaults to 6.
+    pub lookahead_min: f32,
+    /// Additional distance to look ahead, in seconds at the current speed. Defaults to 0.5.
+    pub lookahead_time: f32,
+    /// Throttle per metre per second below the target speed. Defaults to 0.3.
+    pub throttle_gain: f32,
+    /// Brake per metre per second above the target speed. Defaults to 0.2.
+    pub brake_gain: f32,
+    /// How far above the target speed, in metres per second, before braking. Defaults to 1.
+    pub brake_deadband: f32,
+    /// Deceleration to plan for when slowing for waypoints ahead, in metres per second
+    /// squared. Defaults to 6.
+    pub brake_decel: f32,
+    /// Change up above this RPM. Defaults to 6500.
+    pub shift_up_rpm: f32,
+    /// Change down below this RPM. Defaults to 3000.
+    pub shift_down_rpm: f32,
+}
+
+impl Default for AiDriverConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_millis(100),
+            wheelbase: 2.5,
+            max_steer_angle: 0.5,
+            lookahead_min: 6.0,
+            lookahead_time: 0.5,
+            throttle_gain: 0.3,
+            brake_gain: 0.2,
+            brake_deadband: 1.0,
+            brake_decel: 6.0,
+            shift_up_rpm: 6500.0,
+            shift_down_rpm: 3000.0,
+        }
+    }
+}
+
+/// Gear change requested by [`Controls`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shift {
+    /// Change up
+    Up,
+    /// Change down
+    Down,
+}
+
+/// Controls calculated by [`AiDriver::controls`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Controls {
+    /// Steering, from -1.0 at full left lock to 1.0 at full right lock
+    pub steer: f32,
+    /// Throttle, from 0.0 to 1.0
+    pub throttle: f32,
+    /// Brake, from 0.0 to 1.0
+    pub brake: f32,
+    /// Gear change, if any
+    pub shift: Option<Shift>,
+}
+
+impl Controls {
+    /// Convert to [`AiInput`]s for an [`Aic`].
+    pub fn to_inputs(&self) -> Vec<AiInput> {
+        let axis = |value: f32| (value.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16;
+        let steer = (32768.0 + self.steer.clamp(-1.0, 1.0) * 32767.0).round() as u16;
+
+        let mut inputs = vec![
+            AiInput {
+                input: AiInputType::Msx(steer.max(1)),
+                time: None,
+            },
+            AiInput {
+                input: AiInputType::Throttle(axis(self.throttle)),
+                time: None,
+            },
+            AiInput {
+                input: AiInputType::Brake(axis(self.brake)),
+                time: None,
+            },
+        ];
+        match self.shift {
+            Some(Shift::Up) => inputs.push(AiInput {
+                input: AiInputType::Chup(1),
+                time: Some(SHIFT_HOLD),
+            }),
+            Some(Shift::Down) => inputs.push(AiInput {
+                input: AiInputType::Chdn(1),
+                time: Some(SHIFT_HOLD),
+            }),
+            None => {},
+        }
+        inputs
+    }
+}
+
+/// A gear change which has been sent, but not yet seen to complete.
+#[derive(Debug, Clone, Copy)]
+struct Shifting {
+    /// Gear being changed from
+    from: u8,
+    /// Has LFS reported the change as held?
+    held: bool,
+    /// Time since the change was sent, counted in [`AiDriverConfig::interval`]s
+    waited: Duration,
+}
+
+/// Drives a single AI car along a [`Path`]. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct AiDriver {
+    plid: PlayerId,
+    path: Path,
+    config: AiDriverConfig,
+    nearest: Option<usize>,
+    shifting: Option<Shifting>,
+}
+
+impl AiDriver {
+    /// New driver for the AI car `plid`.
+    pub fn new(plid: PlayerId, path: Path, config: AiDriverConfig) -> Self {
+        Self {
+            plid,
+            path,
+            config,
+            nearest: None,
+            shifting: None,
+        }
+    }
+
+    /// Player being driven
+    pub fn plid(&self) -> PlayerId {
+        self.plid
+    }
+
+    /// Path being followed
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Index of the waypoint the car was last nearest to
+    pub fn nearest_waypoint(&self) -> Option<usize> {
+        self.nearest
+    }
+
+    /// Whether the car has reached the end of an open path.
+    pub fn is_finished(&self) -> bool {
+        !self.path.closed
+            && self
+                .nearest
+                .is_some_and(|i| i + 1 == self.path.waypoints.len())
+    }
+
+    /// Take control: start the engine, enable autoclutch and ask LFS to send an [`Aii`]
+    /// every [`AiDriverConfig::interval`].
+    pub fn start(&self) -> Aic {
+        Aic {
+            plid: self.plid,
+            inputs: vec![
+                AiInput {
+                    input: AiInputType::Ignition(AiInputToggle::On),
+                    time: None,
+                },
+                AiInput::set_help_flags(AiHelpFlags::AUTOCLUTCH),
+                AiInput::ai_info_repeat(self.config.interval),
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Stop LFS sending [`Aii`] and hand control back to LFS.
+    pub fn stop(&self) -> Aic {
+        Aic {
+            plid: self.plid,
+            inputs: vec![
+                AiInput::ai_info_repeat(Duration::ZERO),
+                AiInput::stop_control(),
+            ],
+            ..Default::default()
+        }
+    }
+
+    /// Calculate the controls to send in reply to `aii`, or `None` if it is for another car.
+    pub fn update(&mut self, aii: &Aii) -> Option<Aic> {
+        if aii.plid != self.plid {
+            return None;
+        }
+        let controls = self.controls(aii);
+        Some(Aic {
+            plid: self.plid,
+            inputs: controls.to_inputs(),
+            ..Default::default()
+        })
+    }
+
+    /// Calculate the controls for the car state in `aii`, without checking the player.
+    pub fn controls(&mut self, aii: &Aii) -> Controls {
+        let main = &aii.osmain;
+        let x = main.pos.x as f32 / COORDINATE_SCALE;
+        let y = main.pos.y as f32 / COORDINATE_SCALE;
+        let speed = main.vel.0.hypot(main.vel.1);
+
+        let Some(nearest) = self.path.nearest(x, y, self.nearest, SEARCH_WINDOW) else {
+            return Controls {
+                brake: 1.0,
+                ..Default::default()
+            };
+        };
+        self.nearest = Some(nearest);
+
+        let lookahead = self.config.lookahead_min + speed * self.config.lookahead_time;
+        let (tx, ty) = self.path.point_along(nearest, lookahead);
+
+        let target_speed = if self.is_finished() {
+            0.0
+        } else {
+            let decel = self.config.brake_decel;
+            let braking = speed.powi(2) / (2.0 * decel) + lookahead;
+            self.path.speed_limit(nearest, x, y, braking, decel)
+        };
+
+        Controls {
+            steer: self.steer(x, y, main.heading, tx, ty),
+            shift: self.shift(aii, target_speed),
+            ..self.pedals(speed, target_speed)
+        }
+    }
+
+    /// Pure pursuit towards `(tx, ty)`.
+    fn steer(&self, x: f32, y: f32, heading: f32, tx: f32, ty: f32) -> f32 {
+        // Heading is anticlockwise from the Y axis
+        let (sin, cos) = heading.sin_cos();
+        let (dx, dy) = (tx - x, ty - y);
+        let forward = -dx * sin + dy * cos;
+        let right = dx * cos + dy * sin;
+        let distance = dx.hypot(dy);
+        if distance < f32::EPSILON {
+            return 0.0;
+        }
+
+        let alpha = right.atan2(forward);
+        let curvature = 2.0 * alpha.sin() / distance;
+        let angle = (self.config.wheelbase * curvature).atan();
+        (angle / self.config.max_steer_angle).clamp(-1.0, 1.0)
+    }
+
+    fn pedals(&self, speed: f32, target_speed: f32) -> Controls {
+        let error = target_speed - speed;
+        if target_speed <= 0.0 {
+            Controls {
+                brake: 1.0,
+                ..Default::default()
+            }
+        } else if error >= 0.0 {
+            Controls {
+                throttle: (error * self.config.throttle_gain).clamp(0.0, 1.0),
+                ..Default::default()
+            }
+        } else if -error > self.config.brake_deadband {
+            Controls {
+                brake: ((-error - self.config.brake_deadband) * self.config.brake_gain)
+                    .clamp(0.0, 1.0),
+                ..Default::default()
+            }
+        } else {
+            Controls::default()
+        }
+    }
+
+    fn shift(&mut self, aii: &Aii, target_speed: f32) -> Option<Shift> {
+        // Wait for any previous change to complete. If LFS let go of the change without the
+        // gear changing, or never held it at all, it was lost or refused, so try again.
+        let holding = aii.flags.intersects(AiFlags::CHUP | AiFlags::CHDN);
+        if let Some(shifting) = &mut self.shifting {
+            shifting.waited += self.config.interval;
+            shifting.held |= holding;
+            let abandoned = shifting.held || shifting.waited > SHIFT_HOLD + self.config.interval;
+            if holding || (aii.gear == shifting.from && !abandoned) {
+                return None;
+            }
+            self.shifting = None;
+        } else if holding {
+            return None;
+        }
+
+        let shift = if aii.gear <= GEAR_NEUTRAL {
+            (target_speed > 0.0).then_some(Shift::Up)
+        } else if aii.rpm > self.config.shift_up_rpm {
+            Some(Shift::Up)
+        } else if aii.rpm < self.config.shift_down_rpm && aii.gear > GEAR_NEUTRAL + 1 {
+            Some(Shift::Down)
+        } else {
+            None
+        };
+        if shift.is_some() {
+            self.shifting = Some(Shifting {
+                from: aii.gear,
+                held: false,
+                waited: Duration::ZERO,
+            });
+        }
+        shift
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insim::{
+        core::{coordinate::Coordinate, vector::Vector},
+        insim::OsMain,
+    };
+
+    use super::*;
+
+    /// Kinematic bicycle model, standing in for LFS.
+    #[derive(Debug)]
+    struct Sim {
+        x: f32,
+        y: f32,
+        heading: f32,
+        speed: f32,
+        gear: u8,
+    }
+
+    impl Sim {
+        const DT: f32 = 0.1;
+
+        fn aii(&self) -> Aii {
+            let (sin, cos) = self.heading.sin_cos();
+            Aii {
+                plid: PlayerId(1),
+                osmain: OsMain {
+                    heading: self.heading,
+                    vel: Vector(-sin * self.speed, cos * self.speed, 0.0),
+                    pos: Coordinate {
+                        x: (self.x * COORDINATE_SCALE) as i32,
+                        y: (self.y * COORDINATE_SCALE) as i32,
+                        z: 0,
+                    },
+                    ..Default::default()
+                },
+                gear: self.gear,
+                rpm: 4000.0,
+                ..Default::default()
+            }
+        }
+
+        fn step(&mut self, controls: &Controls, config: &AiDriverConfig) {
+            if controls.shift == Some(Shift::Up) {
+                self.gear += 1;
+            }
+            let accel = if self.gear > GEAR_NEUTRAL {
+                controls.throttle * 5.0
+            } else {
+                0.0
+            };
+            self.speed = (self.speed + (accel - controls.brake * 10.0) * Self::DT).max(0.0);
+
+            // Steering right turns clockwise
+            let angle = controls.steer * config.max_steer_angle;
+            self.heading -= self.speed / config.wheelbase * angle.tan() * Self::DT;
+            let (sin, cos) = self.heading.sin_cos();
+            self.x -= sin * self.speed * Self::DT;
+            self.y += cos * self.speed * Self::DT;
+        }
+    }
+
+    #[test]
+    fn test_follows_closed_path() {
+        let points = (0..200).map(|i| {
+            let angle = i as f32 / 200.0 * std::f32::consts::TAU;
+            (50.0 * angle.cos(), 50.0 * angle.sin())
+        });
+        let path = Path::new(
+            points
+                .map(|(x, y)| Waypoint { x, y, speed: 15.0 })
+                .collect(),
+            true,
+        );
+        let config = AiDriverConfig::default();
+        let mut driver = AiDriver::new(PlayerId(1), path, config.clone());
+
+        // Starting on the circle, in neutral and pointing anticlockwise
+        let mut sim = Sim {
+            x: 50.0,
+            y: 0.0,
+            heading: 0.0,
+            speed: 0.0,
+            gear: GEAR_NEUTRAL,
+        };
+        for step in 0..600 {
+            let controls = driver.controls(&sim.aii());
+            sim.step(&controls, &config);
+            if step > 100 {
+                let error = (sim.x.hypot(sim.y) - 50.0).abs();
+                assert!(error < 2.0, "{error}m off the path at step {step}");
+                assert!((sim.speed - 15.0).abs() < 2.0, "{} m/s", sim.speed);
+            }
+        }
+        assert!(!driver.is_finished());
+    }
+
+    #[test]
+    fn test_stops_at_end_of_open_path() {
+        let path = Path::from_points((0..=20).map(|i| (0.0, i as f32 * 10.0)), 10.0);
+        let config = AiDriverConfig::default();
+        let mut driver = AiDriver::new(PlayerId(1), path, config.clone());
+
+        // Starting 3m to the right of the path
+        let mut sim = Sim {
+            x: 3.0,
+            y: 0.0,
+            heading: 0.0,
+            speed: 0.0,
+            gear: GEAR_NEUTRAL + 1,
+        };
+        for _ in 0..600 {
+            let controls = driver.controls(&sim.aii());
+            sim.step(&controls, &config);
+        }
+        assert!(driver.is_finished());
+        assert_eq!(sim.speed, 0.0);
+        assert!(sim.x.abs() < 0.5, "{}m off the path", sim.x);
+        assert!((sim.y - 200.0).abs() < 10.0, "stopped at {}m", sim.y);
+    }
+
+    #[test]
+    fn test_update() {
+        let path = Path::from_points([(0.0, 0.0), (0.0, 100.0)], 10.0);
+        let mut driver = AiDriver::new(PlayerId(1), path, AiDriverConfig::default());
+
+        let mut aii = Aii {
+            plid: PlayerId(2),
+            gear: GEAR_NEUTRAL,
+            ..Default::default()
+        };
+        assert!(driver.update(&aii).is_none());
+
+        aii.plid = PlayerId(1);
+        let aic = driver.update(&aii).unwrap();
+        assert_eq!(aic.plid, PlayerId(1));
+        assert!(matches!(aic.inputs[0].input, AiInputType::Msx(32768)));
+        assert!(matches!(
+            aic.inputs[1].input,
+            AiInputType::Throttle(u16::MAX)
+        ));
+        assert!(matches!(aic.inputs[2].input, AiInputType::Brake(0)));
+        assert!(matches!(aic.inputs[3].input, AiInputType::Chup(1)));
+
+        // Still in neutral, the change up is not repeated whilst it may still happen
+        assert_eq!(driver.update(&aii).unwrap().inputs.len(), 3);
+        assert_eq!(driver.controls(&aii).shift, None);
+        // but is once it has been lost
+        assert_eq!(driver.controls(&aii).shift, Some(Shift::Up));
+        aii.flags = AiFlags::CHUP;
+        assert_eq!(driver.controls(&aii).shift, None);
+        aii.flags = AiFlags::empty();
+
+        aii.gear = GEAR_NEUTRAL + 1;
+        aii.rpm = 7000.0;
+        assert_eq!(driver.controls(&aii).shift, Some(Shift::Up));
+        aii.flags = AiFlags::CHUP;
+        assert_eq!(driver.controls(&aii).shift, None);
+    }
+
+    #[test]
+    fn test_refused_shift_is_retried() {
+        let path = Path::from_points([(0.0, 0.0), (0.0, 100.0)], 10.0);
+        let mut driver = AiDriver::new(PlayerId(1), path, AiDriverConfig::default());
+
+        let mut aii = Aii {
+            plid: PlayerId(1),
+            gear: GEAR_NEUTRAL,
+            ..Default::default()
+        };
+        assert_eq!(driver.controls(&aii).shift, Some(Shift::Up));
+
+        // LFS holds the change, then lets go of it without changing gear
+        aii.flags = AiFlags::CHUP;
+        assert_eq!(driver.controls(&aii).shift, None);
+        aii.flags = AiFlags::empty();
+        assert_eq!(driver.controls(&aii).shift, Some(Shift::Up));
+
+        // a change which completes is not repeated
+        aii.gear = GEAR_NEUTRAL + 1;
+        aii.rpm = 3000.0;
+        assert_eq!(driver.controls(&aii).shift, None);
+        assert_eq!(driver.controls(&aii).shift, None);
+    }
+
+    /// An [`Aii`] for a stationary car in first gear, with a change up held, as used by
+    /// insim's own [`Aii`] packet test.
+    const AII_FIXTURE: [u8; 96] = [
+        24, 69, 1, 3, // size, type, reqi, plid
+        126, 231, 140, 188, 0, 70, 13, 188, 130, 122, 165, 187, // angvel
+        138, 10, 83, 182, // heading
+        248, 153, 138, 60, 156, 143, 135, 186, // pitch, roll
+        47, 77, 153, 57, 159, 58, 102, 57, 222, 252, 251, 58, // accel
+        247, 213, 50, 183, 34, 197, 114, 56, 218, 126, 10, 56, // vel
+        249, 255, 79, 253, 161, 255, 97, 248, 185, 26, 2, 0, // pos
+        5, 2, 0, 0, // flags, gear, spare
+        255, 0, 0, 0, // rpm
+        0, 0, 0, 0, 0, 0, 0, 0, // spare
+        1, 0, 0, 0, // showlights
+        0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, // spare
+    ];
+
+    #[test]
+    fn test_update_from_fixture() {
+        let mut codec = insim::net::Codec::new();
+        codec.feed(&AII_FIXTURE);
+        let Ok(Some(insim::Packet::Aii(aii))) = codec.decode() else {
+            panic!("fixture is not an Aii");
+        };
+
+        // Straight ahead of the car, which is facing along the Y axis
+        let x = aii.osmain.pos.x as f32 / COORDINATE_SCALE;
+        let y = aii.osmain.pos.y as f32 / COORDINATE_SCALE;
+        let path = Path::from_points([(x, y), (x, y + 100.0), (x, y + 500.0)], 20.0);
+        let mut driver = AiDriver::new(PlayerId(3), path, AiDriverConfig::default());
+
+        let aic = driver.update(&aii).unwrap();
+        assert_eq!(aic.plid, PlayerId(3));
+        // full throttle from rest, steering straight, and no further change up whilst one is
+        // still held
+        assert_eq!(aic.inputs.len(), 3);
+        assert!(matches!(aic.inputs[0].input, AiInputType::Msx(32768)));
+        assert!(matches!(
+            aic.inputs[1].input,
+            AiInputType::Throttle(u16::MAX)
+        ));
+        assert!(matches!(aic.inputs[2].input, AiInputType::Brake(0)));
+        assert_eq!(driver.nearest_waypoint(), Some(0));
+    }
+
+    #[test]
+    fn test_steer_direction() {
+        let path = Path::from_points([(0.0, 0.0), (0.0, 100.0)], 10.0);
+        let mut driver = AiDriver::new(PlayerId(1), path, AiDriverConfig::default());
+
+        // Left of the path, facing along it, steer right
+        let aii = Aii {
+            osmain: OsMain {
+                pos: Coordinate {
+                    x: -3 * COORDINATE_SCALE as i32,
+                    y: 0,
+                    z: 0,
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(driver.controls(&aii).steer > 0.0);
+
+        // Facing right, steer left
+        let aii = Aii {
+            osmain: OsMain {
+                heading: -std::f32::consts::FRAC_PI_2,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(driver.controls(&aii).steer < 0.0);
+    }
+}
@@ -0,0 +1,877 @@
+//! Typed LFS host commands.
+//!
+//! [`HostCommand`] formats to the `/command` text LFS expects, via [`std::fmt::Display`], and
+//! parses back from it, via [`std::str::FromStr`], so that every command a bot issues can be
+//! logged and audited. [`HostCommand::to_packet`] validates the length before building the
+//! packet to send.
+//!
+//! ```rust
+//! use insim_extra::command::{HostCommand, Penalty};
+//!
+//! let cmd = HostCommand::Penalty(Penalty::StopGo, "bob".into());
+//! assert_eq!(cmd.to_string(), "/p_sg bob");
+//! assert_eq!("/p_sg bob".parse::<HostCommand>(), Ok(cmd));
+//! ```
+
+use std::{fmt, str::FromStr};
+
+use insim::{
+    core::{string::codepages, track::Track, vehicle::Vehicle},
+    insim::{Mst, Msx},
+};
+
+use crate::world::{GridMode, TimeDemoPreset, TimeSet};
+
+/// Longest text, in bytes, that fits in an [`Mst`].
+pub const MST_MAX_LEN: usize = 63;
+
+/// Longest text, in bytes, that fits in an [`Msx`].
+pub const MSX_MAX_LEN: usize = 95;
+
+/// Errors from parsing or sending a [`HostCommand`].
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+#[non_exhaustive]
+pub enum CommandError {
+    /// The arguments to a command are missing or invalid.
+    #[error("Invalid arguments to /{command}: {reason}")]
+    InvalidArgument {
+        /// Command name, without the `/`
+        command: String,
+        /// What is wrong with the arguments
+        reason: String,
+    },
+
+    /// The formatted command does not fit in a single packet.
+    #[error("Command is {len} bytes, longer than the maximum of {max}")]
+    TooLong {
+        /// Length of the command, once encoded
+        len: usize,
+        /// Maximum length
+        max: usize,
+    },
+}
+
+/// Penalty given or cleared by [`HostCommand::Penalty`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Penalty {
+    /// `/p_dt` - drive through
+    DriveThrough,
+    /// `/p_sg` - stop go
+    StopGo,
+    /// `/p_30` - 30 seconds added
+    Seconds30,
+    /// `/p_45` - 45 seconds added
+    Seconds45,
+    /// `/p_clear` - clear the current penalty
+    Clear,
+}
+
+impl Penalty {
+    const ALL: [Self; 5] = [
+        Self::DriveThrough,
+        Self::StopGo,
+        Self::Seconds30,
+        Self::Seconds45,
+        Self::Clear,
+    ];
+
+    fn command(&self) -> &'static str {
+        match self {
+            Self::DriveThrough => "p_dt",
+            Self::StopGo => "p_sg",
+            Self::Seconds30 => "p_30",
+            Self::Seconds45 => "p_45",
+            Self::Clear => "p_clear",
+        }
+    }
+}
+
+/// Starting grid order, for [`HostCommand::Start`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StartOrder {
+    /// Order of the grid in the game setup screen
+    Fixed,
+    /// Finishing order of the previous race
+    Finish,
+    /// Reverse finishing order of the previous race
+    Reverse,
+    /// Random order
+    Random,
+}
+
+/// What happens to players who wrong way or cause collisions, for [`HostCommand::Autokick`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutoKick {
+    /// Nothing
+    No,
+    /// Kicked
+    Yes,
+    /// Banned
+    Ban,
+    /// Spectated
+    Spec,
+}
+
+/// `/time` command, for [`HostCommand::Time`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimeCommand {
+    /// `/time` - report the current in-game time
+    Status,
+    /// `/time live` - use real-world time
+    Live,
+    /// `/time offset` - shift the in-game time from real-world time
+    Offset {
+        /// Days to shift by
+        days: Option<i32>,
+        /// Minutes to shift by, formatted as `HH:MM`
+        minutes: Option<i32>,
+    },
+    /// `/time set` - set the in-game date and time
+    Set(TimeSet),
+    /// `/time mul` - time multiplier, `0..=240`, when the time has been set
+    Mul(u8),
+    /// `/time demo` - a preset time of day
+    Demo(TimeDemoPreset),
+}
+
+impl TimeCommand {
+    /// Write the arguments following `/time`.
+    fn fmt_args(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Status => Ok(()),
+            Self::Live => f.write_str(" live"),
+            Self::Offset { days, minutes } => {
+                f.write_str(" offset")?;
+                if let Some(days) = days {
+                    write!(f, " {}{}", sign(*days), days.unsigned_abs())?;
+                }
+                if let Some(minutes) = minutes {
+                    let abs = minutes.unsigned_abs();
+                    write!(f, " {}{}:{:02}", sign(*minutes), abs / 60, abs % 60)?;
+                }
+                Ok(())
+            },
+            Self::Set(params) => {
+                f.write_str(" set")?;
+                if let Some((day, month)) = params.date {
+                    write!(f, " {day} {month}")?;
+                }
+                if let Some((hour, minute)) = params.time {
+                    write!(f, " {hour:02}:{minute:02}")?;
+                }
+                if let Some(offset) = params.utc_offset {
+                    write!(f, " utc{}{}", sign(offset.into()), offset.unsigned_abs())?;
+                }
+                Ok(())
+            },
+            Self::Mul(factor) => write!(f, " mul {factor}"),
+            Self::Demo(preset) => write!(f, " demo {preset}"),
+        }
+    }
+}
+
+fn sign(value: i32) -> char {
+    if value < 0 { '-' } else { '+' }
+}
+
+macro_rules! keywords {
+    ($ty:ty { $($variant:ident => $keyword:literal),+ $(,)? }) => {
+        impl fmt::Display for $ty {
+            fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str(match self {
+                    $(Self::$variant => $keyword,)+
+                })
+            }
+        }
+
+        impl FromStr for $ty {
+            type Err = String;
+
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                match s.to_ascii_lowercase().as_str() {
+                    $($keyword => Ok(Self::$variant),)+
+                    _ => Err(format!(
+                        concat!("expected one of ", $($keyword, " "),+, "but found {:?}"),
+                        s
+                    )),
+                }
+            }
+        }
+    };
+}
+
+keywords!(StartOrder {
+    Fixed => "fixed",
+    Finish => "finish",
+    Reverse => "reverse",
+    Random => "random",
+});
+
+keywords!(AutoKick {
+    No => "no",
+    Yes => "yes",
+    Ban => "ban",
+    Spec => "spec",
+});
+
+/// A command, or message, sent to the LFS host.
+///
+/// Player commands take the player's LFS username, not their nickname.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum HostCommand {
+    /// `/end` - finish the race and return to the game setup screen
+    End,
+    /// `/restart` - start a race
+    Restart,
+    /// `/qualify` - start qualifying
+    Qualify,
+    /// `/reinit` - full restart, kicks all connections
+    Reinit,
+    /// `/clear` - remove all connections
+    Clear,
+    /// `/pit_all` - send every player to the pits
+    PitAll,
+    /// `/spec_all` - spectate every player
+    SpecAll,
+
+    /// `/track` - load a track
+    Track(Track),
+    /// `/laps` - race length in laps, `None` for no limit
+    Laps(Option<u8>),
+    /// `/hours` - race length in hours
+    Hours(u8),
+    /// `/qual` - qualifying length in minutes, `0` for none
+    Qual(u8),
+    /// `/weather` - weather and lighting
+    Weather(u8),
+    /// `/wind` - wind strength, `0` for none
+    Wind(u8),
+    /// `/mustpit` - whether a pit stop is required
+    MustPit(bool),
+    /// `/canreset` - whether players may reset their car
+    CanReset(bool),
+    /// `/midrace` - whether players may join during a race
+    MidRace(bool),
+    /// `/fcv` - whether cockpit view is forced
+    ForceCockpitView(bool),
+    /// `/cruise` - whether driving the wrong way is allowed
+    Cruise(bool),
+    /// `/vote` - whether guests may vote to kick or ban
+    Vote(bool),
+    /// `/select` - whether guests may select the track
+    Select(bool),
+    /// `/flood` - whether floodlights are on
+    Flood(bool),
+    /// `/grid open`, `/grid self` or `/grid lock` - who may change the grid
+    Grid(GridMode),
+    /// `/grid real` - whether real players may join the grid
+    GridReal(bool),
+    /// `/grid ai` - whether AI players may join the grid
+    GridAi(bool),
+    /// `/time` - in-game time of day
+    Time(TimeCommand),
+    /// `/start` - starting grid order
+    Start(StartOrder),
+    /// `/autokick` - what happens to players driving the wrong way
+    Autokick(AutoKick),
+    /// `/maxguests` - maximum number of guests
+    MaxGuests(u8),
+    /// `/carsmax` - maximum number of cars in a race
+    CarsMax(u8),
+    /// `/carshost` - maximum number of cars on the host
+    CarsHost(u8),
+    /// `/carsguest` - maximum number of cars per guest
+    CarsGuest(u8),
+    /// `/pps` - smoothness, packets per second sent by each car
+    Pps(u8),
+
+    /// `/axload` - load an autocross layout by name
+    AxLoad(String),
+    /// `/axclear` - clear the autocross layout
+    AxClear,
+
+    /// `/kick` - disconnect a user
+    Kick(String),
+    /// `/ban` - ban a user for a number of days, `0` for 12 hours
+    Ban(String, u32),
+    /// `/unban` - remove a ban
+    Unban(String),
+    /// `/spec` - spectate a user
+    Spec(String),
+    /// `/pitlane` - send a user to the pit lane
+    Pitlane(String),
+    /// `/p_dt`, `/p_sg`, `/p_30`, `/p_45` or `/p_clear` - give a user a penalty, or clear it
+    Penalty(Penalty, String),
+
+    /// `/msg` - message shown to every connection
+    Msg(String),
+    /// `/rcm` - set the race control message, shown by [`HostCommand::RcmPlayer`] or
+    /// [`HostCommand::RcmAll`]
+    Rcm(String),
+    /// `/rcm_ply` - show the race control message to a user
+    RcmPlayer(String),
+    /// `/rcm_all` - show the race control message to everyone
+    RcmAll,
+    /// `/rcc_ply` - clear the race control message for a user
+    RccPlayer(String),
+    /// `/rcc_all` - clear the race control message for everyone
+    RccAll,
+
+    /// `/ai` - add an AI driver, optionally with a name
+    Ai(Option<String>),
+    /// `/car` - select a car
+    Car(Vehicle),
+
+    /// Any other command, including the `/`
+    Other(String),
+    /// Not a command, a chat message sent as the host
+    Say(String),
+}
+
+impl HostCommand {
+    /// Validate and build the packet to send this command.
+    ///
+    /// Commands are sent as an [`Mst`], and must be at most [`MST_MAX_LEN`] bytes. LFS does
+    /// not accept commands sent as an [`Msx`], so only [`HostCommand::Say`] uses one, when it
+    /// is too long for an [`Mst`].
+    pub fn to_packet(&self) -> Result<insim::Packet, CommandError> {
+        let msg = self.to_string();
+        match msg.parse::<Self>() {
+            Ok(parsed) if parsed == *self => {},
+            Ok(parsed) => {
+                return Err(CommandError::InvalidArgument {
+                    command: self.name().to_owned(),
+                    reason: format!("would be sent as {parsed:?}"),
+                });
+            },
+            Err(e) => return Err(e),
+        }
+
+        let len = codepages::to_lossy_bytes(&msg).len();
+        let max = match self {
+            Self::Say(_) => MSX_MAX_LEN,
+            _ => MST_MAX_LEN,
+        };
+        if len > max {
+            Err(CommandError::TooLong { len, max })
+        } else if len > MST_MAX_LEN {
+            Ok(Msx {
+                msg,
+                ..Default::default()
+            }
+            .into())
+        } else {
+            Ok(Mst {
+                msg,
+                ..Default::default()
+            }
+            .into())
+        }
+    }
+
+    /// Parse the command sent by an [`Mst`] or [`Msx`], or `None` for any other packet.
+    pub fn from_packet(packet: &insim::Packet) -> Option<Result<Self, CommandError>> {
+        match packet {
+            insim::Packet::Mst(Mst { msg, .. }) | insim::Packet::Msx(Msx { msg, .. }) => {
+                Some(msg.parse())
+            },
+            _ => None,
+        }
+    }
+
+    /// Name of the command, without the `/`.
+    pub fn name(&self) -> &str {
+        match self {
+            Self::End => "end",
+            Self::Restart => "restart",
+            Self::Qualify => "qualify",
+            Self::Reinit => "reinit",
+            Self::Clear => "clear",
+            Self::PitAll => "pit_all",
+            Self::SpecAll => "spec_all",
+            Self::Track(_) => "track",
+            Self::Laps(_) => "laps",
+            Self::Hours(_) => "hours",
+            Self::Qual(_) => "qual",
+            Self::Weather(_) => "weather",
+            Self::Wind(_) => "wind",
+            Self::MustPit(_) => "mustpit",
+            Self::CanReset(_) => "canreset",
+            Self::MidRace(_) => "midrace",
+            Self::ForceCockpitView(_) => "fcv",
+            Self::Cruise(_) => "cruise",
+            Self::Vote(_) => "vote",
+            Self::Select(_) => "select",
+            Self::Flood(_) => "flood",
+            Self::Grid(_) | Self::GridReal(_) | Self::GridAi(_) => "grid",
+            Self::Time(_) => "time",
+            Self::Start(_) => "start",
+            Self::Autokick(_) => "autokick",
+            Self::MaxGuests(_) => "maxguests",
+            Self::CarsMax(_) => "carsmax",
+            Self::CarsHost(_) => "carshost",
+            Self::CarsGuest(_) => "carsguest",
+            Self::Pps(_) => "pps",
+            Self::AxLoad(_) => "axload",
+            Self::AxClear => "axclear",
+            Self::Kick(_) => "kick",
+            Self::Ban(..) => "ban",
+            Self::Unban(_) => "unban",
+            Self::Spec(_) => "spec",
+            Self::Pitlane(_) => "pitlane",
+            Self::Penalty(penalty, _) => penalty.command(),
+            Self::Msg(_) => "msg",
+            Self::Rcm(_) => "rcm",
+            Self::RcmPlayer(_) => "rcm_ply",
+            Self::RcmAll => "rcm_all",
+            Self::RccPlayer(_) => "rcc_ply",
+            Self::RccAll => "rcc_all",
+            Self::Ai(_) => "ai",
+            Self::Car(_) => "car",
+            Self::Other(text) => text
+                .trim_start_matches('/')
+                .split_whitespace()
+                .next()
+                .unwrap_or_default(),
+            Self::Say(_) => "",
+        }
+    }
+}
+
+fn yes_no(value: bool) -> &'static str {
+    if value { "yes" } else { "no" }
+}
+
+impl fmt::Display for HostCommand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Other(text) | Self::Say(text) => return f.write_str(text),
+            _ => write!(f, "/{}", self.name())?,
+        }
+        match self {
+            Self::Track(track) => write!(f, " {track}"),
+            Self::Laps(Some(n))
+            | Self::Hours(n)
+            | Self::Qual(n)
+            | Self::Weather(n)
+            | Self::Wind(n)
+            | Self::MaxGuests(n)
+            | Self::CarsMax(n)
+            | Self::CarsHost(n)
+            | Self::CarsGuest(n)
+            | Self::Pps(n) => write!(f, " {n}"),
+            Self::MustPit(b)
+            | Self::CanReset(b)
+            | Self::MidRace(b)
+            | Self::ForceCockpitView(b)
+            | Self::Cruise(b)
+            | Self::Vote(b)
+            | Self::Select(b)
+            | Self::Flood(b) => write!(f, " {}", yes_no(*b)),
+            Self::Laps(None) => f.write_str(" no"),
+            Self::Grid(mode) => write!(f, " {mode}"),
+            Self::GridReal(b) => write!(f, " real {}", yes_no(*b)),
+            Self::GridAi(b) => write!(f, " ai {}", yes_no(*b)),
+            Self::Time(time) => time.fmt_args(f),
+            Self::Start(order) => write!(f, " {order}"),
+            Self::Autokick(autokick) => write!(f, " {autokick}"),
+            Self::Ban(uname, days) => write!(f, " {uname} {days}"),
+            Self::AxLoad(s)
+            | Self::Kick(s)
+            | Self::Unban(s)
+            | Self::Spec(s)
+            | Self::Pitlane(s)
+            | Self::Penalty(_, s)
+            | Self::Msg(s)
+            | Self::Rcm(s)
+            | Self::RcmPlayer(s)
+            | Self::RccPlayer(s)
+            | Self::Ai(Some(s)) => write!(f, " {s}"),
+            Self::Car(vehicle) => write!(f, " {vehicle}"),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Arguments to a single command, consumed as they are parsed.
+struct Args<'a> {
+    command: &'a str,
+    rest: &'a str,
+}
+
+impl<'a> Args<'a> {
+    fn error(&self, reason: impl Into<String>) -> CommandError {
+        CommandError::InvalidArgument {
+            command: self.command.to_owned(),
+            reason: reason.into(),
+        }
+    }
+
+    /// Everything remaining, which must not be empty.
+    fn text(&mut self) -> Result<String, CommandError> {
+        let text = std::mem::take(&mut self.rest).trim();
+        if text.is_empty() {
+            return Err(self.error("expected text"));
+        }
+        Ok(text.to_owned())
+    }
+
+    /// The next whitespace separated word.
+    fn word(&mut self, what: &str) -> Result<&'a str, CommandError> {
+        let rest = self.rest.trim_start();
+        let end = rest.find(char::is_whitespace).unwrap_or(rest.len());
+        let (word, rest) = rest.split_at(end);
+        self.rest = rest;
+        if word.is_empty() {
+            return Err(self.error(format!("expected {what}")));
+        }
+        Ok(word)
+    }
+
+    fn parse<T: FromStr>(&mut self, what: &str) -> Result<T, CommandError>
+    where
+        T::Err: fmt::Display,
+    {
+        let word = self.word(what)?;
+        self.parse_word(word, what)
+    }
+
+    fn yes_no(&mut self) -> Result<bool, CommandError> {
+        match self.word("yes or no")? {
+            "yes" => Ok(true),
+            "no" => Ok(false),
+            other => Err(self.error(format!("expected yes or no, but found {other:?}"))),
+        }
+    }
+
+    /// Arguments to `/time`.
+    fn time(&mut self) -> Result<TimeCommand, CommandError> {
+        if self.rest.trim().is_empty() {
+            return Ok(TimeCommand::Status);
+        }
+        match self.word("time command")? {
+            "live" => Ok(TimeCommand::Live),
+            "offset" => {
+                let (mut days, mut minutes) = (None, None);
+                while !self.rest.trim().is_empty() {
+                    let word = self.word("offset")?;
+                    let (negative, abs) = match word.strip_prefix('-') {
+                        Some(abs) => (true, abs),
+                        None => (false, word.strip_prefix('+').unwrap_or(word)),
+                    };
+                    let (slot, value) = match abs.split_once(':') {
+                        Some((hours, mins)) => (
+                            &mut minutes,
+                            self.parse_word::<i32>(hours, "hours")? * 60
+                                + self.parse_word::<i32>(mins, "minutes")?,
+                        ),
+                        None => (&mut days, self.parse_word::<i32>(abs, "days")?),
+                    };
+                    if slot
+                        .replace(if negative { -value } else { value })
+                        .is_some()
+                    {
+                        return Err(self.error(format!("unexpected {word:?}")));
+                    }
+                }
+                Ok(TimeCommand::Offset { days, minutes })
+            },
+            "set" => {
+                let mut params = TimeSet::default();
+                while !self.rest.trim().is_empty() {
+                    let word = self.word("time")?;
+                    let repeated = if let Some(offset) = word.strip_prefix("utc") {
+                        params
+                            .utc_offset
+                            .replace(self.parse_word(offset, "UTC offset")?)
+                            .is_some()
+                    } else if let Some((hour, minute)) = word.split_once(':') {
+                        params
+                            .time
+                            .replace((
+                                self.parse_word(hour, "hour")?,
+                                self.parse_word(minute, "minute")?,
+                            ))
+                            .is_some()
+                    } else {
+                        params
+                            .date
+                            .replace((self.parse_word(word, "day")?, self.parse("month")?))
+                            .is_some()
+                    };
+                    if repeated {
+                        return Err(self.error(format!("unexpected {word:?}")));
+                    }
+                }
+                Ok(TimeCommand::Set(params))
+            },
+            "mul" => Ok(TimeCommand::Mul(self.parse("multiplier")?)),
+            "demo" => Ok(TimeCommand::Demo(self.parse("preset")?)),
+            other => Err(self.error(format!("unknown time command {other:?}"))),
+        }
+    }
+
+    fn parse_word<T: FromStr>(&self, word: &str, what: &str) -> Result<T, CommandError>
+    where
+        T::Err: fmt::Display,
+    {
+        word.parse()
+            .map_err(|e| self.error(format!("invalid {what} {word:?}: {e}")))
+    }
+
+    /// Succeed only if every argument has been consumed.
+    fn end<T>(self, value: T) -> Result<T, CommandError> {
+        if self.rest.trim().is_empty() {
+            Ok(value)
+        } else {
+            Err(self.error(format!("unexpected {:?}", self.rest.trim())))
+        }
+    }
+}
+
+impl FromStr for HostCommand {
+    type Err = CommandError;
+
+    /// Parse a command from the text sent to LFS. Unrecognised commands are parsed as
+    /// [`HostCommand::Other`], and anything not starting with `/` as [`HostCommand::Say`].
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let Some(command) = s.strip_prefix('/') else {
+            return Ok(Self::Say(s.to_owned()));
+        };
+        let end = command.find(char::is_whitespace).unwrap_or(command.len());
+        let (name, rest) = command.split_at(end);
+        let mut args = Args {
+            command: name,
+            rest,
+        };
+
+        let cmd = match name {
+            "end" => Self::End,
+            "restart" => Self::Restart,
+            "qualify" => Self::Qualify,
+            "reinit" => Self::Reinit,
+            "clear" => Self::Clear,
+            "pit_all" => Self::PitAll,
+            "spec_all" => Self::SpecAll,
+            "track" => Self::Track(args.parse("track")?),
+            "laps" => match args.word("laps")? {
+                "no" => Self::Laps(None),
+                laps => Self::Laps(Some(args.parse_word(laps, "laps")?)),
+            },
+            "hours" => Self::Hours(args.parse("hours")?),
+            "qual" => Self::Qual(args.parse("minutes")?),
+            "weather" => Self::Weather(args.parse("weather")?),
+            "wind" => Self::Wind(args.parse("wind")?),
+            "mustpit" => Self::MustPit(args.yes_no()?),
+            "canreset" => Self::CanReset(args.yes_no()?),
+            "midrace" => Self::MidRace(args.yes_no()?),
+            "fcv" => Self::ForceCockpitView(args.yes_no()?),
+            "cruise" => Self::Cruise(args.yes_no()?),
+            "vote" => Self::Vote(args.yes_no()?),
+            "select" => Self::Select(args.yes_no()?),
+            "flood" => Self::Flood(args.yes_no()?),
+            "grid" => match args.word("grid mode")? {
+                "real" => Self::GridReal(args.yes_no()?),
+                "ai" => Self::GridAi(args.yes_no()?),
+                mode => Self::Grid(args.parse_word(mode, "grid mode")?),
+            },
+            "time" => Self::Time(args.time()?),
+            "start" => Self::Start(args.parse("start order")?),
+            "autokick" => Self::Autokick(args.parse("autokick")?),
+            "maxguests" => Self::MaxGuests(args.parse("guests")?),
+            "carsmax" => Self::CarsMax(args.parse("cars")?),
+            "carshost" => Self::CarsHost(args.parse("cars")?),
+            "carsguest" => Self::CarsGuest(args.parse("cars")?),
+            "pps" => Self::Pps(args.parse("packets per second")?),
+            "axload" => Self::AxLoad(args.text()?),
+            "axclear" => Self::AxClear,
+            "kick" => Self::Kick(args.word("username")?.to_owned()),
+            "ban" => Self::Ban(args.word("username")?.to_owned(), args.parse("days")?),
+            "unban" => Self::Unban(args.word("username")?.to_owned()),
+            "spec" => Self::Spec(args.word("username")?.to_owned()),
+            "pitlane" => Self::Pitlane(args.word("username")?.to_owned()),
+            "msg" => Self::Msg(args.text()?),
+            "rcm" => Self::Rcm(args.text()?),
+            "rcm_ply" => Self::RcmPlayer(args.word("username")?.to_owned()),
+            "rcm_all" => Self::RcmAll,
+            "rcc_ply" => Self::RccPlayer(args.word("username")?.to_owned()),
+            "rcc_all" => Self::RccAll,
+            "ai" if args.rest.trim().is_empty() => Self::Ai(None),
+            "ai" => Self::Ai(Some(args.text()?)),
+            "car" => Self::Car(args.parse("car")?),
+            name => match Penalty::ALL.into_iter().find(|p| p.command() == name) {
+                Some(penalty) => Self::Penalty(penalty, args.word("username")?.to_owned()),
+                None => return Ok(Self::Other(s.to_owned())),
+            },
+        };
+        args.end(cmd)
+    }
+}
+
+impl TryFrom<HostCommand> for insim::Packet {
+    type Error = CommandError;
+
+    fn try_from(value: HostCommand) -> Result<Self, Self::Error> {
+        value.to_packet()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::Month;
+
+    #[test]
+    fn test_round_trip() {
+        let commands = [
+            HostCommand::End,
+            HostCommand::SpecAll,
+            HostCommand::Track(Track::Bl1),
+            HostCommand::Laps(Some(5)),
+            HostCommand::Laps(None),
+            HostCommand::Grid(GridMode::Slf),
+            HostCommand::GridReal(true),
+            HostCommand::GridAi(false),
+            HostCommand::Qual(0),
+            HostCommand::MustPit(true),
+            HostCommand::CanReset(false),
+            HostCommand::Start(StartOrder::Reverse),
+            HostCommand::Autokick(AutoKick::Spec),
+            HostCommand::AxLoad("my layout".into()),
+            HostCommand::Kick("bob".into()),
+            HostCommand::Ban("bob".into(), 3),
+            HostCommand::Penalty(Penalty::DriveThrough, "bob".into()),
+            HostCommand::Penalty(Penalty::Clear, "bob".into()),
+            HostCommand::Msg("hello everyone".into()),
+            HostCommand::Rcm("^1Yellow flag".into()),
+            HostCommand::Ai(None),
+            HostCommand::Ai(Some("Bot 1".into())),
+            HostCommand::Car(Vehicle::Xrt),
+            HostCommand::Other("/cars XRT+FXO".into()),
+            HostCommand::Say("hello".into()),
+        ];
+        for cmd in commands {
+            let text = cmd.to_string();
+            assert_eq!(text.parse::<HostCommand>(), Ok(cmd.clone()), "{text}");
+            assert!(cmd.to_packet().is_ok(), "{text}");
+        }
+    }
+
+    #[test]
+    fn test_time_round_trip() {
+        let commands = [
+            (TimeCommand::Status, "/time"),
+            (TimeCommand::Live, "/time live"),
+            (
+                TimeCommand::Offset {
+                    days: Some(-2),
+                    minutes: Some(90),
+                },
+                "/time offset -2 +1:30",
+            ),
+            (
+                TimeCommand::Offset {
+                    days: None,
+                    minutes: Some(-5),
+                },
+                "/time offset -0:05",
+            ),
+            (
+                TimeCommand::Set(TimeSet {
+                    date: Some((23, Month::Jan)),
+                    time: Some((16, 0)),
+                    utc_offset: Some(-3),
+                }),
+                "/time set 23 Jan 16:00 utc-3",
+            ),
+            (TimeCommand::Set(TimeSet::default()), "/time set"),
+            (TimeCommand::Mul(10), "/time mul 10"),
+            (
+                TimeCommand::Demo(TimeDemoPreset::Sunset),
+                "/time demo sunset",
+            ),
+        ];
+        for (time, text) in commands {
+            let cmd = HostCommand::Time(time);
+            assert_eq!(cmd.to_string(), text);
+            assert_eq!(text.parse::<HostCommand>(), Ok(cmd.clone()), "{text}");
+
+            let packet = cmd.to_packet().unwrap();
+            assert_eq!(HostCommand::from_packet(&packet), Some(Ok(cmd)));
+        }
+
+        assert!("/time offset +1 +2".parse::<HostCommand>().is_err());
+        assert!("/time set 23".parse::<HostCommand>().is_err());
+        assert!("/time later".parse::<HostCommand>().is_err());
+    }
+
+    #[test]
+    fn test_format() {
+        assert_eq!(HostCommand::Ban("bob".into(), 0).to_string(), "/ban bob 0");
+        assert_eq!(
+            HostCommand::Penalty(Penalty::Seconds30, "bob".into()).to_string(),
+            "/p_30 bob"
+        );
+        assert_eq!(HostCommand::ForceCockpitView(true).to_string(), "/fcv yes");
+        assert_eq!(HostCommand::Track(Track::Fe1r).to_string(), "/track FE1R");
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            "/kick".parse::<HostCommand>(),
+            Err(CommandError::InvalidArgument { command, .. }) if command == "kick"
+        ));
+        assert!("/kick bob alice".parse::<HostCommand>().is_err());
+        assert!("/laps many".parse::<HostCommand>().is_err());
+        assert!("/mustpit maybe".parse::<HostCommand>().is_err());
+        assert!("/track NOPE".parse::<HostCommand>().is_err());
+    }
+
+    #[test]
+    fn test_to_packet() {
+        assert!(matches!(
+            HostCommand::Kick("bob".into()).to_packet(),
+            Ok(insim::Packet::Mst(Mst { msg, .. })) if msg == "/kick bob"
+        ));
+
+        let long = "a".repeat(80);
+        assert!(matches!(
+            HostCommand::Say(long.clone()).to_packet(),
+            Ok(insim::Packet::Msx(_))
+        ));
+        assert!(matches!(
+            HostCommand::Msg(long).to_packet(),
+            Err(CommandError::TooLong {
+                max: MST_MAX_LEN,
+                ..
+            })
+        ));
+        assert!(matches!(
+            HostCommand::Say("a".repeat(96)).to_packet(),
+            Err(CommandError::TooLong {
+                max: MSX_MAX_LEN,
+                ..
+            })
+        ));
+
+        // Would be sent as a different command
+        assert!(HostCommand::Kick("bob alice".into()).to_packet().is_err());
+        assert!(HostCommand::Say("/kick bob".into()).to_packet().is_err());
+    }
+
+    #[test]
+    fn test_from_packet() {
+        let packet = HostCommand::Spec("bob".into()).to_packet().unwrap();
+        assert_eq!(
+            HostCommand::from_packet(&packet),
+            Some(Ok(HostCommand::Spec("bob".into())))
+        );
+        assert_eq!(
+            HostCommand::from_packet(&insim::insim::TinyType::Ping.into()),
+            None
+        );
+    }
+}
@@ -1,6 +1,9 @@
 //! Standalone/Reusable Insim components.
 
+pub mod ai;
 pub mod chat;
+pub mod command;
+pub mod replay;
 #[allow(missing_docs)]
 pub mod ui;
 pub mod world;
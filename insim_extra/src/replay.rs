@@ -0,0 +1,420 @@
+//! Replay playback automation.
+//!
+//! [`ReplayController`] drives MPR and SPR playback through a spawned connection: loading a
+//! replay, seeking, and stepping through it at fixed intervals collecting [`Mci`] and [`Nlp`]
+//! at each step. Each request waits for LFS's matching [`Rip`] or [`Ssh`](insim::insim::Ssh)
+//! reply, so that a whole archive of replays can be processed unattended.
+//!
+//! ```rust,ignore
+//! let (handle, _) = insim::tcp("127.0.0.1:29999").spawn();
+//! let mut replays = ReplayController::new(handle);
+//! let _ = replays.load("race1.mpr").await?;
+//! let steps = Steps::every(Duration::from_secs(1)).screenshot_at(Duration::from_secs(30));
+//! for frame in replays.run(&steps).await? {
+//!     println!("{:?}: {} cars", frame.time, frame.mci.iter().map(|m| m.info.len()).sum::<usize>());
+//! }
+//! ```
+
+use std::time::Duration;
+
+use insim::{
+    insim::{Mci, Nlp, Rip, RipError, RipOptions, SshError},
+    net::tokio_impl::Handle,
+    request::{MultiCarInfo, NodeLap, Replay, ReplayControl, Request, Screenshot},
+};
+
+/// Errors from a [`ReplayController`].
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum ReplayError {
+    /// LFS refused or failed a replay request
+    #[error("Replay request failed: {0:?}")]
+    Rip(RipError),
+
+    /// LFS failed to take a screenshot
+    #[error("Screenshot failed: {0:?}")]
+    Screenshot(SshError),
+
+    /// The connection failed, or LFS did not reply in time
+    #[error(transparent)]
+    Insim(#[from] insim::Error),
+}
+
+/// Times to step through a replay at, see [`ReplayController::run`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Steps {
+    start: Duration,
+    end: Option<Duration>,
+    interval: Duration,
+    marks: Vec<Duration>,
+}
+
+impl Steps {
+    /// Step through the whole replay every `interval`.
+    ///
+    /// # Panics
+    ///
+    /// If `interval` is zero.
+    pub fn every(interval: Duration) -> Self {
+        assert!(!interval.is_zero(), "Steps interval must be non-zero");
+        Self {
+            start: Duration::ZERO,
+            end: None,
+            interval,
+            marks: Vec::new(),
+        }
+    }
+
+    /// Start at `start`, rather than the beginning of the replay.
+    pub fn from(mut self, start: Duration) -> Self {
+        self.start = start;
+        self
+    }
+
+    /// Stop at `end`, rather than the end of the replay.
+    pub fn until(mut self, end: Duration) -> Self {
+        self.end = Some(end);
+        self
+    }
+
+    /// Also stop at `mark` and take a screenshot.
+    pub fn screenshot_at(mut self, mark: Duration) -> Self {
+        self.marks.push(mark);
+        self
+    }
+
+    /// Every time to stop at, in order, for a replay `length` long, and whether to take a
+    /// screenshot there.
+    pub fn times(&self, length: Duration) -> Vec<(Duration, bool)> {
+        let end = self.end.map_or(length, |end| end.min(length));
+        let mut times: Vec<(Duration, bool)> =
+            std::iter::successors(Some(self.start), |t| t.checked_add(self.interval))
+                .take_while(|t| *t <= end)
+                .map(|t| (t, false))
+                .collect();
+        times.extend(
+            self.marks
+                .iter()
+                .filter(|mark| (self.start..=end).contains(*mark))
+                .map(|mark| (*mark, true)),
+        );
+        // Screenshots sort after, and so replace, a step at the same time
+        times.sort();
+        times.dedup_by(|later, earlier| {
+            let duplicate = later.0 == earlier.0;
+            earlier.1 |= duplicate && later.1;
+            duplicate
+        });
+        times
+    }
+}
+
+/// Everything collected at a single step, see [`ReplayController::step`].
+#[derive(Debug, Clone, Default)]
+pub struct ReplayFrame {
+    /// Position in the replay
+    pub time: Duration,
+    /// Multi car info, one per 16 cars on track
+    pub mci: Vec<Mci>,
+    /// Node and lap info, if anyone is on track
+    pub nlp: Vec<Nlp>,
+    /// Name of the screenshot taken, if any
+    pub screenshot: Option<String>,
+}
+
+/// Controls replay playback. See the [module documentation](self).
+#[derive(Debug, Clone)]
+pub struct ReplayController {
+    handle: Handle,
+    options: RipOptions,
+    timeout: Duration,
+    mpr: bool,
+}
+
+impl ReplayController {
+    /// New controller, sending requests through `handle`.
+    pub fn new(handle: Handle) -> Self {
+        Self {
+            handle,
+            options: RipOptions::empty(),
+            timeout: Duration::from_secs(60),
+            mpr: true,
+        }
+    }
+
+    /// Options to load and play replays with. Defaults to none.
+    pub fn options(mut self, options: RipOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// How long to wait for each reply. Seeking a long way, particularly backwards in an SPR,
+    /// can take some time. Defaults to 60 seconds.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Load a replay and pause at the start, returning its status. `name` is the file name in
+    /// LFS's replay directory. A `.spr` extension loads a single player replay, anything
+    /// else a multiplayer replay.
+    pub async fn load(&mut self, name: &str) -> Result<Rip, ReplayError> {
+        let (name, mpr) = match name
+            .len()
+            .checked_sub(4)
+            .and_then(|i| name.split_at_checked(i))
+        {
+            Some((stem, ext)) if ext.eq_ignore_ascii_case(".spr") => (stem, false),
+            Some((stem, ext)) if ext.eq_ignore_ascii_case(".mpr") => (stem, true),
+            _ => (name, true),
+        };
+        self.mpr = mpr;
+        self.control(Rip {
+            rname: Some(name.to_owned()),
+            ..self.rip(Duration::ZERO, true)
+        })
+        .await
+    }
+
+    /// Seek to `time` in the current replay and pause there.
+    pub async fn seek(&self, time: Duration) -> Result<Rip, ReplayError> {
+        self.control(self.rip(time, true)).await
+    }
+
+    /// Pause or resume playback of the current replay, at the current position.
+    pub async fn pause(&self, paused: bool) -> Result<Rip, ReplayError> {
+        let status = self.status().await?;
+        self.control(self.rip(status.ctime, paused)).await
+    }
+
+    /// Status of the current replay.
+    pub async fn status(&self) -> Result<Rip, ReplayError> {
+        self.request(Replay).await
+    }
+
+    /// Take a screenshot, returning its file name. An empty `name` lets LFS choose one.
+    pub async fn screenshot(&self, name: &str) -> Result<String, ReplayError> {
+        let ssh = self.request(Screenshot(name.to_owned())).await?;
+        match ssh.error {
+            SshError::Ok => Ok(ssh.name),
+            error => Err(ReplayError::Screenshot(error)),
+        }
+    }
+
+    /// Seek to `time` and collect the [`Mci`] and [`Nlp`] there, optionally taking a
+    /// screenshot.
+    pub async fn step(&self, time: Duration, screenshot: bool) -> Result<ReplayFrame, ReplayError> {
+        let _ = self.seek(time).await?;
+        let mci = self.handle.request(MultiCarInfo, self.timeout).await?;
+        let nlp = self.handle.request(NodeLap, self.timeout).await?;
+        let screenshot = if screenshot {
+            Some(self.screenshot("").await?)
+        } else {
+            None
+        };
+        Ok(ReplayFrame {
+            time,
+            mci,
+            nlp,
+            screenshot,
+        })
+    }
+
+    /// Step through the current replay, returning each [`ReplayFrame`] in order.
+    pub async fn run(&self, steps: &Steps) -> Result<Vec<ReplayFrame>, ReplayError> {
+        let length = self.status().await?.ttime;
+        let mut frames = Vec::new();
+        for (time, screenshot) in steps.times(length) {
+            frames.push(self.step(time, screenshot).await?);
+        }
+        Ok(frames)
+    }
+
+    fn rip(&self, ctime: Duration, paused: bool) -> Rip {
+        Rip {
+            mpr: self.mpr,
+            paused,
+            options: self.options,
+            ctime,
+            ..Default::default()
+        }
+    }
+
+    async fn control(&self, rip: Rip) -> Result<Rip, ReplayError> {
+        let reply = self.request(ReplayControl(rip)).await?;
+        match reply.error {
+            RipError::Ok | RipError::Already => Ok(reply),
+            error => Err(ReplayError::Rip(error)),
+        }
+    }
+
+    async fn request<R: Request>(&self, request: R) -> Result<R::Reply, ReplayError> {
+        let description = format!("{request:?}");
+        self.handle
+            .request(request, self.timeout)
+            .await?
+            .pop()
+            .ok_or_else(|| insim::Error::Timeout(format!("No reply to {description}")).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insim::{
+        Packet,
+        identifiers::PlayerId,
+        insim::{CompCar, Mci, Nlp, NodeLapInfo, Ssh, Tiny, TinyType},
+        testing::{FakeHost, Script},
+    };
+
+    use super::*;
+
+    fn secs(secs: u64) -> Duration {
+        Duration::from_secs(secs)
+    }
+
+    #[test]
+    fn test_steps_times() {
+        let steps = Steps::every(secs(10))
+            .from(secs(5))
+            .screenshot_at(secs(15))
+            .screenshot_at(secs(20))
+            .screenshot_at(secs(100));
+        assert_eq!(
+            steps.times(secs(40)),
+            [
+                (secs(5), false),
+                (secs(15), true),
+                (secs(20), true),
+                (secs(25), false),
+                (secs(35), false),
+            ]
+        );
+        assert_eq!(
+            steps.until(secs(16)).times(secs(40)),
+            [(secs(5), false), (secs(15), true)]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_load_and_step() {
+        let rip_reply = |rip: Rip| {
+            move |p: &Packet| match p {
+                Packet::Rip(req) => Some(
+                    Rip {
+                        reqi: req.reqi,
+                        ..rip.clone()
+                    }
+                    .into(),
+                ),
+                _ => None,
+            }
+        };
+        let host = FakeHost::new()
+            .script(
+                Script::new()
+                    .reply(rip_reply(Rip {
+                        mpr: false,
+                        paused: true,
+                        ttime: secs(60),
+                        rname: Some("race".into()),
+                        ..Default::default()
+                    }))
+                    .reply(rip_reply(Rip {
+                        error: RipError::DestOOB,
+                        ..Default::default()
+                    }))
+                    .reply(rip_reply(Rip {
+                        ctime: secs(30),
+                        ..Default::default()
+                    }))
+                    .reply(|p| match p {
+                        Packet::Tiny(Tiny {
+                            subt: TinyType::Mci,
+                            reqi,
+                        }) => Some(
+                            Mci {
+                                reqi: *reqi,
+                                info: vec![CompCar {
+                                    plid: PlayerId(3),
+                                    node: 12,
+                                    lap: 2,
+                                    position: 1,
+                                    ..Default::default()
+                                }],
+                            }
+                            .into(),
+                        ),
+                        _ => None,
+                    })
+                    .reply(|p| match p {
+                        Packet::Tiny(Tiny {
+                            subt: TinyType::Nlp,
+                            reqi,
+                        }) => Some(
+                            Nlp {
+                                reqi: *reqi,
+                                info: vec![NodeLapInfo {
+                                    plid: PlayerId(3),
+                                    node: 12,
+                                    lap: 2,
+                                    position: 1,
+                                }],
+                            }
+                            .into(),
+                        ),
+                        _ => None,
+                    })
+                    .reply(|p| match p {
+                        Packet::Ssh(req) => Some(
+                            Ssh {
+                                reqi: req.reqi,
+                                name: "shot".into(),
+                                ..Default::default()
+                            }
+                            .into(),
+                        ),
+                        _ => None,
+                    }),
+            )
+            .listen_tcp()
+            .await
+            .unwrap();
+
+        let (handle, _) = insim::tcp(host.local_addr()).spawn();
+        let mut replays = ReplayController::new(handle).timeout(secs(5));
+
+        let status = replays.load("race.spr").await.unwrap();
+        assert_eq!(status.ttime, secs(60));
+
+        assert!(matches!(
+            replays.seek(secs(90)).await,
+            Err(ReplayError::Rip(RipError::DestOOB))
+        ));
+
+        let frame = replays.step(secs(30), true).await.unwrap();
+        assert_eq!(frame.time, secs(30));
+        assert_eq!(frame.mci.len(), 1);
+        assert_eq!(frame.mci[0].info.len(), 1);
+        assert_eq!(frame.mci[0].info[0].plid, PlayerId(3));
+        assert_eq!(frame.mci[0].info[0].node, 12);
+        assert_eq!(frame.nlp.len(), 1);
+        assert_eq!(frame.nlp[0].info.len(), 1);
+        assert_eq!(frame.nlp[0].info[0].plid, PlayerId(3));
+        assert_eq!(frame.nlp[0].info[0].lap, 2);
+        assert_eq!(frame.screenshot.as_deref(), Some("shot"));
+
+        let rips: Vec<Rip> = host
+            .received()
+            .into_iter()
+            .filter_map(|p| match p {
+                Packet::Rip(rip) => Some(rip),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(rips[0].rname.as_deref(), Some("race"));
+        assert!(!rips[0].mpr);
+        assert!(rips[0].paused);
+        assert_eq!(rips[2].ctime, secs(30));
+    }
+}
@@ -5,6 +5,8 @@ use insim::{
     insim::{Msl, Mst, Mtc, SoundType},
 };
 
+use crate::command::HostCommand;
+
 /// Build a message-to-connection packet.
 ///
 /// - `ucid: Some(u)` targets a specific UCID (use `0` for host console only, `255` for all).
@@ -37,3 +39,9 @@ pub fn host_command(text: impl Into<String>) -> insim::Packet {
         ..Default::default()
     })
 }
+
+/// Build the packet for a [`HostCommand`], without the checks made by
+/// [`HostCommand::to_packet`].
+pub(crate) fn send_command(command: HostCommand) -> insim::Packet {
+    host_command(command.to_string())
+}
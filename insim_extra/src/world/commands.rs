@@ -1,8 +1,8 @@
 //! Stateless host-command builders for [`World`].
 //!
-//! Each method formats an LFS admin command (or the packets implementing one)
-//! and returns it for the caller to send; none of them read or mutate world
-//! state. They are split out from the state-mirror logic in the parent module
+//! Each method builds an LFS admin command, as a [`HostCommand`] (or the packets
+//! implementing one), and returns it for the caller to send; none of them read
+//! or mutate world state. They are split out from the state-mirror logic in the parent module
 //! purely for readability.
 
 use insim::{
@@ -12,157 +12,134 @@ use insim::{
 };
 
 use super::{GridMode, TimeDemoPreset, TimeSet, World};
-use crate::util::host_command;
+use crate::{
+    command::{HostCommand, TimeCommand},
+    util::send_command,
+};
 
 impl World {
     /// `/end` - finish the current race.
     pub fn end(&self) -> insim::Packet {
-        host_command("/end")
+        send_command(HostCommand::End)
     }
 
     /// `/clear` - remove all connections from the server.
     pub fn clear(&self) -> insim::Packet {
-        host_command("/clear")
+        send_command(HostCommand::Clear)
     }
 
     /// `/track {track}` - load a different track.
     pub fn change_track(&self, track: Track) -> insim::Packet {
-        host_command(format!("/track {track}"))
+        send_command(HostCommand::Track(track))
     }
 
     /// Change race length. Maps onto `/laps`, `/hours`, or `/laps no`.
     pub fn change_laps(&self, laps: RaceLaps) -> insim::Packet {
-        let cmd = match laps {
-            RaceLaps::Untimed => "/laps no".to_string(),
-            RaceLaps::Hours(h) => format!("/hours {h}"),
-            other => format!("/laps {}", Into::<u8>::into(other)),
-        };
-        host_command(cmd)
+        send_command(match laps {
+            RaceLaps::Untimed => HostCommand::Laps(None),
+            RaceLaps::Hours(h) => HostCommand::Hours(h.clamp(1, 48) as u8),
+            other => HostCommand::Laps(Some(other.into())),
+        })
     }
 
     /// `/wind {wind}` - set wind strength (0..=2 typically).
     pub fn change_wind(&self, wind: u8) -> insim::Packet {
-        host_command(format!("/wind {wind}"))
+        send_command(HostCommand::Wind(wind))
     }
 
     /// `/axclear` - clear the autocross layout.
     pub fn ax_clear(&self) -> insim::Packet {
-        host_command("/axclear")
+        send_command(HostCommand::AxClear)
     }
 
     /// `/axload {layout}` - load an autocross layout by name.
     pub fn ax_load(&self, layout: impl Into<String>) -> insim::Packet {
-        host_command(format!("/axload {}", layout.into()))
+        send_command(HostCommand::AxLoad(layout.into()))
     }
 
     /// `/restart` - start a race.
     pub fn restart(&self) -> insim::Packet {
-        host_command("/restart")
+        send_command(HostCommand::Restart)
     }
 
     /// `/qualify` - start qualifying.
     pub fn qualify(&self) -> insim::Packet {
-        host_command("/qualify")
+        send_command(HostCommand::Qualify)
     }
 
     /// `/reinit` - full restart, kicks all connections.
     pub fn reinit(&self) -> insim::Packet {
-        host_command("/reinit")
+        send_command(HostCommand::Reinit)
     }
 
     /// `/weather {weather}` - set weather/lighting.
     pub fn change_weather(&self, weather: u8) -> insim::Packet {
-        host_command(format!("/weather {weather}"))
+        send_command(HostCommand::Weather(weather))
     }
 
     /// `/qual {minutes}` - set qualifying duration. `0` = no qualifying.
     pub fn change_qual(&self, minutes: u8) -> insim::Packet {
-        host_command(format!("/qual {minutes}"))
+        send_command(HostCommand::Qual(minutes))
     }
 
     /// `/time` - report the current in-game time status.
     pub fn time_status(&self) -> insim::Packet {
-        host_command("/time")
+        send_command(HostCommand::Time(TimeCommand::Status))
     }
 
     /// `/time live` - switch to live (real-world) time.
     pub fn time_live(&self) -> insim::Packet {
-        host_command("/time live")
+        send_command(HostCommand::Time(TimeCommand::Live))
     }
 
     /// `/time offset [days] [HH:MM]` - shift in-game time by an offset.
     pub fn time_offset(&self, days: Option<i32>, minutes: Option<i32>) -> insim::Packet {
-        let mut cmd = String::from("/time offset");
-        if let Some(d) = days {
-            let sign = if d < 0 { '-' } else { '+' };
-            cmd.push_str(&format!(" {sign}{}", d.unsigned_abs()));
-        }
-        if let Some(m) = minutes {
-            let sign = if m < 0 { '-' } else { '+' };
-            let abs = m.unsigned_abs();
-            cmd.push_str(&format!(" {sign}{}:{:02}", abs / 60, abs % 60));
-        }
-        host_command(cmd)
+        send_command(HostCommand::Time(TimeCommand::Offset { days, minutes }))
     }
 
     /// `/time set [DD Mon] [HH:MM] [utc±offset]` - set in-game time explicitly.
     pub fn time_set(&self, params: TimeSet) -> insim::Packet {
-        let mut cmd = String::from("/time set");
-        if let Some((day, month)) = params.date {
-            cmd.push_str(&format!(" {day} {month}"));
-        }
-        if let Some((hour, minute)) = params.time {
-            cmd.push_str(&format!(" {hour:02}:{minute:02}"));
-        }
-        if let Some(off) = params.utc_offset {
-            let sign = if off < 0 { '-' } else { '+' };
-            cmd.push_str(&format!(" utc{sign}{}", off.unsigned_abs()));
-        }
-        host_command(cmd)
+        send_command(HostCommand::Time(TimeCommand::Set(params)))
     }
 
     /// `/time mul {0..=240}` - set the time multiplier (set-time mode only).
     pub fn time_multiplier(&self, factor: u8) -> insim::Packet {
-        host_command(format!("/time mul {factor}"))
+        send_command(HostCommand::Time(TimeCommand::Mul(factor)))
     }
 
     /// `/time demo {preset}` - activate a demo time-of-day preset.
     pub fn time_demo(&self, preset: TimeDemoPreset) -> insim::Packet {
-        host_command(format!("/time demo {preset}"))
+        send_command(HostCommand::Time(TimeCommand::Demo(preset)))
     }
 
     /// `/pit_all` - send every player to the pits.
     pub fn pit_all(&self) -> insim::Packet {
-        host_command("/pit_all")
+        send_command(HostCommand::PitAll)
     }
 
     /// `/spec_all` - spectate all players.
     pub fn spec_all(&self) -> insim::Packet {
-        host_command("/spec_all")
+        send_command(HostCommand::SpecAll)
     }
 
     /// `/grid open|self|lock` - set who can modify the grid in the game setup screen.
     pub fn change_grid(&self, mode: GridMode) -> insim::Packet {
-        host_command(format!("/grid {mode}"))
+        send_command(HostCommand::Grid(mode))
     }
 
     /// `/grid real yes` / `/grid real no` - allow or disallow real players joining.
     pub fn change_grid_real(&self, allow: bool) -> insim::Packet {
-        host_command(if allow {
-            "/grid real yes"
-        } else {
-            "/grid real no"
-        })
+        send_command(HostCommand::GridReal(allow))
     }
 
     /// `/grid ai yes` / `/grid ai no` - allow or disallow AI players joining.
     pub fn change_grid_ai(&self, allow: bool) -> insim::Packet {
-        host_command(if allow { "/grid ai yes" } else { "/grid ai no" })
+        send_command(HostCommand::GridAi(allow))
     }
 
     /// `/flood yes` / `/flood no` - switch floodlights on or off.
     pub fn change_flood(&self, on: bool) -> insim::Packet {
-        host_command(if on { "/flood yes" } else { "/flood no" })
+        send_command(HostCommand::Flood(on))
     }
 
     /// Apply vehicle restrictions server-wide (ucid = `ConnectionId::ALL`).
@@ -196,7 +173,7 @@ impl World {
 
     /// Returns an `/unban` packet.
     pub fn unban(&self, uname: impl Into<String>) -> insim::Packet {
-        host_command(format!("/unban {}", uname.into()))
+        send_command(HostCommand::Unban(uname.into()))
     }
 
     /// Returns the packets needed to set and display a Race Control Message for
@@ -206,8 +183,8 @@ impl World {
     /// [`ConnectionInfo::send_rcm`](crate::world::ConnectionInfo::send_rcm).
     pub fn rcm_all(&self, message: &str) -> Vec<insim::Packet> {
         vec![
-            host_command(format!("/rcm {message}")),
-            host_command("/rcm_all"),
+            send_command(HostCommand::Rcm(message.to_owned())),
+            send_command(HostCommand::RcmAll),
         ]
     }
 
@@ -217,6 +194,58 @@ impl World {
     /// For a single connection use
     /// [`ConnectionInfo::clear_rcm`](crate::world::ConnectionInfo::clear_rcm).
     pub fn clear_rcm_all(&self) -> insim::Packet {
-        host_command("/rcc_all")
+        send_command(HostCommand::RccAll)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insim::insim::Mst;
+
+    use super::*;
+    use crate::world::Month;
+
+    fn text(packet: insim::Packet) -> String {
+        let insim::Packet::Mst(Mst { msg, .. }) = packet else {
+            panic!("expected Mst, found {packet:?}");
+        };
+        msg
+    }
+
+    #[test]
+    fn test_commands_are_host_commands() {
+        let world = World::new();
+        let cases = [
+            (world.change_laps(RaceLaps::Untimed), "/laps no"),
+            (world.change_laps(RaceLaps::Laps(5)), "/laps 5"),
+            (world.change_laps(RaceLaps::Hours(2)), "/hours 2"),
+            (world.change_grid(GridMode::Slf), "/grid self"),
+            (world.change_grid_real(false), "/grid real no"),
+            (
+                world.time_offset(Some(1), Some(-90)),
+                "/time offset +1 -1:30",
+            ),
+            (
+                world.time_set(TimeSet {
+                    date: Some((1, Month::Feb)),
+                    time: Some((9, 5)),
+                    utc_offset: None,
+                }),
+                "/time set 1 Feb 09:05",
+            ),
+            (
+                world.time_demo(TimeDemoPreset::Morning),
+                "/time demo morning",
+            ),
+        ];
+        for (packet, expected) in cases {
+            let msg = text(packet);
+            assert_eq!(msg, expected);
+            // every command can be recognised again
+            assert!(!matches!(
+                msg.parse::<HostCommand>(),
+                Ok(HostCommand::Other(_)) | Err(_)
+            ));
+        }
     }
 }
@@ -8,7 +8,10 @@ use std::net::Ipv4Addr;
 
 use insim::{core::vehicle::Vehicle, identifiers::ConnectionId, insim::PenaltyInfo};
 
-use crate::util::host_command;
+use crate::{
+    command::{HostCommand, Penalty},
+    util::send_command,
+};
 
 /// Per-connection record stored by [`World`](crate::world::World).
 #[derive(Debug, Clone)]
@@ -70,53 +73,56 @@ pub use player_info::PlayerInfo;
 impl ConnectionInfo {
     /// Returns a `/kick` packet for this connection.
     pub fn kick(&self) -> insim::Packet {
-        host_command(format!("/kick {}", self.uname))
+        send_command(HostCommand::Kick(self.uname.clone()))
     }
 
     /// Returns a `/ban` packet. `ban_days = 0` means 12 hours (LFS convention).
     pub fn ban(&self, ban_days: u32) -> insim::Packet {
-        host_command(format!("/ban {} {ban_days}", self.uname))
+        send_command(HostCommand::Ban(self.uname.clone(), ban_days))
     }
 
     /// Returns a `/spec` packet for this connection.
     pub fn spec(&self) -> insim::Packet {
-        host_command(format!("/spec {}", self.uname))
+        send_command(HostCommand::Spec(self.uname.clone()))
     }
 
     /// Returns a `/pitlane` packet for this connection.
     pub fn pitlane(&self) -> insim::Packet {
-        host_command(format!("/pitlane {}", self.uname))
+        send_command(HostCommand::Pitlane(self.uname.clone()))
     }
 
     /// Returns a `/p_clear` packet for this connection.
     pub fn clear_penalty(&self) -> insim::Packet {
-        host_command(format!("/p_clear {}", self.uname))
+        send_command(HostCommand::Penalty(Penalty::Clear, self.uname.clone()))
     }
 
     /// Returns a penalty packet for this connection, or `None` for unissueable
     /// penalty variants.
     pub fn give_penalty(&self, penalty: PenaltyInfo) -> Option<insim::Packet> {
-        let cmd = match penalty {
-            PenaltyInfo::Dt => format!("/p_dt {}", self.uname),
-            PenaltyInfo::Sg => format!("/p_sg {}", self.uname),
-            PenaltyInfo::Seconds30 => format!("/p_30 {}", self.uname),
-            PenaltyInfo::Seconds45 => format!("/p_45 {}", self.uname),
+        let penalty = match penalty {
+            PenaltyInfo::Dt => Penalty::DriveThrough,
+            PenaltyInfo::Sg => Penalty::StopGo,
+            PenaltyInfo::Seconds30 => Penalty::Seconds30,
+            PenaltyInfo::Seconds45 => Penalty::Seconds45,
             _ => return None,
         };
-        Some(host_command(cmd))
+        Some(send_command(HostCommand::Penalty(
+            penalty,
+            self.uname.clone(),
+        )))
     }
 
     /// Returns the packets needed to set and display a Race Control Message for
     /// this connection. Send both packets.
     pub fn send_rcm(&self, message: &str) -> Vec<insim::Packet> {
         vec![
-            host_command(format!("/rcm {message}")),
-            host_command(format!("/rcm_ply {}", self.uname)),
+            send_command(HostCommand::Rcm(message.to_owned())),
+            send_command(HostCommand::RcmPlayer(self.uname.clone())),
         ]
     }
 
     /// Returns a packet to clear the Race Control Message for this connection.
     pub fn clear_rcm(&self) -> insim::Packet {
-        host_command(format!("/rcc_ply {}", self.uname))
+        send_command(HostCommand::RccPlayer(self.uname.clone()))
     }
 }
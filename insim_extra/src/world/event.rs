@@ -133,11 +133,14 @@ pub struct AllowedModsChanged {
 
 /// Version information was received (from a `Ver`).
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct VersionReceived {
     /// Product name (e.g. `"S3"`).
     pub product: String,
     /// LFS game version.
     pub version: GameVersion,
+    /// Insim protocol version.
+    pub insimver: u8,
 }
 
 /// Aggregate event produced by [`World::apply_packet`](crate::world::World::apply_packet).
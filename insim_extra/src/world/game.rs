@@ -4,7 +4,7 @@
 //! are defined here. The actual state is owned by
 //! [`World`](crate::world::World), which exposes query methods directly.
 
-use std::time::Duration;
+use std::{str::FromStr, time::Duration};
 
 use insim::{
     core::{game_version::GameVersion, track::Track, vehicle::Vehicle, wind::Wind},
@@ -81,11 +81,21 @@ pub enum MultiplayerState {
 
 /// Version information about the connected LFS instance, from a `Ver` packet.
 #[derive(Debug, Default, Clone)]
+#[non_exhaustive]
 pub struct VersionInfo {
     /// Product name (e.g. `"S3"`).
     pub product: String,
     /// LFS game version.
     pub version: GameVersion,
+    /// Insim protocol version, between [`insim::MIN_VERSION`] and [`insim::VERSION`].
+    pub insimver: u8,
+}
+
+impl VersionInfo {
+    /// Does this Insim version support `packet`, including any fields or flags it uses?
+    pub fn supports(&self, packet: &insim::Packet) -> bool {
+        packet.min_version() <= self.insimver
+    }
 }
 
 /// Snapshot of the game state, produced by [`World::game_info()`](crate::world::World::game_info).
@@ -198,6 +208,19 @@ impl std::fmt::Display for GridMode {
     }
 }
 
+impl FromStr for GridMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "open" => Ok(GridMode::Open),
+            "self" => Ok(GridMode::Slf),
+            "lock" => Ok(GridMode::Lock),
+            _ => Err(format!("expected open, self or lock, but found {s:?}")),
+        }
+    }
+}
+
 /// Month of the year, used in [`TimeSet`].
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Month {
@@ -247,6 +270,28 @@ impl std::fmt::Display for Month {
     }
 }
 
+impl FromStr for Month {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "jan" => Ok(Month::Jan),
+            "feb" => Ok(Month::Feb),
+            "mar" => Ok(Month::Mar),
+            "apr" => Ok(Month::Apr),
+            "may" => Ok(Month::May),
+            "jun" => Ok(Month::Jun),
+            "jul" => Ok(Month::Jul),
+            "aug" => Ok(Month::Aug),
+            "sep" => Ok(Month::Sep),
+            "oct" => Ok(Month::Oct),
+            "nov" => Ok(Month::Nov),
+            "dec" => Ok(Month::Dec),
+            _ => Err(format!("expected a month such as Jan, but found {s:?}")),
+        }
+    }
+}
+
 /// Parameters for [`World::time_set`](crate::world::World::time_set).
 ///
 /// All fields are optional; only the parts that are `Some` are included in the
@@ -260,7 +305,7 @@ impl std::fmt::Display for Month {
 ///     utc_offset: Some(5),
 /// })
 /// ```
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
 pub struct TimeSet {
     /// Day and month `(1..=31, Month)`. Both must be provided together.
     pub date: Option<(u8, Month)>,
@@ -291,3 +336,18 @@ impl std::fmt::Display for TimeDemoPreset {
         write!(f, "{s}")
     }
 }
+
+impl FromStr for TimeDemoPreset {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "morning" => Ok(TimeDemoPreset::Morning),
+            "afternoon" => Ok(TimeDemoPreset::Afternoon),
+            "sunset" => Ok(TimeDemoPreset::Sunset),
+            _ => Err(format!(
+                "expected morning, afternoon or sunset, but found {s:?}"
+            )),
+        }
+    }
+}
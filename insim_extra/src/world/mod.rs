@@ -259,6 +259,7 @@ impl WorldInner {
         self.game.version = Some(VersionInfo {
             product: ver.product.clone(),
             version: ver.version.clone(),
+            insimver: ver.insimver,
         });
     }
 
@@ -468,6 +469,7 @@ fn dispatch(inner: &mut WorldInner, packet: &insim::Packet, events: &mut Vec<Wor
             events.push(WorldEvent::VersionReceived(VersionReceived {
                 product: ver.product.clone(),
                 version: ver.version.clone(),
+                insimver: ver.insimver,
             }));
         },
         Packet::Lap(v) => push_race!(inner.apply_lap(v)),
@@ -791,15 +793,89 @@ mod emission_tests {
     use insim::{
         core::track::Track,
         identifiers::{ConnectionId, PlayerId},
-        insim::{Axi, Ncn, Npl, RaceInProgress, RaceLaps, Rst, Sta, Tiny, TinyType},
+        insim::{Axi, Ncn, Npl, RaceInProgress, RaceLaps, Rst, Sta, Tiny, TinyType, Ver},
     };
 
     use super::{World, WorldEvent};
 
+    #[test]
+    fn replayed_capture_is_deterministic() {
+        use std::{io::Cursor, time::Duration};
+
+        use insim::capture::{CaptureReader, CaptureWriter, Direction, Pace, Replay};
+
+        let mut writer = CaptureWriter::new(Vec::new()).unwrap();
+        for (ms, packet) in [
+            (
+                0,
+                insim::Packet::from(Ncn {
+                    ucid: ConnectionId(1),
+                    uname: "user".into(),
+                    ..Default::default()
+                }),
+            ),
+            (
+                10,
+                Rst {
+                    racelaps: RaceLaps::Laps(5),
+                    ..Default::default()
+                }
+                .into(),
+            ),
+            (
+                20,
+                Npl {
+                    plid: PlayerId(1),
+                    ucid: ConnectionId(1),
+                    nump: 1,
+                    ..Default::default()
+                }
+                .into(),
+            ),
+        ] {
+            let frame = insim::net::Codec::new().encode(&packet).unwrap();
+            writer
+                .write_frame_at(Duration::from_millis(ms), Direction::Incoming, &frame)
+                .unwrap();
+        }
+        let capture = writer.into_inner();
+
+        let replay = || {
+            let world = World::new();
+            let reader = CaptureReader::new(Cursor::new(capture.clone())).unwrap();
+            let events: Vec<String> = Replay::new(reader, Pace::Fast)
+                .flat_map(|captured| world.apply_packet(&captured.unwrap().packet))
+                .map(|event| format!("{event:?}"))
+                .collect();
+            (world.connection_count(), world.player_count(), events)
+        };
+
+        let first = replay();
+        assert_eq!((first.0, first.1), (1, 1));
+        assert!(!first.2.is_empty());
+        assert_eq!(first, replay());
+    }
+
     fn count(events: &[WorldEvent], pred: impl Fn(&WorldEvent) -> bool) -> usize {
         events.iter().filter(|e| pred(e)).count()
     }
 
+    #[test]
+    fn ver_records_insim_version() {
+        let world = World::new();
+        let _ = world.apply_packet(
+            &Ver {
+                insimver: 9,
+                ..Default::default()
+            }
+            .into(),
+        );
+        let version = world.version().unwrap();
+        assert_eq!(version.insimver, 9);
+        assert!(version.supports(&TinyType::Ncn.into()));
+        assert!(!version.supports(&insim::insim::Set::default().into()));
+    }
+
     #[test]
     fn rst_emits_session_started() {
         let world = World::new();
@@ -0,0 +1,103 @@
+//! Per-client packet subscriptions.
+use std::collections::BTreeSet;
+
+/// Which packets a client receives, by the packet's `type`, i.e. `"Mci"` or `"Ncn"`. Types are
+/// matched case insensitively.
+///
+/// A new filter allows every packet. Subscribing to any type restricts the client to only the
+/// types it has subscribed to. Unsubscribing from a type removes it, or, if the client has not
+/// subscribed to anything, excludes it from every packet.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Filter {
+    only: Option<BTreeSet<String>>,
+    except: BTreeSet<String>,
+}
+
+impl Filter {
+    /// A filter which allows every packet.
+    pub fn all() -> Self {
+        Self::default()
+    }
+
+    /// A filter which allows only the given types.
+    pub fn only<I, S>(types: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let mut filter = Self::all();
+        filter.subscribe(types);
+        filter
+    }
+
+    /// Does this filter allow packets of type `ty`?
+    pub fn allows(&self, ty: &str) -> bool {
+        let ty = ty.to_ascii_lowercase();
+        match &self.only {
+            Some(only) => only.contains(&ty),
+            None => !self.except.contains(&ty),
+        }
+    }
+
+    /// Receive packets of each of these types.
+    pub fn subscribe<I, S>(&mut self, types: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let only = self.only.get_or_insert_with(BTreeSet::new);
+        for ty in types {
+            let _ = only.insert(ty.as_ref().to_ascii_lowercase());
+        }
+        self.except.clear();
+    }
+
+    /// Stop receiving packets of each of these types.
+    pub fn unsubscribe<I, S>(&mut self, types: I)
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        for ty in types {
+            let ty = ty.as_ref().to_ascii_lowercase();
+            match &mut self.only {
+                Some(only) => {
+                    let _ = only.remove(&ty);
+                },
+                None => {
+                    let _ = self.except.insert(ty);
+                },
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter() {
+        let mut filter = Filter::all();
+        assert!(filter.allows("Mci"));
+
+        filter.unsubscribe(["mci"]);
+        assert!(!filter.allows("Mci"));
+        assert!(filter.allows("Ncn"));
+
+        filter.subscribe(["Ncn", "Npl"]);
+        assert!(filter.allows("ncn"));
+        assert!(filter.allows("Npl"));
+        assert!(!filter.allows("Mci"));
+        assert!(!filter.allows("Lap"));
+
+        // unsubscribing from everything leaves nothing, rather than everything
+        filter.unsubscribe(["Ncn", "Npl"]);
+        assert!(!filter.allows("Ncn"));
+        assert_eq!(Filter::only(["Lap"]), {
+            let mut f = Filter::all();
+            f.subscribe(["lap"]);
+            f
+        });
+    }
+}
@@ -0,0 +1,459 @@
+//! # insim_gateway
+//!
+//! Exposes a single Insim connection to browsers, and anything else which speaks WebSocket,
+//! as JSON. Every packet received from LFS is pushed to each connected client as a JSON text
+//! message, using the same representation as insim's `serde` feature, i.e.
+//! `{"type":"Ncn","reqi":0,"ucid":1,...}`. Clients may send packets to LFS in the same form.
+//!
+//! ```rust,ignore
+//! let upstream = insim::tcp("127.0.0.1:29999").isi_admin_password("secret".to_string());
+//!
+//! insim_gateway::Gateway::new(upstream)
+//!     .listen("127.0.0.1:29997".parse()?)
+//!     .run()
+//!     .await?;
+//! ```
+//!
+//! By default a client receives every packet. A client may restrict this when connecting, by
+//! listing packet types in the query string, i.e. `ws://127.0.0.1:29997/?types=Mci,Ncn`, or at
+//! any time by sending `{"subscribe":["Mci"]}` or `{"unsubscribe":["Mci"]}`. See [Filter].
+//!
+//! Anything a client sends which cannot be handled is answered with `{"error":"..."}`.
+//!
+//! ## Security
+//!
+//! Browsers allow any web page to open a WebSocket to any address, including localhost. To stop
+//! an arbitrary page from talking to LFS through the gateway:
+//!
+//! - Upgrade requests from browsers are refused with `403 Forbidden`, unless their `Origin` has
+//!   been allowed with [Gateway::allow_origin]. Clients which are not browsers do not send an
+//!   `Origin`, and are always accepted.
+//! - The gateway is read only by default. Packets from clients are only forwarded to LFS after
+//!   calling [Gateway::read_only] with `false`.
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use futures::{SinkExt, StreamExt};
+use insim::{Packet, builder::Builder, net::tokio_impl::Handle};
+use serde_json::{Value, json};
+use tokio::{
+    io::{AsyncRead, AsyncWrite, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::broadcast::{self, error::RecvError},
+};
+use tokio_tungstenite::{
+    WebSocketStream,
+    tungstenite::{
+        Error as WsError, Message, Utf8Bytes,
+        protocol::{CloseFrame, WebSocketConfig, frame::coding::CloseCode},
+    },
+};
+#[cfg(feature = "cli")]
+use {clap as _, tracing_subscriber as _};
+
+mod filter;
+mod ws;
+
+pub use filter::Filter;
+
+/// How long a client has to complete the WebSocket handshake.
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many packets a client may fall behind before it starts missing packets.
+const CLIENT_CAPACITY: usize = 1024;
+
+/// Largest message, after reassembling fragments, we are willing to accept from a client.
+const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+/// Response to a request which could not be upgraded.
+const BAD_REQUEST: &[u8] =
+    b"HTTP/1.1 400 Bad Request\r\nConnection: close\r\nContent-Length: 0\r\n\r\n";
+
+/// A packet received from LFS, serialised once for every client.
+#[derive(Debug)]
+struct Event {
+    ty: String,
+    json: Utf8Bytes,
+}
+
+impl Event {
+    fn new(packet: &Packet) -> serde_json::Result<Self> {
+        let value = serde_json::to_value(packet)?;
+        let ty = value["type"].as_str().unwrap_or_default().to_owned();
+        Ok(Self {
+            ty,
+            json: value.to_string().into(),
+        })
+    }
+}
+
+/// Bridges a single upstream Insim connection to many WebSocket clients.
+#[derive(Debug)]
+pub struct Gateway {
+    upstream: Builder,
+    listen: SocketAddr,
+    read_only: bool,
+    allowed_origins: Vec<String>,
+}
+
+impl Gateway {
+    /// Create a new gateway, which will connect upstream using `upstream`.
+    pub fn new(upstream: Builder) -> Self {
+        Self {
+            upstream,
+            listen: SocketAddr::from(([127, 0, 0, 1], 29997)),
+            read_only: true,
+            allowed_origins: Vec::new(),
+        }
+    }
+
+    /// Address to accept WebSocket clients on. Defaults to 127.0.0.1:29997.
+    pub fn listen(mut self, addr: SocketAddr) -> Self {
+        self.listen = addr;
+        self
+    }
+
+    /// Refuse to forward packets from clients to LFS. Defaults to true.
+    pub fn read_only(mut self, read_only: bool) -> Self {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Accept browsers connecting from pages with this origin, i.e. `http://localhost:8080`.
+    /// May be called multiple times. By default no origins are allowed.
+    pub fn allow_origin<S: Into<String>>(mut self, origin: S) -> Self {
+        self.allowed_origins.push(origin.into());
+        self
+    }
+
+    /// Bind the listening address and run until the upstream connection fails.
+    pub async fn run(self) -> insim::Result<()> {
+        let listener = TcpListener::bind(self.listen).await?;
+        self.serve(listener).await
+    }
+
+    /// Run using an existing listener until the upstream connection fails.
+    pub async fn serve(self, listener: TcpListener) -> insim::Result<()> {
+        let Gateway {
+            upstream,
+            read_only,
+            allowed_origins,
+            ..
+        } = self;
+        let allowed_origins: Arc<[String]> = allowed_origins.into();
+        let (handle, mut subscriber) = upstream.spawn();
+        let (events, _) = broadcast::channel(CLIENT_CAPACITY);
+
+        loop {
+            tokio::select! {
+                packet = subscriber.recv() => {
+                    match Event::new(&packet?) {
+                        // No clients is not an error
+                        Ok(event) => { let _ = events.send(Arc::new(event)); },
+                        Err(e) => tracing::warn!(error = ?e, "failed to serialise packet"),
+                    }
+                },
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, addr)) => {
+                        tracing::debug!(%addr, "client connected");
+                        drop(tokio::spawn(client(
+                            stream,
+                            handle.clone(),
+                            events.subscribe(),
+                            read_only,
+                            allowed_origins.clone(),
+                        )));
+                    },
+                    Err(e) => tracing::warn!(error = ?e, "failed to accept client"),
+                },
+            }
+        }
+    }
+}
+
+/// Drive a single WebSocket client.
+async fn client(
+    mut stream: TcpStream,
+    handle: Handle,
+    mut events: broadcast::Receiver<Arc<Event>>,
+    read_only: bool,
+    allowed_origins: Arc<[String]>,
+) {
+    let mut upgrade = None;
+    #[allow(clippy::result_large_err)] // ErrorResponse is dictated by tungstenite
+    let callback = |request: &_, response| {
+        upgrade = Some(ws::vet(request, &allowed_origins)?);
+        Ok(response)
+    };
+    let config = WebSocketConfig::default().max_message_size(Some(MAX_MESSAGE_LEN));
+    let accepted = tokio::time::timeout(
+        HANDSHAKE_TIMEOUT,
+        tokio_tungstenite::accept_hdr_async_with_config(&mut stream, callback, Some(config)),
+    )
+    .await;
+    let mut ws = match accepted {
+        Ok(Ok(ws)) => ws,
+        // tungstenite has already responded to requests rejected by ws::vet
+        Ok(Err(e @ WsError::Http(_))) => {
+            tracing::debug!(error = %e, "rejecting client");
+            return;
+        },
+        Ok(Err(e)) => {
+            tracing::debug!(error = %e, "rejecting client");
+            let _ = stream.write_all(BAD_REQUEST).await;
+            return;
+        },
+        Err(_) => {
+            tracing::debug!("client did not complete handshake");
+            return;
+        },
+    };
+
+    let mut filter = match upgrade.and_then(|upgrade| upgrade.types) {
+        Some(types) => Filter::only(types),
+        None => Filter::all(),
+    };
+
+    loop {
+        let reply = tokio::select! {
+            message = ws.next() => match message {
+                Some(Ok(Message::Text(text))) => {
+                    match handle_text(&text, &mut filter, &handle, read_only) {
+                        Some(reply) => Message::text(reply),
+                        None => continue,
+                    }
+                },
+                Some(Ok(Message::Binary(_))) => Message::text(error("binary messages are not supported")),
+                // Pings are answered, and closes acknowledged, by tungstenite
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => {
+                    tracing::debug!(error = %e, "closing client");
+                    break;
+                },
+                None => break,
+            },
+            event = events.recv() => match event {
+                Ok(event) if filter.allows(&event.ty) => Message::Text(event.json.clone()),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    tracing::warn!(skipped, "client lagged, skipping packets");
+                    continue;
+                },
+                Err(RecvError::Closed) => {
+                    // The gateway has stopped
+                    close(&mut ws, CloseCode::Away).await;
+                    return;
+                },
+            },
+        };
+        if ws.send(reply).await.is_err() {
+            break;
+        }
+    }
+    close(&mut ws, CloseCode::Normal).await;
+}
+
+/// Close the connection, ignoring errors as the client may already have gone.
+async fn close<S: AsyncRead + AsyncWrite + Unpin>(ws: &mut WebSocketStream<S>, code: CloseCode) {
+    let _ = ws
+        .close(Some(CloseFrame {
+            code,
+            reason: "".into(),
+        }))
+        .await;
+}
+
+/// Handle a text message from a client, returning any reply.
+fn handle_text(
+    text: &str,
+    filter: &mut Filter,
+    handle: &Handle,
+    read_only: bool,
+) -> Option<String> {
+    let value: Value = match serde_json::from_str(text) {
+        Ok(value) => value,
+        Err(e) => return Some(error(format!("invalid JSON: {e}"))),
+    };
+    let Value::Object(object) = &value else {
+        return Some(error("expected an object"));
+    };
+
+    if !object.contains_key("type") {
+        for (key, types) in object {
+            let Some(types) = types.as_array() else {
+                return Some(error(format!("expected an array of types for {key:?}")));
+            };
+            let types = types.iter().filter_map(Value::as_str);
+            match key.as_str() {
+                "subscribe" => filter.subscribe(types),
+                "unsubscribe" => filter.unsubscribe(types),
+                _ => return Some(error(format!("unknown command {key:?}"))),
+            }
+        }
+        return None;
+    }
+
+    if read_only {
+        return Some(error("gateway is read only"));
+    }
+    let packet: Packet = match serde_json::from_value(value) {
+        Ok(packet) => packet,
+        Err(e) => return Some(error(format!("invalid packet: {e}"))),
+    };
+    handle
+        .send(packet)
+        .err()
+        .map(|e| error(format!("failed to send packet: {e}")))
+}
+
+fn error<S: AsRef<str>>(message: S) -> String {
+    json!({ "error": message.as_ref() }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use insim::{
+        insim::{Mso, Mst},
+        testing::{FakeHost, Script},
+    };
+    use tokio::io::AsyncReadExt;
+    use tokio_tungstenite::tungstenite::{
+        client::IntoClientRequest,
+        http::{StatusCode, header::ORIGIN},
+    };
+
+    use super::*;
+
+    type Client = WebSocketStream<TcpStream>;
+
+    async fn try_connect(
+        addr: SocketAddr,
+        target: &str,
+        origin: Option<&str>,
+    ) -> Result<Client, WsError> {
+        let stream = TcpStream::connect(addr).await.unwrap();
+        let mut request = format!("ws://{addr}{target}")
+            .into_client_request()
+            .unwrap();
+        if let Some(origin) = origin {
+            let _ = request
+                .headers_mut()
+                .insert(ORIGIN, origin.parse().unwrap());
+        }
+        tokio_tungstenite::client_async(request, stream)
+            .await
+            .map(|(ws, _)| ws)
+    }
+
+    async fn connect(addr: SocketAddr, target: &str) -> Client {
+        try_connect(addr, target, None).await.unwrap()
+    }
+
+    async fn send(client: &mut Client, value: Value) {
+        client.send(Message::text(value.to_string())).await.unwrap();
+    }
+
+    async fn recv(client: &mut Client) -> Value {
+        match client.next().await {
+            Some(Ok(Message::Text(text))) => serde_json::from_str(&text).unwrap(),
+            other => panic!("expected a text message, got {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gateway_end_to_end() {
+        let host = FakeHost::new()
+            .script(
+                Script::new()
+                    .wait_for(|p| matches!(p, Packet::Mst(_)))
+                    .send(Mso {
+                        msg: "hello".into(),
+                        ..Default::default()
+                    }),
+            )
+            .listen_tcp()
+            .await
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let gateway = tokio::spawn(
+            Gateway::new(insim::tcp(host.local_addr()))
+                .read_only(false)
+                .serve(listener),
+        );
+
+        // filtered to Mso and Mst by the percent encoded query string, so the Ver is never
+        // received
+        let mut first = connect(addr, "/?types=Mso%2CMst").await;
+        let mut second = connect(addr, "/").await;
+        send(&mut second, json!({ "subscribe": ["mso"] })).await;
+
+        send(&mut first, json!("nope")).await;
+        assert_eq!(recv(&mut first).await["error"], "expected an object");
+
+        let mst = serde_json::to_value(Packet::from(Mst {
+            msg: "hi".into(),
+            ..Default::default()
+        }))
+        .unwrap();
+        send(&mut first, mst).await;
+
+        for client in [&mut first, &mut second] {
+            let mso = recv(client).await;
+            assert_eq!(mso["type"], "Mso");
+            assert_eq!(mso["msg"], "hello");
+        }
+
+        assert!(!gateway.is_finished());
+        gateway.abort();
+    }
+
+    #[tokio::test]
+    async fn test_gateway_read_only_by_default() {
+        let host = FakeHost::new().listen_tcp().await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let gateway = tokio::spawn(Gateway::new(insim::tcp(host.local_addr())).serve(listener));
+
+        let mut client = connect(addr, "/?types=Mso").await;
+        send(&mut client, json!({ "type": "Mst", "msg": "hi" })).await;
+        assert_eq!(recv(&mut client).await["error"], "gateway is read only");
+
+        // plain HTTP is refused
+        let mut plain = TcpStream::connect(addr).await.unwrap();
+        plain
+            .write_all(b"GET / HTTP/1.1\r\nHost: localhost\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        let _ = plain.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 400"));
+
+        gateway.abort();
+    }
+
+    #[tokio::test]
+    async fn test_gateway_origin() {
+        let host = FakeHost::new().listen_tcp().await.unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let gateway = tokio::spawn(
+            Gateway::new(insim::tcp(host.local_addr()))
+                .allow_origin("http://localhost:8080")
+                .serve(listener),
+        );
+
+        assert!(
+            try_connect(addr, "/", Some("http://localhost:8080"))
+                .await
+                .is_ok()
+        );
+
+        match try_connect(addr, "/", Some("https://evil.example")).await {
+            Err(WsError::Http(response)) => assert_eq!(response.status(), StatusCode::FORBIDDEN),
+            other => panic!("expected 403, got {other:?}"),
+        }
+
+        gateway.abort();
+    }
+}
@@ -0,0 +1,78 @@
+//! Expose an Insim connection to browsers as JSON over WebSocket.
+use std::net::SocketAddr;
+
+use clap::Parser;
+use form_urlencoded as _;
+use futures as _;
+use insim::insim::IsiFlags;
+use insim_gateway::Gateway;
+use serde_json as _;
+use tokio_tungstenite as _;
+use tracing as _;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[arg(long)]
+    /// host:port of LFS to connect to
+    upstream: SocketAddr,
+
+    #[arg(long, default_value = "127.0.0.1:29997")]
+    /// Address to accept WebSocket clients on
+    listen: SocketAddr,
+
+    #[arg(long, env = "INSIM_GATEWAY_UPSTREAM_ADMIN")]
+    /// Admin password of the LFS host
+    upstream_admin: Option<String>,
+
+    #[arg(long)]
+    /// Forward packets from clients to LFS
+    writable: bool,
+
+    #[arg(long = "allow-origin")]
+    /// Origin of a web page which may connect, i.e. http://localhost:8080. May be repeated
+    allow_origins: Vec<String>,
+
+    #[arg(long)]
+    /// Request MCI packets
+    mci: bool,
+
+    #[arg(long)]
+    /// Request NLP packets
+    nlp: bool,
+}
+
+fn setup_tracing_subscriber() {
+    // Setup with a default log level of INFO RUST_LOG is unset
+    tracing_subscriber::fmt::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::builder()
+                .with_default_directive(tracing_subscriber::filter::LevelFilter::INFO.into())
+                .from_env_lossy(),
+        )
+        .init();
+}
+
+#[tokio::main]
+async fn main() -> insim::Result<()> {
+    setup_tracing_subscriber();
+
+    let cli = Cli::parse();
+
+    let mut flags = IsiFlags::empty();
+    flags.set(IsiFlags::MCI, cli.mci);
+    flags.set(IsiFlags::NLP, cli.nlp);
+
+    let upstream = insim::tcp(cli.upstream)
+        .isi_admin_password(cli.upstream_admin)
+        .isi_iname("insim_gateway".to_string())
+        .isi_flags(flags);
+
+    cli.allow_origins
+        .into_iter()
+        .fold(Gateway::new(upstream), Gateway::allow_origin)
+        .listen(cli.listen)
+        .read_only(!cli.writable)
+        .run()
+        .await
+}
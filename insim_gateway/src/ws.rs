@@ -0,0 +1,106 @@
+//! Vetting WebSocket upgrade requests before they are accepted.
+//!
+//! The WebSocket protocol itself is handled by tokio-tungstenite. This module only decides
+//! whether a request may be upgraded, and extracts what the gateway needs from it.
+use tokio_tungstenite::tungstenite::{
+    handshake::server::{ErrorResponse, Request},
+    http::{StatusCode, header::ORIGIN},
+};
+
+/// What the gateway needs from an accepted upgrade request.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Upgrade {
+    /// Packet types listed in the `types` query parameter, if present
+    pub(crate) types: Option<Vec<String>>,
+}
+
+/// Check the `Origin` of an upgrade request against `allowed_origins`, and parse its query
+/// string.
+///
+/// Browsers always send `Origin` with a WebSocket upgrade, and allow any page to open a
+/// WebSocket to any address, including localhost. Requests with an `Origin` which is not allowed
+/// are therefore refused with `403 Forbidden`. Requests without an `Origin` do not come from a
+/// browser, and are accepted.
+#[allow(clippy::result_large_err)] // ErrorResponse is dictated by tungstenite
+pub(crate) fn vet(request: &Request, allowed_origins: &[String]) -> Result<Upgrade, ErrorResponse> {
+    if let Some(origin) = request.headers().get(ORIGIN) {
+        let allowed = origin.to_str().is_ok_and(|origin| {
+            allowed_origins
+                .iter()
+                .any(|allowed| allowed.eq_ignore_ascii_case(origin))
+        });
+        if !allowed {
+            return Err(reject(StatusCode::FORBIDDEN, "origin not allowed"));
+        }
+    }
+
+    let types = request.uri().query().and_then(|query| {
+        form_urlencoded::parse(query.as_bytes()).find_map(|(key, value)| {
+            (key == "types").then(|| {
+                value
+                    .split(',')
+                    .filter(|ty| !ty.is_empty())
+                    .map(str::to_owned)
+                    .collect()
+            })
+        })
+    });
+
+    Ok(Upgrade { types })
+}
+
+fn reject(status: StatusCode, reason: &str) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(reason.to_owned()));
+    *response.status_mut() = status;
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(target: &str, origin: Option<&str>) -> Request {
+        let mut request = Request::get(target);
+        if let Some(origin) = origin {
+            request = request.header(ORIGIN, origin);
+        }
+        request.body(()).unwrap()
+    }
+
+    #[test]
+    fn test_vet_query() {
+        let upgrade = vet(&request("/?types=Mci%2CNcn&other=1", None), &[]).unwrap();
+        assert_eq!(
+            upgrade.types,
+            Some(vec!["Mci".to_owned(), "Ncn".to_owned()])
+        );
+
+        let upgrade = vet(&request("/?types=Mci,,Ncn", None), &[]).unwrap();
+        assert_eq!(
+            upgrade.types,
+            Some(vec!["Mci".to_owned(), "Ncn".to_owned()])
+        );
+
+        let upgrade = vet(&request("/?other=1", None), &[]).unwrap();
+        assert_eq!(upgrade.types, None);
+
+        let upgrade = vet(&request("/", None), &[]).unwrap();
+        assert_eq!(upgrade.types, None);
+    }
+
+    #[test]
+    fn test_vet_origin() {
+        let allowed = vec!["http://localhost:8080".to_owned()];
+
+        assert!(vet(&request("/", Some("http://localhost:8080")), &allowed).is_ok());
+        assert!(vet(&request("/", Some("HTTP://LOCALHOST:8080")), &allowed).is_ok());
+        assert!(vet(&request("/", None), &allowed).is_ok());
+
+        let rejected = vet(&request("/", Some("https://evil.example")), &allowed).unwrap_err();
+        assert_eq!(rejected.status(), StatusCode::FORBIDDEN);
+
+        // nothing is allowed by default
+        let rejected = vet(&request("/", Some("http://localhost:8080")), &[]).unwrap_err();
+        assert_eq!(rejected.status(), StatusCode::FORBIDDEN);
+    }
+}
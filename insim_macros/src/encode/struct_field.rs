@@ -36,6 +36,7 @@ pub(super) struct Field {
     pub codepage: Option<CodepageArgs>,
     pub ascii: Option<AsciiArgs>,
     pub duration: Option<syn::TypePath>,
+    pub since: Option<u8>,
 }
 
 impl Field {
@@ -43,6 +44,18 @@ impl Field {
         self.skip.unwrap_or(false)
     }
 
+    fn check_since(&self) -> Result<(), darling::Error> {
+        if self.since.is_some()
+            && (self.codepage.is_some() || self.ascii.is_some() || self.duration.is_some())
+        {
+            return Err(darling::Error::custom(
+                "since cannot be combined with codepage, ascii or duration",
+            )
+            .with_span(&self.ty));
+        }
+        Ok(())
+    }
+
     pub(super) fn decode(
         &self,
         parent: &syn::Ident,
@@ -56,6 +69,7 @@ impl Field {
         let pad_before = f.pad_before.unwrap_or(0);
         let field_type = f.ty.clone();
         let context = format!("{}::{}", parent, field_name);
+        f.check_since()?;
 
         let mut tokens = quote! {};
 
@@ -111,6 +125,18 @@ impl Field {
                 #tokens
                 let #field_name = ctx.decode::<#typ>(#context)?;
             };
+
+            // Older versions leave these bytes spare
+            if let Some(since) = f.since {
+                tokens = quote! {
+                    #tokens
+                    let #field_name = if ctx.version().is_some_and(|v| v < #since) {
+                        ::core::default::Default::default()
+                    } else {
+                        #field_name
+                    };
+                };
+            }
         }
 
         if pad_after > 0 {
@@ -133,7 +159,9 @@ impl Field {
             .ok_or_else(|| darling::Error::custom("missing field name").with_span(&f.ty))?;
         let pad_after = f.pad_after.unwrap_or(0);
         let pad_before = f.pad_before.unwrap_or(0);
+        let field_type = &f.ty;
         let context = format!("{}::{}", parent, field_name);
+        f.check_since()?;
         let mut tokens = quote! {};
 
         if pad_before > 0 {
@@ -178,6 +206,16 @@ impl Field {
                 #tokens
                 ctx.encode_duration::<#duration_repr>(#context, self.#field_name)?;
             };
+        } else if let Some(since) = f.since {
+            // Older versions leave these bytes spare
+            tokens = quote! {
+                #tokens
+                if ctx.version().is_some_and(|v| v < #since) {
+                    ctx.encode(#context, &<#field_type as ::core::default::Default>::default())?;
+                } else {
+                    ctx.encode(#context, &self.#field_name)?;
+                }
+            };
         } else {
             tokens = quote! {
                 #tokens
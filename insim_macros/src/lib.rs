@@ -14,6 +14,9 @@ mod encode;
 ///    Fields may have padding before or after using #[insim(pad_after=2)]
 ///    Fields may be skipped by supplying #[insim(skip)]
 ///    Fields which are strings must have either acsii, or codepage directives provided.
+///    Fields which only exist from a protocol version onwards, and are spare before it, may be
+///    marked using #[insim(since=10)]. They are encoded and decoded as their default value for
+///    older versions.
 /// 2. Enums which are repr(typ) and have a supplied discriminant
 ///    Variants may be skipped using #[insim(skip)]
 pub fn derive_encode(input: TokenStream) -> TokenStream {
@@ -35,6 +38,9 @@ pub fn derive_encode(input: TokenStream) -> TokenStream {
 ///    Fields may have padding before or after using #[insim(pad_after=2)]
 ///    Fields may be skipped by supplying #[insim(skip)]
 ///    Fields which are strings must have either acsii, or codepage directives provided.
+///    Fields which only exist from a protocol version onwards, and are spare before it, may be
+///    marked using #[insim(since=10)]. They are encoded and decoded as their default value for
+///    older versions.
 /// 2. Enums which are repr(typ) and have a supplied discriminant
 ///    Variants may be skipped using #[insim(skip)]
 pub fn derive_decode(input: TokenStream) -> TokenStream {
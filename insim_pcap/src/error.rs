@@ -0,0 +1,33 @@
+//! Error
+use std::io::ErrorKind;
+
+use thiserror::Error;
+
+#[non_exhaustive]
+#[derive(Error, Debug)]
+#[allow(missing_docs)]
+pub enum Error {
+    #[error("Not a pcap or pcapng file, found magic {0:#010x}")]
+    UnsupportedFormat(u32),
+
+    #[error("Truncated or malformed {0}")]
+    Malformed(&'static str),
+
+    #[error("{0} bytes missing from the TCP stream")]
+    Gap(u64),
+
+    #[error("IO Error: {kind}: {message}")]
+    IO { kind: ErrorKind, message: String },
+
+    #[error("Insim error: {0}")]
+    Insim(#[from] insim::Error),
+}
+
+impl From<std::io::Error> for Error {
+    fn from(e: std::io::Error) -> Self {
+        Error::IO {
+            kind: e.kind(),
+            message: e.to_string(),
+        }
+    }
+}
@@ -0,0 +1,262 @@
+//! pcap and pcapng container parsing.
+use std::time::Duration;
+
+use crate::Error;
+
+const PCAP_MICROS: u32 = 0xa1b2_c3d4;
+const PCAP_NANOS: u32 = 0xa1b2_3c4d;
+const PCAPNG_SHB: u32 = 0x0a0d_0d0a;
+const PCAPNG_BYTE_ORDER: u32 = 0x1a2b_3c4d;
+
+const PCAPNG_IDB: u32 = 1;
+const PCAPNG_SPB: u32 = 3;
+const PCAPNG_EPB: u32 = 6;
+const PCAPNG_OPT_END: u16 = 0;
+const PCAPNG_OPT_TSRESOL: u16 = 9;
+
+/// A captured link layer frame.
+#[derive(Debug)]
+pub(crate) struct Record<'a> {
+    /// Time since the unix epoch
+    pub(crate) timestamp: Duration,
+    pub(crate) linktype: u32,
+    pub(crate) data: &'a [u8],
+}
+
+/// Reads endian-dependant integers from a slice.
+#[derive(Debug, Clone)]
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+    little: bool,
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(buf: &'a [u8], little: bool) -> Self {
+        Self { buf, little }
+    }
+
+    pub(crate) fn remaining(&self) -> &'a [u8] {
+        self.buf
+    }
+
+    pub(crate) fn take(&mut self, n: usize, what: &'static str) -> Result<&'a [u8], Error> {
+        if self.buf.len() < n {
+            return Err(Error::Malformed(what));
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn u16(&mut self, what: &'static str) -> Result<u16, Error> {
+        let b = self.take(2, what)?;
+        let b = [b[0], b[1]];
+        Ok(if self.little {
+            u16::from_le_bytes(b)
+        } else {
+            u16::from_be_bytes(b)
+        })
+    }
+
+    pub(crate) fn u32(&mut self, what: &'static str) -> Result<u32, Error> {
+        let b = self.take(4, what)?;
+        let b = [b[0], b[1], b[2], b[3]];
+        Ok(if self.little {
+            u32::from_le_bytes(b)
+        } else {
+            u32::from_be_bytes(b)
+        })
+    }
+}
+
+/// Parse every record from a pcap or pcapng file.
+pub(crate) fn records(buf: &[u8]) -> Result<Vec<Record<'_>>, Error> {
+    if buf.len() < 4 {
+        return Err(Error::Malformed("file header"));
+    }
+    let magic_le = u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let magic_be = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+
+    match (magic_le, magic_be) {
+        (PCAPNG_SHB, _) => pcapng(buf),
+        (PCAP_MICROS, _) => pcap(buf, true, 1_000),
+        (PCAP_NANOS, _) => pcap(buf, true, 1),
+        (_, PCAP_MICROS) => pcap(buf, false, 1_000),
+        (_, PCAP_NANOS) => pcap(buf, false, 1),
+        _ => Err(Error::UnsupportedFormat(magic_be)),
+    }
+}
+
+fn pcap(buf: &[u8], little: bool, nanos_per_unit: u32) -> Result<Vec<Record<'_>>, Error> {
+    let mut r = Reader::new(buf, little);
+    let header = r.take(24, "pcap header")?;
+    let linktype = Reader::new(&header[20..], little).u32("pcap linktype")?;
+
+    let mut records = Vec::new();
+    while !r.remaining().is_empty() {
+        let secs = r.u32("pcap record")?;
+        let frac = r.u32("pcap record")?;
+        let caplen = r.u32("pcap record")?;
+        let _origlen = r.u32("pcap record")?;
+        let data = r.take(caplen as usize, "pcap record data")?;
+        records.push(Record {
+            timestamp: Duration::from_secs(secs.into())
+                + Duration::from_nanos(u64::from(frac) * u64::from(nanos_per_unit)),
+            linktype,
+            data,
+        });
+    }
+    Ok(records)
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Interface {
+    linktype: u32,
+    /// Timestamp units per second
+    resolution: u64,
+}
+
+fn pcapng(buf: &[u8]) -> Result<Vec<Record<'_>>, Error> {
+    let mut rest = buf;
+    let mut little = true;
+    let mut interfaces: Vec<Interface> = Vec::new();
+    let mut records = Vec::new();
+
+    while !rest.is_empty() {
+        if rest.len() < 12 {
+            return Err(Error::Malformed("pcapng block"));
+        }
+        let block_type = Reader::new(rest, little).u32("pcapng block type")?;
+        if block_type == PCAPNG_SHB {
+            // Each section may have a different byte order, and has its own interfaces
+            let magic = [rest[8], rest[9], rest[10], rest[11]];
+            little = match (u32::from_le_bytes(magic), u32::from_be_bytes(magic)) {
+                (PCAPNG_BYTE_ORDER, _) => true,
+                (_, PCAPNG_BYTE_ORDER) => false,
+                _ => return Err(Error::Malformed("pcapng section header")),
+            };
+            interfaces.clear();
+        }
+
+        let mut r = Reader::new(rest, little);
+        let _ = r.u32("pcapng block type")?;
+        let len = r.u32("pcapng block length")? as usize;
+        if len < 12 || !len.is_multiple_of(4) || len > rest.len() {
+            return Err(Error::Malformed("pcapng block length"));
+        }
+        let mut body = Reader::new(&rest[8..len - 4], little);
+        rest = &rest[len..];
+
+        match block_type {
+            PCAPNG_IDB => {
+                let linktype = body.u16("pcapng interface")?.into();
+                let _reserved = body.u16("pcapng interface")?;
+                let _snaplen = body.u32("pcapng interface")?;
+                interfaces.push(Interface {
+                    linktype,
+                    resolution: tsresol(body)?,
+                });
+            },
+            PCAPNG_EPB => {
+                let interface = body.u32("pcapng packet")? as usize;
+                let high = body.u32("pcapng packet")?;
+                let low = body.u32("pcapng packet")?;
+                let caplen = body.u32("pcapng packet")?;
+                let _origlen = body.u32("pcapng packet")?;
+                let data = body.take(caplen as usize, "pcapng packet data")?;
+                let interface = interfaces
+                    .get(interface)
+                    .ok_or(Error::Malformed("pcapng interface id"))?;
+                let ts = (u64::from(high) << 32) | u64::from(low);
+                records.push(Record {
+                    timestamp: timestamp(ts, interface.resolution),
+                    linktype: interface.linktype,
+                    data,
+                });
+            },
+            PCAPNG_SPB => {
+                // No timestamp, and always the first interface
+                let origlen = body.u32("pcapng simple packet")? as usize;
+                let data = body.remaining();
+                let data = &data[..origlen.min(data.len())];
+                let interface = interfaces
+                    .first()
+                    .ok_or(Error::Malformed("pcapng interface id"))?;
+                records.push(Record {
+                    timestamp: Duration::ZERO,
+                    linktype: interface.linktype,
+                    data,
+                });
+            },
+            // Section headers have been handled above, everything else is irrelevant
+            _ => {},
+        }
+    }
+    Ok(records)
+}
+
+/// Find the if_tsresol option, defaulting to microseconds.
+fn tsresol(mut options: Reader<'_>) -> Result<u64, Error> {
+    while options.remaining().len() >= 4 {
+        let code = options.u16("pcapng option")?;
+        let len = options.u16("pcapng option")? as usize;
+        if code == PCAPNG_OPT_END {
+            break;
+        }
+        let value = options.take(len.next_multiple_of(4), "pcapng option")?;
+        if code == PCAPNG_OPT_TSRESOL && len == 1 {
+            let exp = u32::from(value[0] & 0x7f);
+            let base: u64 = if value[0] & 0x80 == 0 { 10 } else { 2 };
+            return base
+                .checked_pow(exp)
+                .ok_or(Error::Malformed("pcapng timestamp resolution"));
+        }
+    }
+    Ok(1_000_000)
+}
+
+fn timestamp(ts: u64, resolution: u64) -> Duration {
+    let secs = ts / resolution;
+    let rem = ts % resolution;
+    let nanos = (u128::from(rem) * 1_000_000_000 / u128::from(resolution)) as u32;
+    Duration::new(secs, nanos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pcap_big_endian_nanos() {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&PCAP_NANOS.to_be_bytes());
+        buf.extend_from_slice(&[0, 2, 0, 4]);
+        buf.extend_from_slice(&[0; 12]);
+        buf.extend_from_slice(&101u32.to_be_bytes());
+        buf.extend_from_slice(&5u32.to_be_bytes());
+        buf.extend_from_slice(&7u32.to_be_bytes());
+        buf.extend_from_slice(&2u32.to_be_bytes());
+        buf.extend_from_slice(&2u32.to_be_bytes());
+        buf.extend_from_slice(&[9, 9]);
+
+        let records = records(&buf).unwrap();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].linktype, 101);
+        assert_eq!(records[0].timestamp, Duration::new(5, 7));
+        assert_eq!(records[0].data, &[9, 9]);
+    }
+
+    #[test]
+    fn test_timestamp_resolution() {
+        assert_eq!(timestamp(1_500_000, 1_000_000), Duration::from_millis(1500));
+        assert_eq!(timestamp(3, 2), Duration::from_millis(1500));
+    }
+
+    #[test]
+    fn test_unsupported() {
+        assert!(matches!(
+            records(b"GIF89a"),
+            Err(Error::UnsupportedFormat(_))
+        ));
+    }
+}
@@ -0,0 +1,210 @@
+//! Link, network and transport layer parsing.
+//!
+//! Anything which is not TCP or UDP over IPv4 or IPv6, or which is truncated, is ignored.
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+const LINKTYPE_NULL: u32 = 0;
+const LINKTYPE_ETHERNET: u32 = 1;
+const LINKTYPE_RAW: u32 = 101;
+const LINKTYPE_LOOP: u32 = 108;
+const LINKTYPE_LINUX_SLL: u32 = 113;
+const LINKTYPE_IPV4: u32 = 228;
+const LINKTYPE_IPV6: u32 = 229;
+const LINKTYPE_LINUX_SLL2: u32 = 276;
+
+const ETHERTYPE_IPV4: u16 = 0x0800;
+const ETHERTYPE_IPV6: u16 = 0x86dd;
+const ETHERTYPE_VLAN: u16 = 0x8100;
+const ETHERTYPE_QINQ: u16 = 0x88a8;
+
+const IPPROTO_TCP: u8 = 6;
+const IPPROTO_UDP: u8 = 17;
+
+const TCP_FIN: u8 = 0x01;
+const TCP_SYN: u8 = 0x02;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Transport {
+    Tcp { seq: u32, syn: bool, fin: bool },
+    Udp,
+}
+
+#[derive(Debug)]
+pub(crate) struct Segment<'a> {
+    pub(crate) src: SocketAddr,
+    pub(crate) dst: SocketAddr,
+    pub(crate) transport: Transport,
+    pub(crate) payload: &'a [u8],
+}
+
+fn be16(buf: &[u8], at: usize) -> Option<u16> {
+    Some(u16::from_be_bytes([*buf.get(at)?, *buf.get(at + 1)?]))
+}
+
+fn be32(buf: &[u8], at: usize) -> Option<u32> {
+    Some(u32::from_be_bytes([
+        *buf.get(at)?,
+        *buf.get(at + 1)?,
+        *buf.get(at + 2)?,
+        *buf.get(at + 3)?,
+    ]))
+}
+
+/// Parse a link layer frame down to a TCP segment or UDP datagram.
+pub(crate) fn parse(linktype: u32, data: &[u8]) -> Option<Segment<'_>> {
+    let ip = match linktype {
+        LINKTYPE_ETHERNET => ethernet(data)?,
+        // The address family is in host byte order, so rely on the IP version instead
+        LINKTYPE_NULL | LINKTYPE_LOOP => data.get(4..)?,
+        LINKTYPE_RAW | LINKTYPE_IPV4 | LINKTYPE_IPV6 => data,
+        LINKTYPE_LINUX_SLL => ethertype(be16(data, 14)?, data.get(16..)?)?,
+        LINKTYPE_LINUX_SLL2 => ethertype(be16(data, 0)?, data.get(20..)?)?,
+        _ => return None,
+    };
+
+    match ip.first()? >> 4 {
+        4 => ipv4(ip),
+        6 => ipv6(ip),
+        _ => None,
+    }
+}
+
+fn ethernet(data: &[u8]) -> Option<&[u8]> {
+    let mut offset = 12;
+    let mut kind = be16(data, offset)?;
+    while kind == ETHERTYPE_VLAN || kind == ETHERTYPE_QINQ {
+        offset += 4;
+        kind = be16(data, offset)?;
+    }
+    ethertype(kind, data.get(offset + 2..)?)
+}
+
+fn ethertype(kind: u16, payload: &[u8]) -> Option<&[u8]> {
+    match kind {
+        ETHERTYPE_IPV4 | ETHERTYPE_IPV6 => Some(payload),
+        _ => None,
+    }
+}
+
+fn ipv4(ip: &[u8]) -> Option<Segment<'_>> {
+    let ihl = usize::from(ip.first()? & 0x0f) * 4;
+    if ihl < 20 {
+        return None;
+    }
+    let total = usize::from(be16(ip, 2)?);
+    // Fragments cannot be reassembled without the rest of the datagram, ignore them
+    let flags_offset = be16(ip, 6)?;
+    if flags_offset & 0x3fff != 0 {
+        return None;
+    }
+    let protocol = *ip.get(9)?;
+    let addrs = ip.get(12..20)?;
+    let src = Ipv4Addr::new(addrs[0], addrs[1], addrs[2], addrs[3]);
+    let dst = Ipv4Addr::new(addrs[4], addrs[5], addrs[6], addrs[7]);
+    // The total length excludes any link layer padding
+    let payload = ip.get(ihl..total.min(ip.len()))?;
+    transport(protocol, src.into(), dst.into(), payload)
+}
+
+fn ipv6(ip: &[u8]) -> Option<Segment<'_>> {
+    let len = usize::from(be16(ip, 4)?);
+    let mut next = *ip.get(6)?;
+    let src: [u8; 16] = ip.get(8..24)?.try_into().ok()?;
+    let dst: [u8; 16] = ip.get(24..40)?.try_into().ok()?;
+    let mut payload = ip.get(40..(40 + len).min(ip.len()))?;
+
+    // Skip extension headers: hop-by-hop, routing and destination options
+    while matches!(next, 0 | 43 | 60) {
+        let hdr_len = (usize::from(*payload.get(1)?) + 1) * 8;
+        next = *payload.first()?;
+        payload = payload.get(hdr_len..)?;
+    }
+
+    transport(
+        next,
+        Ipv6Addr::from(src).into(),
+        Ipv6Addr::from(dst).into(),
+        payload,
+    )
+}
+
+fn transport(protocol: u8, src: IpAddr, dst: IpAddr, data: &[u8]) -> Option<Segment<'_>> {
+    let src_port = be16(data, 0)?;
+    let dst_port = be16(data, 2)?;
+    let (transport, payload) = match protocol {
+        IPPROTO_TCP => {
+            let seq = be32(data, 4)?;
+            let offset = usize::from(data.get(12)? >> 4) * 4;
+            let flags = *data.get(13)?;
+            (
+                Transport::Tcp {
+                    seq,
+                    syn: flags & TCP_SYN != 0,
+                    fin: flags & TCP_FIN != 0,
+                },
+                data.get(offset..)?,
+            )
+        },
+        IPPROTO_UDP => {
+            let len = usize::from(be16(data, 4)?);
+            (Transport::Udp, data.get(8..len.min(data.len()))?)
+        },
+        _ => return None,
+    };
+    Some(Segment {
+        src: SocketAddr::new(src, src_port),
+        dst: SocketAddr::new(dst, dst_port),
+        transport,
+        payload,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ipv6_udp_over_sll2() {
+        let mut frame = vec![0u8; 20];
+        frame[0..2].copy_from_slice(&ETHERTYPE_IPV6.to_be_bytes());
+        let mut ip = vec![0x60, 0, 0, 0, 0, 12, IPPROTO_UDP, 64];
+        ip.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        ip.extend_from_slice(&Ipv6Addr::LOCALHOST.octets());
+        ip.extend_from_slice(&[0x75, 0x2f, 0x04, 0xd2, 0, 12, 0, 0, 1, 3, 0, 0]);
+        frame.extend_from_slice(&ip);
+
+        let segment = parse(LINKTYPE_LINUX_SLL2, &frame).unwrap();
+        assert_eq!(segment.transport, Transport::Udp);
+        assert_eq!(segment.src.port(), 29999);
+        assert_eq!(segment.dst.port(), 1234);
+        assert_eq!(segment.payload, &[1, 3, 0, 0]);
+    }
+
+    #[test]
+    fn test_ignores_fragments_and_other_protocols() {
+        let mut ip = vec![0x45, 0, 0, 28, 0, 0, 0x20, 0, 64, IPPROTO_UDP, 0, 0];
+        ip.extend_from_slice(&[127, 0, 0, 1, 127, 0, 0, 1]);
+        ip.extend_from_slice(&[0; 8]);
+        assert!(parse(LINKTYPE_RAW, &ip).is_none());
+
+        ip[6] = 0;
+        ip[9] = 1; // ICMP
+        assert!(parse(LINKTYPE_RAW, &ip).is_none());
+    }
+
+    #[test]
+    fn test_ignores_truncated_ipv4() {
+        let mut ip = vec![0x45, 0, 0, 28, 0, 0, 0, 0, 64, IPPROTO_UDP, 0, 0];
+        ip.extend_from_slice(&[127, 0, 0, 1, 127, 0, 0, 1]);
+        ip.extend_from_slice(&[0x75, 0x2f, 0x04, 0xd2, 0, 8, 0, 0]);
+        assert!(parse(LINKTYPE_RAW, &ip).is_some());
+
+        for len in 10..20 {
+            assert!(parse(LINKTYPE_RAW, &ip[..len]).is_none());
+        }
+
+        // a header length shorter than the fixed header
+        ip[0] = 0x44;
+        assert!(parse(LINKTYPE_RAW, &ip).is_none());
+    }
+}
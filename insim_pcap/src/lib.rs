@@ -0,0 +1,547 @@
+//! # insim_pcap
+//!
+//! Extract Insim packets from a pcap or pcapng file, such as one written by tcpdump or
+//! Wireshark.
+//!
+//! TCP flows to or from the Insim port are reassembled, and UDP datagrams are decoded
+//! individually. Every frame is run through [insim::net::Codec], producing timestamped and
+//! direction-tagged packets. Anything which fails to decode is reported as a [Failure],
+//! including the offset into the stream at which the failing frame started, rather than
+//! aborting the import.
+//!
+//! ```rust,ignore
+//! let import = insim_pcap::Importer::new(29999).read(std::fs::File::open("dump.pcap")?)?;
+//!
+//! for imported in &import.packets {
+//!     println!("{:?} {:?} {:?}", imported.timestamp, imported.direction, imported.packet);
+//! }
+//!
+//! for failure in &import.failures {
+//!     eprintln!("{} at offset {}", failure.error, failure.offset);
+//! }
+//! ```
+//!
+//! Supported link types are Ethernet (including VLAN tagged), raw IP, BSD loopback and Linux
+//! cooked captures (SLL and SLL2). Fragmented IP packets are ignored.
+use std::{
+    collections::HashMap,
+    io::{Read, Write},
+    net::SocketAddr,
+    time::Duration,
+};
+
+use bytes::Bytes;
+use insim::{capture::CaptureWriter, net::Codec};
+
+pub mod error;
+mod file;
+mod layers;
+
+pub use error::Error;
+pub use insim::capture::Direction;
+
+/// If this many out of order bytes are waiting on a missing TCP segment, give up waiting.
+const MAX_PENDING_BYTES: usize = 1024 * 1024;
+
+/// The transport a [Flow] used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Transport {
+    /// TCP
+    Tcp,
+    /// UDP
+    Udp,
+}
+
+/// The endpoints of an Insim connection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Flow {
+    /// TCP or UDP
+    pub transport: Transport,
+    /// The Insim client, i.e. your application
+    pub client: SocketAddr,
+    /// LFS
+    pub server: SocketAddr,
+}
+
+/// A packet extracted from the capture.
+#[derive(Debug, Clone)]
+pub struct Imported {
+    /// When the final byte of the packet was captured, as time since the unix epoch
+    pub timestamp: Duration,
+    /// Which way the packet travelled
+    pub direction: Direction,
+    /// Which connection the packet belongs to
+    pub flow: Flow,
+    /// Where the frame started. For TCP this is the offset into the reassembled stream, for UDP
+    /// the offset into the datagram.
+    pub offset: u64,
+    /// The raw frame
+    pub frame: Bytes,
+    /// The decoded packet
+    pub packet: insim::Packet,
+}
+
+/// Something which could not be decoded.
+#[derive(Debug)]
+pub struct Failure {
+    /// When the failure occurred, as time since the unix epoch
+    pub timestamp: Duration,
+    /// Which way the data travelled
+    pub direction: Direction,
+    /// Which connection the data belongs to
+    pub flow: Flow,
+    /// Where the failing frame started, as with [Imported::offset]. The offset within the frame
+    /// is available from [insim::Error::Decode].
+    pub offset: u64,
+    /// What went wrong
+    pub error: Error,
+}
+
+/// The result of importing a capture.
+#[derive(Debug, Default)]
+pub struct Import {
+    /// Every packet, in the order they were captured
+    pub packets: Vec<Imported>,
+    /// Everything which failed to decode
+    pub failures: Vec<Failure>,
+}
+
+impl Import {
+    /// Write the packets as an [insim::capture], with timestamps relative to the first packet.
+    pub fn write_capture<W: Write>(&self, writer: W) -> Result<W, Error> {
+        let mut capture = CaptureWriter::new(writer)?;
+        let start = self
+            .packets
+            .first()
+            .map(|p| p.timestamp)
+            .unwrap_or_default();
+        for imported in &self.packets {
+            capture.write_frame_at(
+                imported.timestamp.saturating_sub(start),
+                imported.direction,
+                &imported.frame,
+            )?;
+        }
+        Ok(capture.into_inner())
+    }
+}
+
+/// Reads Insim traffic from pcap and pcapng files.
+#[derive(Debug, Clone, Copy)]
+pub struct Importer {
+    port: u16,
+}
+
+impl Default for Importer {
+    fn default() -> Self {
+        Self::new(29999)
+    }
+}
+
+impl Importer {
+    /// Extract traffic to and from the Insim `port`.
+    pub fn new(port: u16) -> Self {
+        Self { port }
+    }
+
+    /// Read and import an entire pcap or pcapng file.
+    pub fn read<R: Read>(&self, mut reader: R) -> Result<Import, Error> {
+        let mut buf = Vec::new();
+        let _ = reader.read_to_end(&mut buf)?;
+        self.parse(&buf)
+    }
+
+    /// Import a pcap or pcapng file held in memory.
+    pub fn parse(&self, buf: &[u8]) -> Result<Import, Error> {
+        let mut import = Import::default();
+        let mut streams: HashMap<(Flow, Direction), Stream> = HashMap::new();
+
+        for record in file::records(buf)? {
+            let Some(segment) = layers::parse(record.linktype, record.data) else {
+                continue;
+            };
+            let (direction, client, server) = if segment.src.port() == self.port {
+                (Direction::Incoming, segment.dst, segment.src)
+            } else if segment.dst.port() == self.port {
+                (Direction::Outgoing, segment.src, segment.dst)
+            } else {
+                continue;
+            };
+
+            let mut sink = Sink {
+                import: &mut import,
+                timestamp: record.timestamp,
+                direction,
+                flow: Flow {
+                    transport: Transport::Tcp,
+                    client,
+                    server,
+                },
+            };
+
+            match segment.transport {
+                layers::Transport::Tcp { seq, syn, fin } => {
+                    let stream = streams.entry((sink.flow, direction)).or_default();
+                    if syn {
+                        // A new connection, possibly reusing the same ports
+                        *stream = Stream::default();
+                        stream.next = Some(seq.wrapping_add(1));
+                        continue;
+                    }
+                    stream.push(seq, segment.payload, &mut sink);
+                    if fin {
+                        stream.finish(&mut sink);
+                    }
+                },
+                layers::Transport::Udp => {
+                    sink.flow.transport = Transport::Udp;
+                    let mut codec = Codec::new();
+                    codec.feed(segment.payload);
+                    let _ = decode(&mut codec, segment.payload.len() as u64, &mut sink);
+                },
+            }
+        }
+
+        // Report anything still waiting on a missing segment
+        let mut remaining: Vec<_> = streams.into_iter().collect();
+        remaining.sort_by_key(|(_, stream)| stream.last_seen);
+        for ((flow, direction), mut stream) in remaining {
+            let mut sink = Sink {
+                import: &mut import,
+                timestamp: stream.last_seen,
+                direction,
+                flow,
+            };
+            stream.finish(&mut sink);
+        }
+
+        Ok(import)
+    }
+}
+
+/// Where decoded packets and failures go, along with the context for them.
+struct Sink<'a> {
+    import: &'a mut Import,
+    timestamp: Duration,
+    direction: Direction,
+    flow: Flow,
+}
+
+impl Sink<'_> {
+    fn fail(&mut self, offset: u64, error: Error) {
+        self.import.failures.push(Failure {
+            timestamp: self.timestamp,
+            direction: self.direction,
+            flow: self.flow,
+            offset,
+            error,
+        });
+    }
+}
+
+/// Decode every complete frame from `codec`. `fed` is the total number of bytes fed to the
+/// codec so far. Returns false if the data can no longer be framed.
+fn decode(codec: &mut Codec, fed: u64, sink: &mut Sink<'_>) -> bool {
+    loop {
+        let buffered = codec.buf_mut().len();
+        let offset = fed.saturating_sub(buffered as u64);
+        match codec.decode_with_frame() {
+            Ok(Some((packet, frame))) => sink.import.packets.push(Imported {
+                timestamp: sink.timestamp,
+                direction: sink.direction,
+                flow: sink.flow,
+                offset,
+                frame,
+                packet,
+            }),
+            Ok(None) => return true,
+            Err(e) => {
+                // If the frame was not consumed the length itself was invalid, and there is
+                // no way to find the start of the next frame.
+                let framed = codec.buf_mut().len() != buffered;
+                sink.fail(offset, e.into());
+                if !framed {
+                    return false;
+                }
+            },
+        }
+    }
+}
+
+/// One direction of a TCP connection.
+#[derive(Debug, Default)]
+struct Stream {
+    codec: Codec,
+    /// The next expected sequence number
+    next: Option<u32>,
+    /// Segments received ahead of `next`
+    pending: Vec<(u32, Vec<u8>)>,
+    pending_bytes: usize,
+    fed: u64,
+    /// The stream can no longer be decoded, until the next SYN
+    broken: bool,
+    last_seen: Duration,
+}
+
+impl Stream {
+    fn push(&mut self, seq: u32, payload: &[u8], sink: &mut Sink<'_>) {
+        self.last_seen = sink.timestamp;
+        if self.broken || payload.is_empty() {
+            return;
+        }
+        // If the capture started mid-connection, start from wherever we are
+        let next = *self.next.get_or_insert(seq);
+
+        if (seq.wrapping_sub(next) as i32) > 0 {
+            self.pending.push((seq, payload.to_vec()));
+            self.pending_bytes += payload.len();
+            if self.pending_bytes > MAX_PENDING_BYTES {
+                self.skip_gap(sink);
+            }
+            return;
+        }
+
+        self.accept(seq, payload, sink);
+        self.drain(sink);
+    }
+
+    /// Feed a segment which starts at or before the next expected sequence number, ignoring any
+    /// retransmitted bytes.
+    fn accept(&mut self, seq: u32, payload: &[u8], sink: &mut Sink<'_>) {
+        let Some(next) = self.next else {
+            return;
+        };
+        let overlap = next.wrapping_sub(seq) as usize;
+        if overlap >= payload.len() {
+            return;
+        }
+        let fresh = &payload[overlap..];
+        self.next = Some(next.wrapping_add(fresh.len() as u32));
+        self.codec.feed(fresh);
+        self.fed += fresh.len() as u64;
+        if !self.broken && !decode(&mut self.codec, self.fed, sink) {
+            self.broken = true;
+        }
+    }
+
+    /// Feed any pending segments which are now contiguous.
+    fn drain(&mut self, sink: &mut Sink<'_>) {
+        while let Some(next) = self.next {
+            let Some(i) = self
+                .pending
+                .iter()
+                .position(|(seq, _)| (seq.wrapping_sub(next) as i32) <= 0)
+            else {
+                break;
+            };
+            let (seq, payload) = self.pending.swap_remove(i);
+            self.pending_bytes -= payload.len();
+            self.accept(seq, &payload, sink);
+        }
+    }
+
+    /// Give up on the missing data. Without it we cannot know where the next frame starts.
+    fn skip_gap(&mut self, sink: &mut Sink<'_>) {
+        let (Some(next), Some(first)) = (
+            self.next,
+            self.pending
+                .iter()
+                .map(|(seq, _)| *seq)
+                .min_by_key(|seq| seq.wrapping_sub(self.next.unwrap_or(*seq))),
+        ) else {
+            return;
+        };
+        sink.fail(self.fed, Error::Gap(first.wrapping_sub(next).into()));
+        self.broken = true;
+        self.pending.clear();
+        self.pending_bytes = 0;
+    }
+
+    fn finish(&mut self, sink: &mut Sink<'_>) {
+        if !self.broken && !self.pending.is_empty() {
+            self.skip_gap(sink);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::Ipv4Addr;
+
+    use insim::{
+        WithRequestId,
+        identifiers::RequestId,
+        insim::{Isi, TinyType, Ver},
+    };
+
+    use super::*;
+
+    const CLIENT: u16 = 50000;
+    const SERVER: u16 = 29999;
+
+    fn encode(packet: impl Into<insim::Packet>) -> Vec<u8> {
+        Codec::new().encode(&packet.into()).unwrap().to_vec()
+    }
+
+    /// An Ethernet + IPv4 + TCP frame
+    fn tcp(src: u16, dst: u16, seq: u32, flags: u8, payload: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0u8; 12];
+        frame.extend_from_slice(&0x0800u16.to_be_bytes());
+        let total = (20 + 20 + payload.len()) as u16;
+        frame.extend_from_slice(&[0x45, 0]);
+        frame.extend_from_slice(&total.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0x40, 0, 64, 6, 0, 0]);
+        frame.extend_from_slice(&Ipv4Addr::LOCALHOST.octets());
+        frame.extend_from_slice(&Ipv4Addr::LOCALHOST.octets());
+        frame.extend_from_slice(&src.to_be_bytes());
+        frame.extend_from_slice(&dst.to_be_bytes());
+        frame.extend_from_slice(&seq.to_be_bytes());
+        frame.extend_from_slice(&[0, 0, 0, 0, 0x50, flags, 0, 0, 0, 0, 0, 0]);
+        frame.extend_from_slice(payload);
+        // ethernet padding, which must be ignored
+        frame.extend_from_slice(&[0; 6]);
+        frame
+    }
+
+    fn pcap(frames: &[Vec<u8>]) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.extend_from_slice(&0xa1b2c3d4u32.to_le_bytes());
+        buf.extend_from_slice(&[2, 0, 4, 0]);
+        buf.extend_from_slice(&[0; 12]);
+        buf.extend_from_slice(&1u32.to_le_bytes());
+        for (i, frame) in frames.iter().enumerate() {
+            buf.extend_from_slice(&100u32.to_le_bytes());
+            buf.extend_from_slice(&(i as u32 * 1000).to_le_bytes());
+            buf.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            buf.extend_from_slice(&(frame.len() as u32).to_le_bytes());
+            buf.extend_from_slice(frame);
+        }
+        buf
+    }
+
+    #[test]
+    fn test_tcp_reassembly() {
+        let isi = encode(Isi {
+            reqi: RequestId(1),
+            ..Default::default()
+        });
+        let mut reply = encode(Ver {
+            reqi: RequestId(1),
+            insimver: insim::VERSION,
+            ..Default::default()
+        });
+        reply.extend(encode(TinyType::None.with_request_id(0)));
+        let (first, second) = reply.split_at(10);
+
+        let buf = pcap(&[
+            tcp(CLIENT, SERVER, 999, 0x02, &[]),
+            tcp(SERVER, CLIENT, 4999, 0x12, &[]),
+            tcp(CLIENT, SERVER, 1000, 0x18, &isi[..20]),
+            tcp(CLIENT, SERVER, 1020, 0x18, &isi[20..]),
+            // out of order
+            tcp(SERVER, CLIENT, 5010, 0x18, second),
+            tcp(SERVER, CLIENT, 5000, 0x18, first),
+            // retransmission, overlapping
+            tcp(SERVER, CLIENT, 5005, 0x18, &reply[5..]),
+        ]);
+
+        let import = Importer::new(SERVER).parse(&buf).unwrap();
+        assert!(import.failures.is_empty(), "{:?}", import.failures);
+        assert_eq!(import.packets.len(), 3);
+
+        let isi = &import.packets[0];
+        assert!(matches!(isi.packet, insim::Packet::Isi(_)));
+        assert_eq!(isi.direction, Direction::Outgoing);
+        assert_eq!(isi.flow.server.port(), SERVER);
+        assert_eq!(isi.flow.transport, Transport::Tcp);
+        assert_eq!(isi.timestamp, Duration::new(100, 3_000_000));
+
+        assert!(matches!(import.packets[1].packet, insim::Packet::Ver(_)));
+        assert_eq!(import.packets[1].direction, Direction::Incoming);
+        assert!(matches!(import.packets[2].packet, insim::Packet::Tiny(_)));
+        assert_eq!(import.packets[2].offset, 20);
+
+        // convert to a capture
+        let capture = import.write_capture(Vec::new()).unwrap();
+        let reader = insim::capture::CaptureReader::new(&capture[..]).unwrap();
+        assert_eq!(reader.count(), 3);
+    }
+
+    #[test]
+    fn test_decode_failures() {
+        let mut data = encode(TinyType::Ping.with_request_id(1));
+        // an invalid Tiny subtype
+        data.extend_from_slice(&[1, 3, 0, 250]);
+        data.extend(encode(TinyType::Ping.with_request_id(2)));
+        // an invalid length, after which the stream cannot be decoded
+        data.extend_from_slice(&[0, 3, 0, 0]);
+        data.extend(encode(TinyType::Ping.with_request_id(3)));
+
+        let buf = pcap(&[tcp(SERVER, CLIENT, 1, 0x18, &data)]);
+        let import = Importer::new(SERVER).parse(&buf).unwrap();
+
+        assert_eq!(import.packets.len(), 2);
+        assert_eq!(import.failures.len(), 2);
+        assert_eq!(import.failures[0].offset, 4);
+        assert!(matches!(
+            import.failures[0].error,
+            Error::Insim(insim::Error::Decode { offset: 4, .. })
+        ));
+        assert_eq!(import.failures[1].offset, 12);
+    }
+
+    #[test]
+    fn test_gap() {
+        let data = encode(TinyType::Ping.with_request_id(1));
+        let buf = pcap(&[
+            tcp(SERVER, CLIENT, 1, 0x18, &data),
+            tcp(SERVER, CLIENT, 9, 0x18, &data),
+        ]);
+        let import = Importer::new(SERVER).parse(&buf).unwrap();
+        assert_eq!(import.packets.len(), 1);
+        assert!(matches!(import.failures[0].error, Error::Gap(4)));
+    }
+
+    #[test]
+    fn test_pcapng_udp() {
+        fn block(kind: u32, body: &[u8]) -> Vec<u8> {
+            let len = (12 + body.len()) as u32;
+            let mut buf = kind.to_le_bytes().to_vec();
+            buf.extend_from_slice(&len.to_le_bytes());
+            buf.extend_from_slice(body);
+            buf.extend_from_slice(&len.to_le_bytes());
+            buf
+        }
+
+        let payload = encode(TinyType::Ping.with_request_id(1));
+        let mut ip = vec![0x45, 0, 0, 32, 0, 0, 0, 0, 64, 17, 0, 0];
+        ip.extend_from_slice(&[127, 0, 0, 1, 127, 0, 0, 1]);
+        ip.extend_from_slice(&CLIENT.to_be_bytes());
+        ip.extend_from_slice(&SERVER.to_be_bytes());
+        ip.extend_from_slice(&[0, 12, 0, 0]);
+        ip.extend_from_slice(&payload);
+
+        let mut buf = block(
+            0x0a0d0d0a,
+            &[
+                0x4d, 0x3c, 0x2b, 0x1a, 1, 0, 0, 0, 255, 255, 255, 255, 255, 255, 255, 255,
+            ],
+        );
+        // raw IP, nanosecond resolution
+        buf.extend(block(
+            1,
+            &[101, 0, 0, 0, 0, 0, 0, 0, 9, 0, 1, 0, 9, 0, 0, 0, 0, 0, 0, 0],
+        ));
+        let mut epb = vec![0, 0, 0, 0, 0, 0, 0, 0, 0xe8, 0x03, 0, 0];
+        epb.extend_from_slice(&(ip.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&(ip.len() as u32).to_le_bytes());
+        epb.extend_from_slice(&ip);
+        buf.extend(block(6, &epb));
+
+        let import = Importer::new(SERVER).parse(&buf).unwrap();
+        assert_eq!(import.packets.len(), 1);
+        assert_eq!(import.packets[0].flow.transport, Transport::Udp);
+        assert_eq!(import.packets[0].direction, Direction::Outgoing);
+        assert_eq!(import.packets[0].timestamp, Duration::from_micros(1));
+    }
+}
@@ -0,0 +1,453 @@
+//! # insim_relay
+//!
+//! LFS limits the number of Insim connections per host. `insim_relay` holds a single upstream
+//! connection to LFS, and accepts any number of downstream Insim clients over TCP, so that
+//! several independent applications can share one slot.
+//!
+//! Downstream clients connect to the relay exactly as they would to LFS, including sending an
+//! [Isi]. See [Router] for how packets are routed between them.
+//!
+//! ```rust,ignore
+//! let upstream = insim::tcp("127.0.0.1:29999").isi_admin_password("secret".to_string());
+//!
+//! insim_relay::Relay::new(upstream)
+//!     .listen("127.0.0.1:29998".parse()?)
+//!     .run()
+//!     .await?;
+//! ```
+//!
+//! The upstream connection's [IsiFlags] and interval are fixed, using [Relay::flags] and
+//! [Relay::interval], and must cover everything your clients need. Clients which ask for other
+//! flags, or a shorter interval, are rejected.
+use std::{collections::HashMap, net::SocketAddr, time::Duration};
+
+use insim::{
+    MIN_VERSION, Packet, VERSION,
+    builder::Builder,
+    identifiers::RequestId,
+    insim::{Isi, IsiFlags, Tiny, TinyType, Ver},
+    net::{Codec, tokio_impl::Framed},
+};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::{
+        mpsc::{self, error::TrySendError},
+        oneshot,
+    },
+};
+#[cfg(feature = "cli")]
+use {clap as _, tracing_subscriber as _};
+
+mod router;
+
+pub use router::{ClientId, Router};
+
+/// How long a downstream client has to send its [Isi].
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How many packets a client may fall behind before it is disconnected.
+const CLIENT_CAPACITY: usize = 1024;
+
+/// How many packets from clients may be waiting for the relay, before clients stop being read.
+const EVENT_CAPACITY: usize = 1024;
+
+/// Number of [insim::identifiers::ClickId]s given to each client by default, allowing 6
+/// clients to use buttons.
+pub const DEFAULT_CLICKS_PER_CLIENT: u8 = 40;
+
+#[derive(Debug)]
+enum Event {
+    Connected {
+        isi: Box<Isi>,
+        tx: mpsc::Sender<Packet>,
+        reply: oneshot::Sender<Option<ClientId>>,
+    },
+    Packet(ClientId, Packet),
+    Disconnected(ClientId),
+}
+
+/// Relays a single upstream Insim connection to many downstream clients.
+#[derive(Debug)]
+pub struct Relay {
+    upstream: Builder,
+    listen: SocketAddr,
+    flags: IsiFlags,
+    interval: Option<Duration>,
+    admin: Option<String>,
+    clicks_per_client: u8,
+}
+
+impl Relay {
+    /// Create a new relay, which will connect upstream using `upstream`. Any flags and interval
+    /// already set on `upstream` are replaced.
+    pub fn new(upstream: Builder) -> Self {
+        Self {
+            upstream,
+            listen: SocketAddr::from(([127, 0, 0, 1], 29998)),
+            flags: IsiFlags::empty(),
+            interval: None,
+            admin: None,
+            clicks_per_client: DEFAULT_CLICKS_PER_CLIENT,
+        }
+    }
+
+    /// Address to accept downstream clients on. Defaults to 127.0.0.1:29998.
+    pub fn listen(mut self, addr: SocketAddr) -> Self {
+        self.listen = addr;
+        self
+    }
+
+    /// Flags the upstream connection uses. Clients may only ask for these flags, or a subset of
+    /// them. Defaults to none.
+    pub fn flags(mut self, flags: IsiFlags) -> Self {
+        self.flags = flags;
+        self
+    }
+
+    /// MCI or NLP interval the upstream connection uses. Clients may only ask for this interval
+    /// or a longer one. Defaults to none.
+    pub fn interval<I: Into<Option<Duration>>>(mut self, interval: I) -> Self {
+        self.interval = interval.into();
+        self
+    }
+
+    /// Require downstream clients to send this admin password.
+    pub fn admin_password<P: Into<Option<String>>>(mut self, password: P) -> Self {
+        self.admin = password.into();
+        self
+    }
+
+    /// How many buttons each client may use. Defaults to [DEFAULT_CLICKS_PER_CLIENT].
+    pub fn clicks_per_client(mut self, clicks: u8) -> Self {
+        self.clicks_per_client = clicks;
+        self
+    }
+
+    /// Bind the listening address and run until the upstream connection fails.
+    pub async fn run(self) -> insim::Result<()> {
+        let listener = TcpListener::bind(self.listen).await?;
+        self.serve(listener).await
+    }
+
+    /// Run using an existing listener until the upstream connection fails.
+    pub async fn serve(self, listener: TcpListener) -> insim::Result<()> {
+        let Relay {
+            upstream,
+            flags,
+            interval,
+            admin,
+            clicks_per_client,
+            ..
+        } = self;
+        let mut router = Router::new(flags, interval, clicks_per_client);
+        let mut clients: HashMap<ClientId, mpsc::Sender<Packet>> = HashMap::new();
+        let (events_tx, mut events) = mpsc::channel(EVENT_CAPACITY);
+
+        let (mut framed, ver) = connect(upstream, router.flags(), router.interval()).await?;
+
+        loop {
+            tokio::select! {
+                packet = framed.read() => {
+                    for (id, packet) in router.from_upstream(packet?) {
+                        let Some(tx) = clients.get(&id) else {
+                            continue;
+                        };
+                        if let Err(TrySendError::Full(_)) = tx.try_send(packet) {
+                            // Dropping the sender stops the client, which then disconnects
+                            tracing::warn!(%id, "client fell behind, dropping it");
+                            let _ = clients.remove(&id);
+                        }
+                    }
+                },
+                accepted = listener.accept() => match accepted {
+                    Ok((stream, addr)) => {
+                        tracing::debug!(%addr, "client connected");
+                        drop(tokio::spawn(client(stream, admin.clone(), events_tx.clone())));
+                    },
+                    Err(e) => tracing::warn!(error = ?e, "failed to accept client"),
+                },
+                event = events.recv() => match event.expect("relay holds a sender") {
+                    Event::Connected { isi, tx, reply } => {
+                        let Some(id) = router.connect(&isi) else {
+                            tracing::info!(
+                                iname = %isi.iname,
+                                flags = ?isi.flags,
+                                interval = ?isi.interval,
+                                "rejecting client with incompatible flags or interval"
+                            );
+                            let _ = reply.send(None);
+                            continue;
+                        };
+                        if reply.send(Some(id)).is_err() {
+                            // Client went away before finishing the handshake
+                            let _ = router.disconnect(id);
+                            continue;
+                        }
+                        tracing::info!(%id, iname = %isi.iname, "client joined");
+
+                        if isi.reqi != RequestId(0) {
+                            let _ = tx.try_send(Ver { reqi: isi.reqi, ..ver.clone() }.into());
+                        }
+                        let _ = clients.insert(id, tx);
+                    },
+                    Event::Packet(id, packet) => {
+                        for packet in router.from_client(id, packet) {
+                            framed.write(packet).await?;
+                        }
+                    },
+                    Event::Disconnected(id) => {
+                        tracing::info!(%id, "client left");
+                        let _ = clients.remove(&id);
+                        for packet in router.disconnect(id) {
+                            framed.write(packet).await?;
+                        }
+                    },
+                },
+            }
+        }
+    }
+}
+
+/// Connect upstream, returning the connection and its [Ver].
+async fn connect(
+    upstream: Builder,
+    flags: IsiFlags,
+    interval: Option<Duration>,
+) -> insim::Result<(Framed, Ver)> {
+    let mut framed = upstream
+        .isi_flags(flags)
+        .isi_interval(interval)
+        .isi_reqi(RequestId(1))
+        .connect_async()
+        .await?;
+    loop {
+        if let Packet::Ver(ver) = framed.read().await? {
+            return Ok((framed, ver));
+        }
+    }
+}
+
+/// Drive a single downstream client.
+async fn client(mut stream: TcpStream, admin: Option<String>, events: mpsc::Sender<Event>) {
+    let mut codec = Codec::new();
+    let isi = match tokio::time::timeout(HANDSHAKE_TIMEOUT, read(&mut stream, &mut codec)).await {
+        Ok(Ok(Packet::Isi(isi))) => isi,
+        other => {
+            tracing::debug!(?other, "client did not send Isi");
+            return;
+        },
+    };
+    if !(MIN_VERSION..=VERSION).contains(&isi.version) {
+        tracing::info!(
+            version = isi.version,
+            "rejecting client with unsupported version"
+        );
+        return;
+    }
+    // Never send the client anything newer than it asked for
+    codec.set_version(isi.version);
+    if admin.as_ref().is_some_and(|admin| *admin != isi.admin) {
+        tracing::info!(iname = %isi.iname, "rejecting client with incorrect admin password");
+        return;
+    }
+
+    let (tx, mut rx) = mpsc::channel(CLIENT_CAPACITY);
+    let (reply, id) = oneshot::channel();
+    if events
+        .send(Event::Connected {
+            isi: Box::new(isi),
+            tx,
+            reply,
+        })
+        .await
+        .is_err()
+    {
+        return;
+    }
+    let Ok(Some(id)) = id.await else {
+        return;
+    };
+
+    loop {
+        tokio::select! {
+            packet = read(&mut stream, &mut codec) => match packet {
+                Ok(Packet::Tiny(Tiny { subt: TinyType::Close, .. })) => break,
+                Ok(packet) => {
+                    if events.send(Event::Packet(id, packet)).await.is_err() {
+                        break;
+                    }
+                },
+                Err(e) => {
+                    tracing::debug!(%id, error = ?e, "client connection lost");
+                    break;
+                },
+            },
+            packet = rx.recv() => {
+                let Some(packet) = packet else {
+                    // The relay has stopped, or dropped us for falling behind
+                    break;
+                };
+                if packet.min_version() > codec.version() {
+                    tracing::trace!(%id, ?packet, "dropping packet unsupported by client version");
+                    continue;
+                }
+                let written = match codec.encode(&packet) {
+                    Ok(buf) => stream.write_all(&buf).await,
+                    Err(e) => {
+                        tracing::warn!(%id, error = ?e, "failed to encode packet");
+                        continue;
+                    },
+                };
+                if written.is_err() {
+                    break;
+                }
+            },
+        }
+    }
+    let _ = events.send(Event::Disconnected(id)).await;
+}
+
+/// Read the next packet from a client. Cancel-safe.
+async fn read(stream: &mut TcpStream, codec: &mut Codec) -> insim::Result<Packet> {
+    loop {
+        if let Some(packet) = codec.decode()? {
+            return Ok(packet);
+        }
+        if stream.read_buf(codec.buf_mut()).await? == 0 {
+            return Err(insim::Error::Disconnected);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use insim::{
+        insim::{Mso, Mst, Set},
+        testing::{FakeHost, Script},
+    };
+
+    use super::*;
+
+    async fn wait_for_mso(framed: &mut Framed) -> Mso {
+        loop {
+            if let Packet::Mso(mso) = framed.read().await.unwrap() {
+                return mso;
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_relay_end_to_end() {
+        let host = FakeHost::new()
+            .script(
+                Script::new()
+                    .wait_for(|p| matches!(p, Packet::Mst(_)))
+                    .send(Mso {
+                        msg: "hello".into(),
+                        ..Default::default()
+                    }),
+            )
+            .listen_tcp()
+            .await
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let relay = tokio::spawn(
+            Relay::new(insim::tcp(host.local_addr()))
+                .admin_password("secret".to_string())
+                .serve(listener),
+        );
+
+        let mut first = insim::tcp(addr)
+            .isi_admin_password("secret".to_string())
+            .connect_async()
+            .await
+            .unwrap();
+        assert!(matches!(first.read().await.unwrap(), Packet::Ver(_)));
+
+        let mut second = insim::tcp(addr)
+            .isi_admin_password("secret".to_string())
+            .connect_async()
+            .await
+            .unwrap();
+        assert!(matches!(second.read().await.unwrap(), Packet::Ver(_)));
+
+        // an incorrect password is rejected without affecting anyone else
+        let mut rejected = insim::tcp(addr).connect_async().await.unwrap();
+        assert!(rejected.read().await.is_err());
+
+        // as is a client asking for flags the upstream connection does not have
+        let mut incompatible = insim::tcp(addr)
+            .isi_admin_password("secret".to_string())
+            .isi_flags(IsiFlags::MCI)
+            .connect_async()
+            .await
+            .unwrap();
+        assert!(incompatible.read().await.is_err());
+
+        first
+            .write(Mst {
+                msg: "hi".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+        assert_eq!(wait_for_mso(&mut first).await.msg, "hello");
+        assert_eq!(wait_for_mso(&mut second).await.msg, "hello");
+
+        // a single upstream connection was shared by both clients
+        assert_eq!(host.connections(), 1);
+        assert!(!relay.is_finished());
+        relay.abort();
+    }
+
+    #[tokio::test]
+    async fn test_relay_older_client() {
+        let host = FakeHost::new()
+            .script(
+                Script::new()
+                    .wait_for(|p| matches!(p, Packet::Mst(_)))
+                    .send(Set::default())
+                    .send(Mso {
+                        msg: "hello".into(),
+                        ..Default::default()
+                    }),
+            )
+            .listen_tcp()
+            .await
+            .unwrap();
+
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let relay = tokio::spawn(Relay::new(insim::tcp(host.local_addr())).serve(listener));
+
+        let mut client = insim::tcp(addr)
+            .isi_version(9)
+            .connect_async()
+            .await
+            .unwrap();
+        assert!(matches!(client.read().await.unwrap(), Packet::Ver(_)));
+        client
+            .write(Mst {
+                msg: "hi".into(),
+                ..Default::default()
+            })
+            .await
+            .unwrap();
+
+        // Set was introduced in Insim 10, so is never sent to the client
+        loop {
+            match client.read().await.unwrap() {
+                Packet::Mso(mso) => {
+                    assert_eq!(mso.msg, "hello");
+                    break;
+                },
+                Packet::Set(_) => panic!("Set sent to an Insim 9 client"),
+                _ => continue,
+            }
+        }
+        relay.abort();
+    }
+}
@@ -0,0 +1,82 @@
+//! Share a single Insim connection between many Insim clients.
+use std::{net::SocketAddr, time::Duration};
+
+use clap::Parser;
+use insim::insim::IsiFlags;
+use insim_relay::Relay;
+use tracing as _;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    #[arg(long)]
+    /// host:port of LFS to connect to
+    upstream: SocketAddr,
+
+    #[arg(long, default_value = "127.0.0.1:29998")]
+    /// Address to accept Insim clients on
+    listen: SocketAddr,
+
+    #[arg(long, env = "INSIM_RELAY_UPSTREAM_ADMIN")]
+    /// Admin password of the LFS host
+    upstream_admin: Option<String>,
+
+    #[arg(long, env = "INSIM_RELAY_ADMIN")]
+    /// Admin password clients must provide
+    admin: Option<String>,
+
+    #[arg(long, default_value_t = insim_relay::DEFAULT_CLICKS_PER_CLIENT)]
+    /// Number of buttons each client may use
+    clicks_per_client: u8,
+
+    #[arg(long)]
+    /// Request MCI packets, allowing clients to ask for them
+    mci: bool,
+
+    #[arg(long)]
+    /// Request NLP packets, allowing clients to ask for them
+    nlp: bool,
+
+    #[arg(long, value_parser = parse_millis)]
+    /// MCI or NLP interval in milliseconds. Clients may ask for this interval or a longer one
+    interval: Option<Duration>,
+}
+
+fn parse_millis(value: &str) -> Result<Duration, std::num::ParseIntError> {
+    value.parse().map(Duration::from_millis)
+}
+
+fn setup_tracing_subscriber() {
+    // Setup with a default log level of INFO RUST_LOG is unset
+    tracing_subscriber::fmt::fmt()
+        .with_env_filter(
+            tracing_subscriber::EnvFilter::builder()
+                .with_default_directive(tracing_subscriber::filter::LevelFilter::INFO.into())
+                .from_env_lossy(),
+        )
+        .init();
+}
+
+#[tokio::main]
+async fn main() -> insim::Result<()> {
+    setup_tracing_subscriber();
+
+    let cli = Cli::parse();
+
+    let mut flags = IsiFlags::empty();
+    flags.set(IsiFlags::MCI, cli.mci);
+    flags.set(IsiFlags::NLP, cli.nlp);
+
+    let upstream = insim::tcp(cli.upstream)
+        .isi_admin_password(cli.upstream_admin)
+        .isi_iname("insim_relay".to_string());
+
+    Relay::new(upstream)
+        .listen(cli.listen)
+        .flags(flags)
+        .interval(cli.interval)
+        .admin_password(cli.admin)
+        .clicks_per_client(cli.clicks_per_client)
+        .run()
+        .await
+}
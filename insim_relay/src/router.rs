@@ -0,0 +1,661 @@
+//! Routing between the upstream connection and downstream clients, without any IO.
+use std::{
+    collections::BTreeMap,
+    fmt,
+    time::{Duration, Instant},
+};
+
+use insim::{
+    Packet, WithRequestId,
+    identifiers::{ClickId, RequestId},
+    insim::{Bfn, BfnType, Isi, IsiFlags, Tiny, TinyType},
+};
+
+/// Identifies a downstream client.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ClientId(pub u64);
+
+impl fmt::Display for ClientId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+#[derive(Debug)]
+struct Client {
+    flags: IsiFlags,
+    /// First upstream ClickId of this client's partition, if it has one
+    clicks: Option<u8>,
+}
+
+/// How long a request answered by a single reply, such as [insim::insim::Rip], keeps its
+/// [RequestId] whilst waiting for that reply.
+const SINGLE_REPLY_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// What completes an [Outstanding] request.
+#[derive(Debug, Clone, Copy)]
+enum Completion {
+    /// The request was a [TinyType::Ping], completed by the [TinyType::Reply]
+    Reply,
+    /// The request was followed by a [TinyType::Ping] of our own, completed by the
+    /// [TinyType::Reply], which is not forwarded
+    Ping,
+    /// The request is answered by a single packet with the given discriminator, which may only
+    /// arrive once LFS has finished, i.e. loading a replay
+    Single { reply: u8, since: Instant },
+}
+
+/// A request from a client which has not yet been completely answered.
+#[derive(Debug, Clone, Copy)]
+struct Outstanding {
+    /// The requesting client, or `None` once it has disconnected
+    client: Option<ClientId>,
+    /// The client's original RequestId
+    reqi: RequestId,
+    completion: Completion,
+}
+
+impl Outstanding {
+    /// Has a single reply request given up waiting?
+    fn is_expired(&self) -> bool {
+        match self.completion {
+            Completion::Single { since, .. } => since.elapsed() >= SINGLE_REPLY_TIMEOUT,
+            _ => false,
+        }
+    }
+}
+
+/// Decides where every packet goes.
+///
+/// - Requests from clients are given an upstream [RequestId] which is unique across all clients,
+///   and replies are returned only to the requesting client with the original [RequestId]. Each
+///   request is followed by a [TinyType::Ping], and the upstream [RequestId] is only reused once
+///   the matching [TinyType::Reply] has been received, so late replies are never sent to the
+///   wrong client. [insim::insim::Rip] and [insim::insim::Ssh] are answered once LFS has
+///   finished, so are not followed by a [TinyType::Ping], and instead keep their [RequestId]
+///   until their reply arrives or a timeout passes. At most 255 requests may be outstanding at
+///   once.
+/// - The [ClickId] space is divided into equal partitions, one per client. Clients number their
+///   buttons from 0 as usual, and only receive [insim::insim::Btc] and [insim::insim::Btt] for
+///   their own buttons.
+/// - The upstream connection's [IsiFlags] and interval are fixed. Clients may only ask for a
+///   subset of the flags, and an interval no shorter than the upstream interval. Clients only
+///   receive the flag dependent packets, such as [insim::insim::Mci], that they asked for.
+#[derive(Debug)]
+pub struct Router {
+    flags: IsiFlags,
+    interval: Option<Duration>,
+    clicks_per_client: u8,
+    clients: BTreeMap<ClientId, Client>,
+    next_client: u64,
+    /// Upstream RequestId -> the request it was allocated to
+    requests: Vec<Option<Outstanding>>,
+    next_reqi: u8,
+}
+
+impl Router {
+    /// Create a new router, for an upstream connection using `flags` and `interval`. Each client
+    /// may use up to `clicks_per_client` buttons.
+    pub fn new(flags: IsiFlags, interval: Option<Duration>, clicks_per_client: u8) -> Self {
+        Self {
+            flags,
+            interval: interval.filter(|interval| !interval.is_zero()),
+            clicks_per_client: clicks_per_client.min(ClickId::MAX + 1),
+            clients: BTreeMap::new(),
+            next_client: 0,
+            requests: vec![None; 256],
+            next_reqi: 1,
+        }
+    }
+
+    /// The flags the upstream connection uses.
+    pub fn flags(&self) -> IsiFlags {
+        self.flags
+    }
+
+    /// The MCI or NLP interval the upstream connection uses, if any.
+    pub fn interval(&self) -> Option<Duration> {
+        self.interval
+    }
+
+    /// Can a client which sent `isi` be served by the upstream connection?
+    pub fn is_compatible(&self, isi: &Isi) -> bool {
+        let interval = isi.interval.is_zero()
+            || self
+                .interval
+                .is_some_and(|interval| interval <= isi.interval);
+        self.flags.contains(isi.flags) && interval
+    }
+
+    /// Number of connected clients.
+    pub fn len(&self) -> usize {
+        self.clients.len()
+    }
+
+    /// Are there no connected clients?
+    pub fn is_empty(&self) -> bool {
+        self.clients.is_empty()
+    }
+
+    /// Register a client which has completed the handshake. Returns `None` if the client is not
+    /// [compatible](Router::is_compatible).
+    ///
+    /// Clients which connect once every [ClickId] partition is in use may still connect, but
+    /// cannot use buttons.
+    pub fn connect(&mut self, isi: &Isi) -> Option<ClientId> {
+        if !self.is_compatible(isi) {
+            return None;
+        }
+        let id = ClientId(self.next_client);
+        self.next_client += 1;
+
+        let clicks = (self.clicks_per_client > 0)
+            .then(|| {
+                let partitions = (u16::from(ClickId::MAX) + 1) / u16::from(self.clicks_per_client);
+                (0..partitions as u8)
+                    .map(|i| i * self.clicks_per_client)
+                    .find(|base| !self.clients.values().any(|c| c.clicks == Some(*base)))
+            })
+            .flatten();
+        if clicks.is_none() {
+            tracing::warn!(%id, "no ClickId range available, buttons will be dropped");
+        }
+
+        let _ = self.clients.insert(
+            id,
+            Client {
+                flags: isi.flags,
+                clicks,
+            },
+        );
+        Some(id)
+    }
+
+    /// Forget a client. Returns packets to send upstream to clean up after it.
+    pub fn disconnect(&mut self, id: ClientId) -> Vec<Packet> {
+        // Requests stay allocated until they are answered, but the replies are dropped
+        for request in self.requests.iter_mut().flatten() {
+            if request.client == Some(id) {
+                request.client = None;
+            }
+        }
+
+        match self.clients.remove(&id) {
+            Some(Client {
+                clicks: Some(base), ..
+            }) => vec![self.delete_buttons(base, 0, self.clicks_per_client - 1)],
+            _ => Vec::new(),
+        }
+    }
+
+    fn delete_buttons(&self, base: u8, from: u8, to: u8) -> Packet {
+        Bfn {
+            subt: BfnType::DelBtn,
+            ucid: insim::identifiers::ConnectionId::ALL,
+            clickid: ClickId(base + from),
+            clickmax: base + to.min(self.clicks_per_client - 1),
+            ..Default::default()
+        }
+        .into()
+    }
+
+    /// Handle a packet from a client, returning what should be sent upstream.
+    pub fn from_client(&mut self, id: ClientId, mut packet: Packet) -> Vec<Packet> {
+        let Some(client) = self.clients.get(&id) else {
+            return Vec::new();
+        };
+        let clicks = client.clicks;
+
+        match &mut packet {
+            // Connection management is handled by the relay
+            Packet::Isi(_)
+            | Packet::Tiny(Tiny {
+                subt: TinyType::None | TinyType::Close,
+                ..
+            }) => return Vec::new(),
+
+            Packet::Btn(btn) => {
+                let Some(base) = clicks.filter(|_| btn.clickid.0 < self.clicks_per_client) else {
+                    tracing::warn!(%id, clickid = %btn.clickid, "dropping button outside of ClickId range");
+                    return Vec::new();
+                };
+                btn.clickid = ClickId(base + btn.clickid.0);
+                // Btn's RequestId is echoed by Btc and Btt, which are routed by ClickId instead
+                return vec![packet];
+            },
+
+            Packet::Bfn(bfn) if matches!(bfn.subt, BfnType::DelBtn | BfnType::Clear) => {
+                let Some(base) = clicks else {
+                    return Vec::new();
+                };
+                let (from, to) = match bfn.subt {
+                    // Only clear this client's buttons, not everyone's
+                    BfnType::Clear => (0, self.clicks_per_client - 1),
+                    _ => (bfn.clickid.0, bfn.clickmax.max(bfn.clickid.0)),
+                };
+                if from >= self.clicks_per_client {
+                    return Vec::new();
+                }
+                let mut delete = self.delete_buttons(base, from, to);
+                if let Packet::Bfn(delete) = &mut delete {
+                    delete.ucid = bfn.ucid;
+                    delete.reqi = bfn.reqi;
+                    delete.inst = bfn.inst;
+                }
+                return vec![delete];
+            },
+
+            _ => {},
+        }
+
+        let reqi = packet.reqi();
+        if reqi == RequestId(0) {
+            return vec![packet];
+        }
+
+        let completion = match packet {
+            // A Ping is already answered by a Reply, so doesn't need one of our own
+            Packet::Tiny(Tiny {
+                subt: TinyType::Ping,
+                ..
+            }) => Completion::Reply,
+            // LFS answers a Ping straight away, long before these are answered
+            Packet::Rip(_) | Packet::Ssh(_) => Completion::Single {
+                reply: packet.discriminator(),
+                since: Instant::now(),
+            },
+            _ => Completion::Ping,
+        };
+        let Some(upstream) = self.allocate(id, reqi, completion) else {
+            tracing::warn!(%id, "dropping request, too many requests outstanding");
+            return Vec::new();
+        };
+        packet.set_reqi(upstream);
+        if matches!(completion, Completion::Ping) {
+            vec![packet, TinyType::Ping.with_request_id(upstream).into()]
+        } else {
+            vec![packet]
+        }
+    }
+
+    fn allocate(
+        &mut self,
+        id: ClientId,
+        reqi: RequestId,
+        completion: Completion,
+    ) -> Option<RequestId> {
+        let upstream = (self.next_reqi..=u8::MAX)
+            .chain(1..self.next_reqi)
+            .find(|upstream| {
+                self.requests[*upstream as usize].is_none_or(|request| request.is_expired())
+            })?;
+        self.next_reqi = upstream.checked_add(1).unwrap_or(1);
+        self.requests[upstream as usize] = Some(Outstanding {
+            client: Some(id),
+            reqi,
+            completion,
+        });
+        Some(RequestId(upstream))
+    }
+
+    /// Handle a packet from upstream, returning which clients should receive it.
+    pub fn from_upstream(&mut self, mut packet: Packet) -> Vec<(ClientId, Packet)> {
+        // Button interactions go to whoever owns the button
+        let click = match &mut packet {
+            Packet::Btc(btc) => Some(&mut btc.clickid),
+            Packet::Btt(btt) => Some(&mut btt.clickid),
+            _ => None,
+        };
+        if let Some(clickid) = click {
+            let owner = self.clients.iter().find_map(|(id, client)| {
+                let base = client.clicks?;
+                (base..base.saturating_add(self.clicks_per_client))
+                    .contains(&clickid.0)
+                    .then_some((*id, base))
+            });
+            return match owner {
+                Some((id, base)) => {
+                    *clickid = ClickId(clickid.0 - base);
+                    vec![(id, packet)]
+                },
+                None => Vec::new(),
+            };
+        }
+
+        let reqi = packet.reqi();
+        if reqi != RequestId(0) {
+            let slot = &mut self.requests[reqi.0 as usize];
+            let Some(request) = *slot else {
+                tracing::debug!(?packet, "dropping reply to an unknown request");
+                return Vec::new();
+            };
+            let is_reply = matches!(
+                packet,
+                Packet::Tiny(Tiny {
+                    subt: TinyType::Reply,
+                    ..
+                })
+            );
+            let complete = match request.completion {
+                Completion::Reply | Completion::Ping => is_reply,
+                Completion::Single { reply, .. } => packet.discriminator() == reply,
+            };
+            if complete {
+                // Every reply has been received
+                *slot = None;
+                if matches!(request.completion, Completion::Ping) {
+                    return Vec::new();
+                }
+            }
+            return match request.client {
+                Some(id) => {
+                    packet.set_reqi(request.reqi);
+                    vec![(id, packet)]
+                },
+                None => Vec::new(),
+            };
+        }
+
+        let required = required_flag(&packet);
+        self.clients
+            .iter()
+            .filter(|(_, client)| required.is_none_or(|flag| client.flags.intersects(flag)))
+            .map(|(id, _)| (*id, packet.clone()))
+            .collect()
+    }
+}
+
+/// The flag a client must have set to receive an unsolicited packet.
+fn required_flag(packet: &Packet) -> Option<IsiFlags> {
+    let flag = match packet {
+        Packet::Nlp(_) => IsiFlags::NLP,
+        Packet::Mci(_) => IsiFlags::MCI,
+        Packet::Con(_) => IsiFlags::CON,
+        Packet::Obh(_) => IsiFlags::OBH,
+        Packet::Hlv(_) => IsiFlags::HLV,
+        Packet::Axm(_) => IsiFlags::AXM_LOAD | IsiFlags::AXM_EDIT,
+        Packet::Jrr(_) => IsiFlags::REQ_JOIN,
+        _ => return None,
+    };
+    Some(flag)
+}
+
+#[cfg(test)]
+mod tests {
+    use insim::{
+        identifiers::ConnectionId,
+        insim::{Btc, Btn, Mci, Ncn, Rip, Ssh},
+    };
+
+    use super::*;
+
+    fn isi(flags: IsiFlags, interval_ms: u64) -> Isi {
+        Isi {
+            flags,
+            interval: Duration::from_millis(interval_ms),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_flags_and_interval_are_fixed() {
+        let flags = IsiFlags::CON | IsiFlags::MCI | IsiFlags::NLP | IsiFlags::MSO_COLS;
+        let mut router = Router::new(flags, Some(Duration::from_millis(200)), 40);
+        let a = router.connect(&isi(IsiFlags::MCI, 500)).unwrap();
+        let _ = router
+            .connect(&isi(IsiFlags::NLP | IsiFlags::MSO_COLS, 200))
+            .unwrap();
+        let _ = router.connect(&isi(IsiFlags::empty(), 0)).unwrap();
+
+        // clients asking for more than the upstream connection has are rejected
+        assert!(router.connect(&isi(IsiFlags::OBH, 0)).is_none());
+        assert!(router.connect(&isi(IsiFlags::MCI, 100)).is_none());
+        assert_eq!(router.len(), 3);
+        assert_eq!(router.flags(), flags);
+        assert_eq!(router.interval(), Some(Duration::from_millis(200)));
+
+        // only the client which asked for MCI receives it
+        let routed = router.from_upstream(Mci::default().into());
+        assert_eq!(routed.len(), 1);
+        assert_eq!(routed[0].0, a);
+        // everyone receives everything else
+        assert_eq!(router.from_upstream(Ncn::default().into()).len(), 3);
+
+        // without an upstream interval, no client may ask for one
+        let mut router = Router::new(IsiFlags::MCI, None, 40);
+        assert!(router.connect(&isi(IsiFlags::MCI, 500)).is_none());
+        assert!(router.connect(&isi(IsiFlags::MCI, 0)).is_some());
+    }
+
+    #[test]
+    fn test_request_ids_are_rewritten() {
+        let mut router = Router::new(IsiFlags::empty(), None, 40);
+        let a = router.connect(&isi(IsiFlags::empty(), 0)).unwrap();
+        let b = router.connect(&isi(IsiFlags::empty(), 0)).unwrap();
+
+        let request = |reqi| {
+            Packet::from(Tiny {
+                subt: TinyType::Ncn,
+                reqi: RequestId(reqi),
+            })
+        };
+        let reply = |reqi| {
+            Packet::from(Ncn {
+                reqi,
+                ..Default::default()
+            })
+        };
+        let done = |reqi| -> Packet { TinyType::Reply.with_request_id(reqi).into() };
+
+        // every request is followed by a ping using the same upstream RequestId
+        let from_a = router.from_client(a, request(5));
+        let from_b = router.from_client(b, request(5));
+        let (a_reqi, b_reqi) = (from_a[0].reqi(), from_b[0].reqi());
+        assert_ne!(a_reqi, b_reqi);
+        assert!(matches!(
+            from_b[..],
+            [
+                Packet::Tiny(Tiny {
+                    subt: TinyType::Ncn,
+                    ..
+                }),
+                Packet::Tiny(Tiny {
+                    subt: TinyType::Ping,
+                    reqi,
+                })
+            ] if reqi == b_reqi
+        ));
+
+        let routed = router.from_upstream(reply(b_reqi));
+        assert_eq!(routed.len(), 1);
+        assert_eq!(routed[0].0, b);
+        assert_eq!(routed[0].1.reqi(), RequestId(5));
+
+        // the reply to our ping completes the request, and is not forwarded
+        assert!(router.from_upstream(done(b_reqi)).is_empty());
+        assert!(router.from_upstream(reply(b_reqi)).is_empty());
+
+        // once disconnected, replies are dropped
+        let _ = router.disconnect(a);
+        assert!(router.from_upstream(reply(a_reqi)).is_empty());
+
+        // a client's own ping is answered by the reply, without adding another
+        let ping = router.from_client(b, TinyType::Ping.with_request_id(RequestId(9)).into());
+        assert_eq!(ping.len(), 1);
+        let routed = router.from_upstream(done(ping[0].reqi()));
+        assert_eq!(routed.len(), 1);
+        assert_eq!(routed[0].1.reqi(), RequestId(9));
+
+        // keepalives and closes are never forwarded
+        assert!(router.from_client(b, TinyType::None.into()).is_empty());
+    }
+
+    #[test]
+    fn test_request_ids_are_reserved_until_answered() {
+        let mut router = Router::new(IsiFlags::empty(), None, 40);
+        let a = router.connect(&isi(IsiFlags::empty(), 0)).unwrap();
+        let b = router.connect(&isi(IsiFlags::empty(), 0)).unwrap();
+
+        let request: Packet = TinyType::Ncn.with_request_id(RequestId(1)).into();
+        let first = router.from_client(a, request.clone())[0].reqi();
+        let _ = router.disconnect(a);
+
+        // every other RequestId is used up, without reusing the disconnected client's
+        for _ in 0..254 {
+            assert_ne!(router.from_client(b, request.clone())[0].reqi(), first);
+        }
+        assert!(router.from_client(b, request.clone()).is_empty());
+
+        // a late reply for the disconnected client is not sent to anyone
+        let late = Ncn {
+            reqi: first,
+            ..Default::default()
+        };
+        assert!(router.from_upstream(late.into()).is_empty());
+
+        // once answered, the RequestId is free again
+        assert!(
+            router
+                .from_upstream(TinyType::Reply.with_request_id(first).into())
+                .is_empty()
+        );
+        assert_eq!(router.from_client(b, request)[0].reqi(), first);
+    }
+
+    #[test]
+    fn test_single_reply_requests_wait_for_their_reply() {
+        let mut router = Router::new(IsiFlags::empty(), None, 40);
+        let a = router.connect(&isi(IsiFlags::empty(), 0)).unwrap();
+
+        let load = Rip {
+            reqi: RequestId(3),
+            rname: Some("race".into()),
+            ..Default::default()
+        };
+        // no ping is added, LFS would answer it before the replay has loaded
+        let rip = router.from_client(a, load.into());
+        assert!(matches!(rip[..], [Packet::Rip(_)]));
+        let rip_reqi = rip[0].reqi();
+
+        let ncn = router.from_client(a, TinyType::Ncn.with_request_id(RequestId(4)).into());
+        assert_eq!(ncn.len(), 2);
+        assert!(
+            router
+                .from_upstream(TinyType::Reply.with_request_id(ncn[1].reqi()).into())
+                .is_empty()
+        );
+
+        // a reply with the same RequestId, which is not a Rip, does not complete the request
+        let routed = router.from_upstream(TinyType::Reply.with_request_id(rip_reqi).into());
+        assert_eq!(routed.len(), 1);
+
+        let loaded = Rip {
+            reqi: rip_reqi,
+            ..Default::default()
+        };
+        let routed = router.from_upstream(loaded.clone().into());
+        assert_eq!(routed.len(), 1);
+        assert_eq!(routed[0].0, a);
+        assert_eq!(routed[0].1.reqi(), RequestId(3));
+
+        // the Rip completed the request
+        assert!(router.from_upstream(loaded.into()).is_empty());
+
+        let ssh = router.from_client(
+            a,
+            Ssh {
+                reqi: RequestId(5),
+                ..Default::default()
+            }
+            .into(),
+        );
+        assert!(matches!(ssh[..], [Packet::Ssh(_)]));
+        let routed = router.from_upstream(
+            Ssh {
+                reqi: ssh[0].reqi(),
+                name: "shot".into(),
+                ..Default::default()
+            }
+            .into(),
+        );
+        assert_eq!(routed[0].1.reqi(), RequestId(5));
+    }
+
+    #[test]
+    fn test_click_ids_are_partitioned() {
+        let mut router = Router::new(IsiFlags::empty(), None, 120);
+        let a = router.connect(&isi(IsiFlags::empty(), 0)).unwrap();
+        let b = router.connect(&isi(IsiFlags::empty(), 0)).unwrap();
+        let c = router.connect(&isi(IsiFlags::empty(), 0)).unwrap();
+
+        let btn = |clickid| {
+            Packet::from(Btn {
+                reqi: RequestId(1),
+                clickid: ClickId(clickid),
+                ..Default::default()
+            })
+        };
+        assert!(matches!(
+            router.from_client(b, btn(3))[..],
+            [Packet::Btn(Btn {
+                clickid: ClickId(123),
+                reqi: RequestId(1),
+                ..
+            })]
+        ));
+        // outside of the client's range, or the client has no range
+        assert!(router.from_client(a, btn(120)).is_empty());
+        assert!(router.from_client(c, btn(0)).is_empty());
+
+        let routed = router.from_upstream(
+            Btc {
+                clickid: ClickId(123),
+                ..Default::default()
+            }
+            .into(),
+        );
+        assert_eq!(routed.len(), 1);
+        assert_eq!(routed[0].0, b);
+        assert!(matches!(
+            routed[0].1,
+            Packet::Btc(Btc {
+                clickid: ClickId(3),
+                ..
+            })
+        ));
+
+        // clearing only clears the client's own buttons
+        let clear = router.from_client(
+            a,
+            Bfn {
+                subt: BfnType::Clear,
+                ucid: ConnectionId(2),
+                ..Default::default()
+            }
+            .into(),
+        );
+        assert!(matches!(
+            clear[..],
+            [Packet::Bfn(Bfn {
+                subt: BfnType::DelBtn,
+                ucid: ConnectionId(2),
+                clickid: ClickId(0),
+                clickmax: 119,
+                ..
+            })]
+        ));
+
+        // disconnecting deletes every button, and frees the range for the next client
+        let cleanup = router.disconnect(b);
+        assert!(matches!(
+            cleanup[..],
+            [Packet::Bfn(Bfn {
+                clickid: ClickId(120),
+                clickmax: 239,
+                ..
+            })]
+        ));
+        let d = router.connect(&isi(IsiFlags::empty(), 0)).unwrap();
+        assert!(router.from_client(d, btn(0)).len() == 1);
+    }
+}
@@ -1,7 +1,9 @@
 //! In-crate unit tests for the dispatch pipeline.
 //!
-//! These exercise the runtime *without* a network connection by driving
+//! Most of these exercise the runtime *without* a network connection by driving
 //! [`crate::app::dispatch_cycle`] directly with hand-built `Dispatch` values.
+//! The remainder run [`crate::run`] end to end against
+//! [`insim::testing::FakeHost`].
 
 // Tests run on `App<()>`, so the `let app_state = app.state;` destructuring
 // (kept uniform with `run()`) binds the unit type; allow it crate-test-wide
@@ -19,12 +21,14 @@ use std::{
         Arc,
         atomic::{AtomicUsize, Ordering},
     },
+    time::Duration,
 };
 
 use clap as _;
 use insim::{
     identifiers::ConnectionId,
-    insim::{Mso, MsoUserType, Ncn},
+    insim::{Mso, MsoUserType, Ncn, Tiny, TinyType},
+    testing::{FakeHost, Script},
 };
 use parking_lot::{Mutex, RwLock};
 use tokio::sync::mpsc;
@@ -833,3 +837,41 @@ async fn pre_handlers_run_sequentially_in_registration_order() {
 
     assert_eq!(*log.lock(), vec!["first", "second"]);
 }
+
+#[tokio::test]
+async fn run_against_fake_host() {
+    // Answer kitcar's startup request for connections, then hang up.
+    let host = FakeHost::new()
+        .script(
+            Script::new()
+                .wait_for(|p| {
+                    matches!(
+                        p,
+                        insim::Packet::Tiny(Tiny {
+                            subt: TinyType::Ncn,
+                            ..
+                        })
+                    )
+                })
+                .send(make_ncn(1, "alice"))
+                .delay(Duration::from_millis(50))
+                .disconnect(),
+        )
+        .listen_tcp()
+        .await
+        .unwrap();
+
+    let state = TestState::default();
+    let result = tokio::time::timeout(
+        Duration::from_secs(5),
+        crate::run(insim::tcp(host.local_addr()), app_with(state.clone())),
+    )
+    .await
+    .expect("run should end when the host disconnects");
+
+    assert!(result.is_err());
+    assert_eq!(host.connections(), 1);
+    assert!(matches!(host.received()[0], insim::Packet::Isi(_)));
+    assert_eq!(state.ncn_hits.load(Ordering::Relaxed), 1);
+    assert_eq!(state.connected_hits.load(Ordering::Relaxed), 1);
+}
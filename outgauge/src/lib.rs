@@ -17,6 +17,7 @@ use insim_core::{
 bitflags::bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
     #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Describes the setup of a player and the various helpers that may be enabled, such as
     /// auto-clutch, etc.
     pub struct OutgaugeFlags: u16 {
@@ -48,6 +49,7 @@ impl Decode for OutgaugeFlags {
 /// Unique Player Identifier, commonly referred to as PLID in Insim.txt
 #[derive(Debug, Ord, PartialOrd, PartialEq, Eq, Hash, Clone, Copy, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct OutgaugeId(pub i32);
 
 impl fmt::Display for OutgaugeId {
@@ -89,11 +91,13 @@ impl Encode for OutgaugeId {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 /// Outgauge packet
 pub struct Outgauge {
     /// Time, useful for ordering
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::millis::<u32>))]
     pub time: Duration,
     /// Vehicle name
     pub car: Vehicle,
@@ -128,8 +132,10 @@ pub struct Outgauge {
     /// Clutch percentage, 0-1
     pub clutch: f32,
     /// Display text, usually fuel
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<16>))]
     pub display1: String,
     /// Display text, usually settings
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::string::<16>))]
     pub display2: String,
 
     /// Optional identifier
@@ -347,4 +353,10 @@ mod test {
             "assert reads and writes. left=actual, right=expected"
         );
     }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_round_trip() {
+        insim_core::arbitrary_helpers::round_trip::<Outgauge>(256, 10_000, None);
+    }
 }
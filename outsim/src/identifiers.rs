@@ -11,6 +11,7 @@ use insim_core::{Decode, DecodeContext, Encode, EncodeContext};
 /// Unique Player Identifier, commonly referred to as PLID in Insim.txt
 #[derive(Debug, Ord, PartialOrd, PartialEq, Eq, Hash, Clone, Copy, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct OutsimId(pub i32);
 
 impl fmt::Display for OutsimId {
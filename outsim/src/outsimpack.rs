@@ -8,11 +8,13 @@ use insim_core::{
 
 use crate::OutsimId;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 /// Outsim packet
 pub struct OutsimPack {
     /// Time, useful for ordering
+    #[cfg_attr(feature = "arbitrary", arbitrary(with = insim_core::arbitrary_helpers::millis::<u32>))]
     pub time: Duration,
 
     /// Angular velocity
@@ -202,4 +204,10 @@ mod test {
             "assert reads and writes. left=actual, right=expected"
         );
     }
+
+    #[cfg(feature = "arbitrary")]
+    #[test]
+    fn test_arbitrary_round_trip() {
+        insim_core::arbitrary_helpers::round_trip::<OutsimPack>(256, 10_000, None);
+    }
 }
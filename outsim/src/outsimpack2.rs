@@ -11,6 +11,7 @@ use crate::OutsimId;
 bitflags::bitflags! {
     #[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Clone, Copy, Default)]
     #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+    #[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
     /// Describes the setup of a player and the various helpers that may be enabled, such as
     /// auto-clutch, etc.
     pub struct OutSimOpts: u16 {
@@ -35,8 +36,30 @@ bitflags::bitflags! {
     }
 }
 
+impl OutSimOpts {
+    /// Size in bytes of an [OutsimPack2] sent with these options.
+    pub fn packet_size(&self) -> usize {
+        [
+            (Self::HEADER, 4),
+            (Self::ID, 4),
+            (Self::TIME, 4),
+            (Self::MAIN, 60),
+            (Self::INPUTS, 20),
+            (Self::DRIVE, 12),
+            (Self::DISTANCE, 8),
+            (Self::WHEELS, 160),
+            (Self::EXTRA, 8),
+        ]
+        .iter()
+        .filter(|(opt, _)| self.contains(*opt))
+        .map(|(_, size)| size)
+        .sum()
+    }
+}
+
 #[derive(Debug, Clone, Copy, Default)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 /// OutsimMain packet
 pub struct OutsimMain {
     /// Angular velocity
@@ -653,4 +676,18 @@ mod test {
 
         assert_eq!(buf.as_ref(), output.as_ref());
     }
+
+    #[test]
+    fn test_packet_size() {
+        for opts in [OutSimOpts::empty(), OutSimOpts::HEADER, OutSimOpts::all()]
+            .into_iter()
+            .chain(OutSimOpts::all().iter())
+        {
+            let mut output = BytesMut::new();
+            OutsimPack2::default()
+                .encode_with_options(&mut EncodeContext::new(&mut output), &opts)
+                .unwrap();
+            assert_eq!(opts.packet_size(), output.len(), "{opts:?}");
+        }
+    }
 }